@@ -0,0 +1,224 @@
+//! GitHub Actions annotations for warnings and errors.
+//!
+//! When `GITHUB_ACTIONS` is `true`, `aps sync` and `aps validate` also print
+//! their warnings, and every command its final error, as `::warning` and
+//! `::error` workflow commands on stdout, so they show up on the run summary
+//! and in the PR checks. Problems with a particular file (an invalid
+//! notebook, a hooks config that does not parse, a missing hook script) are
+//! annotated on that file, with the line when the parser reported one.
+//! Files outside the workspace (`GITHUB_WORKSPACE`, else the current
+//! directory), such as those in a git clone, get a plain annotation.
+
+use crate::error::ApsError;
+use crate::events::{EventSink, SyncEvent};
+use miette::Diagnostic;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Environment variable GitHub Actions sets to `true` on its runners
+pub const GITHUB_ACTIONS_ENV: &str = "GITHUB_ACTIONS";
+
+/// Environment variable holding the checkout directory on the runner
+const GITHUB_WORKSPACE_ENV: &str = "GITHUB_WORKSPACE";
+
+/// Whether annotations should be printed
+pub fn enabled() -> bool {
+    std::env::var(GITHUB_ACTIONS_ENV).is_ok_and(|value| value == "true")
+}
+
+/// Severity of an annotation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Warning,
+    Error,
+}
+
+/// One `::warning` or `::error` workflow command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    pub level: Level,
+    pub message: String,
+    pub title: Option<String>,
+    /// Workspace-relative file the annotation is attached to
+    pub file: Option<PathBuf>,
+    pub line: Option<usize>,
+}
+
+impl Annotation {
+    pub fn new(level: Level, message: impl Into<String>) -> Self {
+        Self {
+            level,
+            message: message.into(),
+            title: None,
+            file: None,
+            line: None,
+        }
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Level::Warning, message)
+    }
+
+    /// Annotation for an error, attached to the file it is about when that
+    /// file is in the workspace; titled with the error's diagnostic code
+    pub fn for_error(level: Level, error: &ApsError) -> Self {
+        Self::for_error_in(level, error, &workspace())
+    }
+
+    fn for_error_in(level: Level, error: &ApsError, workspace: &Path) -> Self {
+        let mut annotation = Self::new(level, error.to_string());
+        annotation.title = error.code().map(|code| code.to_string());
+        if let Some((path, line)) = error_location(error) {
+            annotation.file = workspace_relative(path, workspace);
+            annotation.line = annotation.file.as_ref().and(line);
+        }
+        annotation
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+}
+
+impl fmt::Display for Annotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let command = match self.level {
+            Level::Warning => "warning",
+            Level::Error => "error",
+        };
+        let mut properties = Vec::new();
+        if let Some(ref file) = self.file {
+            // Annotations always use forward slashes, also on Windows runners
+            let file = file.to_string_lossy().replace('\\', "/");
+            properties.push(format!("file={}", escape_property(&file)));
+        }
+        if let Some(line) = self.line {
+            properties.push(format!("line={}", line));
+        }
+        if let Some(ref title) = self.title {
+            properties.push(format!("title={}", escape_property(title)));
+        }
+
+        write!(f, "::{}", command)?;
+        if !properties.is_empty() {
+            write!(f, " {}", properties.join(","))?;
+        }
+        write!(f, "::{}", escape_data(&self.message))
+    }
+}
+
+/// Print annotations to stdout when running on GitHub Actions
+pub fn emit_all(annotations: &[Annotation]) {
+    if !enabled() {
+        return;
+    }
+    for annotation in annotations {
+        println!("{}", annotation);
+    }
+}
+
+/// Event sink that annotates sync warnings, titled with the entry ID.
+///
+/// Errors are not annotated here: the command's final error is annotated
+/// once, with its file, when aps exits.
+pub struct AnnotationSink;
+
+impl EventSink for AnnotationSink {
+    fn emit(&self, event: &SyncEvent) {
+        if let SyncEvent::Warning { id, message } = event {
+            println!(
+                "{}",
+                Annotation::warning(message.as_str()).title(format!("aps: {}", id))
+            );
+        }
+    }
+}
+
+/// The file an error is about, and the line when the message carries one
+fn error_location(error: &ApsError) -> Option<(&Path, Option<usize>)> {
+    match error {
+        ApsError::InvalidHooksConfig { path, message } => Some((path, line_in(message))),
+        ApsError::InvalidNotebook { path, reason, .. } => Some((path, line_in(reason))),
+        ApsError::MissingHooksSection { path }
+        | ApsError::HookScriptNotFound { path }
+        | ApsError::UnterminatedGitignoreBlock { path }
+        | ApsError::SizeMismatch { path, .. }
+        | ApsError::Conflict { path }
+        | ApsError::UnsupportedSymlink { path, .. } => Some((path, None)),
+        _ => None,
+    }
+}
+
+/// Line number from a parser message such as `... at line 3 column 7`
+fn line_in(message: &str) -> Option<usize> {
+    let (_, rest) = message.rsplit_once("line ")?;
+    let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok().filter(|&line| line > 0)
+}
+
+fn workspace() -> PathBuf {
+    std::env::var_os(GITHUB_WORKSPACE_ENV)
+        .map(PathBuf::from)
+        .or_else(|| std::env::current_dir().ok())
+        .unwrap_or_default()
+}
+
+/// `path` relative to `workspace`, or `None` when it lies outside
+fn workspace_relative(path: &Path, workspace: &Path) -> Option<PathBuf> {
+    if path.is_relative() {
+        return Some(path.to_path_buf());
+    }
+    path.strip_prefix(workspace)
+        .ok()
+        .filter(|relative| !relative.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+}
+
+/// Escape a message: `%`, CR and LF would end or corrupt the command
+fn escape_data(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape a property value, which additionally cannot contain `:` or `,`
+fn escape_property(value: &str) -> String {
+    escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotation_formats_workflow_command() {
+        let workspace = Path::new("/work/repo");
+        let error = ApsError::InvalidHooksConfig {
+            path: workspace.join(".cursor/hooks.json"),
+            message: "did not find expected ',' at line 4 column 3".to_string(),
+        };
+        assert_eq!(
+            Annotation::for_error_in(Level::Error, &error, workspace).to_string(),
+            "::error file=.cursor/hooks.json,line=4,title=aps%3A%3Ahooks%3A%3Aconfig_invalid::\
+             Invalid hooks config at /work/repo/.cursor/hooks.json: \
+             did not find expected ',' at line 4 column 3"
+        );
+
+        // Outside the workspace: no file, and so no line either
+        let elsewhere = ApsError::InvalidHooksConfig {
+            path: PathBuf::from("/tmp/clone/hooks.json"),
+            message: "bad at line 2 column 1".to_string(),
+        };
+        let annotation = Annotation::for_error_in(Level::Warning, &elsewhere, workspace);
+        assert_eq!((annotation.file, annotation.line), (None, None));
+
+        let warning = Annotation::warning("100% done\r\nnext").title("aps: a,b");
+        assert_eq!(
+            warning.to_string(),
+            "::warning title=aps%3A a%2Cb::100%25 done%0D%0Anext"
+        );
+        assert_eq!(Annotation::warning("plain").to_string(), "::warning::plain");
+    }
+}
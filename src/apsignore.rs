@@ -0,0 +1,156 @@
+//! `.apsignore` files: excludes maintained by the source.
+//!
+//! A source can keep internal files out of every downstream sync by placing
+//! an `.apsignore` file at the root of the synced path. Matching paths are
+//! skipped while copying, on top of whatever the consumer's entry selects.
+//! The file itself is never synced.
+//!
+//! Patterns follow `.gitignore` semantics:
+//! - Blank lines and lines starting with `#` are skipped; `\#` and `\!`
+//!   escape a leading `#` or `!`.
+//! - `!pattern` re-includes paths excluded by an earlier pattern, except
+//!   inside an excluded directory; the last matching pattern wins.
+//! - A pattern starting with `/`, or containing a `/` before its end, is
+//!   anchored to the `.apsignore` directory; otherwise it matches at any depth.
+//! - A trailing `/` matches only directories.
+//! - `*` and `?` match within one path segment, `**` matches any number of
+//!   segments. Character classes (`[abc]`) are not supported.
+
+use crate::codeowners::segments_match;
+use std::path::Path;
+
+/// File name of a source's ignore file
+pub const APSIGNORE_FILE: &str = ".apsignore";
+
+/// One pattern line
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    /// Pattern segments, starting with `**` when the pattern is unanchored
+    segments: Vec<String>,
+    dir_only: bool,
+    negated: bool,
+}
+
+impl Rule {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, pattern) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+
+        let dir_only = pattern.ends_with('/');
+        let trimmed = pattern.trim_end_matches('/');
+        let anchored = trimmed.starts_with('/') || trimmed.contains('/');
+        let mut segments: Vec<String> = trimmed
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect();
+        if segments.is_empty() {
+            return None;
+        }
+        if !anchored {
+            segments.insert(0, "**".to_string());
+        }
+        Some(Self {
+            segments,
+            dir_only,
+            negated,
+        })
+    }
+
+    fn matches(&self, path: &[&str], is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let segments: Vec<&str> = self.segments.iter().map(String::as_str).collect();
+        segments_match(&segments, path)
+    }
+}
+
+/// Parsed `.apsignore` file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ApsIgnore {
+    rules: Vec<Rule>,
+}
+
+impl ApsIgnore {
+    /// Parse `.apsignore` content
+    pub fn parse(content: &str) -> Self {
+        Self {
+            rules: content.lines().filter_map(Rule::parse).collect(),
+        }
+    }
+
+    /// Read the `.apsignore` file directly inside `dir`, if there is one
+    pub fn load(dir: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(dir.join(APSIGNORE_FILE)).ok()?;
+        Some(Self::parse(&content))
+    }
+
+    /// Whether a path relative to the `.apsignore` directory (`/`-separated)
+    /// is excluded
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        if segments.is_empty() {
+            return false;
+        }
+        if segments == [APSIGNORE_FILE] {
+            return true;
+        }
+        // Nothing inside an excluded directory can be re-included
+        (1..segments.len()).any(|len| self.decides(&segments[..len], true))
+            || self.decides(&segments, is_dir)
+    }
+
+    /// Verdict of the last rule matching `path`
+    fn decides(&self, path: &[&str], is_dir: bool) -> bool {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(path, is_dir))
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gitignore_semantics() {
+        let ignore = ApsIgnore::parse(
+            "\
+# internal notes
+*.draft.md
+/TODO.md
+internal/
+docs/*.txt
+!docs/keep.txt
+\\#literal
+build
+!build/keep.md
+",
+        );
+
+        assert!(ignore.is_ignored("rules.draft.md", false));
+        assert!(ignore.is_ignored("nested/deep/rules.draft.md", false));
+        assert!(ignore.is_ignored("TODO.md", false));
+        assert!(!ignore.is_ignored("nested/TODO.md", false));
+        assert!(ignore.is_ignored("internal", true));
+        assert!(ignore.is_ignored("a/internal/secret.md", false));
+        assert!(!ignore.is_ignored("internal", false));
+        assert!(ignore.is_ignored("docs/notes.txt", false));
+        assert!(!ignore.is_ignored("docs/keep.txt", false));
+        assert!(!ignore.is_ignored("docs/sub/notes.txt", false));
+        assert!(ignore.is_ignored("#literal", false));
+        // Excluded directories cannot have their contents re-included
+        assert!(ignore.is_ignored("build/keep.md", false));
+        assert!(ignore.is_ignored(APSIGNORE_FILE, false));
+        assert!(!ignore.is_ignored("rules.md", false));
+    }
+}
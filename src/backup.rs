@@ -1,13 +1,47 @@
 use crate::error::{ApsError, Result};
 use chrono::Local;
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use tracing::{debug, info};
 
 /// Directory for storing backups
 pub const BACKUP_DIR: &str = ".aps-backups";
 
-/// Create a backup of an existing file or directory
-pub fn create_backup(base_dir: &Path, dest_path: &Path) -> Result<PathBuf> {
+/// Length of the `-YYYY-MM-DD-HHMM` suffix appended to backup names
+const TIMESTAMP_SUFFIX_LEN: usize = 16;
+
+/// Outcome of backing up a destination
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Backup {
+    /// A new backup was written
+    Created(PathBuf),
+    /// The content matches its most recent backup, so no copy was made
+    Unchanged(PathBuf),
+}
+
+impl Backup {
+    /// Path of the backup holding the destination's content
+    pub fn path(&self) -> &Path {
+        match self {
+            Backup::Created(path) | Backup::Unchanged(path) => path,
+        }
+    }
+}
+
+impl std::fmt::Display for Backup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Backup::Created(path) => write!(f, "Created backup at: {:?}", path),
+            Backup::Unchanged(path) => write!(f, "Unchanged since backup at: {:?}", path),
+        }
+    }
+}
+
+/// Create a backup of an existing file or directory.
+///
+/// If the newest existing backup of the same path already holds identical
+/// content, it is reused instead of writing another copy.
+pub fn create_backup(base_dir: &Path, dest_path: &Path) -> Result<Backup> {
     let backup_root = base_dir.join(BACKUP_DIR);
 
     // Create backup directory if it doesn't exist
@@ -31,6 +65,16 @@ pub fn create_backup(base_dir: &Path, dest_path: &Path) -> Result<PathBuf> {
         .to_string_lossy()
         .replace(['/', '\\'], "-");
 
+    if let Some(latest) = list_backups(&backup_root)?
+        .remove(&relative_path)
+        .and_then(|mut paths| paths.pop())
+    {
+        if same_content(dest_path, &latest) {
+            info!("{:?} is unchanged since backup {:?}", dest_path, latest);
+            return Ok(Backup::Unchanged(latest));
+        }
+    }
+
     let backup_name = format!("{}-{}", relative_path, timestamp);
     let backup_path = backup_root.join(&backup_name);
 
@@ -44,7 +88,93 @@ pub fn create_backup(base_dir: &Path, dest_path: &Path) -> Result<PathBuf> {
         info!("Backed up directory to {:?}", backup_path);
     }
 
-    Ok(backup_path)
+    Ok(Backup::Created(backup_path))
+}
+
+/// Remove all but the newest `keep` backups of each backed-up path.
+///
+/// Returns the removed backup paths.
+pub fn prune_backups(base_dir: &Path, keep: usize) -> Result<Vec<PathBuf>> {
+    let mut removed = Vec::new();
+    for (_, paths) in list_backups(&base_dir.join(BACKUP_DIR))? {
+        let excess = paths.len().saturating_sub(keep);
+        for path in paths.into_iter().take(excess) {
+            let result = if path.is_dir() {
+                std::fs::remove_dir_all(&path)
+            } else {
+                std::fs::remove_file(&path)
+            };
+            result.map_err(|e| ApsError::io(e, format!("Failed to remove backup {:?}", path)))?;
+            debug!("Removed old backup {:?}", path);
+            removed.push(path);
+        }
+    }
+    Ok(removed)
+}
+
+/// Group backups by the path they were taken from, oldest first
+fn list_backups(backup_root: &Path) -> Result<BTreeMap<String, Vec<PathBuf>>> {
+    let mut groups: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    if !backup_root.is_dir() {
+        return Ok(groups);
+    }
+    for entry in std::fs::read_dir(backup_root).map_err(|e| {
+        ApsError::io(
+            e,
+            format!("Failed to read backup directory {:?}", backup_root),
+        )
+    })? {
+        let entry = entry.map_err(|e| ApsError::io(e, "Failed to read directory entry"))?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(split) = name.len().checked_sub(TIMESTAMP_SUFFIX_LEN) else {
+            continue;
+        };
+        if !name.is_char_boundary(split) || !is_timestamp_suffix(&name[split..]) {
+            continue;
+        }
+        groups
+            .entry(name[..split].to_string())
+            .or_default()
+            .push(entry.path());
+    }
+    // Timestamps sort lexicographically in chronological order
+    for paths in groups.values_mut() {
+        paths.sort();
+    }
+    Ok(groups)
+}
+
+/// Whether `suffix` looks like `-YYYY-MM-DD-HHMM`
+fn is_timestamp_suffix(suffix: &str) -> bool {
+    suffix.len() == TIMESTAMP_SUFFIX_LEN
+        && suffix.char_indices().all(|(i, c)| match i {
+            0 | 5 | 8 | 11 => c == '-',
+            _ => c.is_ascii_digit(),
+        })
+}
+
+/// Compare two files or directory trees byte-for-byte
+fn same_content(a: &Path, b: &Path) -> bool {
+    if a.is_file() && b.is_file() {
+        return match (std::fs::read(a), std::fs::read(b)) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        };
+    }
+    if !(a.is_dir() && b.is_dir()) {
+        return false;
+    }
+    let (Ok(a_entries), Ok(b_entries)) = (std::fs::read_dir(a), std::fs::read_dir(b)) else {
+        return false;
+    };
+    let mut a_names: Vec<_> = a_entries.flatten().map(|e| e.file_name()).collect();
+    let mut b_names: Vec<_> = b_entries.flatten().map(|e| e.file_name()).collect();
+    a_names.sort();
+    b_names.sort();
+    a_names == b_names
+        && a_names
+            .iter()
+            .all(|name| same_content(&a.join(name), &b.join(name)))
 }
 
 /// Recursively copy a directory
@@ -156,4 +286,56 @@ mod tests {
 
         assert!(is_aps_managed_dir(&dir));
     }
+
+    #[test]
+    fn test_unchanged_content_reuses_latest_backup() {
+        let temp = tempdir().unwrap();
+        let dest = temp.path().join("AGENTS.md");
+        fs::write(&dest, "v1").unwrap();
+        fs::create_dir_all(temp.path().join(BACKUP_DIR)).unwrap();
+        let old = temp
+            .path()
+            .join(BACKUP_DIR)
+            .join("AGENTS.md-2024-01-01-0000");
+        fs::write(&old, "v1").unwrap();
+
+        assert_eq!(
+            create_backup(temp.path(), &dest).unwrap(),
+            Backup::Unchanged(old.clone())
+        );
+
+        fs::write(&dest, "v2").unwrap();
+        let backup = create_backup(temp.path(), &dest).unwrap();
+        assert!(matches!(backup, Backup::Created(_)));
+        assert_eq!(fs::read_to_string(backup.path()).unwrap(), "v2");
+    }
+
+    #[test]
+    fn test_prune_backups_keeps_newest_per_path() {
+        let temp = tempdir().unwrap();
+        let root = temp.path().join(BACKUP_DIR);
+        fs::create_dir_all(&root).unwrap();
+        for name in [
+            "AGENTS.md-2024-01-01-0000",
+            "AGENTS.md-2024-02-01-0000",
+            "AGENTS.md-2024-03-01-0000",
+            ".cursor-rules-2024-01-01-0000",
+            "notes.txt",
+        ] {
+            fs::write(root.join(name), name).unwrap();
+        }
+
+        let removed = prune_backups(temp.path(), 1).unwrap();
+
+        assert_eq!(
+            removed,
+            [
+                root.join("AGENTS.md-2024-01-01-0000"),
+                root.join("AGENTS.md-2024-02-01-0000"),
+            ]
+        );
+        assert!(root.join("AGENTS.md-2024-03-01-0000").exists());
+        assert!(root.join(".cursor-rules-2024-01-01-0000").exists());
+        assert!(root.join("notes.txt").exists());
+    }
 }
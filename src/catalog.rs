@@ -11,6 +11,7 @@
 use crate::error::{ApsError, Result};
 use crate::manifest::{AssetKind, Entry, Manifest};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use tracing::{debug, info, warn};
 
@@ -60,6 +61,30 @@ pub struct CatalogEntry {
     /// Short description extracted from the asset file (if available)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub short_description: Option<String>,
+
+    /// Key-value metadata from the asset's YAML (`---`) or TOML (`+++`)
+    /// frontmatter, e.g. `title`, `tags`, `model`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, serde_yaml::Value>,
+
+    /// Where the asset was resolved from (absent for composite entries)
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+}
+
+/// Source metadata recorded for each catalog entry
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Provenance {
+    /// Source display name (repository URL or filesystem root)
+    pub source: String,
+
+    /// Resolved git ref (git sources only)
+    #[serde(rename = "ref", skip_serializing_if = "Option::is_none")]
+    pub resolved_ref: Option<String>,
+
+    /// Resolved commit SHA (git sources only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
 }
 
 impl Catalog {
@@ -108,6 +133,25 @@ impl Catalog {
         Ok(())
     }
 
+    /// Names that are provided by more than one catalog entry of the same kind.
+    ///
+    /// Returns `(name, entry ids)` pairs sorted by name. Agents that load
+    /// assets by name cannot tell these apart.
+    pub fn duplicate_names(&self) -> Vec<(String, Vec<String>)> {
+        let mut by_name: BTreeMap<(String, String), Vec<String>> = BTreeMap::new();
+        for entry in &self.entries {
+            by_name
+                .entry((entry.name.clone(), format!("{:?}", entry.kind)))
+                .or_default()
+                .push(entry.id.clone());
+        }
+        by_name
+            .into_iter()
+            .filter(|(_, ids)| ids.len() > 1)
+            .map(|((name, _), ids)| (name, ids))
+            .collect()
+    }
+
     /// Generate a catalog from a manifest by enumerating all individual assets
     pub fn generate_from_manifest(manifest: &Manifest, manifest_dir: &Path) -> Result<Self> {
         let mut catalog = Catalog::new();
@@ -117,6 +161,9 @@ impl Catalog {
             catalog.entries.extend(entries);
         }
 
+        // Sort so the catalog does not depend on manifest or directory order
+        catalog.entries.sort_by(|a, b| a.id.cmp(&b.id));
+
         info!(
             "Generated catalog with {} entries from {} manifest entries",
             catalog.entries.len(),
@@ -141,6 +188,8 @@ fn enumerate_entry_assets(entry: &Entry, manifest_dir: &Path) -> Result<Vec<Cata
             kind: AssetKind::CompositeAgentsMd,
             destination: format!("./{}", base_dest.display()),
             short_description: Some(format!("Composed from {} sources", entry.sources.len())),
+            metadata: BTreeMap::new(),
+            provenance: None,
         });
         return Ok(catalog_entries);
     }
@@ -162,6 +211,12 @@ fn enumerate_entry_assets(entry: &Entry, manifest_dir: &Path) -> Result<Vec<Cata
         });
     }
 
+    let provenance = Provenance {
+        source: resolved.source_display.clone(),
+        resolved_ref: resolved.git_info.as_ref().map(|g| g.resolved_ref.clone()),
+        commit: resolved.git_info.as_ref().map(|g| g.commit_sha.clone()),
+    };
+
     match entry.kind {
         AssetKind::AgentsMd => {
             // Single file - create one entry
@@ -172,6 +227,7 @@ fn enumerate_entry_assets(entry: &Entry, manifest_dir: &Path) -> Result<Vec<Cata
                 .unwrap_or_else(|| "AGENTS.md".to_string());
 
             let short_description = extract_agents_md_description(&resolved.source_path);
+            let metadata = extract_file_metadata(&resolved.source_path);
 
             catalog_entries.push(CatalogEntry {
                 id: format!("{}:{}", entry.id, name),
@@ -179,6 +235,8 @@ fn enumerate_entry_assets(entry: &Entry, manifest_dir: &Path) -> Result<Vec<Cata
                 kind: AssetKind::AgentsMd,
                 destination: format!("./{}", base_dest.display()),
                 short_description,
+                metadata,
+                provenance: None,
             });
         }
         AssetKind::CompositeAgentsMd => {
@@ -189,6 +247,8 @@ fn enumerate_entry_assets(entry: &Entry, manifest_dir: &Path) -> Result<Vec<Cata
                 kind: AssetKind::CompositeAgentsMd,
                 destination: format!("./{}", base_dest.display()),
                 short_description: None,
+                metadata: BTreeMap::new(),
+                provenance: None,
             });
         }
         AssetKind::CursorRules => {
@@ -205,6 +265,7 @@ fn enumerate_entry_assets(entry: &Entry, manifest_dir: &Path) -> Result<Vec<Cata
                 }
 
                 let short_description = extract_cursor_rule_description(&file_path);
+                let metadata = extract_file_metadata(&file_path);
                 let dest_path = base_dest.join(&name);
 
                 catalog_entries.push(CatalogEntry {
@@ -213,6 +274,8 @@ fn enumerate_entry_assets(entry: &Entry, manifest_dir: &Path) -> Result<Vec<Cata
                     kind: AssetKind::CursorRules,
                     destination: format!("./{}", dest_path.display()),
                     short_description,
+                    metadata,
+                    provenance: None,
                 });
             }
         }
@@ -239,6 +302,8 @@ fn enumerate_entry_assets(entry: &Entry, manifest_dir: &Path) -> Result<Vec<Cata
                     kind: entry.kind.clone(),
                     destination: format!("./{}", dest_path.display()),
                     short_description: None,
+                    metadata: BTreeMap::new(),
+                    provenance: None,
                 });
             }
         }
@@ -256,6 +321,7 @@ fn enumerate_entry_assets(entry: &Entry, manifest_dir: &Path) -> Result<Vec<Cata
                 }
 
                 let short_description = extract_cursor_skill_description(&folder_path);
+                let metadata = extract_file_metadata(&folder_path.join("SKILL.md"));
                 let dest_path = base_dest.join(&name);
 
                 catalog_entries.push(CatalogEntry {
@@ -264,6 +330,8 @@ fn enumerate_entry_assets(entry: &Entry, manifest_dir: &Path) -> Result<Vec<Cata
                     kind: AssetKind::CursorSkillsRoot,
                     destination: format!("./{}", dest_path.display()),
                     short_description,
+                    metadata,
+                    provenance: None,
                 });
             }
         }
@@ -281,6 +349,7 @@ fn enumerate_entry_assets(entry: &Entry, manifest_dir: &Path) -> Result<Vec<Cata
                 }
 
                 let short_description = extract_agent_skill_description(&folder_path);
+                let metadata = extract_file_metadata(&folder_path.join("SKILL.md"));
                 let dest_path = base_dest.join(&name);
 
                 catalog_entries.push(CatalogEntry {
@@ -289,11 +358,17 @@ fn enumerate_entry_assets(entry: &Entry, manifest_dir: &Path) -> Result<Vec<Cata
                     kind: AssetKind::AgentSkill,
                     destination: format!("./{}", dest_path.display()),
                     short_description,
+                    metadata,
+                    provenance: None,
                 });
             }
         }
     }
 
+    for catalog_entry in &mut catalog_entries {
+        catalog_entry.provenance = Some(provenance.clone());
+    }
+
     Ok(catalog_entries)
 }
 
@@ -395,6 +470,71 @@ fn extract_frontmatter_description(content: &str) -> Option<String> {
     None
 }
 
+/// Read the frontmatter metadata of a file (empty if unreadable or absent)
+fn extract_file_metadata(path: &Path) -> BTreeMap<String, serde_yaml::Value> {
+    std::fs::read_to_string(path)
+        .map(|content| parse_frontmatter(&content))
+        .unwrap_or_default()
+}
+
+/// Parse leading frontmatter into a key-value map.
+///
+/// Supports YAML fenced by `---` and TOML fenced by `+++`. Only top-level
+/// `key = value` pairs are read from TOML; values use the inline syntax TOML
+/// shares with YAML (strings, numbers, booleans, arrays). Content without
+/// frontmatter, or frontmatter that does not parse to a mapping, yields an
+/// empty map.
+pub fn parse_frontmatter(content: &str) -> BTreeMap<String, serde_yaml::Value> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let Some((fence, body)) = ["---", "+++"].iter().find_map(|fence| {
+        let rest = content.strip_prefix(fence)?;
+        let rest = rest
+            .strip_prefix("\r\n")
+            .or_else(|| rest.strip_prefix('\n'))?;
+        let end = rest
+            .match_indices(fence)
+            .find(|(i, _)| *i == 0 || rest[..*i].ends_with('\n'))?
+            .0;
+        Some((*fence, &rest[..end]))
+    }) else {
+        return BTreeMap::new();
+    };
+
+    if fence == "+++" {
+        return parse_toml_pairs(body);
+    }
+    match serde_yaml::from_str::<BTreeMap<String, serde_yaml::Value>>(body) {
+        Ok(map) => map,
+        Err(e) => {
+            debug!("Ignoring unparsable YAML frontmatter: {}", e);
+            BTreeMap::new()
+        }
+    }
+}
+
+/// Top-level `key = value` pairs of a TOML frontmatter block
+fn parse_toml_pairs(body: &str) -> BTreeMap<String, serde_yaml::Value> {
+    let mut map = BTreeMap::new();
+    for line in body.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            // Tables are not flattened into the top-level map
+            break;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().trim_matches('"');
+        if key.is_empty() || key.starts_with('#') {
+            continue;
+        }
+        if let Ok(value) = serde_yaml::from_str::<serde_yaml::Value>(value.trim()) {
+            map.insert(key.to_string(), value);
+        }
+    }
+    map
+}
+
 /// Strip YAML frontmatter from content
 fn strip_frontmatter(content: &str) -> String {
     if !content.starts_with("---") {
@@ -622,6 +762,54 @@ mod tests {
         );
     }
 
+    fn rule_entry(id: &str, name: &str) -> CatalogEntry {
+        CatalogEntry {
+            id: id.to_string(),
+            name: name.to_string(),
+            kind: AssetKind::CursorRules,
+            destination: format!("./.cursor/rules/{}", name),
+            short_description: None,
+            metadata: BTreeMap::new(),
+            provenance: None,
+        }
+    }
+
+    #[test]
+    fn test_duplicate_names() {
+        let catalog = Catalog {
+            version: 1,
+            entries: vec![
+                rule_entry("team:style.mdc", "style.mdc"),
+                rule_entry("org:style.mdc", "style.mdc"),
+                rule_entry("org:lint.mdc", "lint.mdc"),
+            ],
+        };
+        assert_eq!(
+            catalog.duplicate_names(),
+            vec![(
+                "style.mdc".to_string(),
+                vec!["team:style.mdc".to_string(), "org:style.mdc".to_string()]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_provenance_serialized_inline() {
+        let mut entry = rule_entry("org:lint.mdc", "lint.mdc");
+        entry.provenance = Some(Provenance {
+            source: "https://github.com/org/prompts.git".to_string(),
+            resolved_ref: Some("main".to_string()),
+            commit: Some("abc123".to_string()),
+        });
+        let yaml = serde_yaml::to_string(&entry).unwrap();
+        assert!(yaml.contains("source: https://github.com/org/prompts.git"));
+        assert!(yaml.contains("ref: main"));
+        assert!(yaml.contains("commit: abc123"));
+
+        let parsed: CatalogEntry = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(parsed.provenance, entry.provenance);
+    }
+
     #[test]
     fn test_enumerate_files() -> Result<()> {
         let temp_dir = TempDir::new().unwrap();
@@ -720,6 +908,33 @@ other: value
         assert_eq!(extract_frontmatter_description(content), None);
     }
 
+    #[test]
+    fn test_parse_frontmatter_yaml_and_toml() {
+        let yaml = "---\ntitle: Review\ntags: [code, review]\nmodel: gpt-4o\n---\n# Body\n";
+        let metadata = parse_frontmatter(yaml);
+        assert_eq!(metadata["title"], serde_yaml::Value::from("Review"));
+        assert_eq!(
+            metadata["tags"],
+            serde_yaml::from_str::<serde_yaml::Value>("[code, review]").unwrap()
+        );
+        assert_eq!(metadata.len(), 3);
+
+        let toml = "+++\ntitle = \"Review\"\nversion = 2\ndraft = false\n[extra]\nx = 1\n+++\nBody";
+        let metadata = parse_frontmatter(toml);
+        assert_eq!(metadata["title"], serde_yaml::Value::from("Review"));
+        assert_eq!(metadata["version"], serde_yaml::Value::from(2));
+        assert_eq!(metadata["draft"], serde_yaml::Value::from(false));
+        assert!(!metadata.contains_key("x"));
+
+        for content in [
+            "# No frontmatter",
+            "---\nunterminated",
+            "---\n- a list\n---\n",
+        ] {
+            assert!(parse_frontmatter(content).is_empty(), "{}", content);
+        }
+    }
+
     #[test]
     fn test_extract_first_paragraph() {
         let content = r#"# Heading
@@ -1,4 +1,5 @@
 use crate::error::{ApsError, Result};
+use crate::sources::{ResolvedSource, TreeEntryKind};
 use sha2::{Digest, Sha256};
 use std::path::Path;
 use walkdir::WalkDir;
@@ -14,8 +15,10 @@ pub fn compute_checksum(path: &Path) -> Result<String> {
         hasher.update(&content);
     } else if path.is_dir() {
         // Collect all file paths relative to the directory, sorted for determinism
-        // Exclude .git directories since their contents vary between clones
+        // Exclude .git directories since their contents vary between clones.
+        // Symlinks are followed so linked content changes are detected.
         let mut files: Vec<_> = WalkDir::new(path)
+            .follow_links(true)
             .into_iter()
             .filter_map(|e| e.ok())
             .filter(|e| {
@@ -64,3 +67,77 @@ pub fn compute_string_checksum(content: &str) -> String {
     let result = hasher.finalize();
     format!("sha256:{}", hex::encode(result))
 }
+
+/// Content-addressed ID of a resolved source: a SHA256 over the sorted
+/// `(path, mode, content hash)` triples of its files.
+///
+/// Only content, relative paths and modes contribute, so the same files
+/// from different commits, repositories or source types share an ID. With
+/// `include` prefixes, only top-level entries whose name starts with one of
+/// them are hashed (as `include` selects what is installed). Symlinks
+/// contribute their target path; submodules are skipped.
+pub fn snapshot_id(resolved: &ResolvedSource, include: &[String]) -> Result<String> {
+    let single_file = resolved.source_path.is_file();
+    let mut hasher = Sha256::new();
+
+    for entry in resolved.list_tree()? {
+        let included = include.is_empty()
+            || entry
+                .path
+                .split('/')
+                .next()
+                .is_some_and(|first| include.iter().any(|prefix| first.starts_with(prefix)));
+        if !included {
+            continue;
+        }
+        let path = if single_file {
+            resolved.source_path.clone()
+        } else {
+            resolved.source_path.join(&entry.path)
+        };
+        let content = match entry.kind {
+            TreeEntryKind::File | TreeEntryKind::Executable => std::fs::read(&path),
+            TreeEntryKind::Symlink => std::fs::read_link(&path)
+                .map(|target| target.to_string_lossy().replace('\\', "/").into_bytes()),
+            TreeEntryKind::Submodule => continue,
+        }
+        .map_err(|e| ApsError::io(e, format!("Failed to read {:?} for snapshot", path)))?;
+
+        let line = format!(
+            "{:06o} {} {}\0",
+            entry.mode,
+            hex::encode(Sha256::digest(&content)),
+            entry.path
+        );
+        hasher.update(line.as_bytes());
+    }
+
+    Ok(format!("snap:{}", hex::encode(hasher.finalize())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::{FilesystemSource, SourceAdapter};
+
+    fn snapshot(root: &Path, include: &[String]) -> String {
+        let source = FilesystemSource::new(root.to_string_lossy().to_string(), false, None);
+        snapshot_id(&source.resolve(root).unwrap(), include).unwrap()
+    }
+
+    #[test]
+    fn test_snapshot_id_depends_only_on_content() {
+        let (a, b) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        for dir in [a.path(), b.path()] {
+            std::fs::create_dir(dir.join("rules")).unwrap();
+            std::fs::write(dir.join("rules/style.md"), "# Style\n").unwrap();
+            std::fs::write(dir.join("README.md"), "readme").unwrap();
+        }
+        assert_eq!(snapshot(a.path(), &[]), snapshot(b.path(), &[]));
+
+        std::fs::write(b.path().join("README.md"), "changed").unwrap();
+        assert_ne!(snapshot(a.path(), &[]), snapshot(b.path(), &[]));
+        let rules = ["rules".to_string()];
+        assert_eq!(snapshot(a.path(), &rules), snapshot(b.path(), &rules));
+    }
+}
@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
@@ -35,8 +35,17 @@ pub enum Commands {
     /// Display status from lockfile
     Status(StatusArgs),
 
+    /// Show which git entries have moved upstream since they were locked
+    Outdated(OutdatedArgs),
+
     /// Catalog operations for asset discovery
     Catalog(CatalogArgs),
+
+    /// Export an entry's source as a zip archive or an OCI image layout
+    Export(ExportArgs),
+
+    /// Create or update a bare mirror of a git repository
+    Mirror(MirrorArgs),
 }
 
 #[derive(Parser, Debug)]
@@ -94,6 +103,17 @@ pub enum ManifestFormat {
     Toml,
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GitignoreMode {
+    /// Leave .gitignore alone
+    #[default]
+    Off,
+    /// List synced destinations in a managed block
+    Ignore,
+    /// Remove the managed block so synced files can be committed
+    Track,
+}
+
 #[derive(Parser, Debug)]
 pub struct SyncArgs {
     /// Path to the manifest file
@@ -126,6 +146,61 @@ pub struct SyncArgs {
     /// Use --upgrade to fetch the latest versions and update the lockfile.
     #[arg(long, short = 'u')]
     pub upgrade: bool,
+
+    /// Verify destinations match their sources without writing anything.
+    ///
+    /// Lists files that a sync would add (+), change (~), or remove (-), and
+    /// exits with an error if any entry is out of date.
+    #[arg(long, conflicts_with_all = ["dry_run", "upgrade"])]
+    pub check: bool,
+
+    /// With --check, also print a unified diff of each changed text file
+    #[arg(long, requires = "check")]
+    pub diff: bool,
+
+    /// Print how long each entry spent resolving, hashing, and installing
+    #[arg(long)]
+    pub timings: bool,
+
+    /// After copying from a git source, compare each file's size with its git tree entry
+    #[arg(long)]
+    pub verify_sizes: bool,
+
+    /// If resolving a git source's ref fails, keep or reinstall the commit
+    /// recorded in the lockfile instead of failing, with a warning
+    #[arg(long)]
+    pub fallback_to_lock: bool,
+
+    /// Copy up to N files at once when installing a directory
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    pub copy_jobs: usize,
+
+    /// After syncing, keep only the newest N backups of each path in .aps-backups
+    #[arg(long, value_name = "N")]
+    pub keep_backups: Option<usize>,
+
+    /// How synced destinations appear in the .gitignore next to the manifest
+    #[arg(long, value_enum, default_value = "off")]
+    pub gitignore: GitignoreMode,
+
+    /// Commit the changed destinations and the lockfile to the git repository
+    /// containing the manifest (nothing is pushed)
+    #[arg(long, conflicts_with_all = ["dry_run", "check"])]
+    pub commit: bool,
+
+    /// Commit message template for --commit; `{count}` and `{sources}` are
+    /// replaced with the number of changed entries and one line per entry
+    #[arg(long, value_name = "TEMPLATE", requires = "commit")]
+    pub commit_message: Option<String>,
+
+    /// Write what changed in the lockfile to FILE as YAML: added, removed
+    /// and changed entries, with the commit range of each update
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["dry_run", "check"])]
+    pub lock_diff: Option<PathBuf>,
+
+    /// Stream progress events to FILE as NDJSON (one JSON object per line)
+    #[arg(long, value_name = "FILE")]
+    pub events: Option<PathBuf>,
 }
 
 #[derive(Parser, Debug)]
@@ -146,6 +221,55 @@ pub struct StatusArgs {
     pub manifest: Option<PathBuf>,
 }
 
+#[derive(Parser, Debug)]
+pub struct OutdatedArgs {
+    /// Path to the manifest file
+    #[arg(long)]
+    pub manifest: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+#[command(group(ArgGroup::new("format").required(true).args(["zip", "oci"])))]
+pub struct ExportArgs {
+    /// ID of the manifest entry to export
+    #[arg(value_name = "ID")]
+    pub id: String,
+
+    /// Path to the manifest file
+    #[arg(long)]
+    pub manifest: Option<PathBuf>,
+
+    /// Write the files an install would copy into a zip archive at FILE,
+    /// under a folder named by the commit's short SHA for git sources
+    #[arg(long, value_name = "FILE")]
+    pub zip: Option<PathBuf>,
+
+    /// With --zip, drop this leading directory from the archived paths
+    #[arg(long, value_name = "PATH", requires = "zip")]
+    pub strip_prefix: Option<PathBuf>,
+
+    /// Write an OCI image layout to DIR, ready to push with
+    /// `oras copy --from-oci-layout`
+    #[arg(long, value_name = "DIR")]
+    pub oci: Option<PathBuf>,
+
+    /// Only export this file or directory, relative to the entry's source
+    #[arg(long, value_name = "PATH", requires = "oci")]
+    pub asset_path: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct MirrorArgs {
+    /// Repository URL to mirror
+    #[arg(value_name = "REPO")]
+    pub repo: String,
+
+    /// Directory of the bare mirror; created on the first run, fetched
+    /// (pruning deleted refs) on later runs
+    #[arg(value_name = "DIR")]
+    pub dir: PathBuf,
+}
+
 #[derive(Parser, Debug)]
 pub struct CatalogArgs {
     #[command(subcommand)]
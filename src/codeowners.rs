@@ -0,0 +1,211 @@
+//! CODEOWNERS parsing and matching.
+//!
+//! Entries with `codeowners: true` record, for each synced file, the owners
+//! the source repository's CODEOWNERS file assigns to it. The file is looked
+//! up in the same locations GitHub uses (`.github/`, the root, then `docs/`),
+//! and the first one found wins.
+//!
+//! Matching follows GitHub's CODEOWNERS rules:
+//! - Later rules take precedence: the last matching pattern wins.
+//! - A pattern starting with `/`, or containing a `/` before its end, is
+//!   anchored to the repository root; otherwise it matches at any depth.
+//! - A trailing `/` matches only directories (and everything beneath them).
+//! - `*` and `?` match within one path segment, `**` matches any number of
+//!   segments.
+//! - A pattern naming a directory owns everything beneath it, except when its
+//!   last segment contains a wildcard (`docs/*` owns direct children only).
+//! - A pattern without owners explicitly leaves matching files unowned.
+
+use std::path::{Path, PathBuf};
+
+/// Locations searched for a CODEOWNERS file, in priority order
+pub const CODEOWNERS_LOCATIONS: &[&str] = &[".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+/// One `pattern owner...` line
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    pattern: String,
+    owners: Vec<String>,
+}
+
+/// Parsed CODEOWNERS file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CodeOwners {
+    rules: Vec<Rule>,
+}
+
+impl CodeOwners {
+    /// Parse CODEOWNERS content, skipping blank lines and comments
+    pub fn parse(content: &str) -> Self {
+        let rules = content
+            .lines()
+            .filter_map(|line| {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    return None;
+                }
+                let mut tokens = line.split_whitespace();
+                let pattern = tokens.next()?.to_string();
+                let owners = tokens
+                    .take_while(|t| !t.starts_with('#'))
+                    .map(str::to_string)
+                    .collect();
+                Some(Rule { pattern, owners })
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// Find and parse the CODEOWNERS file of a repository checkout
+    pub fn load(repo_root: &Path) -> Option<(PathBuf, Self)> {
+        CODEOWNERS_LOCATIONS.iter().find_map(|location| {
+            let path = repo_root.join(location);
+            let content = std::fs::read_to_string(&path).ok()?;
+            Some((path, Self::parse(&content)))
+        })
+    }
+
+    /// Owners of a repository-relative path (`/`-separated); `None` when no
+    /// rule matches or the matching rule lists no owners
+    pub fn owners_for(&self, path: &str) -> Option<&[String]> {
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| pattern_matches(&rule.pattern, &segments))
+            .map(|rule| rule.owners.as_slice())
+            .filter(|owners| !owners.is_empty())
+    }
+}
+
+/// Whether a CODEOWNERS pattern matches a file path
+fn pattern_matches(pattern: &str, path: &[&str]) -> bool {
+    let dir_only = pattern.ends_with('/');
+    let trimmed = pattern.trim_end_matches('/');
+    let anchored = trimmed.starts_with('/') || trimmed.contains('/');
+
+    let mut segments: Vec<&str> = trimmed.split('/').filter(|s| !s.is_empty()).collect();
+    if segments.is_empty() {
+        return false;
+    }
+    let owns_subtree = !has_wildcard(segments[segments.len() - 1]);
+    if !anchored {
+        segments.insert(0, "**");
+    }
+
+    // A file matches the pattern itself, or sits inside a matching directory
+    let file_match = !dir_only && segments_match(&segments, path);
+    let dir_match = (dir_only || owns_subtree)
+        && (1..path.len()).any(|len| segments_match(&segments, &path[..len]));
+    file_match || dir_match
+}
+
+fn has_wildcard(segment: &str) -> bool {
+    segment.contains(['*', '?'])
+}
+
+/// Match pattern segments (which may include `**`) against path segments
+pub(crate) fn segments_match(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| segments_match(rest, &path[skip..])),
+        Some((segment, rest)) => {
+            !path.is_empty() && glob_segment(segment, path[0]) && segments_match(rest, &path[1..])
+        }
+    }
+}
+
+/// Match one segment against `*` / `?` wildcards
+fn glob_segment(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\
+# Default owners
+*       @org/everyone
+*.md    @docs-team   # inline comment
+/build/logs/ @ops
+docs/*  docs@example.com
+apps/   @octocat
+/prompts/**/review.md @reviewers
+/prompts/unowned.md
+";
+
+    fn owners(codeowners: &CodeOwners, path: &str) -> Option<Vec<String>> {
+        codeowners.owners_for(path).map(|o| o.to_vec())
+    }
+
+    #[test]
+    fn test_last_matching_rule_wins() {
+        let codeowners = CodeOwners::parse(SAMPLE);
+        let one = |o: &str| Some(vec![o.to_string()]);
+
+        assert_eq!(owners(&codeowners, "src/main.rs"), one("@org/everyone"));
+        assert_eq!(
+            owners(&codeowners, "deep/nested/README.md"),
+            one("@docs-team")
+        );
+        assert_eq!(owners(&codeowners, "build/logs/a/b.log"), one("@ops"));
+        assert_eq!(
+            owners(&codeowners, "x/build/logs/b.log"),
+            one("@org/everyone")
+        );
+        assert_eq!(
+            owners(&codeowners, "docs/guide.txt"),
+            one("docs@example.com")
+        );
+        assert_eq!(
+            owners(&codeowners, "docs/api/ref.txt"),
+            one("@org/everyone")
+        );
+        assert_eq!(owners(&codeowners, "web/apps/x/main.js"), one("@octocat"));
+        assert_eq!(
+            owners(&codeowners, "prompts/review.md"),
+            one("@reviewers"),
+            "** matches zero directories"
+        );
+        assert_eq!(
+            owners(&codeowners, "prompts/a/b/review.md"),
+            one("@reviewers")
+        );
+        assert_eq!(owners(&codeowners, "prompts/unowned.md"), None);
+    }
+
+    #[test]
+    fn test_glob_segment_wildcards() {
+        assert!(glob_segment("*.md", "AGENTS.md"));
+        assert!(glob_segment("a?c", "abc"));
+        assert!(glob_segment("*-*.txt", "x-y-z.txt"));
+        assert!(!glob_segment("*.md", "AGENTS.mdx"));
+        assert!(!glob_segment("a?c", "ac"));
+    }
+}
@@ -1,22 +1,43 @@
+use crate::annotations::{self, Annotation, AnnotationSink, Level};
+use crate::backup::prune_backups;
 use crate::catalog::Catalog;
+use crate::checksum::snapshot_id;
 use crate::cli::{
-    AddArgs, AddAssetKind, CatalogGenerateArgs, InitArgs, ManifestFormat, StatusArgs, SyncArgs,
-    ValidateArgs,
+    AddArgs, AddAssetKind, CatalogGenerateArgs, ExportArgs, GitignoreMode, InitArgs,
+    ManifestFormat, MirrorArgs, OutdatedArgs, StatusArgs, SyncArgs, ValidateArgs,
 };
+use crate::commit::{commit_sync, DEFAULT_COMMIT_MESSAGE};
+use crate::dest_writer::write_source_matching;
 use crate::error::{ApsError, Result};
+use crate::events::{Events, NdjsonSink, SyncEvent};
 use crate::github_url::parse_github_url;
-use crate::hooks::validate_cursor_hooks;
-use crate::install::{install_composite_entry, install_entry, InstallOptions, InstallResult};
+use crate::gitignore::{destination_patterns, write_managed_block};
+use crate::hooks::cursor_hook_issues;
+use crate::install::{
+    check_file_count, check_notebooks, check_required_files, check_source_kind, entry_transform,
+    install_composite_entry, install_entry, plan_sync, resolve_entry_source, InstallOptions,
+    InstallResult, SourceFilter,
+};
+use crate::lock_diff::diff_locks;
 use crate::lockfile::{display_status, Lockfile};
 use crate::manifest::{
     discover_manifest, load_manifest, manifest_dir, validate_manifest, AssetKind, Entry, Manifest,
-    Source, DEFAULT_MANIFEST_NAME,
+    Source, SymlinkMode, DEFAULT_MANIFEST_NAME,
 };
+use crate::oci::export_oci_layout;
 use crate::orphan::{detect_orphaned_paths, prompt_and_cleanup_orphans};
-use crate::sync_output::{print_sync_results, print_sync_summary, SyncDisplayItem, SyncStatus};
+use crate::redact::redact_url;
+use crate::sources::{mirror_sync, CloneCache};
+use crate::sync_output::{
+    print_check_results, print_plan_diffs, print_sync_results, print_sync_summary,
+    print_sync_timings, print_update_report, SyncDisplayItem, SyncStatus,
+};
+use crate::transform::Substitutions;
+use crate::updates::check_updates;
+use crate::zip_writer::ZipWriter;
 use std::fs;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tracing::info;
 
 /// Execute the `aps init` command
@@ -143,6 +164,32 @@ pub fn cmd_add(args: AddArgs) -> Result<()> {
             r#ref: parsed.git_ref.clone(),
             shallow: true,
             path: Some(skill_path.clone()),
+            as_of: None,
+            message_match: None,
+            follow_redirects: None,
+            fallback_to_default_branch: false,
+            follow_moved: false,
+            client_cert: None,
+            client_key: None,
+            channels: Default::default(),
+            require_signed_tag: false,
+            signing_keys: Vec::new(),
+            strategies: Vec::new(),
+            verify_default_branch: false,
+            default_branches: Vec::new(),
+            user_agent: None,
+            http_headers: Default::default(),
+            connect_timeout: None,
+            transfer_timeout: None,
+            git_config: Default::default(),
+            shallow_since: None,
+            expected_commit: None,
+            index_file: None,
+            submodules: false,
+            submodule_paths: Vec::new(),
+            fetch_notes: false,
+            describe: false,
+            describe_match: None,
         }),
         sources: Vec::new(),
         dest: Some(format!(
@@ -154,6 +201,25 @@ pub fn cmd_add(args: AddArgs) -> Result<()> {
             entry_id
         )),
         include: Vec::new(),
+        required_files: Vec::new(),
+        extra_dests: Vec::new(),
+        placeholders: false,
+        source_symlinks: SymlinkMode::default(),
+        source_kind: None,
+        codeowners: false,
+        licenses: false,
+        last_changed: false,
+        encoding: None,
+        ensure_final_newline: false,
+        trim_trailing_whitespace: false,
+        exclude_vcs: false,
+        max_files: None,
+        metrics: false,
+        validate_notebooks: false,
+        strip_notebook_outputs: false,
+        preserve_mtime: None,
+        include_export_ignored: false,
+        submodule_pins: false,
     };
 
     // Find or create manifest
@@ -197,6 +263,18 @@ pub fn cmd_add(args: AddArgs) -> Result<()> {
                             dry_run: false,
                             strict: false,
                             upgrade: false,
+                            check: false,
+                            diff: false,
+                            timings: false,
+                            copy_jobs: 1,
+                            keep_backups: None,
+                            gitignore: GitignoreMode::Off,
+                            commit: false,
+                            commit_message: None,
+                            lock_diff: None,
+                            events: None,
+                            verify_sizes: false,
+                            fallback_to_lock: false,
                         })?;
                     } else {
                         println!("Run `aps sync` to install the skill.");
@@ -246,6 +324,18 @@ pub fn cmd_add(args: AddArgs) -> Result<()> {
             dry_run: false,
             strict: false,
             upgrade: false,
+            check: false,
+            diff: false,
+            timings: false,
+            copy_jobs: 1,
+            keep_backups: None,
+            gitignore: GitignoreMode::Off,
+            commit: false,
+            commit_message: None,
+            lock_diff: None,
+            events: None,
+            verify_sizes: false,
+            fallback_to_lock: false,
         })?;
     } else {
         println!("Run `aps sync` to install the skill.");
@@ -296,20 +386,72 @@ pub fn cmd_sync(args: SyncArgs) -> Result<()> {
         yes: args.yes,
         strict: args.strict,
         upgrade: args.upgrade,
+        verify_sizes: args.verify_sizes,
+        fallback_to_lock: args.fallback_to_lock,
+        copy_jobs: args.copy_jobs,
+        clones: CloneCache::new(),
+        events: {
+            let events = match args.events {
+                Some(ref path) => Events::new(NdjsonSink::create(path)?),
+                None => Events::default(),
+            };
+            if annotations::enabled() {
+                events.with(AnnotationSink)
+            } else {
+                events
+            }
+        },
     };
 
+    if args.check {
+        let plans = entries_to_install
+            .iter()
+            .map(|entry| plan_sync(entry, &base_dir, &lockfile, &options))
+            .collect::<Result<Vec<_>>>()?;
+        let drifts: Vec<_> = plans.iter().map(|plan| plan.drift()).collect();
+        print_check_results(&drifts, &manifest_path);
+        if args.diff {
+            print_plan_diffs(&plans);
+        }
+
+        let count = plans.iter().filter(|plan| !plan.is_empty()).count();
+        if count > 0 {
+            return Err(ApsError::SyncCheckFailed { count });
+        }
+        return Ok(());
+    }
+
     // Detect orphaned paths (destinations that changed)
     let orphans = detect_orphaned_paths(&entries_to_install, &lockfile, &base_dir);
 
     // Install selected entries
     let mut results: Vec<InstallResult> = Vec::new();
     for entry in &entries_to_install {
+        options.events.emit(SyncEvent::SourceStarted {
+            id: entry.id.clone(),
+        });
         // Use composite install for composite entries, regular install otherwise
         let result = if entry.is_composite() {
-            install_composite_entry(entry, &base_dir, &lockfile, &options)?
+            install_composite_entry(entry, &base_dir, &lockfile, &options)
         } else {
-            install_entry(entry, &base_dir, &lockfile, &options)?
+            install_entry(entry, &base_dir, &lockfile, &options)
         };
+        let result = result.inspect_err(|e| {
+            options.events.emit(SyncEvent::Error {
+                id: entry.id.clone(),
+                message: e.to_string(),
+            })
+        })?;
+        for warning in &result.warnings {
+            options.events.emit(SyncEvent::Warning {
+                id: entry.id.clone(),
+                message: warning.clone(),
+            });
+        }
+        options.events.emit(SyncEvent::SourceCompleted {
+            id: entry.id.clone(),
+            changed: !result.skipped_no_change,
+        });
         results.push(result);
     }
 
@@ -322,6 +464,7 @@ pub fn cmd_sync(args: SyncArgs) -> Result<()> {
 
     // Update lockfile with results
     if !args.dry_run {
+        let previous = args.lock_diff.is_some().then(|| lockfile.clone());
         for result in &results {
             if let Some(ref locked_entry) = result.locked_entry {
                 lockfile.upsert(result.id.clone(), locked_entry.clone());
@@ -342,12 +485,50 @@ pub fn cmd_sync(args: SyncArgs) -> Result<()> {
 
         // Save lockfile
         lockfile.save(&lockfile_path)?;
+
+        if let (Some(ref path), Some(previous)) = (&args.lock_diff, previous) {
+            let mut diff = diff_locks(&previous, &lockfile);
+            diff.count_commits(&options.clones);
+            diff.save(path)?;
+        }
+
+        if let Some(keep) = args.keep_backups {
+            let pruned = prune_backups(&base_dir, keep)?;
+            if !pruned.is_empty() {
+                println!("Removed {} old backup(s)", pruned.len());
+            }
+        }
+
+        if args.gitignore != GitignoreMode::Off {
+            let patterns = destination_patterns(&manifest.entries, &base_dir);
+            let patterns = (args.gitignore == GitignoreMode::Ignore).then_some(&patterns[..]);
+            if write_managed_block(&base_dir.join(".gitignore"), patterns)? {
+                println!("Updated the aps block in .gitignore");
+            }
+        }
+
+        if args.commit {
+            let template = args
+                .commit_message
+                .as_deref()
+                .unwrap_or(DEFAULT_COMMIT_MESSAGE);
+            let gitignore = base_dir.join(".gitignore");
+            let mut files = vec![lockfile_path.as_path()];
+            if args.gitignore != GitignoreMode::Off {
+                files.push(&gitignore);
+            }
+            let commit_dests = args.gitignore != GitignoreMode::Ignore;
+            if let Some(sha) = commit_sync(&base_dir, &results, commit_dests, &files, template)? {
+                println!("Committed synced changes as {}", &sha[..8.min(sha.len())]);
+            }
+        }
     }
 
     // Convert results to display items
+    // Entries with extra destinations get one display item per destination
     let display_items: Vec<SyncDisplayItem> = results
         .iter()
-        .map(|r| {
+        .flat_map(|r| {
             let status = if !r.warnings.is_empty() {
                 SyncStatus::Warning
             } else if r.skipped_no_change && r.upgrade_available.is_some() {
@@ -360,27 +541,33 @@ pub fn cmd_sync(args: SyncArgs) -> Result<()> {
                 SyncStatus::Copied
             };
 
-            let mut item = SyncDisplayItem::new(
-                r.id.clone(),
-                r.dest_path.to_string_lossy().to_string(),
-                status,
-            );
-
-            // Add warning message if present
-            if !r.warnings.is_empty() {
-                item = item.with_message(r.warnings.join(", "));
-            }
+            std::iter::once(&r.dest_path)
+                .chain(&r.extra_dest_paths)
+                .map(|dest| {
+                    let mut item = SyncDisplayItem::new(
+                        r.id.clone(),
+                        dest.to_string_lossy().to_string(),
+                        status,
+                    );
+
+                    // Add warning message if present
+                    if !r.warnings.is_empty() {
+                        item = item.with_message(r.warnings.join(", "));
+                    }
 
-            // Add upgrade info message if available
-            if let Some(ref upgrade_info) = r.upgrade_available {
-                let current_short =
-                    &upgrade_info.current_commit[..8.min(upgrade_info.current_commit.len())];
-                let available_short =
-                    &upgrade_info.available_commit[..8.min(upgrade_info.available_commit.len())];
-                item = item.with_message(format!("{} → {}", current_short, available_short));
-            }
+                    // Add upgrade info message if available
+                    if let Some(ref upgrade_info) = r.upgrade_available {
+                        let current_short = &upgrade_info.current_commit
+                            [..8.min(upgrade_info.current_commit.len())];
+                        let available_short = &upgrade_info.available_commit
+                            [..8.min(upgrade_info.available_commit.len())];
+                        item =
+                            item.with_message(format!("{} → {}", current_short, available_short));
+                    }
 
-            item
+                    item
+                })
+                .collect::<Vec<_>>()
         })
         .collect();
 
@@ -409,6 +596,11 @@ pub fn cmd_sync(args: SyncArgs) -> Result<()> {
         .filter(|i| i.status == SyncStatus::Warning)
         .count();
 
+    if args.timings {
+        let timings: Vec<_> = results.iter().map(|r| (r.id.clone(), r.timings)).collect();
+        print_sync_timings(&timings);
+    }
+
     // Print summary
     print_sync_summary(
         synced_count,
@@ -462,7 +654,7 @@ pub fn cmd_validate(args: ValidateArgs) -> Result<()> {
                                     path: resolved.source_path,
                                 });
                             }
-                            warnings.push(warning);
+                            warnings.push(Annotation::warning(warning).title(&entry.id));
                             all_valid = false;
                         }
                     }
@@ -472,7 +664,7 @@ pub fn cmd_validate(args: ValidateArgs) -> Result<()> {
                             return Err(e);
                         }
                         let warning = format!("Source validation failed: {}", e);
-                        warnings.push(warning);
+                        warnings.push(Annotation::warning(warning).title(&entry.id));
                         all_valid = false;
                     }
                 }
@@ -501,14 +693,14 @@ pub fn cmd_validate(args: ValidateArgs) -> Result<()> {
                     });
                 }
                 println!("  [WARN] {} - {}", entry.id, warning);
-                warnings.push(warning);
+                warnings.push(Annotation::warning(warning).title(&entry.id));
                 continue;
             }
         };
 
         let adapter = source.to_adapter();
         let source_type = adapter.source_type();
-        let display_name = adapter.display_name();
+        let display_name = redact_url(&adapter.display_name());
 
         // For git sources, show progress indicator
         if source_type == "git" {
@@ -534,8 +726,27 @@ pub fn cmd_validate(args: ValidateArgs) -> Result<()> {
                     } else {
                         println!("  [WARN] {} - {}", entry.id, warning);
                     }
-                    warnings.push(warning);
+                    warnings.push(Annotation::warning(warning).title(&entry.id));
                 } else {
+                    if let Err(e) = check_source_kind(entry, &resolved.source_path)
+                        .and_then(|_| check_required_files(entry, &resolved.source_path))
+                        .and_then(|_| check_file_count(entry, &resolved.source_path).map(|_| ()))
+                        .and_then(|_| check_notebooks(entry, &resolved.source_path))
+                    {
+                        if args.strict {
+                            if source_type == "git" {
+                                println!(" FAILED");
+                            }
+                            return Err(e);
+                        }
+                        let warning = e.to_string();
+                        if source_type == "git" {
+                            println!(" WARN");
+                        }
+                        println!("       Warning: {}", warning);
+                        warnings.push(Annotation::for_error(Level::Warning, &e));
+                        continue;
+                    }
                     // Validate skills if applicable
                     if entry.kind == AssetKind::CursorSkillsRoot {
                         let skill_warnings = validate_skills_for_validate(
@@ -543,15 +754,24 @@ pub fn cmd_validate(args: ValidateArgs) -> Result<()> {
                             &entry.id,
                             args.strict,
                         )?;
-                        warnings.extend(skill_warnings);
+                        warnings.extend(
+                            skill_warnings
+                                .into_iter()
+                                .map(|warning| Annotation::warning(warning).title(&entry.id)),
+                        );
                     }
                     if entry.kind == AssetKind::CursorHooks {
-                        let hook_warnings =
-                            validate_cursor_hooks(&resolved.source_path, args.strict)?;
-                        for warning in &hook_warnings {
-                            println!("       Warning: {}", warning);
+                        let issues = cursor_hook_issues(&resolved.source_path);
+                        if args.strict {
+                            if let Some(issue) = issues.into_iter().next() {
+                                return Err(issue);
+                            }
+                        } else {
+                            for issue in &issues {
+                                println!("       Warning: {}", issue);
+                                warnings.push(Annotation::for_error(Level::Warning, issue));
+                            }
                         }
-                        warnings.extend(hook_warnings);
                     }
                     // Format output based on source type
                     if let Some(git_info) = &resolved.git_info {
@@ -576,7 +796,7 @@ pub fn cmd_validate(args: ValidateArgs) -> Result<()> {
                 }
                 let warning = format!("Source validation failed: {}", e);
                 println!("       Warning: {}", warning);
-                warnings.push(warning);
+                warnings.push(Annotation::warning(warning).title(&entry.id));
             }
         }
     }
@@ -594,6 +814,7 @@ pub fn cmd_validate(args: ValidateArgs) -> Result<()> {
             println!("Run with --strict to treat warnings as errors.");
         }
     }
+    annotations::emit_all(&warnings);
 
     Ok(())
 }
@@ -650,6 +871,21 @@ pub fn cmd_status(args: StatusArgs) -> Result<()> {
     Ok(())
 }
 
+/// Execute the `aps outdated` command
+pub fn cmd_outdated(args: OutdatedArgs) -> Result<()> {
+    let (manifest, manifest_path) = discover_manifest(args.manifest.as_deref())?;
+    validate_manifest(&manifest)?;
+
+    let lockfile_path = Lockfile::path_for_manifest(&manifest_path);
+    let lockfile = Lockfile::load(&lockfile_path).unwrap_or_else(|_| Lockfile::new());
+
+    let entries: Vec<&Entry> = manifest.entries.iter().collect();
+    let statuses = check_updates(&entries, &lockfile);
+    print_update_report(&statuses, &manifest_path);
+
+    Ok(())
+}
+
 /// Execute the `aps catalog generate` command
 pub fn cmd_catalog_generate(args: CatalogGenerateArgs) -> Result<()> {
     // Discover and load manifest
@@ -664,6 +900,14 @@ pub fn cmd_catalog_generate(args: CatalogGenerateArgs) -> Result<()> {
     // Generate catalog
     let catalog = Catalog::generate_from_manifest(&manifest, &base_dir)?;
 
+    for (name, ids) in catalog.duplicate_names() {
+        println!(
+            "Warning: '{}' is provided by multiple entries: {}",
+            name,
+            ids.join(", ")
+        );
+    }
+
     // Determine output path
     let output_path = args
         .output
@@ -691,3 +935,112 @@ pub fn cmd_catalog_generate(args: CatalogGenerateArgs) -> Result<()> {
 
     Ok(())
 }
+
+/// Execute the `aps export` command
+pub fn cmd_export(args: ExportArgs) -> Result<()> {
+    let (manifest, manifest_path) = discover_manifest(args.manifest.as_deref())?;
+    let base_dir = manifest_dir(&manifest_path);
+    validate_manifest(&manifest)?;
+
+    let entry = manifest
+        .entries
+        .iter()
+        .find(|e| e.id == args.id)
+        .ok_or_else(|| ApsError::EntryNotFound {
+            id: args.id.clone(),
+        })?;
+    let source = entry
+        .source
+        .as_ref()
+        .ok_or_else(|| ApsError::EntryRequiresSource {
+            id: entry.id.clone(),
+        })?;
+
+    // Export what a sync would install: the locked commit, when there is one
+    let lockfile = Lockfile::load(&Lockfile::path_for_manifest(&manifest_path))
+        .unwrap_or_else(|_| Lockfile::new());
+    let clones = CloneCache::new();
+    let resolved =
+        resolve_entry_source(source, lockfile.entries.get(&entry.id), &base_dir, &clones)?;
+    if !resolved.source_path.exists() {
+        return Err(ApsError::SourcePathNotFound {
+            path: resolved.source_path,
+        });
+    }
+
+    // Both formats get what a sync would install: the same files, transformed
+    let substitutions = entry
+        .placeholders
+        .then(|| Substitutions::from_resolved(&resolved));
+    let transform = entry_transform(entry, substitutions.as_ref());
+    let filter = SourceFilter::for_entry(entry, &resolved)?;
+
+    if let Some(ref archive) = args.zip {
+        let mut writer = ZipWriter::for_source(archive, &resolved)?;
+        if let Some(ref prefix) = args.strip_prefix {
+            writer = writer.strip_prefix(prefix);
+        }
+        // A single-file source is archived under its own name
+        let dest = match resolved.source_path.file_name() {
+            Some(name) if resolved.source_path.is_file() => PathBuf::from(name),
+            _ => PathBuf::new(),
+        };
+        let written = write_source_matching(
+            &resolved,
+            &dest,
+            &entry.include,
+            &filter,
+            &transform,
+            &mut writer,
+        )?;
+        println!(
+            "Exported {} ({} files) to {:?}",
+            entry.id,
+            written.len(),
+            archive
+        );
+    }
+
+    if let Some(ref dir) = args.oci {
+        let asset_path = args.asset_path.as_deref().unwrap_or("");
+        let descriptor = export_oci_layout(
+            &resolved,
+            asset_path,
+            &entry.include,
+            &filter,
+            &transform,
+            dir,
+        )?;
+        println!("Exported {} to OCI layout {:?}", entry.id, dir);
+        println!("  manifest {}", descriptor.digest);
+    }
+
+    // Identical content gets the same ID, whichever commit or repository it
+    // came from
+    println!("  snapshot {}", snapshot_id(&resolved, &entry.include)?);
+
+    Ok(())
+}
+
+/// Execute the `aps mirror` command
+pub fn cmd_mirror(args: MirrorArgs) -> Result<()> {
+    let report = mirror_sync(&args.repo, &args.dir)?;
+    println!(
+        "Mirrored {} to {:?}: {} added, {} updated, {} removed",
+        redact_url(&args.repo),
+        args.dir,
+        report.added.len(),
+        report.updated.len(),
+        report.removed.len()
+    );
+    for (marker, refs) in [
+        ('+', &report.added),
+        ('~', &report.updated),
+        ('-', &report.removed),
+    ] {
+        for name in refs {
+            println!("  {} {}", marker, name);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,206 @@
+//! Committing synced changes to the destination repository.
+//!
+//! With `aps sync --commit`, the destinations that changed during the sync
+//! (and the lockfile) are staged in the git repository that contains the
+//! manifest and committed with a message describing where each entry came
+//! from. Nothing is pushed. Other staged or modified files in the repository
+//! are left out of the commit. When the destinations are gitignored
+//! (`--gitignore ignore`), only the lockfile and `.gitignore` are committed.
+
+use crate::error::{ApsError, Result};
+use crate::install::InstallResult;
+use crate::sources::{GitCli, GitOutput, GitTransport};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Commit message used when `--commit-message` is not given
+pub const DEFAULT_COMMIT_MESSAGE: &str = "Sync {count} aps entries\n\n{sources}";
+
+/// Stage and commit the changes of a sync in the repository containing
+/// `dest_repo`; returns the new commit SHA, or `None` when nothing changed.
+///
+/// `files` (the lockfile, `.gitignore`) are always committed along with the
+/// changed destinations; with `commit_dests` false only `files` are.
+pub fn commit_sync(
+    dest_repo: &Path,
+    results: &[InstallResult],
+    commit_dests: bool,
+    files: &[&Path],
+    message_template: &str,
+) -> Result<Option<String>> {
+    commit_sync_with(
+        &GitCli,
+        dest_repo,
+        results,
+        commit_dests,
+        files,
+        message_template,
+    )
+}
+
+/// [`commit_sync`] using the given transport
+pub fn commit_sync_with(
+    transport: &dyn GitTransport,
+    dest_repo: &Path,
+    results: &[InstallResult],
+    commit_dests: bool,
+    files: &[&Path],
+    message_template: &str,
+) -> Result<Option<String>> {
+    let changed: Vec<&InstallResult> = results.iter().filter(|r| !r.skipped_no_change).collect();
+    if changed.is_empty() {
+        info!("No synced changes to commit");
+        return Ok(None);
+    }
+
+    let toplevel =
+        PathBuf::from(run(transport, dest_repo, &["rev-parse", "--show-toplevel"])?.trim());
+    let toplevel = toplevel.canonicalize().unwrap_or(toplevel);
+
+    let mut paths: Vec<OsString> = Vec::new();
+    for path in changed
+        .iter()
+        .filter(|_| commit_dests)
+        .flat_map(|r| std::iter::once(&r.dest_path).chain(&r.extra_dest_paths))
+        .map(PathBuf::as_path)
+        .chain(files.iter().copied())
+    {
+        let absolute = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        match absolute.strip_prefix(&toplevel) {
+            Ok(relative) => paths.push(relative.as_os_str().to_owned()),
+            Err(_) => warn!("Not committing {:?}: it is outside {:?}", path, toplevel),
+        }
+    }
+    if paths.is_empty() {
+        return Ok(None);
+    }
+
+    let mut add: Vec<OsString> = vec!["add".into(), "-A".into(), "--".into()];
+    add.extend(paths.iter().cloned());
+    run_args(transport, &toplevel, &add)?;
+
+    let mut staged: Vec<OsString> = vec![
+        "diff".into(),
+        "--cached".into(),
+        "--quiet".into(),
+        "--".into(),
+    ];
+    staged.extend(paths.iter().cloned());
+    if git(transport, &toplevel, &staged)?.success {
+        info!("Synced files match the last commit; nothing to commit");
+        return Ok(None);
+    }
+
+    let message = render_commit_message(message_template, &changed);
+    let mut commit: Vec<OsString> = vec![
+        "commit".into(),
+        "-q".into(),
+        "-m".into(),
+        message.into(),
+        "--".into(),
+    ];
+    commit.extend(paths);
+    run_args(transport, &toplevel, &commit)?;
+
+    let sha = run(transport, &toplevel, &["rev-parse", "HEAD"])?;
+    Ok(Some(sha.trim().to_string()))
+}
+
+/// Fill in a commit message template.
+///
+/// `{count}` is replaced with the number of changed entries and `{sources}`
+/// with one `- <id>: <source> @ <commit>` line per entry (the commit is
+/// omitted for sources without one).
+pub fn render_commit_message(template: &str, changed: &[&InstallResult]) -> String {
+    let sources = changed
+        .iter()
+        .map(|r| {
+            let locked = r.locked_entry.as_ref();
+            let source = locked.map(|l| l.source.to_string()).unwrap_or_default();
+            match locked.and_then(|l| l.commit.as_deref()) {
+                Some(commit) => format!("- {}: {} @ {}", r.id, source, commit),
+                None => format!("- {}: {}", r.id, source),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    template
+        .replace("{count}", &changed.len().to_string())
+        .replace("{sources}", &sources)
+}
+
+fn git(transport: &dyn GitTransport, repo: &Path, args: &[OsString]) -> Result<GitOutput> {
+    let mut full: Vec<OsString> = vec!["-C".into(), repo.as_os_str().to_owned()];
+    full.extend(args.iter().cloned());
+    transport.run(&full)
+}
+
+fn run_args(transport: &dyn GitTransport, repo: &Path, args: &[OsString]) -> Result<String> {
+    let output = git(transport, repo, args)?;
+    if !output.success {
+        return Err(ApsError::SyncCommitFailed {
+            message: output.stderr.trim().to_string(),
+        });
+    }
+    Ok(output.stdout)
+}
+
+fn run(transport: &dyn GitTransport, repo: &Path, args: &[&str]) -> Result<String> {
+    let args: Vec<OsString> = args.iter().map(OsString::from).collect();
+    run_args(transport, repo, &args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::install::PhaseTimings;
+    use crate::lockfile::LockedEntry;
+
+    fn result(id: &str, locked: LockedEntry) -> InstallResult {
+        InstallResult {
+            id: id.to_string(),
+            installed: true,
+            skipped_no_change: false,
+            locked_entry: Some(locked),
+            warnings: Vec::new(),
+            dest_path: PathBuf::from(format!("{}.md", id)),
+            extra_dest_paths: Vec::new(),
+            was_symlink: false,
+            upgrade_available: None,
+            timings: PhaseTimings::default(),
+        }
+    }
+
+    #[test]
+    fn test_render_commit_message_lists_sources() {
+        let git = result(
+            "rules",
+            LockedEntry::new_git(
+                "https://github.com/org/prompts.git",
+                "./rules.md",
+                "main".to_string(),
+                "0123abcd".to_string(),
+                "sha256:0".to_string(),
+            ),
+        );
+        let local = result(
+            "notes",
+            LockedEntry::new_filesystem(
+                "/src/notes",
+                "./notes.md",
+                "sha256:1".to_string(),
+                false,
+                None,
+                Vec::new(),
+            ),
+        );
+
+        assert_eq!(
+            render_commit_message(DEFAULT_COMMIT_MESSAGE, &[&git, &local]),
+            "Sync 2 aps entries\n\n\
+             - rules: https://github.com/org/prompts.git @ 0123abcd\n\
+             - notes: /src/notes"
+        );
+    }
+}
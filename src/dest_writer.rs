@@ -0,0 +1,232 @@
+//! Pluggable destinations for exported content.
+//!
+//! A resolved source can be streamed into any [`DestinationWriter`], such as
+//! the zip archive `aps export --zip` writes. Writers receive paths relative
+//! to their own root, the file bytes (with placeholders already applied), and
+//! a Unix-style mode.
+//!
+//! Only exports go through this trait. `aps sync` still installs with the
+//! filesystem copy in `install.rs` (staging, backups, symlinks, merges), so
+//! sync itself cannot target another writer, and writers have no way to
+//! delete files.
+
+use crate::error::{ApsError, Result};
+use crate::install::SourceFilter;
+use crate::sources::ResolvedSource;
+use crate::transform::{transform_content, ContentTransform};
+use std::path::{Path, PathBuf};
+
+/// Mode reported for files whose permissions cannot be read
+const DEFAULT_MODE: u32 = 0o644;
+
+/// Target that synced files are written to
+pub trait DestinationWriter {
+    /// Write `bytes` to `path`, replacing any existing content
+    fn write_file(&mut self, path: &Path, bytes: &[u8], mode: u32) -> Result<()>;
+
+    /// Flush or commit everything written so far
+    fn finalize(&mut self) -> Result<()>;
+}
+
+/// Stream the files of a resolved source that an install would copy into
+/// `writer` below `dest`: those under a top-level entry matching one of the
+/// `include` prefixes (all when empty) and not left out by `filter` (VCS
+/// metadata, `.apsignore`, `export-ignore`).
+///
+/// A single-file source is written to `dest` itself; a directory source is
+/// written to `dest/<relative path>`. Symlinks are written as the files they
+/// point to and, as in a sync, must resolve inside the source root. Returns
+/// the paths written.
+pub fn write_source_matching(
+    resolved: &ResolvedSource,
+    dest: &Path,
+    include: &[String],
+    filter: &SourceFilter,
+    transform: &ContentTransform<'_>,
+    writer: &mut dyn DestinationWriter,
+) -> Result<Vec<PathBuf>> {
+    stream_files(resolved, dest, transform, writer, |rel| {
+        let included = include.is_empty()
+            || rel
+                .split('/')
+                .next()
+                .is_some_and(|first| include.iter().any(|prefix| first.starts_with(prefix)));
+        included && !filter.skips_file(&resolved.source_path, rel)
+    })
+}
+
+fn stream_files(
+    resolved: &ResolvedSource,
+    dest: &Path,
+    transform: &ContentTransform<'_>,
+    writer: &mut dyn DestinationWriter,
+    wanted: impl Fn(&str) -> bool,
+) -> Result<Vec<PathBuf>> {
+    let single_file = resolved.source_path.is_file();
+    let root = resolved.root.as_deref().unwrap_or(&resolved.source_path);
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let mut written = Vec::new();
+    for rel in resolved.list_files()? {
+        if !single_file && !wanted(&rel) {
+            continue;
+        }
+        let (target, source_file) = if single_file {
+            (dest.to_path_buf(), resolved.source_path.clone())
+        } else {
+            (dest.join(&rel), resolved.source_path.join(&rel))
+        };
+        if !source_file
+            .canonicalize()
+            .is_ok_and(|target| target.starts_with(&root))
+        {
+            return Err(ApsError::UnsupportedSymlink {
+                path: source_file,
+                reason: "points outside the source repository".to_string(),
+            });
+        }
+        let bytes = transform_content(Path::new(&rel), resolved.read_file(&rel)?, transform);
+        writer.write_file(&target, &bytes, file_mode(&source_file))?;
+        written.push(target);
+    }
+    writer.finalize()?;
+    Ok(written)
+}
+
+#[cfg(unix)]
+fn file_mode(path: &Path) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path)
+        .map(|m| m.permissions().mode() & 0o777)
+        .unwrap_or(DEFAULT_MODE)
+}
+
+#[cfg(not(unix))]
+fn file_mode(_path: &Path) -> u32 {
+    DEFAULT_MODE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::Entry;
+    use crate::transform::Substitutions;
+    use std::collections::BTreeMap;
+    use tempfile::tempdir;
+
+    /// In-memory writer standing in for a non-filesystem target
+    #[derive(Default)]
+    struct MemoryWriter {
+        files: BTreeMap<PathBuf, (Vec<u8>, u32)>,
+        finalized: bool,
+    }
+
+    impl DestinationWriter for MemoryWriter {
+        fn write_file(&mut self, path: &Path, bytes: &[u8], mode: u32) -> Result<()> {
+            self.files
+                .insert(path.to_path_buf(), (bytes.to_vec(), mode));
+            Ok(())
+        }
+
+        fn finalize(&mut self) -> Result<()> {
+            self.finalized = true;
+            Ok(())
+        }
+    }
+
+    fn resolved_dir(dir: &Path) -> ResolvedSource {
+        ResolvedSource::filesystem(
+            dir.to_path_buf(),
+            "shared".to_string(),
+            false,
+            ".".to_string(),
+            dir.to_string_lossy().to_string(),
+        )
+        .with_root(dir)
+    }
+
+    #[test]
+    fn test_write_source_matching_streams_files_into_writer() {
+        let temp = tempdir().unwrap();
+        std::fs::create_dir_all(temp.path().join("nested")).unwrap();
+        std::fs::write(temp.path().join("a.md"), "from {{APS_SOURCE}}").unwrap();
+        std::fs::write(temp.path().join("nested/b.md"), "b").unwrap();
+        let resolved = resolved_dir(temp.path());
+        let subs = Substitutions::from_resolved(&resolved);
+        let transform = ContentTransform::new(Some(&subs), None);
+
+        let mut writer = MemoryWriter::default();
+        let written = write_source_matching(
+            &resolved,
+            Path::new("rules"),
+            &[],
+            &SourceFilter::default(),
+            &transform,
+            &mut writer,
+        )
+        .unwrap();
+
+        assert_eq!(
+            written,
+            [
+                PathBuf::from("rules/a.md"),
+                PathBuf::from("rules/nested/b.md")
+            ]
+        );
+        assert_eq!(writer.files[Path::new("rules/a.md")].0, b"from shared");
+        assert!(writer.finalized);
+    }
+
+    #[test]
+    fn test_write_source_matching_honors_include_and_apsignore() {
+        let temp = tempdir().unwrap();
+        std::fs::create_dir_all(temp.path().join("rules/drafts")).unwrap();
+        std::fs::write(temp.path().join("rules/a.md"), "a").unwrap();
+        std::fs::write(temp.path().join("rules/drafts/wip.md"), "wip").unwrap();
+        std::fs::write(temp.path().join("README.md"), "readme").unwrap();
+        std::fs::write(temp.path().join(".apsignore"), "drafts/\n").unwrap();
+        let resolved = resolved_dir(temp.path());
+        let transform = ContentTransform::new(None, None);
+        let entry: Entry = serde_yaml::from_str("id: shared\nkind: agents_md\n").unwrap();
+
+        let mut writer = MemoryWriter::default();
+        let written = write_source_matching(
+            &resolved,
+            Path::new("out"),
+            &["rules".to_string()],
+            &SourceFilter::for_entry(&entry, &resolved).unwrap(),
+            &transform,
+            &mut writer,
+        )
+        .unwrap();
+
+        assert_eq!(written, [PathBuf::from("out/rules/a.md")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_source_matching_rejects_links_outside_the_source() {
+        let temp = tempdir().unwrap();
+        let outside = tempdir().unwrap();
+        std::fs::write(outside.path().join("secret.md"), "secret").unwrap();
+        std::os::unix::fs::symlink(
+            outside.path().join("secret.md"),
+            temp.path().join("link.md"),
+        )
+        .unwrap();
+        let resolved = resolved_dir(temp.path());
+        let transform = ContentTransform::new(None, None);
+
+        let mut writer = MemoryWriter::default();
+        let result = write_source_matching(
+            &resolved,
+            Path::new("out"),
+            &[],
+            &SourceFilter::default(),
+            &transform,
+            &mut writer,
+        );
+
+        assert!(matches!(result, Err(ApsError::UnsupportedSymlink { .. })));
+        assert!(writer.files.is_empty());
+    }
+}
@@ -0,0 +1,202 @@
+//! Line-based unified diffs for previewing text changes.
+//!
+//! The diff is computed from the longest common subsequence of lines, which
+//! is exact but quadratic; files too large for that are shown as a single
+//! hunk replacing every line.
+
+/// Lines of context shown around each change
+const CONTEXT: usize = 3;
+
+/// Largest `old lines × new lines` table computed before falling back to a
+/// whole-file hunk
+const MAX_TABLE: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Keep,
+    Delete,
+    Insert,
+}
+
+/// Whether content should be diffed as text (valid UTF-8 without NUL bytes)
+pub fn is_text(content: &[u8]) -> bool {
+    !content.contains(&0) && std::str::from_utf8(content).is_ok()
+}
+
+/// Unified diff from `old` to `new`, with `---`/`+++` headers naming the two
+/// sides. Returns an empty string when the texts are equal.
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    if old == new {
+        return String::new();
+    }
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let ops = diff_ops(&old_lines, &new_lines);
+
+    let mut out = format!("--- {}\n+++ {}\n", old_label, new_label);
+    for (start, end) in hunk_ranges(&ops) {
+        // Index of the hunk's first line on each side
+        let old_start = ops[..start].iter().filter(|op| **op != Op::Insert).count();
+        let new_start = ops[..start].iter().filter(|op| **op != Op::Delete).count();
+        let old_len = ops[start..end]
+            .iter()
+            .filter(|op| **op != Op::Insert)
+            .count();
+        let new_len = ops[start..end]
+            .iter()
+            .filter(|op| **op != Op::Delete)
+            .count();
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_len),
+            hunk_range(new_start, new_len)
+        ));
+
+        let (mut o, mut n) = (old_start, new_start);
+        for op in &ops[start..end] {
+            match op {
+                Op::Keep => {
+                    out.push_str(&format!(" {}\n", old_lines[o]));
+                    o += 1;
+                    n += 1;
+                }
+                Op::Delete => {
+                    out.push_str(&format!("-{}\n", old_lines[o]));
+                    o += 1;
+                }
+                Op::Insert => {
+                    out.push_str(&format!("+{}\n", new_lines[n]));
+                    n += 1;
+                }
+            }
+        }
+    }
+    out
+}
+
+/// `start,len` as written in a hunk header (start is 1-based, or the line
+/// before an empty range)
+fn hunk_range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, len),
+    }
+}
+
+/// Edit script turning `old` into `new`
+fn diff_ops(old: &[&str], new: &[&str]) -> Vec<Op> {
+    // Common prefix and suffix need no table
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    let mut ops = vec![Op::Keep; prefix];
+    if old_mid.len().saturating_mul(new_mid.len()) > MAX_TABLE {
+        ops.extend(std::iter::repeat_n(Op::Delete, old_mid.len()));
+        ops.extend(std::iter::repeat_n(Op::Insert, new_mid.len()));
+    } else {
+        ops.extend(lcs_ops(old_mid, new_mid));
+    }
+    ops.extend(std::iter::repeat_n(Op::Keep, suffix));
+    ops
+}
+
+/// Edit script from a longest-common-subsequence table
+fn lcs_ops(old: &[&str], new: &[&str]) -> Vec<Op> {
+    let width = new.len() + 1;
+    // lengths[i * width + j]: LCS length of old[i..] and new[j..]
+    let mut lengths = vec![0u32; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i * width + j] = if old[i] == new[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::with_capacity(old.len() + new.len());
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            ops.push(Op::Keep);
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            ops.push(Op::Delete);
+            i += 1;
+        } else {
+            ops.push(Op::Insert);
+            j += 1;
+        }
+    }
+    ops.extend(std::iter::repeat_n(Op::Delete, old.len() - i));
+    ops.extend(std::iter::repeat_n(Op::Insert, new.len() - j));
+    ops
+}
+
+/// `[start, end)` ranges of `ops` to print: each change plus its context,
+/// merging changes whose context overlaps
+fn hunk_ranges(ops: &[Op]) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for (i, _) in ops.iter().enumerate().filter(|(_, op)| **op != Op::Keep) {
+        let start = i.saturating_sub(CONTEXT);
+        let end = (i + 1 + CONTEXT).min(ops.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff_hunks() {
+        let old = "one\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine\nten\n";
+        let new = "one\n2\nthree\nfour\nfive\nsix\nseven\neight\nnine\nten\neleven\n";
+
+        assert_eq!(
+            unified_diff(old, new, "a/rules.md", "b/rules.md"),
+            "\
+--- a/rules.md
++++ b/rules.md
+@@ -1,5 +1,5 @@
+ one
+-two
++2
+ three
+ four
+ five
+@@ -8,3 +8,4 @@
+ eight
+ nine
+ ten
++eleven
+"
+        );
+        assert_eq!(unified_diff(old, old, "a", "b"), "");
+        assert_eq!(
+            unified_diff("", "new\n", "a", "b"),
+            "--- a\n+++ b\n@@ -0,0 +1 @@\n+new\n"
+        );
+    }
+
+    #[test]
+    fn test_is_text() {
+        assert!(is_text("# Title\n".as_bytes()));
+        assert!(!is_text(b"\x89PNG\r\n\x1a\n\x00"));
+        assert!(!is_text(&[0xff, 0xfe, 0x41]));
+    }
+}
@@ -5,7 +5,8 @@
 #![allow(unused_assignments)]
 
 use miette::Diagnostic;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, ApsError>;
@@ -50,6 +51,31 @@ pub enum ApsError {
     #[diagnostic(code(aps::source::path_not_found))]
     SourcePathNotFound { path: PathBuf },
 
+    #[error("Entry '{id}' is missing required files: {files:?}")]
+    #[diagnostic(
+        code(aps::source::missing_required_file),
+        help("Check the source path and ref, or update 'required_files' in the manifest")
+    )]
+    MissingRequiredFile { id: String, files: Vec<String> },
+
+    #[error("Invalid source file path: {path}")]
+    #[diagnostic(
+        code(aps::source::invalid_file_path),
+        help("Use a path relative to the source that stays inside it")
+    )]
+    InvalidSourceFilePath { path: String },
+
+    #[error("Symlink in source cannot be synced: {path}")]
+    #[diagnostic(code(aps::source::unsupported_symlink), help("The link {reason}"))]
+    UnsupportedSymlink { path: PathBuf, reason: String },
+
+    #[error("Out-of-date entries: {count}")]
+    #[diagnostic(
+        code(aps::sync::check_failed),
+        help("Run `aps sync` to update the destinations")
+    )]
+    SyncCheckFailed { count: usize },
+
     #[error("Conflict detected at {path}")]
     #[diagnostic(
         code(aps::install::conflict),
@@ -76,6 +102,17 @@ pub enum ApsError {
         source: std::io::Error,
     },
 
+    #[error("Destination is not writable: {path}")]
+    #[diagnostic(
+        code(aps::install::destination_not_writable),
+        help("Check the permissions of the destination path; the source was readable")
+    )]
+    DestinationNotWritable {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
     #[error("Failed to read lockfile: {message}")]
     #[diagnostic(code(aps::lockfile::read_error))]
     LockfileReadError { message: String },
@@ -98,6 +135,111 @@ pub enum ApsError {
     #[diagnostic(code(aps::git::error))]
     GitError { message: String },
 
+    #[error("Git request was blocked by an HTTP redirect: {message}")]
+    #[diagnostic(
+        code(aps::git::redirect),
+        help("Point `repo` at the canonical URL, or set `follow_redirects: all` on the source")
+    )]
+    GitRedirect { message: String },
+
+    #[error("Repository {url} has moved to {new_url}")]
+    #[diagnostic(
+        code(aps::git::repository_moved),
+        help("Update `repo` to {new_url}, or set `follow_moved: true` on the source to follow the move")
+    )]
+    RepositoryMoved { url: String, new_url: String },
+
+    #[error("Ref '{tag}' is not an acceptable signed release tag: {reason}")]
+    #[diagnostic(
+        code(aps::git::unsigned_release_tag),
+        help("Point `ref` at a GPG-signed annotated tag whose key is in your keyring (and listed in `signing_keys`), or remove `require_signed_tag`")
+    )]
+    UnsignedReleaseTag { tag: String, reason: String },
+
+    #[error("TLS handshake with the git server failed: {message}")]
+    #[diagnostic(
+        code(aps::git::tls),
+        help("Check that `client_cert` and `client_key` on the source point at a readable PEM certificate and matching key that the server accepts")
+    )]
+    GitTls { message: String },
+
+    #[error("Repository access requires SAML single sign-on authorization: {message}")]
+    #[diagnostic(
+        code(aps::git::sso_authorization_required),
+        help("Authorize your token (or SSH key) for the organization's single sign-on: {authorization_url}")
+    )]
+    SsoAuthorizationRequired {
+        authorization_url: String,
+        message: String,
+    },
+
+    #[error("Could not connect to the git daemon: {message}")]
+    #[diagnostic(
+        code(aps::git::daemon_unreachable),
+        help("Check that `git daemon` is running at the host and port in the `git://` URL (9418 by default) and that no firewall blocks it")
+    )]
+    GitDaemonUnreachable { message: String },
+
+    #[error("The git daemon does not serve this repository: {message}")]
+    #[diagnostic(
+        code(aps::git::not_exported),
+        help("Check the path in the `git://` URL against the daemon's base path; the daemon only serves repositories containing a `git-daemon-export-ok` file unless it runs with `--export-all`")
+    )]
+    GitRepoNotExported { message: String },
+
+    #[error("Git {phase} timed out: {message}")]
+    #[diagnostic(
+        code(aps::git::timeout),
+        help("Check that the host is reachable, or raise `connect_timeout` / `transfer_timeout` (seconds) on the source")
+    )]
+    GitTimeout { phase: String, message: String },
+
+    #[error("Git server refused a shallow fetch: {message}")]
+    #[diagnostic(
+        code(aps::git::shallow_refused),
+        help("The server does not serve limited history; set `shallow: false` (and drop `shallow_since`) on the source")
+    )]
+    GitShallowRefused { message: String },
+
+    #[error("Invalid git config '{key}': {reason}")]
+    #[diagnostic(
+        code(aps::git::invalid_config),
+        help("Keys look like `section.name` or `section.subsection.name` (e.g. `http.postBuffer`, `core.longpaths`); values must be a single line")
+    )]
+    InvalidGitConfig { key: String, reason: String },
+
+    #[error("Git fixture {path}: {message}")]
+    #[diagnostic(
+        code(aps::git::fixture),
+        help("Re-record the fixture if the git commands being replayed have changed")
+    )]
+    GitFixture { path: String, message: String },
+
+    #[error("Host not allowed: {host} ({url})")]
+    #[diagnostic(
+        code(aps::git::host_not_allowed),
+        help("The host is rejected because {reason}. Update the source `repo` or the host policy")
+    )]
+    HostNotAllowed {
+        host: String,
+        url: String,
+        reason: String,
+    },
+
+    #[error("Git repository has no commits: {url}")]
+    #[diagnostic(
+        code(aps::git::empty_repository),
+        help("Push at least one commit to the repository, or point `repo` at a populated one")
+    )]
+    EmptyRepository { url: String },
+
+    #[error("Git repository has no branches; its HEAD is a detached commit: {url}")]
+    #[diagnostic(
+        code(aps::git::no_branches),
+        help("Use `ref: auto` to sync the commit HEAD points at, or pin a tag or commit SHA")
+    )]
+    NoBranches { url: String },
+
     #[error("Git ref not found: tried {refs:?}")]
     #[diagnostic(
         code(aps::git::ref_not_found),
@@ -105,6 +247,161 @@ pub enum ApsError {
     )]
     GitRefNotFound { refs: Vec<String> },
 
+    #[error("Ref {git_ref} selects a reflog entry, which only local repositories have")]
+    #[diagnostic(
+        code(aps::git::reflog_not_local),
+        help("Reflog and stash refs such as stash@{{0}} only work with local sources; use a branch, tag or commit for {url}")
+    )]
+    ReflogRefNotLocal { git_ref: String, url: String },
+
+    #[error("Commit prefix {prefix} is ambiguous")]
+    #[diagnostic(
+        code(aps::git::ambiguous_commit),
+        help("Use a longer prefix or the full 40-character commit SHA as `ref`")
+    )]
+    AmbiguousCommit { prefix: String },
+
+    #[error("No commit on '{git_ref}' has a message matching '{pattern}' (searched {searched})")]
+    #[diagnostic(
+        code(aps::git::no_matching_commit_message),
+        help("`message_match` is a POSIX extended regex matched against each line of the commit message")
+    )]
+    NoCommitMatchesMessage {
+        pattern: String,
+        git_ref: String,
+        searched: String,
+    },
+
+    #[error("Ref '{git_ref}' resolved to {actual}, expected {expected}")]
+    #[diagnostic(
+        code(aps::git::unexpected_commit),
+        help("The ref was moved (e.g. force-pushed) or the content was tampered with; check the repository, then update `expected_commit` if the new commit is intended")
+    )]
+    UnexpectedCommit {
+        git_ref: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("Invalid expected_commit '{value}': must be a full commit SHA")]
+    #[diagnostic(
+        code(aps::manifest::invalid_expected_commit),
+        help("Use the full 40-character (or 64-character SHA-256) commit hash, e.g. from `git rev-parse <ref>`")
+    )]
+    InvalidExpectedCommit { value: String },
+
+    #[error("No release tag of {repo} matches {requirement}")]
+    #[diagnostic(
+        code(aps::git::no_matching_version),
+        help("List the release tags with `git ls-remote --tags <repo>`; prerelease tags never match a version requirement")
+    )]
+    NoMatchingVersion { requirement: String, repo: String },
+
+    #[error("Invalid channel index {path}: {reason}")]
+    #[diagnostic(
+        code(aps::git::invalid_channel_index),
+        help("The index file on the default branch must map channel names to a tag, branch or commit SHA, e.g. {{\"stable\": \"v1.2.0\"}}")
+    )]
+    InvalidChannelIndex { path: String, reason: String },
+
+    #[error("Channel '{channel}' is not in {path}")]
+    #[diagnostic(
+        code(aps::git::channel_not_in_index),
+        help("Channels in the index: {available}")
+    )]
+    ChannelNotInIndex {
+        channel: String,
+        path: String,
+        available: String,
+    },
+
+    #[error("Commit not found: {prefix}")]
+    #[diagnostic(
+        code(aps::git::commit_not_found),
+        help("Check that the commit is reachable from a branch or tag of the repository")
+    )]
+    CommitNotFound { prefix: String },
+
+    #[error("Source for entry '{id}' is a {actual}, expected a {expected}: {path:?}")]
+    #[diagnostic(
+        code(aps::source::kind_mismatch),
+        help("Point `path` at a {expected}, or change `source_kind` on the entry")
+    )]
+    SourceKindMismatch {
+        id: String,
+        path: PathBuf,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("Entry '{id}' has more than {limit} files to sync in {path:?}")]
+    #[diagnostic(
+        code(aps::install::too_many_files),
+        help("Check the entry's path and include filters, or raise max_files")
+    )]
+    TooManyFiles {
+        id: String,
+        path: PathBuf,
+        limit: usize,
+    },
+
+    #[error("Entry '{id}' has an invalid notebook {path:?}: {reason}")]
+    #[diagnostic(
+        code(aps::install::invalid_notebook),
+        help("Fix the notebook in the source (re-saving it from Jupyter usually does), or remove validate_notebooks")
+    )]
+    InvalidNotebook {
+        id: String,
+        path: PathBuf,
+        reason: String,
+    },
+
+    #[error("Size mismatch for {path:?}: expected {expected} bytes, found {actual}")]
+    #[diagnostic(
+        code(aps::install::size_mismatch),
+        help("The write was likely truncated (e.g. disk full). Free space and run the sync again")
+    )]
+    SizeMismatch {
+        path: PathBuf,
+        expected: u64,
+        actual: u64,
+    },
+
+    #[error("Unterminated aps block in {path:?}")]
+    #[diagnostic(
+        code(aps::gitignore::unterminated_block),
+        help("Restore the closing `# <<< aps managed block <<<` line, or delete the block so aps can rewrite it")
+    )]
+    UnterminatedGitignoreBlock { path: PathBuf },
+
+    #[error("Failed to commit synced changes: {message}")]
+    #[diagnostic(
+        code(aps::commit::failed),
+        help("Check that the manifest is inside a git repository and that `git commit` works there (user.name and user.email set, no hooks rejecting it)")
+    )]
+    SyncCommitFailed { message: String },
+
+    #[error("Failed to copy {count} files:\n{details}")]
+    #[diagnostic(
+        code(aps::install::copy_failed),
+        help("Fix the listed paths and run the sync again")
+    )]
+    CopyFailed { count: usize, details: String },
+
+    #[error("Invalid as_of timestamp: {value}")]
+    #[diagnostic(
+        code(aps::manifest::invalid_as_of),
+        help("Use an RFC 3339 timestamp (2024-06-01T12:00:00Z) or a date (2024-06-01)")
+    )]
+    InvalidAsOf { value: String },
+
+    #[error("Invalid shallow_since timestamp: {value}")]
+    #[diagnostic(
+        code(aps::manifest::invalid_shallow_since),
+        help("Use an RFC 3339 timestamp (2024-06-01T12:00:00Z) or a date (2024-06-01)")
+    )]
+    InvalidShallowSince { value: String },
+
     #[error("Entry not found: {id}")]
     #[diagnostic(
         code(aps::manifest::entry_not_found),
@@ -130,6 +427,13 @@ pub enum ApsError {
     )]
     CompositeRequiresSources { id: String },
 
+    #[error("Composite entry '{id}' does not support 'extra_dests'")]
+    #[diagnostic(
+        code(aps::manifest::composite_extra_dests),
+        help("Add a separate composite entry for each destination")
+    )]
+    CompositeExtraDests { id: String },
+
     #[error("Entry '{id}' requires a 'source' field")]
     #[diagnostic(
         code(aps::manifest::entry_requires_source),
@@ -173,4 +477,122 @@ impl ApsError {
             source: err,
         }
     }
+
+    /// Wrap an IO error from writing to a destination path, surfacing
+    /// permission problems as [`ApsError::DestinationNotWritable`]
+    pub fn dest_io(err: std::io::Error, path: &Path, context: impl Into<String>) -> Self {
+        if is_permission_error(&err) {
+            ApsError::DestinationNotWritable {
+                path: path.to_path_buf(),
+                source: err,
+            }
+        } else {
+            ApsError::io(err, context)
+        }
+    }
+}
+
+/// Whether an IO error means the path cannot be written (permissions or a
+/// read-only filesystem)
+pub fn is_permission_error(err: &std::io::Error) -> bool {
+    matches!(
+        err.kind(),
+        std::io::ErrorKind::PermissionDenied | std::io::ErrorKind::ReadOnlyFilesystem
+    )
+}
+
+/// Waits before each retry of an IO operation that failed transiently
+const IO_RETRY_DELAYS: [Duration; 3] = [
+    Duration::from_millis(50),
+    Duration::from_millis(200),
+    Duration::from_millis(500),
+];
+
+/// OS error codes for files held by another process: Windows sharing and
+/// lock violations (antivirus scanners and indexers cause these)
+#[cfg(windows)]
+const TRANSIENT_OS_ERRORS: &[i32] = &[32, 33];
+
+/// OS error codes for busy files: `EBUSY` and `ETXTBSY`
+#[cfg(unix)]
+const TRANSIENT_OS_ERRORS: &[i32] = &[16, 26];
+
+#[cfg(not(any(unix, windows)))]
+const TRANSIENT_OS_ERRORS: &[i32] = &[];
+
+/// Whether an IO error may clear up on its own, such as a file locked by
+/// another process or an interrupted call. Permission errors are not.
+pub fn is_transient_io_error(err: &std::io::Error) -> bool {
+    matches!(
+        err.kind(),
+        std::io::ErrorKind::Interrupted
+            | std::io::ErrorKind::WouldBlock
+            | std::io::ErrorKind::TimedOut
+    ) || err
+        .raw_os_error()
+        .is_some_and(|code| TRANSIENT_OS_ERRORS.contains(&code))
+}
+
+/// Run an IO operation, retrying it after a short backoff while it fails
+/// with a transient error; other errors are returned at once
+pub fn retry_transient_io<T>(mut op: impl FnMut() -> std::io::Result<T>) -> std::io::Result<T> {
+    let mut delays = IO_RETRY_DELAYS.iter();
+    loop {
+        match op() {
+            Err(err) if is_transient_io_error(&err) => {
+                let Some(delay) = delays.next() else {
+                    return Err(err);
+                };
+                tracing::debug!("Retrying in {:?} after transient IO error: {}", delay, err);
+                std::thread::sleep(*delay);
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_transient_io_retries_only_transient_errors() {
+        let mut attempts = 0;
+        let result = retry_transient_io(|| {
+            attempts += 1;
+            if attempts < 3 {
+                Err(std::io::Error::from(std::io::ErrorKind::WouldBlock))
+            } else {
+                Ok(attempts)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+
+        let mut attempts = 0;
+        let result: std::io::Result<()> = retry_transient_io(|| {
+            attempts += 1;
+            Err(std::io::Error::from(std::io::ErrorKind::PermissionDenied))
+        });
+        assert_eq!(
+            result.unwrap_err().kind(),
+            std::io::ErrorKind::PermissionDenied
+        );
+        assert_eq!(attempts, 1);
+        assert!(!is_transient_io_error(&std::io::Error::from(
+            std::io::ErrorKind::NotFound
+        )));
+    }
+
+    #[test]
+    fn test_dest_io_maps_permission_errors() {
+        let err = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        let mapped = ApsError::dest_io(err, Path::new("out/AGENTS.md"), "Failed to write");
+        assert!(
+            matches!(mapped, ApsError::DestinationNotWritable { ref path, .. } if path == Path::new("out/AGENTS.md"))
+        );
+
+        let err = std::io::Error::from(std::io::ErrorKind::NotFound);
+        let mapped = ApsError::dest_io(err, Path::new("out/AGENTS.md"), "Failed to write");
+        assert!(matches!(mapped, ApsError::Io { .. }));
+    }
 }
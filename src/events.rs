@@ -0,0 +1,213 @@
+//! Progress events emitted while syncing.
+//!
+//! A sync reports what it is doing through an [`Events`] handle: each entry
+//! starts, resolves its source, copies files and completes (or fails).
+//! Frontends and CI tooling consume these as they happen instead of waiting
+//! for the final summary; `aps sync --events <FILE>` writes them as NDJSON,
+//! one JSON object per line, flushed after every event.
+//!
+//! Git runs with captured output, so `clone_progress` marks the phases of
+//! resolving a git source rather than relaying git's own progress meter.
+
+use crate::error::{ApsError, Result};
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Something that happened during a sync
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncEvent {
+    /// An entry started installing
+    SourceStarted { id: String },
+    /// A phase of resolving a git source began
+    CloneProgress { id: String, message: String },
+    /// A git source resolved to a commit
+    SourceResolved { id: String, sha: String },
+    /// A file was written to a destination
+    FileCopied { id: String, path: PathBuf },
+    /// An entry finished; `changed` is false when it was already up to date
+    SourceCompleted { id: String, changed: bool },
+    /// A non-fatal problem with an entry
+    Warning { id: String, message: String },
+    /// An entry failed; the sync stops after this event
+    Error { id: String, message: String },
+}
+
+impl SyncEvent {
+    /// Event name used as the `event` field
+    pub fn name(&self) -> &'static str {
+        match self {
+            SyncEvent::SourceStarted { .. } => "source_started",
+            SyncEvent::CloneProgress { .. } => "clone_progress",
+            SyncEvent::SourceResolved { .. } => "source_resolved",
+            SyncEvent::FileCopied { .. } => "file_copied",
+            SyncEvent::SourceCompleted { .. } => "source_completed",
+            SyncEvent::Warning { .. } => "warning",
+            SyncEvent::Error { .. } => "error",
+        }
+    }
+
+    /// Serialize as a single-line JSON object:
+    /// `{"event":"<name>","id":"<entry>",...}`
+    pub fn to_json(&self) -> String {
+        let mut json = format!("{{\"event\":\"{}\"", self.name());
+        let mut field = |key: &str, value: &str| {
+            let _ = write!(json, ",\"{}\":{}", key, json_string(value));
+        };
+        match self {
+            SyncEvent::SourceStarted { id } => field("id", id),
+            SyncEvent::CloneProgress { id, message }
+            | SyncEvent::Warning { id, message }
+            | SyncEvent::Error { id, message } => {
+                field("id", id);
+                field("message", message);
+            }
+            SyncEvent::SourceResolved { id, sha } => {
+                field("id", id);
+                field("sha", sha);
+            }
+            SyncEvent::FileCopied { id, path } => {
+                field("id", id);
+                field("path", &path.to_string_lossy());
+            }
+            SyncEvent::SourceCompleted { id, changed } => {
+                field("id", id);
+                let _ = write!(json, ",\"changed\":{}", changed);
+            }
+        }
+        json.push('}');
+        json
+    }
+}
+
+/// Quote and escape a string as a JSON string literal
+pub fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Receiver of sync events; called from the syncing thread and from copy
+/// workers
+pub trait EventSink: Send + Sync {
+    fn emit(&self, event: &SyncEvent);
+}
+
+impl<S: EventSink + ?Sized> EventSink for Arc<S> {
+    fn emit(&self, event: &SyncEvent) {
+        (**self).emit(event);
+    }
+}
+
+/// Writes events as NDJSON, flushing after every line
+pub struct NdjsonSink<W: Write + Send> {
+    out: Mutex<W>,
+}
+
+impl<W: Write + Send> NdjsonSink<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out: Mutex::new(out),
+        }
+    }
+}
+
+impl NdjsonSink<std::fs::File> {
+    /// Create (or truncate) an NDJSON event file
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = std::fs::File::create(path)
+            .map_err(|e| ApsError::io(e, format!("Failed to create event file {:?}", path)))?;
+        Ok(Self::new(file))
+    }
+}
+
+impl<W: Write + Send> EventSink for NdjsonSink<W> {
+    fn emit(&self, event: &SyncEvent) {
+        let mut out = self.out.lock().unwrap_or_else(|e| e.into_inner());
+        // Events are best-effort: a full disk must not fail the sync
+        let _ = writeln!(out, "{}", event.to_json()).and_then(|_| out.flush());
+    }
+}
+
+/// Cheap, cloneable handle to the event sinks; emitting without a sink does
+/// nothing
+#[derive(Clone, Default)]
+pub struct Events {
+    sinks: Vec<Arc<dyn EventSink>>,
+}
+
+impl Events {
+    pub fn new(sink: impl EventSink + 'static) -> Self {
+        Self::default().with(sink)
+    }
+
+    /// Also deliver events to `sink`
+    pub fn with(mut self, sink: impl EventSink + 'static) -> Self {
+        self.sinks.push(Arc::new(sink));
+        self
+    }
+
+    pub fn emit(&self, event: SyncEvent) {
+        for sink in &self.sinks {
+            sink.emit(&event);
+        }
+    }
+}
+
+impl std::fmt::Debug for Events {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Events")
+            .field("sinks", &self.sinks.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_serialize_as_ndjson() {
+        let sink = Arc::new(NdjsonSink::new(Vec::new()));
+        let events = Events::new(sink.clone());
+
+        events.emit(SyncEvent::SourceResolved {
+            id: "rules".to_string(),
+            sha: "0123abcd".to_string(),
+        });
+        events.emit(SyncEvent::Warning {
+            id: "rules".to_string(),
+            message: "say \"hi\"\n\tthen\u{1}leave\\".to_string(),
+        });
+        events.emit(SyncEvent::SourceCompleted {
+            id: "rules".to_string(),
+            changed: true,
+        });
+        Events::default().emit(SyncEvent::SourceStarted {
+            id: "ignored".to_string(),
+        });
+
+        let written = String::from_utf8(sink.out.lock().unwrap().clone()).unwrap();
+        assert_eq!(
+            written,
+            "{\"event\":\"source_resolved\",\"id\":\"rules\",\"sha\":\"0123abcd\"}\n\
+             {\"event\":\"warning\",\"id\":\"rules\",\"message\":\"say \\\"hi\\\"\\n\\tthen\\u0001leave\\\\\"}\n\
+             {\"event\":\"source_completed\",\"id\":\"rules\",\"changed\":true}\n"
+        );
+    }
+}
@@ -0,0 +1,201 @@
+//! Managed `.gitignore` block for synced destinations.
+//!
+//! With `aps sync --gitignore ignore`, the destinations of every manifest
+//! entry are listed in the `.gitignore` next to the manifest, between two
+//! marker comments. Everything outside the markers belongs to the user and is
+//! never touched. With `--gitignore track` the block is removed so synced
+//! files can be committed; `off` (the default) leaves `.gitignore` alone.
+//!
+//! Rewriting the block is idempotent: the file is only written when its
+//! content changes.
+
+use crate::error::{ApsError, Result};
+use crate::manifest::Entry;
+use std::path::{Component, Path};
+
+/// First line of the managed block
+pub const BLOCK_START: &str = "# >>> aps managed block (do not edit) >>>";
+/// Last line of the managed block
+pub const BLOCK_END: &str = "# <<< aps managed block <<<";
+
+/// `.gitignore` patterns for every destination inside `base_dir`, sorted and
+/// de-duplicated. Destinations outside `base_dir` cannot be ignored by its
+/// `.gitignore` and are skipped.
+pub fn destination_patterns(entries: &[Entry], base_dir: &Path) -> Vec<String> {
+    let mut patterns: Vec<String> = entries
+        .iter()
+        .flat_map(|entry| std::iter::once(entry.destination()).chain(entry.extra_destinations()))
+        .filter_map(|dest| {
+            let relative = if dest.is_absolute() {
+                dest.strip_prefix(base_dir).ok()?.to_path_buf()
+            } else {
+                dest
+            };
+            let mut segments = Vec::new();
+            for component in relative.components() {
+                match component {
+                    Component::Normal(segment) => segments.push(escape(&segment.to_string_lossy())),
+                    Component::CurDir => {}
+                    _ => return None,
+                }
+            }
+            (!segments.is_empty()).then(|| format!("/{}", segments.join("/")))
+        })
+        .collect();
+    patterns.sort();
+    patterns.dedup();
+    patterns
+}
+
+/// Escape characters that `.gitignore` treats as wildcards
+fn escape(segment: &str) -> String {
+    let mut escaped = String::with_capacity(segment.len());
+    for c in segment.chars() {
+        if matches!(c, '\\' | '*' | '?' | '[') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Replace (or, with `None` or no patterns, remove) the managed block in
+/// `existing`, leaving every other line as it was
+pub fn render_managed_block(
+    path: &Path,
+    existing: &str,
+    patterns: Option<&[String]>,
+) -> Result<String> {
+    let mut lines: Vec<&str> = existing.lines().collect();
+
+    let position = match lines.iter().position(|l| l.trim_end() == BLOCK_START) {
+        Some(start) => {
+            let len = lines[start..]
+                .iter()
+                .position(|l| l.trim_end() == BLOCK_END)
+                .ok_or_else(|| ApsError::UnterminatedGitignoreBlock {
+                    path: path.to_path_buf(),
+                })?;
+            lines.drain(start..=start + len);
+            Some(start)
+        }
+        None => None,
+    };
+
+    let block: Vec<&str> = match patterns {
+        Some(patterns) if !patterns.is_empty() => std::iter::once(BLOCK_START)
+            .chain(patterns.iter().map(String::as_str))
+            .chain(std::iter::once(BLOCK_END))
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    match position {
+        Some(start) => {
+            let at_end = start == lines.len() && start > 0 && lines[start - 1].is_empty();
+            if block.is_empty() && at_end {
+                // Drop the separator that was added together with the block
+                lines.pop();
+            } else {
+                lines.splice(start..start, block);
+            }
+        }
+        None if !block.is_empty() => {
+            if lines.last().is_some_and(|l| !l.is_empty()) {
+                lines.push("");
+            }
+            lines.extend(block);
+        }
+        None => {}
+    }
+
+    let mut rendered = lines.join("\n");
+    if !rendered.is_empty() {
+        rendered.push('\n');
+    }
+    Ok(rendered)
+}
+
+/// Update the managed block of the `.gitignore` at `path`; returns whether
+/// the file changed
+pub fn write_managed_block(path: &Path, patterns: Option<&[String]>) -> Result<bool> {
+    let existing = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(ApsError::io(e, "Failed to read .gitignore")),
+    };
+
+    let rendered = render_managed_block(path, &existing, patterns)?;
+    if rendered == existing {
+        return Ok(false);
+    }
+    std::fs::write(path, rendered).map_err(|e| ApsError::io(e, "Failed to write .gitignore"))?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_managed_block_is_idempotent_and_keeps_user_lines() {
+        let path = Path::new(".gitignore");
+        let user = "target/\n# my notes\n*.log\n";
+        let first = patterns(&["/AGENTS.md", "/.claude/skills/review"]);
+
+        let added = render_managed_block(path, user, Some(&first)).unwrap();
+        assert_eq!(
+            added,
+            format!(
+                "{}\n{}\n/AGENTS.md\n/.claude/skills/review\n{}\n",
+                user, BLOCK_START, BLOCK_END
+            )
+        );
+        assert_eq!(
+            render_managed_block(path, &added, Some(&first)).unwrap(),
+            added
+        );
+
+        // The block is replaced in place; lines after it survive
+        let edited = format!("{}/dist\n", added);
+        let updated = render_managed_block(path, &edited, Some(&patterns(&["/x.md"]))).unwrap();
+        assert_eq!(
+            updated,
+            format!("{}\n{}\n/x.md\n{}\n/dist\n", user, BLOCK_START, BLOCK_END)
+        );
+
+        // Removing the block restores the user's file
+        assert_eq!(render_managed_block(path, &added, None).unwrap(), user);
+        assert_eq!(render_managed_block(path, "", None).unwrap(), "");
+
+        let broken = format!("{}\n/AGENTS.md\n", BLOCK_START);
+        assert!(matches!(
+            render_managed_block(path, &broken, None),
+            Err(ApsError::UnterminatedGitignoreBlock { .. })
+        ));
+    }
+
+    #[test]
+    fn test_destination_patterns_skip_paths_outside_base_dir() {
+        let yaml = "\
+- id: a
+  kind: agents_md
+  source: { type: filesystem, root: /src, path: AGENTS.md }
+  dest: ./AGENTS.md
+  extra_dests: [/work/project/docs/AGENTS.md, ../other/AGENTS.md, /elsewhere/AGENTS.md]
+- id: b
+  kind: cursor_rules
+  source: { type: filesystem, root: /src, path: rules }
+  dest: ./.cursor/rules/[draft]
+";
+        let entries: Vec<Entry> = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(
+            destination_patterns(&entries, Path::new("/work/project")),
+            ["/.cursor/rules/\\[draft]", "/AGENTS.md", "/docs/AGENTS.md"]
+        );
+    }
+}
@@ -4,45 +4,39 @@ use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 
 pub fn validate_cursor_hooks(hooks_dir: &Path, strict: bool) -> Result<Vec<String>> {
-    validate_hooks(hooks_dir, strict)
+    let issues = cursor_hook_issues(hooks_dir);
+    if strict {
+        if let Some(issue) = issues.into_iter().next() {
+            return Err(issue);
+        }
+        return Ok(Vec::new());
+    }
+    Ok(issues.iter().map(ToString::to_string).collect())
 }
 
-fn validate_hooks(hooks_dir: &Path, strict: bool) -> Result<Vec<String>> {
-    let mut warnings = Vec::new();
+/// Problems with a cursor hooks directory, in the order they were found;
+/// each names the file it is about
+pub fn cursor_hook_issues(hooks_dir: &Path) -> Vec<ApsError> {
+    let mut issues = Vec::new();
 
     let hooks_root = hooks_root_dir(hooks_dir);
     let config_path = hooks_root.join("hooks.json");
     if !config_path.exists() {
-        warn_or_error(
-            &mut warnings,
-            strict,
-            ApsError::MissingHooksConfig {
-                path: config_path.clone(),
-            },
-        )?;
-        return Ok(warnings);
+        issues.push(ApsError::MissingHooksConfig { path: config_path });
+        return issues;
     }
 
     let config_value = match read_hooks_config(&config_path) {
         Ok(value) => value,
         Err(err) => {
-            warn_or_error(&mut warnings, strict, err)?;
-            return Ok(warnings);
+            issues.push(err);
+            return issues;
         }
     };
 
-    let hooks_section = match get_hooks_section(&config_value) {
-        Some(section) => section,
-        None => {
-            warn_or_error(
-                &mut warnings,
-                strict,
-                ApsError::MissingHooksSection {
-                    path: config_path.clone(),
-                },
-            )?;
-            return Ok(warnings);
-        }
+    let Some(hooks_section) = get_hooks_section(&config_value) else {
+        issues.push(ApsError::MissingHooksSection { path: config_path });
+        return issues;
     };
 
     let commands = collect_hook_commands(hooks_section);
@@ -51,15 +45,11 @@ fn validate_hooks(hooks_dir: &Path, strict: bool) -> Result<Vec<String>> {
     for rel_path in referenced_scripts {
         let script_path = hooks_root.join(rel_path);
         if !script_path.is_file() {
-            warn_or_error(
-                &mut warnings,
-                strict,
-                ApsError::HookScriptNotFound { path: script_path },
-            )?;
+            issues.push(ApsError::HookScriptNotFound { path: script_path });
         }
     }
 
-    Ok(warnings)
+    issues
 }
 
 fn hooks_root_dir(hooks_dir: &Path) -> PathBuf {
@@ -169,12 +159,3 @@ fn extract_relative_path(token: &str) -> Option<String> {
 fn trim_token(token: &str) -> &str {
     token.trim_matches(|c: char| matches!(c, '"' | '\'' | ';' | ')' | '(' | ','))
 }
-
-fn warn_or_error(warnings: &mut Vec<String>, strict: bool, error: ApsError) -> Result<()> {
-    if strict {
-        return Err(error);
-    }
-
-    warnings.push(error.to_string());
-    Ok(())
-}
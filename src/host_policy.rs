@@ -0,0 +1,151 @@
+//! Allowlist/denylist of hosts that git sources may contact.
+//!
+//! Locked-down environments can restrict which servers aps talks to by
+//! setting `APS_ALLOWED_HOSTS` and/or `APS_DENIED_HOSTS` to comma-separated
+//! host patterns. Every clone and remote lookup is checked against the policy
+//! before git is invoked.
+//!
+//! Matching rules:
+//! - Hosts are compared case-insensitively, using the same parsing as
+//!   [`canonicalize_repo_url`] (so scp-like `git@host:org/repo` works).
+//! - A pattern without a port matches the host on any port; a pattern with a
+//!   port (`git.example.com:8443`) only matches that port.
+//! - `*.example.com` matches any subdomain of `example.com`, but not
+//!   `example.com` itself.
+//! - The denylist wins over the allowlist. When the allowlist is non-empty,
+//!   hosts not on it are rejected.
+//! - Local paths and `file://` URLs have no host and are always allowed.
+
+use crate::error::{ApsError, Result};
+use crate::redact::redact_url;
+use crate::repo_id::canonicalize_repo_url;
+
+/// Environment variable listing the only hosts git sources may use
+pub const ALLOWED_HOSTS_ENV: &str = "APS_ALLOWED_HOSTS";
+/// Environment variable listing hosts git sources must not use
+pub const DENIED_HOSTS_ENV: &str = "APS_DENIED_HOSTS";
+
+/// Host policy applied before any network access
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostPolicy {
+    allowed: Vec<String>,
+    denied: Vec<String>,
+}
+
+impl HostPolicy {
+    /// Create a policy from allow and deny patterns
+    pub fn new<A, D>(allowed: A, denied: D) -> Self
+    where
+        A: IntoIterator,
+        A::Item: AsRef<str>,
+        D: IntoIterator,
+        D::Item: AsRef<str>,
+    {
+        let normalize = |p: &str| p.trim().to_ascii_lowercase();
+        Self {
+            allowed: allowed
+                .into_iter()
+                .map(|p| normalize(p.as_ref()))
+                .filter(|p| !p.is_empty())
+                .collect(),
+            denied: denied
+                .into_iter()
+                .map(|p| normalize(p.as_ref()))
+                .filter(|p| !p.is_empty())
+                .collect(),
+        }
+    }
+
+    /// Read the policy from `APS_ALLOWED_HOSTS` / `APS_DENIED_HOSTS`
+    pub fn from_env() -> Self {
+        let read = |name: &str| std::env::var(name).unwrap_or_default();
+        let allowed = read(ALLOWED_HOSTS_ENV);
+        let denied = read(DENIED_HOSTS_ENV);
+        Self::new(allowed.split(','), denied.split(','))
+    }
+
+    /// Reject `url` if its host is not permitted
+    pub fn check(&self, url: &str) -> Result<()> {
+        let Some(host) = canonicalize_repo_url(url).host else {
+            return Ok(());
+        };
+
+        let reason = if self.denied.iter().any(|p| host_matches(p, &host)) {
+            Some(format!("it matches {}", DENIED_HOSTS_ENV))
+        } else if !self.allowed.is_empty() && !self.allowed.iter().any(|p| host_matches(p, &host)) {
+            Some(format!("it is not listed in {}", ALLOWED_HOSTS_ENV))
+        } else {
+            None
+        };
+
+        match reason {
+            Some(reason) => Err(ApsError::HostNotAllowed {
+                host,
+                url: redact_url(url),
+                reason,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Whether a lowercased `pattern` matches a canonical `host[:port]`
+fn host_matches(pattern: &str, host: &str) -> bool {
+    let pattern_has_port = pattern.contains(':');
+    let host = if pattern_has_port {
+        host
+    } else {
+        host.split_once(':').map(|(h, _)| h).unwrap_or(host)
+    };
+
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.ends_with('.')),
+        None => pattern == host,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowlist_accepts_listed_hosts_in_any_spelling() {
+        let policy = HostPolicy::new(["GitHub.com", "*.corp.example"], Vec::<String>::new());
+        for url in [
+            "https://github.com/org/repo.git",
+            "git@GITHUB.COM:org/repo.git",
+            "ssh://git@github.com:2222/org/repo",
+            "https://git.corp.example/team/prompts",
+            "/srv/git/local-repo",
+        ] {
+            assert!(policy.check(url).is_ok(), "{}", url);
+        }
+        for url in [
+            "https://gitlab.com/org/repo.git",
+            "https://corp.example/org/repo.git",
+            "git@evil-github.com:org/repo.git",
+        ] {
+            assert!(
+                matches!(policy.check(url), Err(ApsError::HostNotAllowed { .. })),
+                "{}",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn test_denylist_wins_and_respects_ports() {
+        let policy = HostPolicy::new(
+            ["*.example.com"],
+            ["bad.example.com", "git.example.com:8443"],
+        );
+        assert!(policy.check("https://bad.example.com/r.git").is_err());
+        assert!(policy.check("https://git.example.com:8443/r.git").is_err());
+        assert!(policy.check("https://git.example.com/r.git").is_ok());
+        assert!(HostPolicy::default()
+            .check("https://anything.test/r.git")
+            .is_ok());
+    }
+}
@@ -1,17 +1,33 @@
+use crate::apsignore::ApsIgnore;
 use crate::backup::{create_backup, has_conflict};
 use crate::checksum::{compute_source_checksum, compute_string_checksum};
+use crate::codeowners::CodeOwners;
 use crate::compose::{
     compose_markdown, read_source_file, write_composed_file, ComposeOptions, ComposedSource,
 };
-use crate::error::{ApsError, Result};
+use crate::diff::{is_text, unified_diff};
+use crate::error::{is_permission_error, retry_transient_io, ApsError, Result};
+use crate::events::{Events, SyncEvent};
 use crate::hooks::validate_cursor_hooks;
-use crate::lockfile::{LockedEntry, Lockfile};
-use crate::manifest::{AssetKind, Entry};
-use crate::sources::{clone_at_commit, get_remote_commit_sha, GitInfo, ResolvedSource};
+use crate::license::detect_licenses;
+use crate::lockfile::{FileMetrics, LastChange, LockedEntry, Lockfile, SubmodulePin};
+use crate::manifest::{AssetKind, Entry, MtimeSource, Source, SourceKind, SymlinkMode};
+use crate::notebook::{is_notebook, validate_notebook};
+use crate::redact::redact_url;
+use crate::sources::{
+    commit_date, export_ignored_paths, get_remote_commit_sha, get_remote_default_branch,
+    last_changed_commits, list_submodule_pins, CloneCache, CloneIntent, ResolvedSource,
+    TreeEntryKind,
+};
+use crate::transform::{copy_file, ContentTransform, Substitutions};
 use dialoguer::Confirm;
+use std::collections::{BTreeMap, BTreeSet};
 use std::io::IsTerminal;
 use std::path::{Path, PathBuf};
-use tracing::{debug, info};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{debug, info, warn};
 use walkdir::WalkDir;
 
 /// Normalize a path by removing trailing slashes
@@ -34,6 +50,16 @@ pub struct InstallOptions {
     /// When true, fetch latest versions from sources (ignore locked versions)
     /// When false (default), respect locked versions from the lockfile
     pub upgrade: bool,
+    /// Compare copied file sizes with the git tree after installing
+    pub verify_sizes: bool,
+    /// Fall back to the locked commit when resolving a git ref fails
+    pub fallback_to_lock: bool,
+    /// Number of files copied concurrently within a directory tree
+    pub copy_jobs: usize,
+    /// Git clones shared by entries that use the same repository and ref
+    pub clones: CloneCache,
+    /// Receiver of progress events
+    pub events: Events,
 }
 
 /// Handle conflict detection and resolution for a destination path.
@@ -73,8 +99,7 @@ fn handle_conflict(
     }
 
     // Create backup
-    let backup_path = create_backup(manifest_dir, dest_path)?;
-    println!("Created backup at: {:?}", backup_path);
+    println!("{}", create_backup(manifest_dir, dest_path)?);
 
     Ok(true)
 }
@@ -121,8 +146,7 @@ fn handle_partial_conflict(
     }
 
     for path in conflict_paths {
-        let backup_path = create_backup(manifest_dir, path)?;
-        println!("Created backup at: {:?}", backup_path);
+        println!("{}", create_backup(manifest_dir, path)?);
     }
 
     Ok(true)
@@ -137,9 +161,39 @@ pub struct InstallResult {
     pub locked_entry: Option<LockedEntry>,
     pub warnings: Vec<String>,
     pub dest_path: PathBuf,
+    /// Additional destinations that received the same content
+    pub extra_dest_paths: Vec<PathBuf>,
     pub was_symlink: bool,
     /// Whether a newer version is available (for git sources in locked mode)
     pub upgrade_available: Option<UpgradeInfo>,
+    /// Time spent in each phase of the install
+    pub timings: PhaseTimings,
+}
+
+/// Wall-clock time spent in each phase of installing an entry
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PhaseTimings {
+    /// Resolving the source: remote checks, clone and checkout
+    pub resolve: Duration,
+    /// Hashing the resolved source content
+    pub checksum: Duration,
+    /// Copying or symlinking into the destinations
+    pub install: Duration,
+}
+
+impl PhaseTimings {
+    /// Sum of all phases
+    pub fn total(&self) -> Duration {
+        self.resolve + self.checksum + self.install
+    }
+}
+
+impl std::ops::AddAssign for PhaseTimings {
+    fn add_assign(&mut self, other: Self) {
+        self.resolve += other.resolve;
+        self.checksum += other.checksum;
+        self.install += other.install;
+    }
 }
 
 /// Information about an available upgrade
@@ -157,6 +211,7 @@ pub fn install_entry(
     options: &InstallOptions,
 ) -> Result<InstallResult> {
     info!("Processing entry: {}", entry.id);
+    let started = Instant::now();
 
     // Get the source (required for non-composite entries)
     let source = entry
@@ -166,10 +221,20 @@ pub fn install_entry(
             id: entry.id.clone(),
         })?;
 
+    let extra_dest_paths: Vec<PathBuf> = entry
+        .extra_destinations()
+        .iter()
+        .map(|d| manifest_dir.join(d))
+        .collect();
+    let extra_dests_exist = extra_dest_paths.iter().all(|p| p.exists());
+
     // For git sources, handle locked vs upgrade mode
+    let mut fallback_warning = None;
     let resolved = if let Some((repo, git_ref)) = source.git_info() {
         let dest_path = manifest_dir.join(entry.destination());
         let locked_entry = lockfile.entries.get(&entry.id);
+        let advertised = advertised_default_branch(source, repo, &git_ref);
+        let remote_ref = advertised.as_deref().unwrap_or(&git_ref);
 
         // Check if we should use the locked commit
         let use_locked_commit =
@@ -181,7 +246,14 @@ pub fn install_entry(
             let locked_ref = locked.resolved_ref.as_deref().unwrap_or("unknown");
 
             // Check if there's a newer version available on the remote
-            let upgrade_available = match get_remote_commit_sha(repo, git_ref) {
+            // (a source pinned with as_of never moves, and a commit selected
+            // by message need not be the tip, so skip the check)
+            let remote_sha = if source.as_of().is_some() || source.message_match().is_some() {
+                Ok(None)
+            } else {
+                get_remote_commit_sha(repo, remote_ref, &source.clone_options())
+            };
+            let upgrade_available = match remote_sha {
                 Ok(Some(remote_sha)) if remote_sha != *locked_commit => {
                     debug!(
                         "Upgrade available for {}: {} -> {}",
@@ -198,7 +270,7 @@ pub fn install_entry(
             };
 
             // If destination exists and commit matches, we're up to date
-            if dest_path.exists() {
+            if dest_path.exists() && extra_dests_exist && recorded_metadata_matches(entry, locked) {
                 info!(
                     "Entry {} is up to date (using locked commit {})",
                     entry.id,
@@ -212,8 +284,13 @@ pub fn install_entry(
                     locked_entry: None,
                     warnings: Vec::new(),
                     dest_path: dest_path.clone(),
+                    extra_dest_paths: extra_dest_paths.clone(),
                     was_symlink,
                     upgrade_available,
+                    timings: PhaseTimings {
+                        resolve: started.elapsed(),
+                        ..Default::default()
+                    },
                 });
             }
 
@@ -223,32 +300,48 @@ pub fn install_entry(
                 entry.id,
                 &locked_commit[..8.min(locked_commit.len())]
             );
-            let resolved_git = clone_at_commit(repo, locked_commit, locked_ref)?;
-
-            // Build the path within the cloned repo
-            let path = source
-                .git_path()
-                .map(|p| p.to_string())
-                .unwrap_or_else(|| ".".to_string());
-            let source_path = if path == "." {
-                resolved_git.repo_path.clone()
-            } else {
-                resolved_git.repo_path.join(&path)
-            };
-
-            let git_info = GitInfo {
-                resolved_ref: resolved_git.resolved_ref.clone(),
-                commit_sha: resolved_git.commit_sha.clone(),
-            };
-
-            ResolvedSource::git(source_path, repo.to_string(), git_info, resolved_git)
+            options.events.emit(SyncEvent::CloneProgress {
+                id: entry.id.clone(),
+                message: format!(
+                    "Cloning {} at locked commit {}",
+                    redact_url(repo),
+                    &locked_commit[..8.min(locked_commit.len())]
+                ),
+            });
+            resolve_locked_git(source, locked_commit, locked_ref, &options.clones)?
         } else {
             // Upgrade mode or no locked commit: check remote and clone latest
-            // Fast-path: skip if remote commit matches lockfile and dest exists
-            if dest_path.exists() {
-                debug!("Checking remote commit for {} ({})", repo, git_ref);
-                if let Ok(Some(remote_sha)) = get_remote_commit_sha(repo, git_ref) {
-                    if lockfile.commit_matches(&entry.id, &remote_sha) {
+            // Fast-path: skip if remote commit matches lockfile and dest exists,
+            // unless the remote's default branch moved away from the locked one
+            let locked_ref = locked_entry.and_then(|e| e.resolved_ref.as_deref());
+            let default_moved = match (advertised.as_deref(), locked_ref) {
+                (Some(head), Some(locked)) if head != locked => {
+                    warn!(
+                        "Default branch of {} changed from '{}' to '{}'; re-resolving {}",
+                        redact_url(repo),
+                        locked,
+                        head,
+                        entry.id
+                    );
+                    true
+                }
+                _ => false,
+            };
+            // The tip says nothing about a commit selected by `as_of` or
+            // `message_match`; those clone and rely on the checksum instead
+            let selects_older_commit = source.as_of().is_some() || source.message_match().is_some();
+            if dest_path.exists() && extra_dests_exist && !default_moved && !selects_older_commit {
+                debug!(
+                    "Checking remote commit for {} ({})",
+                    redact_url(repo),
+                    remote_ref
+                );
+                if let Ok(Some(remote_sha)) =
+                    get_remote_commit_sha(repo, remote_ref, &source.clone_options())
+                {
+                    if lockfile.commit_matches(&entry.id, &remote_sha)
+                        && locked_entry.is_some_and(|l| recorded_metadata_matches(entry, l))
+                    {
                         info!(
                             "Entry {} is up to date (commit {} unchanged)",
                             entry.id,
@@ -266,8 +359,13 @@ pub fn install_entry(
                             locked_entry: None,
                             warnings: Vec::new(),
                             dest_path: dest_path.clone(),
+                            extra_dest_paths: extra_dest_paths.clone(),
                             was_symlink,
                             upgrade_available: None,
+                            timings: PhaseTimings {
+                                resolve: started.elapsed(),
+                                ..Default::default()
+                            },
                         });
                     }
                     debug!(
@@ -278,8 +376,54 @@ pub fn install_entry(
             }
 
             // Clone latest from branch
-            let adapter = source.to_adapter();
-            adapter.resolve(manifest_dir)?
+            options.events.emit(SyncEvent::CloneProgress {
+                id: entry.id.clone(),
+                message: format!("Cloning {} at {}", redact_url(repo), git_ref),
+            });
+            let intent = clone_intent(entry, locked_entry);
+            match resolve_source(source, manifest_dir, &options.clones, Some(intent)) {
+                Ok(resolved) => resolved,
+                // Policy violations are not outages; never paper over them
+                Err(
+                    e @ (ApsError::HostNotAllowed { .. } | ApsError::UnsignedReleaseTag { .. }),
+                ) => return Err(e),
+                Err(e) => {
+                    let Some((locked, locked_commit)) = locked_entry
+                        .filter(|_| options.fallback_to_lock)
+                        .and_then(|l| Some((l, l.commit.as_ref()?)))
+                    else {
+                        return Err(e);
+                    };
+                    let warning = format!(
+                        "Could not resolve '{}' for entry '{}' ({}); using last-known-good commit {} from the lockfile",
+                        git_ref,
+                        entry.id,
+                        e,
+                        &locked_commit[..8.min(locked_commit.len())]
+                    );
+                    warn!("{}", warning);
+                    if dest_path.exists() && extra_dests_exist {
+                        return Ok(InstallResult {
+                            id: entry.id.clone(),
+                            installed: false,
+                            skipped_no_change: true,
+                            locked_entry: None,
+                            warnings: vec![warning],
+                            dest_path: dest_path.clone(),
+                            extra_dest_paths: extra_dest_paths.clone(),
+                            was_symlink: locked.is_symlink,
+                            upgrade_available: None,
+                            timings: PhaseTimings {
+                                resolve: started.elapsed(),
+                                ..Default::default()
+                            },
+                        });
+                    }
+                    let locked_ref = locked.resolved_ref.as_deref().unwrap_or("unknown");
+                    fallback_warning = Some(warning);
+                    resolve_locked_git(source, locked_commit, locked_ref, &options.clones)?
+                }
+            }
         }
     } else {
         // Non-git source (filesystem): use adapter directly
@@ -287,6 +431,22 @@ pub fn install_entry(
         adapter.resolve(manifest_dir)?
     };
     debug!("Source path: {:?}", resolved.source_path);
+    if let Some(ref git_info) = resolved.git_info {
+        if let Some(strategy) = git_info.strategy {
+            options.events.emit(SyncEvent::CloneProgress {
+                id: entry.id.clone(),
+                message: format!("Cloned with the {} strategy", strategy),
+            });
+        }
+        options.events.emit(SyncEvent::SourceResolved {
+            id: entry.id.clone(),
+            sha: git_info.commit_sha.clone(),
+        });
+    }
+    let mut timings = PhaseTimings {
+        resolve: started.elapsed(),
+        ..Default::default()
+    };
 
     // Verify source exists
     if !resolved.source_path.exists() {
@@ -294,9 +454,86 @@ pub fn install_entry(
             path: resolved.source_path,
         });
     }
+    check_source_kind(entry, &resolved.source_path)?;
+    check_required_files(entry, &resolved.source_path)?;
+    let large_tree = check_file_count(entry, &resolved.source_path)?;
+    check_notebooks(entry, &resolved.source_path)?;
 
     // Compute checksum
-    let checksum = compute_source_checksum(&resolved.source_path)?;
+    let checksum_started = Instant::now();
+    let mut checksum = compute_source_checksum(&resolved.source_path)?;
+    timings.checksum = checksum_started.elapsed();
+
+    // Placeholder values depend on the resolved commit, not only on content,
+    // so fold them into the checksum to re-render when the commit moves
+    let substitutions = if entry.placeholders {
+        let subs = Substitutions::from_resolved(&resolved);
+        checksum = compute_string_checksum(&format!("{}\n{}", checksum, subs.fingerprint()));
+        Some(subs)
+    } else {
+        None
+    };
+    // Changing the encoding changes the installed bytes too
+    if let Some(encoding) = entry.encoding {
+        checksum = compute_string_checksum(&format!("{}\nencoding={}", checksum, encoding));
+    }
+    // So does whitespace normalization
+    if entry.ensure_final_newline || entry.trim_trailing_whitespace {
+        checksum = compute_string_checksum(&format!(
+            "{}\nfinal_newline={} trim_trailing_whitespace={}",
+            checksum, entry.ensure_final_newline, entry.trim_trailing_whitespace
+        ));
+    }
+    // And stripping notebook outputs
+    if entry.strip_notebook_outputs {
+        checksum = compute_string_checksum(&format!("{}\nstrip_notebook_outputs", checksum));
+    }
+    if entry.include_export_ignored {
+        checksum = compute_string_checksum(&format!("{}\ninclude_export_ignored", checksum));
+    }
+    if entry.exclude_vcs {
+        checksum = compute_string_checksum(&format!("{}\nexclude_vcs", checksum));
+    }
+    // Preserved links and materialized copies are different installs
+    if entry.source_symlinks != SymlinkMode::default() {
+        checksum = compute_string_checksum(&format!(
+            "{}\nsource_symlinks={:?}",
+            checksum, entry.source_symlinks
+        ));
+    }
+    // Per-destination filters decide what each extra destination receives
+    for extra in &entry.extra_dests {
+        if let Some(include) = extra.include() {
+            checksum = compute_string_checksum(&format!(
+                "{}\nextra_dest={} include={}",
+                checksum,
+                extra.dest(),
+                include.join(",")
+            ));
+        }
+    }
+    // Modification times are not content, but a changed setting must still
+    // rewrite the files
+    if let Some(mtime) = entry.preserve_mtime {
+        checksum = compute_string_checksum(&format!("{}\npreserve_mtime={}", checksum, mtime));
+    }
+    // Recorded metadata is not content either, but turning it on or off
+    // must update the lockfile
+    if entry.codeowners {
+        checksum = compute_string_checksum(&format!("{}\ncodeowners", checksum));
+    }
+    if entry.licenses {
+        checksum = compute_string_checksum(&format!("{}\nlicenses", checksum));
+    }
+    if entry.last_changed {
+        checksum = compute_string_checksum(&format!("{}\nlast_changed", checksum));
+    }
+    if entry.metrics {
+        checksum = compute_string_checksum(&format!("{}\nmetrics", checksum));
+    }
+    if entry.submodule_pins {
+        checksum = compute_string_checksum(&format!("{}\nsubmodule_pins", checksum));
+    }
     debug!("Source checksum: {}", checksum);
 
     // Resolve destination path
@@ -345,7 +582,7 @@ pub fn install_entry(
             false // No locked entry
         };
 
-        if dest_valid {
+        if dest_valid && extra_dests_exist {
             info!("Entry {} is up to date (checksum match)", entry.id);
             // Get was_symlink from lockfile if available
             let was_symlink = lockfile
@@ -360,8 +597,10 @@ pub fn install_entry(
                 locked_entry: None,
                 warnings: Vec::new(),
                 dest_path: dest_path.clone(),
+                extra_dest_paths: extra_dest_paths.clone(),
                 was_symlink,
                 upgrade_available: None,
+                timings,
             });
         } else {
             debug!(
@@ -388,48 +627,72 @@ pub fn install_entry(
         }
     };
 
-    if should_check_conflict {
-        if matches!(entry.kind, AssetKind::CursorHooks) {
-            let mut conflicts = collect_hook_conflicts(&resolved.source_path, &dest_path)?;
-            if let Some((source_config, dest_config)) =
-                hooks_config_paths(&entry.kind, &resolved.source_path, &dest_path)?
-            {
-                if source_config.exists()
-                    && dest_config.exists()
-                    && !dest_config
+    // Every destination (primary and extra) gets its own conflict check
+    for target in std::iter::once(&dest_path).chain(&extra_dest_paths) {
+        if should_check_conflict {
+            if matches!(entry.kind, AssetKind::CursorHooks) {
+                let mut conflicts = collect_hook_conflicts(&resolved.source_path, target)?;
+                if let Some((source_config, dest_config)) =
+                    hooks_config_paths(&entry.kind, &resolved.source_path, target)?
+                {
+                    if source_config.exists()
+                        && dest_config.exists()
+                        && !dest_config
+                            .symlink_metadata()
+                            .map(|m| m.file_type().is_symlink())
+                            .unwrap_or(false)
+                    {
+                        conflicts.push(dest_config);
+                    }
+                }
+                if target.exists() {
+                    let is_symlink = target
                         .symlink_metadata()
                         .map(|m| m.file_type().is_symlink())
-                        .unwrap_or(false)
-                {
-                    conflicts.push(dest_config);
+                        .unwrap_or(false);
+                    if is_symlink || !target.is_dir() {
+                        conflicts.push(target.clone());
+                    }
                 }
-            }
-            if dest_path.exists() {
-                let is_symlink = dest_path
-                    .symlink_metadata()
-                    .map(|m| m.file_type().is_symlink())
-                    .unwrap_or(false);
-                if is_symlink || !dest_path.is_dir() {
-                    conflicts.push(dest_path.clone());
+                conflicts.sort();
+                conflicts.dedup();
+                let should_proceed =
+                    handle_partial_conflict(target, &conflicts, manifest_dir, options)?;
+                if !should_proceed {
+                    // dry-run mode, skip actual installation but continue
+                }
+            } else {
+                let should_proceed = handle_conflict(target, manifest_dir, options)?;
+                if !should_proceed {
+                    // dry-run mode, skip actual installation but continue
                 }
-            }
-            conflicts.sort();
-            conflicts.dedup();
-            let should_proceed =
-                handle_partial_conflict(&dest_path, &conflicts, manifest_dir, options)?;
-            if !should_proceed {
-                // dry-run mode, skip actual installation but continue
-            }
-        } else {
-            let should_proceed = handle_conflict(&dest_path, manifest_dir, options)?;
-            if !should_proceed {
-                // dry-run mode, skip actual installation but continue
             }
         }
     }
 
+    let mut warnings: Vec<String> = fallback_warning.into_iter().chain(large_tree).collect();
+    if resolved
+        .git_info
+        .as_ref()
+        .is_some_and(|g| g.shallow_refused)
+    {
+        warnings.push(format!(
+            "{} refused a shallow clone; cloned the full history instead",
+            redact_url(&resolved.source_display)
+        ));
+    }
+    if let Some(new_url) = resolved
+        .git_info
+        .as_ref()
+        .and_then(|g| g.final_url.as_deref())
+    {
+        warnings.push(format!(
+            "Repository {} has moved to {}; update `repo` in the manifest",
+            redact_url(&resolved.source_display),
+            redact_url(new_url)
+        ));
+    }
     // Validate skills if this is a skills root
-    let mut warnings = Vec::new();
     if entry.kind == AssetKind::CursorSkillsRoot {
         warnings.extend(validate_skills_root(&resolved.source_path, options.strict)?);
     }
@@ -439,39 +702,150 @@ pub fn install_entry(
             options.strict,
         )?);
     }
+    if entry.placeholders && resolved.use_symlink {
+        warnings.push(format!(
+            "Entry '{}' enables placeholders but is symlinked; placeholders are only replaced in copied files",
+            entry.id
+        ));
+    }
+    if entry.encoding.is_some() && resolved.use_symlink {
+        warnings.push(format!(
+            "Entry '{}' sets an encoding but is symlinked; encodings are only converted in copied files",
+            entry.id
+        ));
+    }
+    if (entry.ensure_final_newline || entry.trim_trailing_whitespace) && resolved.use_symlink {
+        warnings.push(format!(
+            "Entry '{}' normalizes whitespace but is symlinked; only copied files are normalized",
+            entry.id
+        ));
+    }
+    if entry.preserve_mtime.is_some() && resolved.use_symlink {
+        warnings.push(format!(
+            "Entry '{}' sets preserve_mtime but is symlinked; only copied files get commit times",
+            entry.id
+        ));
+    }
+    if entry.strip_notebook_outputs && resolved.use_symlink {
+        warnings.push(format!(
+            "Entry '{}' strips notebook outputs but is symlinked; only copied notebooks are stripped",
+            entry.id
+        ));
+    }
+    let owners = if entry.codeowners {
+        collect_owners(entry, &resolved, &mut warnings)
+    } else {
+        BTreeMap::new()
+    };
+    let last_changed = if entry.last_changed {
+        collect_last_changed(entry, &resolved, &mut warnings)
+    } else {
+        BTreeMap::new()
+    };
+    let metrics = if entry.metrics {
+        collect_metrics(entry, &resolved)
+    } else {
+        BTreeMap::new()
+    };
+    let submodules = if entry.submodule_pins {
+        collect_submodule_pins(entry, &resolved, &mut warnings)
+    } else {
+        BTreeMap::new()
+    };
+    let commit_times = collect_commit_times(entry, &resolved, &last_changed, &mut warnings);
+    let licenses = if entry.licenses {
+        let root = resolved.root.as_deref().unwrap_or(&resolved.source_path);
+        let licenses = detect_licenses(root, &resolved.source_path);
+        if licenses.is_empty() {
+            warnings.push(format!(
+                "Entry '{}' records licenses but the source has no license file",
+                entry.id
+            ));
+        }
+        licenses
+    } else {
+        Vec::new()
+    };
     for warning in &warnings {
         println!("Warning: {}", warning);
     }
+    if let Some(previous) = lockfile.entries.get(&entry.id) {
+        for change in metric_changes(&previous.metrics, &metrics) {
+            println!("Size change in '{}': {}", entry.id, change);
+        }
+    }
 
-    // Perform the install
-    let symlinked_items = if options.dry_run {
-        Vec::new()
-    } else {
-        install_asset(
-            &entry.kind,
-            &resolved.source_path,
-            &dest_path,
-            resolved.use_symlink,
-            &entry.include,
-        )?
-    };
+    // Perform the install, reusing the resolved source for every destination
+    let mut symlinked_items = Vec::new();
+    let install_started = Instant::now();
+    if !options.dry_run {
+        let copy = CopyContext::new(
+            entry_transform(entry, substitutions.as_ref()),
+            entry.source_symlinks,
+            resolved.root.as_deref().unwrap_or(&resolved.source_path),
+            options.copy_jobs,
+            &options.events,
+            &entry.id,
+        )
+        .exclude_vcs(entry.exclude_vcs);
+        let copy = match export_ignored(entry, &resolved)? {
+            Some((root, paths)) => copy.export_ignored(root, paths),
+            None => copy,
+        };
+        for (target, include) in std::iter::once(&dest_path)
+            .chain(&extra_dest_paths)
+            .zip(entry.destination_includes())
+        {
+            symlinked_items.extend(install_asset(
+                &entry.kind,
+                &resolved.source_path,
+                target,
+                resolved.use_symlink,
+                include,
+                &copy,
+            )?);
+
+            if matches!(entry.kind, AssetKind::CursorHooks) {
+                sync_hooks_config(
+                    &entry.kind,
+                    &resolved.source_path,
+                    target,
+                    resolved.use_symlink,
+                )?;
+                if !resolved.use_symlink {
+                    make_shell_scripts_executable(target)?;
+                }
+            }
 
-    if !options.dry_run && matches!(entry.kind, AssetKind::CursorHooks) {
-        sync_hooks_config(
-            &entry.kind,
-            &resolved.source_path,
-            &dest_path,
-            resolved.use_symlink,
-        )?;
-        if !resolved.use_symlink {
-            make_shell_scripts_executable(&dest_path)?;
+            if options.verify_sizes {
+                verify_copied_sizes(entry, &resolved, target)?;
+            }
+            if let (Some(times), false) = (&commit_times, resolved.use_symlink) {
+                apply_commit_times(entry, &resolved, target, times)?;
+            }
         }
     }
 
+    timings.install = install_started.elapsed();
+    debug!(
+        "Timings for {}: resolve {:?}, checksum {:?}, install {:?}",
+        entry.id, timings.resolve, timings.checksum, timings.install
+    );
+
     // Create locked entry from resolved source
     // Store relative path in lockfile for portability across machines
     let relative_dest = entry.destination();
-    let locked_entry = resolved.to_locked_entry(&relative_dest, checksum, symlinked_items);
+    let mut locked_entry = resolved.to_locked_entry(&relative_dest, checksum, symlinked_items);
+    locked_entry.extra_dests = entry
+        .extra_destinations()
+        .iter()
+        .map(|d| d.to_string_lossy().to_string())
+        .collect();
+    locked_entry.owners = owners;
+    locked_entry.licenses = licenses;
+    locked_entry.last_changed = last_changed;
+    locked_entry.submodules = submodules;
+    locked_entry.metrics = metrics;
 
     Ok(InstallResult {
         id: entry.id.clone(),
@@ -480,55 +854,627 @@ pub fn install_entry(
         locked_entry: Some(locked_entry),
         warnings,
         dest_path,
+        extra_dest_paths,
         was_symlink: resolved.use_symlink,
         upgrade_available: None,
+        timings,
     })
 }
 
-/// Install a composite entry (merge multiple sources into one file)
-pub fn install_composite_entry(
+/// Content transforms configured on an entry
+pub fn entry_transform<'a>(
     entry: &Entry,
-    manifest_dir: &Path,
-    lockfile: &Lockfile,
-    options: &InstallOptions,
-) -> Result<InstallResult> {
-    info!("Processing composite entry: {}", entry.id);
+    substitutions: Option<&'a Substitutions>,
+) -> ContentTransform<'a> {
+    ContentTransform::new(substitutions, entry.encoding)
+        .ensure_final_newline(entry.ensure_final_newline)
+        .trim_trailing_whitespace(entry.trim_trailing_whitespace)
+        .strip_notebook_outputs(entry.strip_notebook_outputs)
+}
 
-    if entry.sources.is_empty() {
-        return Err(ApsError::CompositeRequiresSources {
-            id: entry.id.clone(),
-        });
+/// Compare each copied file's size with the size recorded in the git tree.
+///
+/// Catches truncated writes that did not surface as IO errors. Only plain
+/// copies from git sources are checked: symlinked installs have nothing to
+/// compare, and content transforms (placeholders, encodings, whitespace
+/// normalization, notebook stripping) legitimately change sizes. Files that were not copied (e.g. excluded by
+/// `include`) are skipped.
+fn verify_copied_sizes(entry: &Entry, resolved: &ResolvedSource, target: &Path) -> Result<()> {
+    if resolved.git_info.is_none()
+        || resolved.use_symlink
+        || !entry_transform(entry, None).is_identity()
+        || entry.placeholders
+    {
+        return Ok(());
+    }
+    let single_file = resolved.source_path.is_file();
+    for tree_entry in resolved.list_tree()? {
+        let (TreeEntryKind::File | TreeEntryKind::Executable) = tree_entry.kind else {
+            continue;
+        };
+        let Some(expected) = tree_entry.size else {
+            continue;
+        };
+        let path = if single_file {
+            target.to_path_buf()
+        } else {
+            target.join(&tree_entry.path)
+        };
+        let Ok(metadata) = path.symlink_metadata() else {
+            continue;
+        };
+        if metadata.len() != expected {
+            return Err(ApsError::SizeMismatch {
+                path,
+                expected,
+                actual: metadata.len(),
+            });
+        }
     }
+    debug!("Verified file sizes for {} at {:?}", entry.id, target);
+    Ok(())
+}
 
-    // Resolve all sources and collect their content
-    let mut composed_sources: Vec<ComposedSource> = Vec::new();
-    let mut all_checksums: Vec<String> = Vec::new();
+/// Whether `locked` holds the metadata `entry` asks to record, and none it
+/// no longer asks for.
+///
+/// The git fast paths skip an entry before its checksum is computed, so
+/// they check this to pick up a metadata option turned on or off since the
+/// last sync. Metadata that came out empty looks unrecorded; such an entry
+/// is resolved again, and the checksum then skips the copy.
+fn recorded_metadata_matches(entry: &Entry, locked: &LockedEntry) -> bool {
+    [
+        (entry.codeowners, locked.owners.is_empty()),
+        (entry.licenses, locked.licenses.is_empty()),
+        (entry.last_changed, locked.last_changed.is_empty()),
+        (entry.metrics, locked.metrics.is_empty()),
+        (entry.submodule_pins, locked.submodules.is_empty()),
+    ]
+    .into_iter()
+    .all(|(wanted, empty)| wanted != empty)
+}
 
-    for source in &entry.sources {
-        let adapter = source.to_adapter();
-        let resolved = adapter.resolve(manifest_dir)?;
+/// The branch the remote's `HEAD` advertises, for `ref: auto` sources with
+/// `verify_default_branch`; `None` otherwise or when it cannot be determined
+fn advertised_default_branch(source: &Source, repo: &str, git_ref: &str) -> Option<String> {
+    if git_ref != "auto" || !source.verify_default_branch() {
+        return None;
+    }
+    get_remote_default_branch(repo, &source.clone_options())
+        .ok()
+        .flatten()
+}
 
-        if !resolved.source_path.exists() {
-            return Err(ApsError::SourcePathNotFound {
-                path: resolved.source_path,
-            });
+/// Map each file of the resolved source to its CODEOWNERS owners.
+///
+/// Paths are relative to the repository (or filesystem) root, the same paths
+/// CODEOWNERS patterns are written against. Honors `include`. A missing
+/// CODEOWNERS file is reported as a warning, not an error.
+fn collect_owners(
+    entry: &Entry,
+    resolved: &ResolvedSource,
+    warnings: &mut Vec<String>,
+) -> BTreeMap<String, Vec<String>> {
+    let mut owners = BTreeMap::new();
+    let root = resolved.root.as_deref().unwrap_or(&resolved.source_path);
+    let Some((path, codeowners)) = CodeOwners::load(root) else {
+        warnings.push(format!(
+            "Entry '{}' records CODEOWNERS but the source has no CODEOWNERS file",
+            entry.id
+        ));
+        return owners;
+    };
+    debug!("Matching owners for {} using {:?}", entry.id, path);
+
+    for rel in source_files(entry, resolved, root) {
+        if let Some(file_owners) = codeowners.owners_for(&rel) {
+            owners.insert(rel, file_owners.to_vec());
         }
+    }
+    owners
+}
 
-        // Read the source file
-        let composed_source = read_source_file(&resolved.source_path)?;
-        composed_sources.push(composed_source);
+/// Paths of a git source that `.gitattributes` marks `export-ignore`, with
+/// the repository root they are relative to; `None` for filesystem sources,
+/// with `include_export_ignored`, or when nothing is marked
+fn export_ignored<'a>(
+    entry: &Entry,
+    resolved: &'a ResolvedSource,
+) -> Result<Option<(&'a Path, BTreeSet<String>)>> {
+    let (Some(root), true) = (resolved.root.as_deref(), resolved.git_info.is_some()) else {
+        return Ok(None);
+    };
+    if entry.include_export_ignored {
+        return Ok(None);
+    }
+    // Without attributes anywhere in the repository nothing can be marked
+    let has_attributes = WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git")
+        .filter_map(|e| e.ok())
+        .any(|e| e.file_name() == ".gitattributes");
+    if !has_attributes {
+        return Ok(None);
+    }
 
-        // Compute and collect checksum for this source
-        let source_checksum = compute_source_checksum(&resolved.source_path)?;
-        all_checksums.push(source_checksum);
+    let paths: Vec<String> = WalkDir::new(&resolved.source_path)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git")
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let rel = e.path().strip_prefix(root).ok()?;
+            (!rel.as_os_str().is_empty()).then(|| rel.to_string_lossy().replace('\\', "/"))
+        })
+        .collect();
+    let ignored = export_ignored_paths(root, &paths)?;
+    if !ignored.is_empty() {
+        debug!(
+            "Skipping {} export-ignore paths of {}",
+            ignored.len(),
+            entry.id
+        );
     }
+    Ok((!ignored.is_empty()).then_some((root, ignored)))
+}
 
-    // Compose all sources into one markdown string
-    let compose_options = ComposeOptions {
-        add_separators: false,
-        include_source_info: false,
+/// Find the commit that last changed each file of a git source.
+///
+/// Paths are relative to the repository root. Honors `include`. History
+/// that cannot be read is reported as a warning, not an error.
+fn collect_last_changed(
+    entry: &Entry,
+    resolved: &ResolvedSource,
+    warnings: &mut Vec<String>,
+) -> BTreeMap<String, LastChange> {
+    let (Some(root), true) = (resolved.root.as_deref(), resolved.git_info.is_some()) else {
+        warnings.push(format!(
+            "Entry '{}' records last-changed commits but is not a git source",
+            entry.id
+        ));
+        return BTreeMap::new();
+    };
+    let pathspec = match resolved.source_path.strip_prefix(root) {
+        Ok(rel) if !rel.as_os_str().is_empty() => rel.to_string_lossy().replace('\\', "/"),
+        _ => ".".to_string(),
+    };
+
+    let files = source_files(entry, resolved, root);
+    match last_changed_commits(root, &pathspec, &files) {
+        Ok(changes) => changes,
+        Err(e) => {
+            warnings.push(format!(
+                "Entry '{}' could not record last-changed commits: {}",
+                entry.id, e
+            ));
+            BTreeMap::new()
+        }
+    }
+}
+
+/// Find the commit each submodule under the source path is pinned to at the
+/// resolved commit. Paths are relative to the repository root; a tree that
+/// cannot be read is reported as a warning, not an error.
+fn collect_submodule_pins(
+    entry: &Entry,
+    resolved: &ResolvedSource,
+    warnings: &mut Vec<String>,
+) -> BTreeMap<String, SubmodulePin> {
+    let (Some(root), Some(git_info)) = (resolved.root.as_deref(), resolved.git_info.as_ref())
+    else {
+        warnings.push(format!(
+            "Entry '{}' records submodule pins but is not a git source",
+            entry.id
+        ));
+        return BTreeMap::new();
+    };
+    let prefix = match resolved.source_path.strip_prefix(root) {
+        Ok(rel) if !rel.as_os_str().is_empty() => Some(rel.to_string_lossy().replace('\\', "/")),
+        _ => None,
+    };
+
+    match list_submodule_pins(root, &git_info.commit_sha) {
+        Ok(pins) => pins
+            .into_iter()
+            .filter(|(path, _)| {
+                prefix.as_deref().is_none_or(|prefix| {
+                    path == prefix || path.starts_with(&format!("{}/", prefix))
+                })
+            })
+            .collect(),
+        Err(e) => {
+            warnings.push(format!(
+                "Entry '{}' could not record submodule pins: {}",
+                entry.id, e
+            ));
+            BTreeMap::new()
+        }
+    }
+}
+
+/// Modification times for `preserve_mtime`
+struct CommitTimes {
+    /// Date of the resolved commit
+    commit: SystemTime,
+    /// With `last_change`, the date of each file's last change, by
+    /// repository-relative path
+    files: BTreeMap<String, SystemTime>,
+}
+
+/// Read the commit dates `preserve_mtime` needs. Reuses `last_changed` when
+/// the entry records it; history that cannot be read is reported as a
+/// warning and leaves modification times alone.
+fn collect_commit_times(
+    entry: &Entry,
+    resolved: &ResolvedSource,
+    last_changed: &BTreeMap<String, LastChange>,
+    warnings: &mut Vec<String>,
+) -> Option<CommitTimes> {
+    let mode = entry.preserve_mtime?;
+    let (Some(root), Some(git_info)) = (resolved.root.as_deref(), resolved.git_info.as_ref())
+    else {
+        warnings.push(format!(
+            "Entry '{}' sets preserve_mtime but is not a git source",
+            entry.id
+        ));
+        return None;
+    };
+    let parse = |date: &str| {
+        chrono::DateTime::parse_from_rfc3339(date)
+            .ok()
+            .map(SystemTime::from)
+    };
+
+    let date = commit_date(root, &git_info.commit_sha);
+    let Some(commit) = date.as_deref().ok().and_then(parse) else {
+        let reason = date.err().map(|e| e.to_string()).unwrap_or_default();
+        warnings.push(format!(
+            "Entry '{}' could not read the commit date for preserve_mtime: {}",
+            entry.id, reason
+        ));
+        return None;
+    };
+    let mut files = BTreeMap::new();
+    if mode == MtimeSource::LastChange {
+        let computed;
+        let changes = if entry.last_changed {
+            last_changed
+        } else {
+            computed = collect_last_changed(entry, resolved, warnings);
+            &computed
+        };
+        for (path, change) in changes {
+            if let Some(time) = parse(&change.date) {
+                files.insert(path.clone(), time);
+            }
+        }
+    }
+    Some(CommitTimes { commit, files })
+}
+
+/// Set the modification time of each file copied to `target` from
+/// `times`, falling back to the commit date
+fn apply_commit_times(
+    entry: &Entry,
+    resolved: &ResolvedSource,
+    target: &Path,
+    times: &CommitTimes,
+) -> Result<()> {
+    let root = resolved.root.as_deref().unwrap_or(&resolved.source_path);
+    let single_file = resolved.source_path.is_file();
+    for rel in source_files(entry, resolved, root) {
+        let path = if single_file {
+            target.to_path_buf()
+        } else {
+            let source_file = root.join(&rel);
+            let Ok(within) = source_file.strip_prefix(&resolved.source_path) else {
+                continue;
+            };
+            target.join(within)
+        };
+        if !path.symlink_metadata().is_ok_and(|m| m.is_file()) {
+            continue;
+        }
+        let time = times.files.get(&rel).copied().unwrap_or(times.commit);
+        std::fs::File::open(&path)
+            .and_then(|file| file.set_modified(time))
+            .map_err(|e| {
+                ApsError::io(e, format!("Failed to set modification time of {:?}", path))
+            })?;
+    }
+    debug!("Set modification times for {} at {:?}", entry.id, target);
+    Ok(())
+}
+
+/// Measure each file of the resolved source selected by `include`.
+///
+/// Paths are relative to the source `path` (its parent for a single-file
+/// source), matching where the files land under the destination.
+fn collect_metrics(entry: &Entry, resolved: &ResolvedSource) -> BTreeMap<String, FileMetrics> {
+    let root = if resolved.source_path.is_file() {
+        resolved
+            .source_path
+            .parent()
+            .unwrap_or(&resolved.source_path)
+    } else {
+        &resolved.source_path
     };
-    let composed_content = compose_markdown(&composed_sources, &compose_options)?;
+    source_files(entry, resolved, root)
+        .into_iter()
+        .filter_map(|rel| {
+            let content = std::fs::read(root.join(&rel)).ok()?;
+            Some((rel, FileMetrics::of(&content)))
+        })
+        .collect()
+}
+
+/// Describe files whose size changed from the locked metrics, and files
+/// that are new. Nothing is reported when the lockfile has no metrics yet.
+fn metric_changes(
+    old: &BTreeMap<String, FileMetrics>,
+    new: &BTreeMap<String, FileMetrics>,
+) -> Vec<String> {
+    if old.is_empty() {
+        return Vec::new();
+    }
+    let delta = |old: u64, new: u64, unit: &str| {
+        format!(
+            "{} -> {} {} ({:+})",
+            old,
+            new,
+            unit,
+            new as i64 - old as i64
+        )
+    };
+    new.iter()
+        .filter_map(|(path, now)| match old.get(path) {
+            None => Some(format!("{} is new ({} bytes)", path, now.bytes)),
+            Some(before) if before == now => None,
+            Some(before) => {
+                let mut parts = Vec::new();
+                if let (Some(old_lines), Some(new_lines)) = (before.lines, now.lines) {
+                    parts.push(delta(old_lines, new_lines, "lines"));
+                }
+                parts.push(delta(before.bytes, now.bytes, "bytes"));
+                Some(format!("{}: {}", path, parts.join(", ")))
+            }
+        })
+        .collect()
+}
+
+/// Files of the resolved source selected by `include`, as `/`-separated
+/// paths relative to `root`
+fn source_files(entry: &Entry, resolved: &ResolvedSource, root: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    let walker = WalkDir::new(&resolved.source_path)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git");
+    for file in walker.filter_map(|e| e.ok()) {
+        if !file.file_type().is_file() {
+            continue;
+        }
+        let included = entry.include.is_empty()
+            || file
+                .path()
+                .strip_prefix(&resolved.source_path)
+                .ok()
+                .and_then(|rel| rel.components().next())
+                .is_some_and(|first| {
+                    let name = first.as_os_str().to_string_lossy();
+                    entry.include.iter().any(|prefix| name.starts_with(prefix))
+                });
+        if !included {
+            continue;
+        }
+        let Ok(rel) = file.path().strip_prefix(root) else {
+            continue;
+        };
+        files.push(
+            rel.components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+        );
+    }
+    files
+}
+
+/// Clone a git source at its locked commit and point at the entry's path
+fn resolve_locked_git(
+    source: &Source,
+    locked_commit: &str,
+    locked_ref: &str,
+    clones: &CloneCache,
+) -> Result<ResolvedSource> {
+    let git = source.git_source().ok_or_else(|| ApsError::GitError {
+        message: "Locked commits are only supported for git sources".to_string(),
+    })?;
+    git.resolve_at_commit_cached(clones, locked_commit, locked_ref)
+}
+
+/// What the clone of an entry's ref is used for besides reading its files:
+/// history for last-changed commits, else the locked commit so the lock
+/// diff can count the commits in between
+fn clone_intent(entry: &Entry, locked: Option<&LockedEntry>) -> CloneIntent {
+    if entry.last_changed || entry.preserve_mtime == Some(MtimeSource::LastChange) {
+        CloneIntent::NeedFullHistory
+    } else if let Some(commit) = locked.and_then(|l| l.commit.clone()) {
+        CloneIntent::NeedDiffAgainstLock { locked: commit }
+    } else {
+        CloneIntent::CheckoutOnly
+    }
+}
+
+/// Resolve a source, reusing a clone another entry made of the same git ref
+fn resolve_source(
+    source: &Source,
+    manifest_dir: &Path,
+    clones: &CloneCache,
+    intent: Option<CloneIntent>,
+) -> Result<ResolvedSource> {
+    match source.git_source() {
+        Some(git) => git.with_intent(intent).resolve_cached(clones),
+        None => source.to_adapter().resolve(manifest_dir),
+    }
+}
+
+/// Resolve a source at the commit `locked` records for it, if it is a git
+/// source with one, else at its current ref
+pub fn resolve_entry_source(
+    source: &Source,
+    locked: Option<&LockedEntry>,
+    manifest_dir: &Path,
+    clones: &CloneCache,
+) -> Result<ResolvedSource> {
+    match (source.git_info(), locked.and_then(|l| l.commit.as_deref())) {
+        (Some(_), Some(commit)) => {
+            let locked_ref = locked
+                .and_then(|l| l.resolved_ref.as_deref())
+                .unwrap_or("unknown");
+            resolve_locked_git(source, commit, locked_ref, clones)
+        }
+        _ => resolve_source(source, manifest_dir, clones, None),
+    }
+}
+
+/// Verify that the resolved source is a file or directory as the entry's
+/// `source_kind` demands (no-op when the kind is inferred)
+pub fn check_source_kind(entry: &Entry, source_path: &Path) -> Result<()> {
+    let Some(expected) = entry.source_kind else {
+        return Ok(());
+    };
+    let actual = if source_path.is_dir() {
+        SourceKind::Directory
+    } else {
+        SourceKind::File
+    };
+    if actual == expected {
+        return Ok(());
+    }
+    Err(ApsError::SourceKindMismatch {
+        id: entry.id.clone(),
+        path: source_path.to_path_buf(),
+        expected: expected.to_string(),
+        actual: actual.to_string(),
+    })
+}
+
+/// Verify that every file listed in the entry's `required_files` exists in
+/// the resolved source, reporting all missing paths at once
+pub fn check_required_files(entry: &Entry, source_path: &Path) -> Result<()> {
+    let missing: Vec<String> = entry
+        .required_files
+        .iter()
+        .filter(|file| !source_path.join(file).exists())
+        .cloned()
+        .collect();
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(ApsError::MissingRequiredFile {
+            id: entry.id.clone(),
+            files: missing,
+        })
+    }
+}
+
+/// Number of synced files above which an entry without `max_files` warns
+pub const LARGE_TREE_FILES: usize = 10_000;
+
+/// Count the files the entry would sync, stopping as soon as the count
+/// exceeds its `max_files` limit.
+///
+/// Exceeding `max_files` is an error, raised before anything is written.
+/// Without a limit, a source with more than [`LARGE_TREE_FILES`] files
+/// returns a warning instead. Honors `include` and skips VCS metadata.
+pub fn check_file_count(entry: &Entry, source_path: &Path) -> Result<Option<String>> {
+    let limit = entry.max_files.unwrap_or(LARGE_TREE_FILES);
+    let count = synced_files(entry, source_path).take(limit + 1).count();
+
+    if count <= limit {
+        return Ok(None);
+    }
+    if entry.max_files.is_some() {
+        return Err(ApsError::TooManyFiles {
+            id: entry.id.clone(),
+            path: source_path.to_path_buf(),
+            limit,
+        });
+    }
+    Ok(Some(format!(
+        "Entry '{}' syncs more than {} files; check its path, or set max_files to guard against huge trees",
+        entry.id, LARGE_TREE_FILES
+    )))
+}
+
+/// Files of `source_path` the entry would sync: those under a top-level
+/// entry matching `include` (all when empty), skipping VCS metadata
+fn synced_files<'a>(
+    entry: &'a Entry,
+    source_path: &'a Path,
+) -> impl Iterator<Item = walkdir::DirEntry> + 'a {
+    WalkDir::new(source_path)
+        .into_iter()
+        .filter_entry(|e| !is_vcs_metadata(e.file_name(), entry.exclude_vcs))
+        .filter_map(|e| e.ok())
+        .filter(move |file| {
+            file.file_type().is_file()
+                && (entry.include.is_empty()
+                    || file
+                        .path()
+                        .strip_prefix(source_path)
+                        .ok()
+                        .and_then(|rel| rel.components().next())
+                        .is_some_and(|first| {
+                            let name = first.as_os_str().to_string_lossy();
+                            entry.include.iter().any(|prefix| name.starts_with(prefix))
+                        }))
+        })
+}
+
+/// With `validate_notebooks`, check the structure of every Jupyter
+/// notebook the entry would sync, before anything is written
+pub fn check_notebooks(entry: &Entry, source_path: &Path) -> Result<()> {
+    if !entry.validate_notebooks {
+        return Ok(());
+    }
+    for file in synced_files(entry, source_path).filter(|f| is_notebook(f.path())) {
+        let bytes = std::fs::read(file.path())
+            .map_err(|e| ApsError::io(e, format!("Failed to read {:?}", file.path())))?;
+        if let Err(reason) = validate_notebook(&bytes) {
+            let path = file
+                .path()
+                .strip_prefix(source_path)
+                .ok()
+                .filter(|rel| !rel.as_os_str().is_empty())
+                .unwrap_or(file.path());
+            return Err(ApsError::InvalidNotebook {
+                id: entry.id.clone(),
+                path: path.to_path_buf(),
+                reason,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Install a composite entry (merge multiple sources into one file)
+pub fn install_composite_entry(
+    entry: &Entry,
+    manifest_dir: &Path,
+    lockfile: &Lockfile,
+    options: &InstallOptions,
+) -> Result<InstallResult> {
+    info!("Processing composite entry: {}", entry.id);
+
+    if entry.sources.is_empty() {
+        return Err(ApsError::CompositeRequiresSources {
+            id: entry.id.clone(),
+        });
+    }
+
+    let mut timings = PhaseTimings::default();
+    let composed_content = compose_entry_sources(entry, manifest_dir, &mut timings)?;
 
     // Compute checksum of the final composed content
     let checksum = compute_string_checksum(&composed_content);
@@ -551,8 +1497,10 @@ pub fn install_composite_entry(
             locked_entry: None,
             warnings: Vec::new(),
             dest_path: dest_path.clone(),
+            extra_dest_paths: Vec::new(),
             was_symlink: false,
             upgrade_available: None,
+            timings,
         });
     }
 
@@ -560,12 +1508,14 @@ pub fn install_composite_entry(
     handle_conflict(&dest_path, manifest_dir, options)?;
 
     // Write the composed file
+    let install_started = Instant::now();
     if !options.dry_run {
         write_composed_file(&composed_content, &dest_path)?;
         info!("Wrote composed file to {:?}", dest_path);
     } else {
         println!("[dry-run] Would write composed file to {:?}", dest_path);
     }
+    timings.install = install_started.elapsed();
 
     // Create locked entry with original source paths (preserving shell variables like $HOME)
     // Store relative path in lockfile for portability across machines
@@ -582,11 +1532,320 @@ pub fn install_composite_entry(
         locked_entry: Some(locked_entry),
         warnings: Vec::new(),
         dest_path,
+        extra_dest_paths: Vec::new(),
         was_symlink: false,
         upgrade_available: None,
+        timings,
     })
 }
 
+/// Differences between an entry's destinations and what a sync would produce
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EntryDrift {
+    pub id: String,
+    /// Files a sync would create
+    pub added: Vec<PathBuf>,
+    /// Files whose content a sync would change
+    pub changed: Vec<PathBuf>,
+    /// Files a sync would delete
+    pub removed: Vec<PathBuf>,
+}
+
+impl EntryDrift {
+    /// Whether the destinations already match the source
+    pub fn is_clean(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// How a sync would change one destination file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileChangeKind {
+    /// The file does not exist yet
+    Added,
+    /// The file exists with different content
+    Changed,
+    /// The file would be deleted
+    Removed,
+}
+
+/// One file a sync would write or delete
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedChange {
+    /// Destination path, relative to the manifest directory when possible
+    pub path: PathBuf,
+    pub kind: FileChangeKind,
+    /// Whether either side is binary; binary files get no textual diff
+    pub binary: bool,
+    /// Unified diff of a changed text file
+    pub diff: Option<String>,
+}
+
+/// Every file change a sync of one entry would make
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncPlan {
+    pub id: String,
+    pub changes: Vec<PlannedChange>,
+}
+
+impl SyncPlan {
+    /// Whether the destinations already match the source
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Summarize the plan as added / changed / removed paths
+    pub fn drift(&self) -> EntryDrift {
+        let mut drift = EntryDrift {
+            id: self.id.clone(),
+            ..Default::default()
+        };
+        for change in &self.changes {
+            let paths = match change.kind {
+                FileChangeKind::Added => &mut drift.added,
+                FileChangeKind::Changed => &mut drift.changed,
+                FileChangeKind::Removed => &mut drift.removed,
+            };
+            paths.push(change.path.clone());
+        }
+        drift
+    }
+}
+
+/// Work out every file change a sync of `entry` would make, without writing
+/// to its destinations.
+///
+/// The source is resolved the same way `install_entry` does (respecting
+/// locked commits unless upgrading) and installed into a temporary staging
+/// directory, which is then compared file by file with each destination.
+/// Changed text files come with a unified diff.
+pub fn plan_sync(
+    entry: &Entry,
+    manifest_dir: &Path,
+    lockfile: &Lockfile,
+    options: &InstallOptions,
+) -> Result<SyncPlan> {
+    info!("Checking entry: {}", entry.id);
+    let mut plan = SyncPlan {
+        id: entry.id.clone(),
+        ..Default::default()
+    };
+    let staging =
+        tempfile::tempdir().map_err(|e| ApsError::io(e, "Failed to create staging directory"))?;
+
+    if entry.is_composite() {
+        let content = compose_entry_sources(entry, manifest_dir, &mut PhaseTimings::default())?;
+        let staged = staging.path().join("composed.md");
+        write_composed_file(&content, &staged)?;
+        let dest = manifest_dir.join(entry.destination());
+        compare_trees(&staged, &dest, false, manifest_dir, &mut plan)?;
+        return Ok(plan);
+    }
+
+    let source = entry
+        .source
+        .as_ref()
+        .ok_or_else(|| ApsError::EntryRequiresSource {
+            id: entry.id.clone(),
+        })?;
+    let locked = lockfile.entries.get(&entry.id).filter(|_| !options.upgrade);
+    let resolved = resolve_entry_source(source, locked, manifest_dir, &options.clones)?;
+    if !resolved.source_path.exists() {
+        return Err(ApsError::SourcePathNotFound {
+            path: resolved.source_path,
+        });
+    }
+    check_source_kind(entry, &resolved.source_path)?;
+    check_required_files(entry, &resolved.source_path)?;
+    check_file_count(entry, &resolved.source_path)?;
+    check_notebooks(entry, &resolved.source_path)?;
+
+    let substitutions = entry
+        .placeholders
+        .then(|| Substitutions::from_resolved(&resolved));
+    let copy = CopyContext::new(
+        entry_transform(entry, substitutions.as_ref()),
+        entry.source_symlinks,
+        resolved.root.as_deref().unwrap_or(&resolved.source_path),
+        options.copy_jobs,
+        &options.events,
+        &entry.id,
+    )
+    .exclude_vcs(entry.exclude_vcs);
+    let copy = match export_ignored(entry, &resolved)? {
+        Some((root, paths)) => copy.export_ignored(root, paths),
+        None => copy,
+    };
+
+    // Copies replace the destination directory, so extra files there would
+    // be removed; symlinked and merged (hooks) installs leave them alone
+    let replaces_dest = !resolved.use_symlink && !matches!(entry.kind, AssetKind::CursorHooks);
+
+    let dests = std::iter::once(entry.destination())
+        .chain(entry.extra_destinations())
+        .zip(entry.destination_includes());
+    for (i, (dest, include)) in dests.enumerate() {
+        let staged = staging.path().join(i.to_string()).join("dest");
+        install_asset(
+            &entry.kind,
+            &resolved.source_path,
+            &staged,
+            resolved.use_symlink,
+            include,
+            &copy,
+        )?;
+        compare_trees(
+            &staged,
+            &manifest_dir.join(dest),
+            replaces_dest,
+            manifest_dir,
+            &mut plan,
+        )?;
+    }
+
+    Ok(plan)
+}
+
+/// Compare the files of a staged install against a destination
+fn compare_trees(
+    staged: &Path,
+    dest: &Path,
+    report_removed: bool,
+    manifest_dir: &Path,
+    plan: &mut SyncPlan,
+) -> Result<()> {
+    let expected = collect_tree_files(staged)?;
+    let actual = collect_tree_files(dest)?;
+    let display = |rel: &Path| {
+        let path = if rel.as_os_str().is_empty() {
+            dest.to_path_buf()
+        } else {
+            dest.join(rel)
+        };
+        path.strip_prefix(manifest_dir)
+            .map(Path::to_path_buf)
+            .unwrap_or(path)
+    };
+
+    for (rel, content) in &expected {
+        let change = match actual.get(rel) {
+            None => PlannedChange {
+                path: display(rel),
+                kind: FileChangeKind::Added,
+                binary: !is_text(content),
+                diff: None,
+            },
+            Some(existing) if existing != content => {
+                let path = display(rel);
+                let binary = !is_text(existing) || !is_text(content);
+                let diff = (!binary).then(|| {
+                    let label = path.to_string_lossy();
+                    unified_diff(
+                        &String::from_utf8_lossy(existing),
+                        &String::from_utf8_lossy(content),
+                        &format!("a/{}", label),
+                        &format!("b/{}", label),
+                    )
+                });
+                PlannedChange {
+                    path,
+                    kind: FileChangeKind::Changed,
+                    binary,
+                    diff,
+                }
+            }
+            Some(_) => continue,
+        };
+        plan.changes.push(change);
+    }
+    if report_removed {
+        for (rel, content) in actual
+            .iter()
+            .filter(|(rel, _)| !expected.contains_key(*rel))
+        {
+            plan.changes.push(PlannedChange {
+                path: display(rel),
+                kind: FileChangeKind::Removed,
+                binary: !is_text(content),
+                diff: None,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Read every file under `path` (following symlinks), keyed by relative path.
+///
+/// A single file is keyed by the empty path; a missing path yields no files.
+fn collect_tree_files(path: &Path) -> Result<std::collections::BTreeMap<PathBuf, Vec<u8>>> {
+    let mut files = std::collections::BTreeMap::new();
+    if path.is_file() {
+        let content = std::fs::read(path)
+            .map_err(|e| ApsError::io(e, format!("Failed to read {:?}", path)))?;
+        files.insert(PathBuf::new(), content);
+        return Ok(files);
+    }
+    if !path.is_dir() {
+        return Ok(files);
+    }
+
+    for entry in WalkDir::new(path)
+        .follow_links(true)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git")
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let content = std::fs::read(entry.path())
+            .map_err(|e| ApsError::io(e, format!("Failed to read {:?}", entry.path())))?;
+        if let Ok(rel) = entry.path().strip_prefix(path) {
+            files.insert(rel.to_path_buf(), content);
+        }
+    }
+    Ok(files)
+}
+
+/// Resolve every source of a composite entry and compose them into one string
+fn compose_entry_sources(
+    entry: &Entry,
+    manifest_dir: &Path,
+    timings: &mut PhaseTimings,
+) -> Result<String> {
+    // Resolve all sources and collect their content
+    let mut composed_sources: Vec<ComposedSource> = Vec::new();
+    let mut all_checksums: Vec<String> = Vec::new();
+
+    for source in &entry.sources {
+        let resolve_started = Instant::now();
+        let adapter = source.to_adapter();
+        let resolved = adapter.resolve(manifest_dir)?;
+        timings.resolve += resolve_started.elapsed();
+
+        if !resolved.source_path.exists() {
+            return Err(ApsError::SourcePathNotFound {
+                path: resolved.source_path,
+            });
+        }
+
+        // Read the source file
+        let composed_source = read_source_file(&resolved.source_path)?;
+        composed_sources.push(composed_source);
+
+        // Compute and collect checksum for this source
+        let checksum_started = Instant::now();
+        let source_checksum = compute_source_checksum(&resolved.source_path)?;
+        timings.checksum += checksum_started.elapsed();
+        all_checksums.push(source_checksum);
+    }
+
+    // Compose all sources into one markdown string
+    let compose_options = ComposeOptions {
+        add_separators: false,
+        include_source_info: false,
+    };
+    compose_markdown(&composed_sources, &compose_options)
+}
+
 /// Install an asset based on its kind
 fn install_asset(
     kind: &AssetKind,
@@ -594,15 +1853,25 @@ fn install_asset(
     dest: &Path,
     use_symlink: bool,
     include: &[String],
+    copy: &CopyContext,
 ) -> Result<Vec<String>> {
     // Track symlinked items for lockfile
     let mut symlinked_items = Vec::new();
 
+    // Symlinked installs point at the source itself; only copies are checked
+    let copy = if use_symlink {
+        copy.clone()
+    } else {
+        copy.for_tree(source, dest)?
+    }
+    .with_apsignore(source);
+
     // Ensure destination parent exists
     if let Some(parent) = dest.parent() {
         if !parent.exists() {
-            std::fs::create_dir_all(parent)
-                .map_err(|e| ApsError::io(e, "Failed to create destination directory"))?;
+            std::fs::create_dir_all(parent).map_err(|e| {
+                ApsError::dest_io(e, parent, "Failed to create destination directory")
+            })?;
         }
     }
 
@@ -614,9 +1883,7 @@ fn install_asset(
                 symlinked_items.push(source.to_string_lossy().to_string());
                 debug!("Symlinked file {:?} to {:?}", source, dest);
             } else {
-                std::fs::copy(source, dest).map_err(|e| {
-                    ApsError::io(e, format!("Failed to copy {:?} to {:?}", source, dest))
-                })?;
+                copy.copy_file(source, dest)?;
                 debug!("Copied file {:?} to {:?}", source, dest);
             }
         }
@@ -635,16 +1902,20 @@ fn install_asset(
                 if include.is_empty() {
                     // Symlink individual files (not the directory itself)
                     // This allows multiple sources to contribute to the same dest
-                    symlink_directory_files(source, dest, &mut symlinked_items)?;
+                    symlink_directory_files(source, dest, &copy, &mut symlinked_items)?;
                     debug!("Symlinked directory files from {:?} to {:?}", source, dest);
                 } else {
                     // Filter and symlink individual items
-                    let items = filter_by_prefix(source, include)?;
+                    let items = filter_by_prefix(source, include, &copy)?;
 
                     // Ensure dest directory exists for individual symlinks
                     if !dest.exists() {
                         std::fs::create_dir_all(dest).map_err(|e| {
-                            ApsError::io(e, format!("Failed to create directory {:?}", dest))
+                            ApsError::dest_io(
+                                e,
+                                dest,
+                                format!("Failed to create directory {:?}", dest),
+                            )
                         })?;
                     }
 
@@ -674,20 +1945,28 @@ fn install_asset(
                             })?;
                             if meta.file_type().is_symlink() || meta.file_type().is_file() {
                                 std::fs::remove_file(dest).map_err(|e| {
-                                    ApsError::io(e, format!("Failed to remove file {:?}", dest))
+                                    ApsError::dest_io(
+                                        e,
+                                        dest,
+                                        format!("Failed to remove file {:?}", dest),
+                                    )
                                 })?;
                             }
                         }
                         std::fs::create_dir_all(dest).map_err(|e| {
-                            ApsError::io(e, format!("Failed to create directory {:?}", dest))
+                            ApsError::dest_io(
+                                e,
+                                dest,
+                                format!("Failed to create directory {:?}", dest),
+                            )
                         })?;
-                        copy_directory_merge(source, dest)?;
+                        copy_directory_merge(source, dest, &copy)?;
                     } else {
-                        copy_directory(source, dest)?;
+                        copy_directory(source, dest, &copy)?;
                     }
                 } else {
                     // Filter and copy individual items
-                    let items = filter_by_prefix(source, include)?;
+                    let items = filter_by_prefix(source, include, &copy)?;
 
                     // Ensure dest exists
                     if matches!(kind, AssetKind::CursorHooks) {
@@ -697,24 +1976,37 @@ fn install_asset(
                             })?;
                             if meta.file_type().is_symlink() || meta.file_type().is_file() {
                                 std::fs::remove_file(dest).map_err(|e| {
-                                    ApsError::io(e, format!("Failed to remove file {:?}", dest))
+                                    ApsError::dest_io(
+                                        e,
+                                        dest,
+                                        format!("Failed to remove file {:?}", dest),
+                                    )
                                 })?;
                             }
                         }
                         std::fs::create_dir_all(dest).map_err(|e| {
-                            ApsError::io(e, format!("Failed to create directory {:?}", dest))
+                            ApsError::dest_io(
+                                e,
+                                dest,
+                                format!("Failed to create directory {:?}", dest),
+                            )
                         })?;
                     } else {
                         if dest.exists() {
                             std::fs::remove_dir_all(dest).map_err(|e| {
-                                ApsError::io(
+                                ApsError::dest_io(
                                     e,
+                                    dest,
                                     format!("Failed to remove existing directory {:?}", dest),
                                 )
                             })?;
                         }
                         std::fs::create_dir_all(dest).map_err(|e| {
-                            ApsError::io(e, format!("Failed to create directory {:?}", dest))
+                            ApsError::dest_io(
+                                e,
+                                dest,
+                                format!("Failed to create directory {:?}", dest),
+                            )
                         })?;
                     }
 
@@ -729,11 +2021,18 @@ fn install_asset(
                             )
                         })?;
                         let item_dest = dest.join(item_name);
-                        if item.is_dir() {
+                        if item.is_symlink() {
+                            copy_source_symlink(
+                                &item,
+                                &item_dest,
+                                &copy,
+                                matches!(kind, AssetKind::CursorHooks),
+                            )?;
+                        } else if item.is_dir() {
                             if matches!(kind, AssetKind::CursorHooks) {
-                                copy_directory_merge(&item, &item_dest)?;
+                                copy_directory_merge(&item, &item_dest, &copy)?;
                             } else {
-                                copy_directory(&item, &item_dest)?;
+                                copy_directory(&item, &item_dest, &copy)?;
                             }
                         } else {
                             if item_dest.exists() {
@@ -745,23 +2044,23 @@ fn install_asset(
                                 })?;
                                 if meta.file_type().is_symlink() {
                                     std::fs::remove_file(&item_dest).map_err(|e| {
-                                        ApsError::io(
+                                        ApsError::dest_io(
                                             e,
+                                            &item_dest,
                                             format!("Failed to remove file {:?}", item_dest),
                                         )
                                     })?;
                                 } else if item_dest.is_dir() {
                                     std::fs::remove_dir_all(&item_dest).map_err(|e| {
-                                        ApsError::io(
+                                        ApsError::dest_io(
                                             e,
+                                            &item_dest,
                                             format!("Failed to remove directory {:?}", item_dest),
                                         )
                                     })?;
                                 }
                             }
-                            std::fs::copy(&item, &item_dest).map_err(|e| {
-                                ApsError::io(e, format!("Failed to copy {:?}", item))
-                            })?;
+                            copy.copy_file(&item, &item_dest)?;
                         }
                     }
                 }
@@ -771,17 +2070,283 @@ fn install_asset(
     Ok(symlinked_items)
 }
 
+/// Settings shared by the copy helpers for one install
+#[derive(Clone)]
+struct CopyContext<'a> {
+    /// Placeholder substitution and encoding conversion for copied files
+    transform: ContentTransform<'a>,
+    /// How symlinks inside the source are copied
+    symlinks: SymlinkMode,
+    /// Canonical repository or filesystem root; link targets must stay inside
+    root: PathBuf,
+    /// Canonical source path being copied
+    tree: PathBuf,
+    /// Destination the source tree is copied to
+    dest_root: PathBuf,
+    /// Maximum number of files copied at once
+    jobs: usize,
+    /// Progress events, reported for the entry being installed
+    events: &'a Events,
+    id: &'a str,
+    /// Source entries left out of the copy
+    filter: SourceFilter,
+}
+
+/// Source entries an install leaves out: VCS metadata, paths excluded by the
+/// source's `.apsignore`, and paths marked `export-ignore`
+#[derive(Clone, Default)]
+pub(crate) struct SourceFilter {
+    /// Skip the metadata directories of every VCS, not only `.git`
+    exclude_vcs: bool,
+    /// The source's `.apsignore` and the directory its patterns are
+    /// relative to
+    ignore: Option<(PathBuf, Arc<ApsIgnore>)>,
+    /// Paths marked `export-ignore`, relative to the repository root given
+    /// with them
+    export_ignored: Option<(PathBuf, Arc<BTreeSet<String>>)>,
+}
+
+impl SourceFilter {
+    /// The filter a sync of `entry` applies to its resolved source
+    pub(crate) fn for_entry(entry: &Entry, resolved: &ResolvedSource) -> Result<Self> {
+        let mut filter = Self {
+            exclude_vcs: entry.exclude_vcs,
+            ..Self::default()
+        };
+        filter.set_apsignore(&resolved.source_path);
+        if let Some((root, paths)) = export_ignored(entry, resolved)? {
+            filter.export_ignored = Some((normalize_path(root), Arc::new(paths)));
+        }
+        Ok(filter)
+    }
+
+    fn set_apsignore(&mut self, source: &Path) {
+        let source = normalize_path(source);
+        self.ignore = ApsIgnore::load(&source).map(|ignore| (source, Arc::new(ignore)));
+    }
+
+    /// Whether the file at `rel` (`/`-separated) below `source` is left
+    /// out, checking its parent directories as a copy walking the tree would
+    pub(crate) fn skips_file(&self, source: &Path, rel: &str) -> bool {
+        let parts: Vec<&str> = rel.split('/').collect();
+        let mut path = normalize_path(source);
+        parts.iter().enumerate().any(|(i, part)| {
+            path.push(part);
+            self.skips(&path, i + 1 < parts.len())
+        })
+    }
+
+    /// Whether a source entry is left out of the copy
+    fn skips(&self, path: &Path, is_dir: bool) -> bool {
+        if path
+            .file_name()
+            .is_some_and(|name| is_vcs_metadata(name, self.exclude_vcs))
+        {
+            return true;
+        }
+        let relative = |root: &Path| {
+            path.strip_prefix(root)
+                .ok()
+                .map(|rel| rel.to_string_lossy().replace('\\', "/"))
+        };
+        if let Some((root, ignore)) = &self.ignore {
+            if relative(root).is_some_and(|rel| ignore.is_ignored(&rel, is_dir)) {
+                return true;
+            }
+        }
+        self.export_ignored
+            .as_ref()
+            .is_some_and(|(root, paths)| relative(root).is_some_and(|rel| paths.contains(&rel)))
+    }
+}
+
+impl<'a> CopyContext<'a> {
+    fn new(
+        transform: ContentTransform<'a>,
+        symlinks: SymlinkMode,
+        root: &Path,
+        jobs: usize,
+        events: &'a Events,
+        id: &'a str,
+    ) -> Self {
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        Self {
+            transform,
+            symlinks,
+            tree: root.clone(),
+            dest_root: PathBuf::new(),
+            root,
+            jobs: jobs.max(1),
+            events,
+            id,
+            filter: SourceFilter::default(),
+        }
+    }
+
+    /// Skip the metadata directories of other version control systems too
+    fn exclude_vcs(mut self, exclude: bool) -> Self {
+        self.filter.exclude_vcs = exclude;
+        self
+    }
+
+    /// Honor the `.apsignore` file at the root of `source`, if it has one
+    fn with_apsignore(mut self, source: &Path) -> Self {
+        self.filter.set_apsignore(source);
+        self
+    }
+
+    /// Skip the paths of `repo_root` that `.gitattributes` marks
+    /// `export-ignore` (see [`export_ignored`])
+    fn export_ignored(mut self, repo_root: &Path, paths: BTreeSet<String>) -> Self {
+        self.filter.export_ignored = Some((normalize_path(repo_root), Arc::new(paths)));
+        self
+    }
+
+    /// Whether a source entry is left out of the copy: VCS metadata, a path
+    /// excluded by the source's `.apsignore`, or one marked `export-ignore`
+    fn skips(&self, path: &Path, is_dir: bool) -> bool {
+        self.filter.skips(path, is_dir)
+    }
+
+    /// Copy one file through the transform and report it
+    fn copy_file(&self, src: &Path, dst: &Path) -> Result<()> {
+        // Files briefly locked by another process (common on Windows) are
+        // retried on their own; files already copied are not redone
+        retry_transient_io(|| copy_file(src, dst, &self.transform))
+            .map_err(|e| copy_error(e, src, dst))?;
+        self.events.emit(SyncEvent::FileCopied {
+            id: self.id.to_string(),
+            path: dst.to_path_buf(),
+        });
+        Ok(())
+    }
+
+    /// Bind the context to a source tree and its destination, rejecting a
+    /// source path that itself resolves outside the root
+    fn for_tree(&self, source: &Path, dest: &Path) -> Result<Self> {
+        let tree = self.resolve_link_target(source)?;
+        Ok(Self {
+            tree,
+            dest_root: normalize_path(dest),
+            ..self.clone()
+        })
+    }
+
+    /// Canonicalize a path, requiring the result to be inside the root
+    fn resolve_link_target(&self, path: &Path) -> Result<PathBuf> {
+        let target = path
+            .canonicalize()
+            .map_err(|e| ApsError::io(e, format!("Failed to resolve {:?}", path)))?;
+        if !target.starts_with(&self.root) {
+            return Err(ApsError::UnsupportedSymlink {
+                path: path.to_path_buf(),
+                reason: "points outside the source repository".to_string(),
+            });
+        }
+        Ok(target)
+    }
+}
+
+/// Copy a symlink found in the source tree.
+///
+/// In `materialize` mode the link target's content is copied to the link's
+/// location. In `preserve` mode (Unix only) the link is recreated as a
+/// relative link, which requires the target to be part of the copied tree.
+/// Either way the target must resolve inside the source root, so the result
+/// does not depend on what exists outside the repository.
+fn copy_source_symlink(link: &Path, dst: &Path, copy: &CopyContext, merge: bool) -> Result<()> {
+    let target = copy.resolve_link_target(link)?;
+    let is_dir = target.is_dir();
+    if is_dir {
+        let parent = link.parent().and_then(|p| p.canonicalize().ok());
+        if parent.is_some_and(|p| p.starts_with(&target)) {
+            return Err(ApsError::UnsupportedSymlink {
+                path: link.to_path_buf(),
+                reason: "points to one of its parent directories".to_string(),
+            });
+        }
+    }
+
+    // Clear whatever is at the destination so links are never written through
+    if let Ok(meta) = dst.symlink_metadata() {
+        let keep_dir =
+            merge && is_dir && meta.is_dir() && copy.symlinks == SymlinkMode::Materialize;
+        if meta.is_dir() && !keep_dir {
+            std::fs::remove_dir_all(dst).map_err(|e| {
+                ApsError::dest_io(e, dst, format!("Failed to remove directory {:?}", dst))
+            })?;
+        } else if !meta.is_dir() {
+            std::fs::remove_file(dst).map_err(|e| {
+                ApsError::dest_io(e, dst, format!("Failed to remove file {:?}", dst))
+            })?;
+        }
+    }
+
+    #[cfg(unix)]
+    if copy.symlinks == SymlinkMode::Preserve {
+        let relative_target = target.strip_prefix(&copy.tree).map_err(|_| {
+            ApsError::UnsupportedSymlink {
+                path: link.to_path_buf(),
+                reason: "points outside the synced path and cannot be preserved; use source_symlinks: materialize".to_string(),
+            }
+        })?;
+        let depth = dst
+            .strip_prefix(&copy.dest_root)
+            .map(|rel| rel.components().count().saturating_sub(1))
+            .unwrap_or(0);
+        let mut relative = PathBuf::new();
+        for _ in 0..depth {
+            relative.push("..");
+        }
+        relative.push(relative_target);
+        std::os::unix::fs::symlink(&relative, dst).map_err(|e| {
+            ApsError::dest_io(e, dst, format!("Failed to create symlink {:?}", dst))
+        })?;
+        debug!("Preserved symlink {:?} -> {:?}", dst, relative);
+        return Ok(());
+    }
+
+    if is_dir {
+        if merge {
+            copy_directory_merge(&target, dst, copy)?;
+        } else {
+            copy_directory(&target, dst, copy)?;
+        }
+    } else {
+        copy.copy_file(&target, dst)?;
+    }
+    debug!("Materialized symlink {:?} at {:?}", link, dst);
+    Ok(())
+}
+
+/// Classify a failed file copy.
+///
+/// `std::fs::copy` reports read and write failures the same way, so when the
+/// error is a permission problem, probe the source to tell whether the
+/// destination is the side that could not be written.
+fn copy_error(err: std::io::Error, src: &Path, dst: &Path) -> ApsError {
+    if is_permission_error(&err) && std::fs::File::open(src).is_ok() {
+        return ApsError::DestinationNotWritable {
+            path: dst.to_path_buf(),
+            source: err,
+        };
+    }
+    ApsError::io(err, format!("Failed to copy {:?} to {:?}", src, dst))
+}
+
 /// Recursively symlink all files in a directory, creating real directories for structure.
 /// This allows multiple sources to contribute files to the same destination directory.
 fn symlink_directory_files(
     source: &Path,
     dest: &Path,
+    copy: &CopyContext,
     symlinked_items: &mut Vec<String>,
 ) -> Result<()> {
     // Create destination directory if it doesn't exist
     if !dest.exists() {
-        std::fs::create_dir_all(dest)
-            .map_err(|e| ApsError::io(e, format!("Failed to create directory {:?}", dest)))?;
+        std::fs::create_dir_all(dest).map_err(|e| {
+            ApsError::dest_io(e, dest, format!("Failed to create directory {:?}", dest))
+        })?;
     }
 
     for entry in std::fs::read_dir(source)
@@ -790,11 +2355,14 @@ fn symlink_directory_files(
         let entry = entry.map_err(|e| ApsError::io(e, "Failed to read directory entry"))?;
         let entry_path = entry.path();
         let entry_name = entry.file_name();
+        if copy.skips(&entry_path, entry_path.is_dir()) {
+            continue;
+        }
         let dest_path = dest.join(&entry_name);
 
         if entry_path.is_dir() {
             // Recurse into subdirectory (create real directory at dest)
-            symlink_directory_files(&entry_path, &dest_path, symlinked_items)?;
+            symlink_directory_files(&entry_path, &dest_path, copy, symlinked_items)?;
         } else {
             // Symlink individual file
             create_symlink(&entry_path, &dest_path)?;
@@ -807,13 +2375,20 @@ fn symlink_directory_files(
 }
 
 /// Filter directory entries by prefix
-fn filter_by_prefix(source_dir: &Path, prefixes: &[String]) -> Result<Vec<PathBuf>> {
+fn filter_by_prefix(
+    source_dir: &Path,
+    prefixes: &[String],
+    copy: &CopyContext,
+) -> Result<Vec<PathBuf>> {
     let mut matches = Vec::new();
 
     for entry in std::fs::read_dir(source_dir)
         .map_err(|e| ApsError::io(e, format!("Failed to read directory {:?}", source_dir)))?
     {
         let entry = entry.map_err(|e| ApsError::io(e, "Failed to read directory entry"))?;
+        if copy.skips(&entry.path(), entry.path().is_dir()) {
+            continue;
+        }
         let name = entry.file_name().to_string_lossy().to_string();
 
         // Check if name starts with any of the prefixes
@@ -830,6 +2405,20 @@ fn filter_by_prefix(source_dir: &Path, prefixes: &[String]) -> Result<Vec<PathBu
     Ok(matches)
 }
 
+/// Metadata directories of version control systems other than git, skipped
+/// with `exclude_vcs: true`
+const OTHER_VCS_DIRS: &[&str] = &[".hg", ".svn", ".bzr", ".jj", "_darcs", "CVS"];
+
+/// Whether a directory entry is version control metadata that is never
+/// synced: `.git` (a directory, or a file in worktrees and submodules)
+/// always, and other systems' directories when `exclude_vcs` is set.
+///
+/// This is what makes a source path of `.` (the whole repository) copy the
+/// working tree without the repository itself.
+fn is_vcs_metadata(name: &std::ffi::OsStr, exclude_vcs: bool) -> bool {
+    name == ".git" || (exclude_vcs && OTHER_VCS_DIRS.iter().any(|dir| name == *dir))
+}
+
 /// Create a symbolic link (platform-specific)
 #[cfg(unix)]
 fn create_symlink(source: &Path, dest: &Path) -> Result<()> {
@@ -841,7 +2430,11 @@ fn create_symlink(source: &Path, dest: &Path) -> Result<()> {
     if let Some(parent) = dest.parent() {
         if !parent.exists() {
             std::fs::create_dir_all(parent).map_err(|e| {
-                ApsError::io(e, format!("Failed to create parent directory {:?}", parent))
+                ApsError::dest_io(
+                    e,
+                    parent,
+                    format!("Failed to create parent directory {:?}", parent),
+                )
             })?;
         }
     }
@@ -854,17 +2447,20 @@ fn create_symlink(source: &Path, dest: &Path) -> Result<()> {
                 .map(|m| m.file_type().is_symlink())
                 .unwrap_or(false)
         {
-            std::fs::remove_dir_all(&dest)
-                .map_err(|e| ApsError::io(e, format!("Failed to remove directory {:?}", dest)))?;
+            std::fs::remove_dir_all(&dest).map_err(|e| {
+                ApsError::dest_io(e, &dest, format!("Failed to remove directory {:?}", dest))
+            })?;
         } else {
-            std::fs::remove_file(&dest)
-                .map_err(|e| ApsError::io(e, format!("Failed to remove file {:?}", dest)))?;
+            std::fs::remove_file(&dest).map_err(|e| {
+                ApsError::dest_io(e, &dest, format!("Failed to remove file {:?}", dest))
+            })?;
         }
     }
 
     std::os::unix::fs::symlink(&source, &dest).map_err(|e| {
-        ApsError::io(
+        ApsError::dest_io(
             e,
+            &dest,
             format!("Failed to create symlink {:?} -> {:?}", dest, source),
         )
     })?;
@@ -882,7 +2478,11 @@ fn create_symlink(source: &Path, dest: &Path) -> Result<()> {
     if let Some(parent) = dest.parent() {
         if !parent.exists() {
             std::fs::create_dir_all(parent).map_err(|e| {
-                ApsError::io(e, format!("Failed to create parent directory {:?}", parent))
+                ApsError::dest_io(
+                    e,
+                    &parent,
+                    format!("Failed to create parent directory {:?}", parent),
+                )
             })?;
         }
     }
@@ -890,11 +2490,13 @@ fn create_symlink(source: &Path, dest: &Path) -> Result<()> {
     // Remove existing destination if present
     if dest.exists() {
         if dest.is_dir() {
-            std::fs::remove_dir_all(&dest)
-                .map_err(|e| ApsError::io(e, format!("Failed to remove directory {:?}", dest)))?;
+            std::fs::remove_dir_all(&dest).map_err(|e| {
+                ApsError::dest_io(e, &dest, format!("Failed to remove directory {:?}", dest))
+            })?;
         } else {
-            std::fs::remove_file(&dest)
-                .map_err(|e| ApsError::io(e, format!("Failed to remove file {:?}", dest)))?;
+            std::fs::remove_file(&dest).map_err(|e| {
+                ApsError::dest_io(e, &dest, format!("Failed to remove file {:?}", dest))
+            })?;
         }
     }
 
@@ -951,8 +2553,68 @@ fn validate_skills_root(source: &Path, strict: bool) -> Result<Vec<String>> {
     Ok(warnings)
 }
 
+/// Copy queued `(source, destination)` files, up to `copy.jobs` at a time.
+///
+/// Destination directories must already exist and every destination must be
+/// distinct, so workers never touch the same path. A serial copy stops at the
+/// first failure; a parallel one lets every worker finish and reports all
+/// failures together.
+fn copy_files(files: &[(PathBuf, PathBuf)], copy: &CopyContext) -> Result<()> {
+    let jobs = copy.jobs.min(files.len());
+    if jobs <= 1 {
+        for (src, dst) in files {
+            copy.copy_file(src, dst)?;
+        }
+        return Ok(());
+    }
+
+    let next = AtomicUsize::new(0);
+    let failures = Mutex::new(Vec::new());
+    std::thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| {
+                while let Some((src, dst)) = files.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    if let Err(e) = copy.copy_file(src, dst) {
+                        let mut failures = failures.lock().unwrap_or_else(|e| e.into_inner());
+                        failures.push((src.clone(), e));
+                    }
+                }
+            });
+        }
+    });
+
+    let mut failures = failures.into_inner().unwrap_or_else(|e| e.into_inner());
+    failures.sort_by(|a, b| a.0.cmp(&b.0));
+    if failures.len() <= 1 {
+        return failures.pop().map_or(Ok(()), |(_, err)| Err(err));
+    }
+    Err(ApsError::CopyFailed {
+        count: failures.len(),
+        details: failures
+            .iter()
+            .map(|(_, err)| format!("  - {}", err))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    })
+}
+
 /// Copy a directory recursively
-fn copy_directory(src: &Path, dst: &Path) -> Result<()> {
+fn copy_directory(src: &Path, dst: &Path, copy: &CopyContext) -> Result<()> {
+    let mut files = Vec::new();
+    copy_directory_structure(src, dst, copy, &mut files)?;
+    copy_files(&files, copy)?;
+    debug!("Copied directory {:?} to {:?}", normalize_path(src), dst);
+    Ok(())
+}
+
+/// Recreate `src` at `dst` (replacing it), queueing regular files for
+/// [`copy_files`] instead of copying them
+fn copy_directory_structure(
+    src: &Path,
+    dst: &Path,
+    copy: &CopyContext,
+    files: &mut Vec<(PathBuf, PathBuf)>,
+) -> Result<()> {
     // Normalize paths to handle trailing slashes
     let src = normalize_path(src);
     let dst = normalize_path(dst);
@@ -961,36 +2623,47 @@ fn copy_directory(src: &Path, dst: &Path) -> Result<()> {
     if let Some(parent) = dst.parent() {
         if !parent.exists() {
             std::fs::create_dir_all(parent).map_err(|e| {
-                ApsError::io(e, format!("Failed to create parent directory {:?}", parent))
+                ApsError::dest_io(
+                    e,
+                    parent,
+                    format!("Failed to create parent directory {:?}", parent),
+                )
             })?;
         }
     }
 
     if dst.exists() {
         std::fs::remove_dir_all(&dst).map_err(|e| {
-            ApsError::io(e, format!("Failed to remove existing directory {:?}", dst))
+            ApsError::dest_io(
+                e,
+                &dst,
+                format!("Failed to remove existing directory {:?}", dst),
+            )
         })?;
     }
 
     std::fs::create_dir_all(&dst)
-        .map_err(|e| ApsError::io(e, format!("Failed to create directory {:?}", dst)))?;
+        .map_err(|e| ApsError::dest_io(e, &dst, format!("Failed to create directory {:?}", dst)))?;
 
     for entry in std::fs::read_dir(&src)
         .map_err(|e| ApsError::io(e, format!("Failed to read directory {:?}", src)))?
     {
         let entry = entry.map_err(|e| ApsError::io(e, "Failed to read directory entry"))?;
+        if copy.skips(&entry.path(), entry.path().is_dir()) {
+            continue;
+        }
         let src_path = entry.path();
         let dst_path = dst.join(entry.file_name());
 
-        if src_path.is_dir() {
-            copy_directory(&src_path, &dst_path)?;
+        if src_path.is_symlink() {
+            copy_source_symlink(&src_path, &dst_path, copy, false)?;
+        } else if src_path.is_dir() {
+            copy_directory_structure(&src_path, &dst_path, copy, files)?;
         } else {
-            std::fs::copy(&src_path, &dst_path)
-                .map_err(|e| ApsError::io(e, format!("Failed to copy {:?}", src_path)))?;
+            files.push((src_path, dst_path));
         }
     }
 
-    debug!("Copied directory {:?} to {:?}", src, dst);
     Ok(())
 }
 
@@ -998,17 +2671,23 @@ fn copy_directory(src: &Path, dst: &Path) -> Result<()> {
 ///
 /// Overwrites destination entries that conflict with source entries while
 /// preserving other destination content.
-fn copy_directory_merge(src: &Path, dst: &Path) -> Result<()> {
+fn copy_directory_merge(src: &Path, dst: &Path, copy: &CopyContext) -> Result<()> {
     // Normalize paths to handle trailing slashes
     let src = normalize_path(src);
     let dst = normalize_path(dst);
 
     if !dst.exists() {
-        std::fs::create_dir_all(&dst)
-            .map_err(|e| ApsError::io(e, format!("Failed to create directory {:?}", dst)))?;
+        std::fs::create_dir_all(&dst).map_err(|e| {
+            ApsError::dest_io(e, &dst, format!("Failed to create directory {:?}", dst))
+        })?;
     }
 
-    for entry in WalkDir::new(&src).follow_links(true) {
+    // Symlinks are not followed here; copy_source_symlink handles each one
+    let mut files = Vec::new();
+    for entry in WalkDir::new(&src)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !copy.skips(e.path(), e.file_type().is_dir()))
+    {
         let entry = entry.map_err(|e| {
             ApsError::io(
                 std::io::Error::other(e),
@@ -1027,25 +2706,39 @@ fn copy_directory_merge(src: &Path, dst: &Path) -> Result<()> {
         }
         let dest_path = dst.join(rel);
 
-        if entry.file_type().is_dir() {
+        if entry.path_is_symlink() {
+            copy_source_symlink(path, &dest_path, copy, true)?;
+        } else if entry.file_type().is_dir() {
             if dest_path.exists() {
                 let meta = dest_path.symlink_metadata().map_err(|e| {
                     ApsError::io(e, format!("Failed to read metadata for {:?}", dest_path))
                 })?;
                 if meta.file_type().is_symlink() || meta.file_type().is_file() {
                     std::fs::remove_file(&dest_path).map_err(|e| {
-                        ApsError::io(e, format!("Failed to remove file {:?}", dest_path))
+                        ApsError::dest_io(
+                            e,
+                            &dest_path,
+                            format!("Failed to remove file {:?}", dest_path),
+                        )
                     })?;
                 }
             }
             std::fs::create_dir_all(&dest_path).map_err(|e| {
-                ApsError::io(e, format!("Failed to create directory {:?}", dest_path))
+                ApsError::dest_io(
+                    e,
+                    &dest_path,
+                    format!("Failed to create directory {:?}", dest_path),
+                )
             })?;
         } else {
             if let Some(parent) = dest_path.parent() {
                 if !parent.exists() {
                     std::fs::create_dir_all(parent).map_err(|e| {
-                        ApsError::io(e, format!("Failed to create directory {:?}", parent))
+                        ApsError::dest_io(
+                            e,
+                            parent,
+                            format!("Failed to create directory {:?}", parent),
+                        )
                     })?;
                 }
             }
@@ -1055,18 +2748,26 @@ fn copy_directory_merge(src: &Path, dst: &Path) -> Result<()> {
                 })?;
                 if meta.file_type().is_symlink() {
                     std::fs::remove_file(&dest_path).map_err(|e| {
-                        ApsError::io(e, format!("Failed to remove file {:?}", dest_path))
+                        ApsError::dest_io(
+                            e,
+                            &dest_path,
+                            format!("Failed to remove file {:?}", dest_path),
+                        )
                     })?;
                 } else if dest_path.is_dir() {
                     std::fs::remove_dir_all(&dest_path).map_err(|e| {
-                        ApsError::io(e, format!("Failed to remove directory {:?}", dest_path))
+                        ApsError::dest_io(
+                            e,
+                            &dest_path,
+                            format!("Failed to remove directory {:?}", dest_path),
+                        )
                     })?;
                 }
             }
-            std::fs::copy(path, &dest_path)
-                .map_err(|e| ApsError::io(e, format!("Failed to copy {:?}", path)))?;
+            files.push((path.to_path_buf(), dest_path));
         }
     }
+    copy_files(&files, copy)?;
 
     debug!("Merged directory {:?} into {:?}", src, dst);
     Ok(())
@@ -1167,8 +2868,13 @@ fn sync_hooks_config(
 
     if let Some(parent) = dest_config.parent() {
         if !parent.exists() {
-            std::fs::create_dir_all(parent)
-                .map_err(|e| ApsError::io(e, format!("Failed to create directory {:?}", parent)))?;
+            std::fs::create_dir_all(parent).map_err(|e| {
+                ApsError::dest_io(
+                    e,
+                    parent,
+                    format!("Failed to create directory {:?}", parent),
+                )
+            })?;
         }
     }
 
@@ -1182,21 +2888,26 @@ fn sync_hooks_config(
             ApsError::io(e, format!("Failed to read metadata for {:?}", dest_config))
         })?;
         if meta.file_type().is_symlink() {
-            std::fs::remove_file(&dest_config)
-                .map_err(|e| ApsError::io(e, format!("Failed to remove file {:?}", dest_config)))?;
+            std::fs::remove_file(&dest_config).map_err(|e| {
+                ApsError::dest_io(
+                    e,
+                    &dest_config,
+                    format!("Failed to remove file {:?}", dest_config),
+                )
+            })?;
         } else if dest_config.is_dir() {
             std::fs::remove_dir_all(&dest_config).map_err(|e| {
-                ApsError::io(e, format!("Failed to remove directory {:?}", dest_config))
+                ApsError::dest_io(
+                    e,
+                    &dest_config,
+                    format!("Failed to remove directory {:?}", dest_config),
+                )
             })?;
         }
     }
 
-    std::fs::copy(&source_config, &dest_config).map_err(|e| {
-        ApsError::io(
-            e,
-            format!("Failed to copy {:?} to {:?}", source_config, dest_config),
-        )
-    })?;
+    std::fs::copy(&source_config, &dest_config)
+        .map_err(|e| copy_error(e, &source_config, &dest_config))?;
 
     Ok(())
 }
@@ -0,0 +1,210 @@
+//! License detection for synced sources.
+//!
+//! Entries with `licenses: true` record the SPDX identifiers of the license
+//! files found at the source's root and at the entry's path. A file carrying
+//! an `SPDX-License-Identifier:` line is taken at its word; otherwise the
+//! text is matched against phrases distinctive to common licenses. Texts
+//! that match none, or more than one, are reported as `unknown`.
+
+use std::path::{Path, PathBuf};
+
+/// Identifier recorded for license texts that could not be identified
+pub const UNKNOWN_LICENSE: &str = "unknown";
+
+/// Distinctive phrases, all of which must appear (case-insensitively, with
+/// whitespace collapsed) for a text to be identified as the license. GPL
+/// family licenses mention each other, so they are told apart by their
+/// title and version line.
+const SIGNATURES: &[(&str, &[&str])] = &[
+    ("MIT", &["permission is hereby granted, free of charge"]),
+    ("Apache-2.0", &["apache license version 2.0"]),
+    (
+        "GPL-2.0",
+        &["gnu general public license version 2, june 1991"],
+    ),
+    (
+        "GPL-3.0",
+        &["gnu general public license version 3, 29 june 2007"],
+    ),
+    (
+        "LGPL-2.1",
+        &["gnu lesser general public license version 2.1, february 1999"],
+    ),
+    (
+        "LGPL-3.0",
+        &["gnu lesser general public license version 3, 29 june 2007"],
+    ),
+    (
+        "AGPL-3.0",
+        &["gnu affero general public license version 3, 19 november 2007"],
+    ),
+    ("MPL-2.0", &["mozilla public license version 2.0"]),
+    (
+        "BSD-3-Clause",
+        &[
+            "redistribution and use in source and binary forms",
+            "neither the name",
+        ],
+    ),
+    (
+        "ISC",
+        &["permission to use, copy, modify, and/or distribute this software for any purpose"],
+    ),
+    (
+        "Unlicense",
+        &["this is free and unencumbered software released into the public domain"],
+    ),
+    ("CC0-1.0", &["cc0 1.0 universal"]),
+    (
+        "CC-BY-4.0",
+        &["creative commons attribution 4.0 international"],
+    ),
+];
+
+/// SPDX identifiers of the license files in `root` and in the directory of
+/// `source_path`, sorted and de-duplicated; empty when there are none
+pub fn detect_licenses(root: &Path, source_path: &Path) -> Vec<String> {
+    let asset_dir = if source_path.is_dir() {
+        source_path
+    } else {
+        source_path.parent().unwrap_or(root)
+    };
+
+    let mut licenses: Vec<String> = license_files(root)
+        .into_iter()
+        .chain(if asset_dir == root {
+            Vec::new()
+        } else {
+            license_files(asset_dir)
+        })
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .map(|text| identify_license(&text))
+        .collect();
+    licenses.sort();
+    licenses.dedup();
+    licenses
+}
+
+/// License files directly inside `dir`, by their conventional names
+fn license_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+        .filter(|e| is_license_file_name(&e.file_name().to_string_lossy()))
+        .map(|e| e.path())
+        .collect();
+    files.sort();
+    files
+}
+
+/// `LICENSE`, `LICENCE`, `COPYING` or `UNLICENSE`, optionally followed by an
+/// extension or suffix (`LICENSE.md`, `LICENSE-MIT`)
+fn is_license_file_name(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    ["LICENSE", "LICENCE", "COPYING", "UNLICENSE"]
+        .iter()
+        .any(|stem| match upper.strip_prefix(stem) {
+            Some(rest) => rest.is_empty() || rest.starts_with(['.', '-', '_']),
+            None => false,
+        })
+}
+
+/// SPDX identifier of a license text, or [`UNKNOWN_LICENSE`]
+pub fn identify_license(text: &str) -> String {
+    if let Some(id) = text.lines().find_map(|line| {
+        let (_, id) = line.split_once("SPDX-License-Identifier:")?;
+        let id = id.trim().trim_end_matches("*/").trim();
+        (!id.is_empty()).then(|| id.to_string())
+    }) {
+        return id;
+    }
+
+    // Compare with whitespace collapsed, so line wrapping does not matter
+    let normalized = text
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    let matches: Vec<&str> = SIGNATURES
+        .iter()
+        .filter(|(_, phrases)| phrases.iter().all(|p| normalized.contains(p)))
+        .map(|(id, _)| *id)
+        .collect();
+    match matches.as_slice() {
+        [id] => id.to_string(),
+        // The BSD clause text without the "neither the name" clause
+        [] if normalized.contains("redistribution and use in source and binary forms") => {
+            "BSD-2-Clause".to_string()
+        }
+        _ => UNKNOWN_LICENSE.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identify_license() {
+        let mit = "MIT License\n\nCopyright (c) 2024 Example\n\nPermission is hereby granted,\nfree of charge, to any person obtaining a copy";
+        assert_eq!(identify_license(mit), "MIT");
+        assert_eq!(
+            identify_license("Apache License\nVersion 2.0, January 2004\n"),
+            "Apache-2.0"
+        );
+        assert_eq!(
+            identify_license(
+                "Redistribution and use in source and binary forms, with or without modification"
+            ),
+            "BSD-2-Clause"
+        );
+        assert_eq!(
+            identify_license("// SPDX-License-Identifier: MIT OR Apache-2.0\n"),
+            "MIT OR Apache-2.0"
+        );
+        assert_eq!(
+            identify_license(
+                "GNU LESSER GENERAL PUBLIC LICENSE\n  Version 3, 29 June 2007\n\nrefers to version 3 of the GNU General Public License"
+            ),
+            "LGPL-3.0"
+        );
+        assert_eq!(identify_license("All rights reserved."), UNKNOWN_LICENSE);
+        // Two licenses in one file cannot be told apart
+        assert_eq!(
+            identify_license(&format!("{}\n\nApache License\nVersion 2.0\n", mit)),
+            UNKNOWN_LICENSE
+        );
+    }
+
+    #[test]
+    fn test_detect_licenses_scans_root_and_asset_dir() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        std::fs::write(
+            root.join("LICENSE-MIT"),
+            "Permission is hereby granted, free of charge, to any person",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("LICENSE-APACHE"),
+            "Apache License\nVersion 2.0, January 2004\n",
+        )
+        .unwrap();
+        std::fs::create_dir(root.join("prompts")).unwrap();
+        std::fs::write(root.join("prompts/COPYING"), "Custom terms").unwrap();
+        std::fs::write(root.join("prompts/AGENTS.md"), "# Agents").unwrap();
+        std::fs::write(root.join("prompts/LICENSES.txt"), "not a license file").unwrap();
+
+        assert_eq!(
+            detect_licenses(root, &root.join("prompts/AGENTS.md")),
+            ["Apache-2.0", "MIT", "unknown"]
+        );
+        assert_eq!(
+            detect_licenses(&root.join("prompts"), &root.join("prompts")),
+            ["unknown"]
+        );
+    }
+}
@@ -0,0 +1,274 @@
+//! Differences between two lockfiles, for reviewing dependency updates.
+//!
+//! [`diff_locks`] compares entries by ID: entries only in the new lockfile
+//! are added, entries only in the old one removed, and entries whose source,
+//! commit or content changed are listed with both sides. For a commit change
+//! within the same repository the diff carries the `old..new` range, and
+//! [`LockDiff::count_commits`] fills in how far the new commit is ahead of
+//! (and behind) the old one from clones already on disk. `aps sync
+//! --lock-diff FILE` writes the diff of each run as YAML, e.g. for a CI
+//! comment.
+
+use crate::error::{ApsError, Result};
+use crate::lockfile::{LockedEntry, Lockfile};
+use crate::repo_id::canonicalize_repo_url;
+use crate::sources::{CloneCache, GitCli, GitTransport};
+use serde::Serialize;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// One side of an added or removed entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LockDiffEntry {
+    /// Entry ID
+    pub id: String,
+    /// Source as recorded in the lockfile (repository URL or path)
+    pub source: String,
+    /// Resolved git ref
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved_ref: Option<String>,
+    /// Locked commit SHA
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+}
+
+impl LockDiffEntry {
+    fn of(id: &str, entry: &LockedEntry) -> Self {
+        Self {
+            id: id.to_string(),
+            source: entry.source.to_string(),
+            resolved_ref: entry.resolved_ref.clone(),
+            commit: entry.commit.clone(),
+        }
+    }
+}
+
+/// An entry present in both lockfiles that changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LockDiffChange {
+    /// Entry ID
+    pub id: String,
+    /// The entry in the old lockfile
+    pub old: LockDiffEntry,
+    /// The entry in the new lockfile
+    pub new: LockDiffEntry,
+    /// `old..new` commit range, when both commits are from the same
+    /// repository
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub range: Option<String>,
+    /// Commits in `new` that are not in `old`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ahead: Option<u64>,
+    /// Commits in `old` that are not in `new` (non-zero after a force-push
+    /// or a switch to another branch)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub behind: Option<u64>,
+    /// Whether the installed content changed (its checksum differs)
+    pub content_changed: bool,
+}
+
+/// Entries added, removed and changed between two lockfiles, each sorted by
+/// entry ID
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LockDiff {
+    /// Entries only in the new lockfile
+    pub added: Vec<LockDiffEntry>,
+    /// Entries only in the old lockfile
+    pub removed: Vec<LockDiffEntry>,
+    /// Entries in both that changed
+    pub changed: Vec<LockDiffChange>,
+}
+
+/// Compare two lockfiles entry by entry.
+///
+/// Timestamps and metadata such as owners or metrics are ignored; an entry
+/// counts as changed when its source, resolved ref, commit or checksum does.
+pub fn diff_locks(old: &Lockfile, new: &Lockfile) -> LockDiff {
+    let mut diff = LockDiff::default();
+
+    for (id, new_entry) in &new.entries {
+        let Some(old_entry) = old.entries.get(id) else {
+            diff.added.push(LockDiffEntry::of(id, new_entry));
+            continue;
+        };
+        let content_changed = old_entry.checksum != new_entry.checksum;
+        let old_side = LockDiffEntry::of(id, old_entry);
+        let new_side = LockDiffEntry::of(id, new_entry);
+        if old_side == new_side && !content_changed {
+            continue;
+        }
+
+        let same_repo = !old_entry.source.is_composite()
+            && canonicalize_repo_url(&old_side.source) == canonicalize_repo_url(&new_side.source);
+        let range = match (&old_side.commit, &new_side.commit) {
+            (Some(from), Some(to)) if same_repo && from != to => Some(format!("{}..{}", from, to)),
+            _ => None,
+        };
+        diff.changed.push(LockDiffChange {
+            id: id.clone(),
+            old: old_side,
+            new: new_side,
+            range,
+            ahead: None,
+            behind: None,
+            content_changed,
+        });
+    }
+    for (id, old_entry) in &old.entries {
+        if !new.entries.contains_key(id) {
+            diff.removed.push(LockDiffEntry::of(id, old_entry));
+        }
+    }
+
+    diff.added.sort_by(|a, b| a.id.cmp(&b.id));
+    diff.removed.sort_by(|a, b| a.id.cmp(&b.id));
+    diff.changed.sort_by(|a, b| a.id.cmp(&b.id));
+    diff
+}
+
+impl LockDiff {
+    /// Write the diff to `path` as YAML
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_yaml::to_string(self).map_err(|e| ApsError::LockfileReadError {
+            message: format!("Failed to serialize lockfile diff: {}", e),
+        })?;
+        std::fs::write(path, content)
+            .map_err(|e| ApsError::io(e, format!("Failed to write lockfile diff at {:?}", path)))
+    }
+
+    /// Fill in ahead/behind counts for commit ranges whose repository has a
+    /// clone in `cache`
+    pub fn count_commits(&mut self, cache: &CloneCache) {
+        self.count_commits_with(&GitCli, |repo| cache.repo_path(repo));
+    }
+
+    /// Fill in ahead/behind counts using `repo_path` to find a local clone
+    /// of each repository.
+    ///
+    /// Counts stay unset when there is no clone or it lacks either commit
+    /// (e.g. a shallow clone).
+    pub fn count_commits_with<F>(&mut self, transport: &dyn GitTransport, repo_path: F)
+    where
+        F: Fn(&str) -> Option<PathBuf>,
+    {
+        for change in &mut self.changed {
+            let (Some(from), Some(to)) = (&change.old.commit, &change.new.commit) else {
+                continue;
+            };
+            if change.range.is_none() {
+                continue;
+            }
+            let Some(path) = repo_path(&change.new.source) else {
+                continue;
+            };
+            if let Some((behind, ahead)) = left_right_count(transport, &path, from, to) {
+                change.ahead = Some(ahead);
+                change.behind = Some(behind);
+            }
+        }
+    }
+}
+
+/// `(commits only in from, commits only in to)`, or `None` when git cannot
+/// tell
+fn left_right_count(
+    transport: &dyn GitTransport,
+    repo_path: &Path,
+    from: &str,
+    to: &str,
+) -> Option<(u64, u64)> {
+    let args: Vec<OsString> = vec![
+        "-C".into(),
+        repo_path.as_os_str().to_owned(),
+        "rev-list".into(),
+        "--left-right".into(),
+        "--count".into(),
+        format!("{}...{}", from, to).into(),
+    ];
+    let output = transport.run(&args).ok()?;
+    if !output.success {
+        return None;
+    }
+    let mut counts = output.stdout.split_whitespace().map(str::parse::<u64>);
+    match (counts.next(), counts.next()) {
+        (Some(Ok(behind)), Some(Ok(ahead))) => Some((behind, ahead)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::GitOutput;
+    use std::sync::Mutex;
+
+    struct CountTransport(Mutex<Vec<Vec<OsString>>>);
+
+    impl GitTransport for CountTransport {
+        fn run(&self, args: &[OsString]) -> Result<GitOutput> {
+            self.0.lock().unwrap().push(args.to_vec());
+            Ok(GitOutput::ok("1\t3\n"))
+        }
+    }
+
+    fn git_entry(repo: &str, commit: &str, checksum: &str) -> LockedEntry {
+        LockedEntry::new_git(
+            repo,
+            "./AGENTS.md",
+            "main".to_string(),
+            commit.to_string(),
+            checksum.to_string(),
+        )
+    }
+
+    #[test]
+    fn test_diff_locks_classifies_entries() {
+        let mut old = Lockfile::new();
+        old.upsert(
+            "moved".into(),
+            git_entry("https://github.com/org/a.git", "aaa", "sha256:1"),
+        );
+        old.upsert(
+            "same".into(),
+            git_entry("https://github.com/org/b.git", "bbb", "sha256:2"),
+        );
+        old.upsert(
+            "gone".into(),
+            git_entry("https://github.com/org/c.git", "ccc", "sha256:3"),
+        );
+        let mut new = Lockfile::new();
+        new.upsert(
+            "moved".into(),
+            git_entry("git@github.com:org/a.git", "abc", "sha256:9"),
+        );
+        new.upsert(
+            "same".into(),
+            git_entry("https://github.com/org/b.git", "bbb", "sha256:2"),
+        );
+        new.upsert(
+            "fresh".into(),
+            git_entry("https://github.com/org/d.git", "ddd", "sha256:4"),
+        );
+
+        let mut diff = diff_locks(&old, &new);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].id, "fresh");
+        assert_eq!(diff.removed[0].id, "gone");
+        assert_eq!(diff.changed.len(), 1);
+        let change = &diff.changed[0];
+        assert_eq!(change.range.as_deref(), Some("aaa..abc"));
+        assert!(change.content_changed);
+        assert_eq!(diff_locks(&new, &new), LockDiff::default());
+
+        let transport = CountTransport(Mutex::new(Vec::new()));
+        diff.count_commits_with(&transport, |repo| {
+            repo.contains("org/a").then(|| PathBuf::from("/tmp/clone"))
+        });
+        assert_eq!(diff.changed[0].ahead, Some(3));
+        assert_eq!(diff.changed[0].behind, Some(1));
+        assert_eq!(transport.0.lock().unwrap()[0][5], "aaa...abc");
+
+        let yaml = serde_yaml::to_string(&diff).unwrap();
+        assert!(yaml.contains("range: aaa..abc"));
+    }
+}
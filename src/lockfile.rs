@@ -1,7 +1,8 @@
+use crate::diff::is_text;
 use crate::error::{ApsError, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 use std::path::{Path, PathBuf};
 use tracing::{debug, info};
@@ -202,6 +203,85 @@ pub struct LockedEntry {
     /// List of symlinked items (for filtered symlinks)
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub symlinked_items: Vec<String>,
+
+    /// Additional destination paths that receive the same content
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_dests: Vec<String>,
+
+    /// CODEOWNERS owners by repository-relative file path (`codeowners: true`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub owners: BTreeMap<String, Vec<String>>,
+
+    /// SPDX identifiers of the source's license files (`licenses: true`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub licenses: Vec<String>,
+
+    /// Most recent commit changing each file, by repository-relative path
+    /// (`last_changed: true`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub last_changed: BTreeMap<String, LastChange>,
+
+    /// Size of each synced file at the resolved source, by path relative to
+    /// the source `path` (`metrics: true`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metrics: BTreeMap<String, FileMetrics>,
+
+    /// Commit each submodule under the source `path` is pinned to at the
+    /// resolved commit, by repository-relative path (`submodule_pins: true`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub submodules: BTreeMap<String, SubmodulePin>,
+
+    /// Nearest tag description of the commit, e.g. `v1.2.0-3-gabc123d`
+    /// (`describe: true` on the source)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub describe: Option<String>,
+
+    /// Git note attached to the commit (`fetch_notes: true` on the source)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+/// The commit that last modified a file
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct LastChange {
+    /// Commit SHA
+    pub commit: String,
+    /// Committer date of the commit (RFC 3339)
+    pub date: String,
+}
+
+/// The commit a submodule is pinned to (its gitlink)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SubmodulePin {
+    /// URL declared in `.gitmodules`, when the submodule is declared there
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Pinned commit SHA
+    pub commit: String,
+}
+
+/// Size of a synced file
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct FileMetrics {
+    /// Size in bytes
+    pub bytes: u64,
+    /// Number of lines, for text files only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lines: Option<u64>,
+}
+
+impl FileMetrics {
+    /// Measure file content; a final line without a trailing newline counts
+    pub fn of(content: &[u8]) -> Self {
+        let lines = is_text(content).then(|| {
+            let newlines = content.iter().filter(|&&b| b == b'\n').count() as u64;
+            newlines + u64::from(content.last().is_some_and(|&b| b != b'\n'))
+        });
+        Self {
+            bytes: content.len() as u64,
+            lines,
+        }
+    }
 }
 
 impl LockedEntry {
@@ -224,6 +304,14 @@ impl LockedEntry {
             is_symlink,
             target_path,
             symlinked_items,
+            extra_dests: Vec::new(),
+            owners: BTreeMap::new(),
+            licenses: Vec::new(),
+            last_changed: BTreeMap::new(),
+            metrics: BTreeMap::new(),
+            submodules: BTreeMap::new(),
+            describe: None,
+            notes: None,
         }
     }
 
@@ -246,6 +334,14 @@ impl LockedEntry {
             is_symlink: false,
             target_path: None,
             symlinked_items: Vec::new(),
+            extra_dests: Vec::new(),
+            owners: BTreeMap::new(),
+            licenses: Vec::new(),
+            last_changed: BTreeMap::new(),
+            metrics: BTreeMap::new(),
+            submodules: BTreeMap::new(),
+            describe: None,
+            notes: None,
         }
     }
 
@@ -261,6 +357,14 @@ impl LockedEntry {
             is_symlink: false,
             target_path: None,
             symlinked_items: Vec::new(),
+            extra_dests: Vec::new(),
+            owners: BTreeMap::new(),
+            licenses: Vec::new(),
+            last_changed: BTreeMap::new(),
+            metrics: BTreeMap::new(),
+            submodules: BTreeMap::new(),
+            describe: None,
+            notes: None,
         }
     }
 }
@@ -434,6 +538,9 @@ pub fn display_status(lockfile: &Lockfile) {
             }
         }
         println!("Destination:  {}", entry.dest);
+        for extra in &entry.extra_dests {
+            println!("              {}", extra);
+        }
         if let Some(ref resolved_ref) = entry.resolved_ref {
             println!("Ref:          {}", resolved_ref);
         }
@@ -568,4 +675,24 @@ mod tests {
         assert!(removed.is_empty());
         assert_eq!(lockfile.entries.len(), 2);
     }
+
+    #[test]
+    fn test_file_metrics_count_lines_of_text_only() {
+        assert_eq!(
+            FileMetrics::of(b"# Title\n\nBody"),
+            FileMetrics {
+                bytes: 13,
+                lines: Some(3)
+            }
+        );
+        assert_eq!(FileMetrics::of(b"a\n").lines, Some(1));
+        assert_eq!(FileMetrics::of(b"").lines, Some(0));
+        assert_eq!(
+            FileMetrics::of(&[0x89, b'P', b'N', b'G', 0, b'\n']),
+            FileMetrics {
+                bytes: 6,
+                lines: None
+            }
+        );
+    }
 }
@@ -1,22 +1,45 @@
+mod annotations;
+mod apsignore;
 mod backup;
 mod catalog;
 mod checksum;
 mod cli;
+mod codeowners;
 mod commands;
+mod commit;
 mod compose;
+mod dest_writer;
+mod diff;
 mod error;
+mod events;
 mod github_url;
+mod gitignore;
 mod hooks;
+mod host_policy;
 mod install;
+mod license;
+mod lock_diff;
 mod lockfile;
 mod manifest;
+mod notebook;
+mod oci;
 mod orphan;
+mod redact;
+mod repo_id;
 mod sources;
 mod sync_output;
+mod transform;
+mod updates;
+mod url_rewrite;
+mod version;
+mod zip_writer;
 
 use clap::Parser;
 use cli::{CatalogCommands, Cli, Commands};
-use commands::{cmd_add, cmd_catalog_generate, cmd_init, cmd_status, cmd_sync, cmd_validate};
+use commands::{
+    cmd_add, cmd_catalog_generate, cmd_export, cmd_init, cmd_mirror, cmd_outdated, cmd_status,
+    cmd_sync, cmd_validate,
+};
 use miette::Result;
 use tracing::Level;
 use tracing_subscriber::FmtSubscriber;
@@ -48,11 +71,21 @@ fn main() -> Result<()> {
         Commands::Sync(args) => cmd_sync(args),
         Commands::Validate(args) => cmd_validate(args),
         Commands::Status(args) => cmd_status(args),
+        Commands::Outdated(args) => cmd_outdated(args),
         Commands::Catalog(args) => match args.command {
             CatalogCommands::Generate(gen_args) => cmd_catalog_generate(gen_args),
         },
+        Commands::Export(args) => cmd_export(args),
+        Commands::Mirror(args) => cmd_mirror(args),
     };
 
+    if let Err(ref e) = result {
+        annotations::emit_all(&[annotations::Annotation::for_error(
+            annotations::Level::Error,
+            e,
+        )]);
+    }
+
     // Convert our error type to miette for nice display
     result.map_err(|e| e.into())
 }
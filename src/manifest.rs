@@ -1,13 +1,20 @@
 use crate::error::{ApsError, Result};
-use crate::sources::{FilesystemSource, GitSource, SourceAdapter};
+use crate::sources::{
+    check_git_config, is_full_commit_sha, parse_as_of, parse_shallow_since, CloneOptions,
+    CloneStrategy, FilesystemSource, FollowRedirects, GitSource, SourceAdapter,
+};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
 use std::path::{Path, PathBuf};
 use tracing::{debug, info};
 
 /// Default manifest filename
 pub const DEFAULT_MANIFEST_NAME: &str = "aps.yaml";
 
+/// Prefix of a `ref` that names a release channel instead of a branch
+pub const CHANNEL_REF_PREFIX: &str = "channel:";
+
 /// The main manifest structure
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Manifest {
@@ -45,9 +52,93 @@ pub struct Entry {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dest: Option<String>,
 
+    /// Additional destinations that receive a copy of the same resolved source
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_dests: Vec<ExtraDest>,
+
     /// Optional list of prefixes to filter which files/folders to sync
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub include: Vec<String>,
+
+    /// Files that must exist in the resolved source (relative to its path)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required_files: Vec<String>,
+
+    /// Replace provenance placeholders (e.g. `{{APS_COMMIT_SHA}}`) in copied text files
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub placeholders: bool,
+
+    /// How symlinks inside the source are copied (ignored for symlinked entries)
+    #[serde(default, skip_serializing_if = "SymlinkMode::is_default")]
+    pub source_symlinks: SymlinkMode,
+
+    /// Whether the source path must be a file or a directory (inferred when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_kind: Option<SourceKind>,
+
+    /// Record the source repository's CODEOWNERS owners for each synced file
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub codeowners: bool,
+
+    /// Detect the source's licenses and record their SPDX identifiers
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub licenses: bool,
+
+    /// Record the most recent commit that changed each synced file (git
+    /// sources only)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub last_changed: bool,
+
+    /// Record the commit each submodule under the source path is pinned to
+    /// (git sources only)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub submodule_pins: bool,
+
+    /// Convert copied text files from this encoding to UTF-8
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<SourceEncoding>,
+
+    /// End copied text files with a newline
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ensure_final_newline: bool,
+
+    /// Strip trailing spaces and tabs from every line of copied text files
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub trim_trailing_whitespace: bool,
+
+    /// Also skip the metadata directories of version control systems other
+    /// than git (`.git` itself is never copied)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub exclude_vcs: bool,
+
+    /// Also copy paths that the source's `.gitattributes` marks
+    /// `export-ignore`; like `git archive`, git entries skip them by default
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub include_export_ignored: bool,
+
+    /// Fail before writing anything when the source has more files than
+    /// this (after `include`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_files: Option<usize>,
+
+    /// Record the byte size and line count of each synced file, and report
+    /// how they changed since the locked version
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub metrics: bool,
+
+    /// Fail before writing anything when a synced Jupyter notebook is not
+    /// valid JSON with the structure Jupyter expects
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub validate_notebooks: bool,
+
+    /// Clear code cell outputs and execution counts in copied notebooks
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strip_notebook_outputs: bool,
+
+    /// Set copied files' modification times from git history instead of
+    /// the time of the sync (git sources only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preserve_mtime: Option<MtimeSource>,
 }
 
 impl Entry {
@@ -63,7 +154,26 @@ impl Entry {
             }),
             sources: Vec::new(),
             dest: None,
+            extra_dests: Vec::new(),
             include: Vec::new(),
+            required_files: Vec::new(),
+            placeholders: false,
+            source_symlinks: SymlinkMode::default(),
+            source_kind: None,
+            codeowners: false,
+            licenses: false,
+            last_changed: false,
+            encoding: None,
+            ensure_final_newline: false,
+            trim_trailing_whitespace: false,
+            exclude_vcs: false,
+            max_files: None,
+            metrics: false,
+            validate_notebooks: false,
+            strip_notebook_outputs: false,
+            preserve_mtime: None,
+            include_export_ignored: false,
+            submodule_pins: false,
         }
     }
 
@@ -75,14 +185,164 @@ impl Entry {
     /// Get the destination path for this entry (with shell variable expansion)
     pub fn destination(&self) -> PathBuf {
         if let Some(ref dest) = self.dest {
-            let expanded = shellexpand::full(dest)
-                .map(|s| s.into_owned())
-                .unwrap_or_else(|_| dest.clone());
-            PathBuf::from(expanded)
+            expand_dest(dest)
         } else {
             self.kind.default_dest()
         }
     }
+
+    /// Get the additional destination paths (with shell variable expansion)
+    pub fn extra_destinations(&self) -> Vec<PathBuf> {
+        self.extra_dests
+            .iter()
+            .map(|d| expand_dest(d.dest()))
+            .collect()
+    }
+
+    /// Include filter of each destination, primary first then the extra
+    /// destinations in order
+    pub fn destination_includes(&self) -> Vec<&[String]> {
+        std::iter::once(self.include.as_slice())
+            .chain(
+                self.extra_dests
+                    .iter()
+                    .map(|extra| extra.include().unwrap_or(&self.include)),
+            )
+            .collect()
+    }
+}
+
+/// An additional destination: a path, or a path with its own `include`
+/// filter that replaces the entry's for that destination
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ExtraDest {
+    Path(String),
+    Filtered {
+        dest: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        include: Option<Vec<String>>,
+    },
+}
+
+impl ExtraDest {
+    /// Destination path as written in the manifest
+    pub fn dest(&self) -> &str {
+        match self {
+            ExtraDest::Path(dest) | ExtraDest::Filtered { dest, .. } => dest,
+        }
+    }
+
+    /// The destination's own include filter, if it sets one
+    pub fn include(&self) -> Option<&[String]> {
+        match self {
+            ExtraDest::Filtered {
+                include: Some(include),
+                ..
+            } => Some(include),
+            _ => None,
+        }
+    }
+}
+
+fn expand_dest(dest: &str) -> PathBuf {
+    let expanded = shellexpand::full(dest)
+        .map(|s| s.into_owned())
+        .unwrap_or_else(|_| dest.to_string());
+    PathBuf::from(expanded)
+}
+
+/// Expected type of an entry's resolved source path
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceKind {
+    /// A single file
+    File,
+    /// A directory
+    Directory,
+}
+
+impl std::fmt::Display for SourceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SourceKind::File => write!(f, "file"),
+            SourceKind::Directory => write!(f, "directory"),
+        }
+    }
+}
+
+/// Character encoding of source text files, converted to UTF-8 on copy
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum SourceEncoding {
+    /// Detect from a byte order mark or UTF-16 byte patterns
+    #[serde(rename = "auto")]
+    Auto,
+    /// UTF-8 (a leading byte order mark is removed)
+    #[serde(rename = "utf-8", alias = "utf8")]
+    Utf8,
+    /// Little-endian UTF-16
+    #[serde(rename = "utf-16le")]
+    Utf16Le,
+    /// Big-endian UTF-16
+    #[serde(rename = "utf-16be")]
+    Utf16Be,
+    /// ISO-8859-1
+    #[serde(rename = "latin1", alias = "iso-8859-1")]
+    Latin1,
+    /// Windows code page 1252
+    #[serde(rename = "windows-1252", alias = "cp1252")]
+    Windows1252,
+}
+
+impl std::fmt::Display for SourceEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            SourceEncoding::Auto => "auto",
+            SourceEncoding::Utf8 => "utf-8",
+            SourceEncoding::Utf16Le => "utf-16le",
+            SourceEncoding::Utf16Be => "utf-16be",
+            SourceEncoding::Latin1 => "latin1",
+            SourceEncoding::Windows1252 => "windows-1252",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Which commit date a copied file's modification time is taken from
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MtimeSource {
+    /// The resolved commit, for every file
+    Commit,
+    /// The commit that last changed each file (the resolved commit for
+    /// files whose last change is outside the searched history)
+    LastChange,
+}
+
+impl std::fmt::Display for MtimeSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MtimeSource::Commit => write!(f, "commit"),
+            MtimeSource::LastChange => write!(f, "last_change"),
+        }
+    }
+}
+
+/// Handling of symlinks found inside a source when copying it
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SymlinkMode {
+    /// Copy the content the link points to into the link's location
+    #[default]
+    Materialize,
+    /// Recreate the link (as a relative link) on Unix; materialize elsewhere
+    Preserve,
+}
+
+impl SymlinkMode {
+    fn is_default(&self) -> bool {
+        *self == SymlinkMode::default()
+    }
 }
 
 /// Asset kinds supported by APS
@@ -136,6 +396,9 @@ impl AssetKind {
 /// Source types for syncing assets
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(tag = "type", rename_all = "lowercase")]
+// Sources are parsed once per manifest entry; boxing the git fields would
+// only complicate every match on them
+#[allow(clippy::large_enum_variant)]
 pub enum Source {
     /// Git repository source
     Git {
@@ -151,6 +414,99 @@ pub enum Source {
         /// Optional path within the repository
         #[serde(default)]
         path: Option<String>,
+        /// Pin to the newest commit on `ref` at or before this timestamp
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        as_of: Option<String>,
+        /// How HTTP redirects are followed: initial (git's default), all, or none
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        follow_redirects: Option<FollowRedirects>,
+        /// Select the newest commit on `ref` whose message matches this
+        /// extended regex instead of the tip
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        message_match: Option<String>,
+        /// Fall back to the remote's default branch when `ref` does not exist
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        fallback_to_default_branch: bool,
+        /// Follow a redirect to a moved (renamed or transferred) repository
+        /// with a warning instead of failing
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        follow_moved: bool,
+        /// Client certificate (PEM path) for servers that require mutual TLS
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        client_cert: Option<String>,
+        /// Private key (PEM path) for `client_cert`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        client_key: Option<String>,
+        /// Branch or tag each `channel:<name>` ref resolves to; channels not
+        /// listed use `release/<name>`
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        channels: BTreeMap<String, String>,
+        /// Only accept a `ref` that is a GPG-signed annotated tag
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        require_signed_tag: bool,
+        /// Key fingerprints allowed to sign the tag (any trusted key when empty)
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        signing_keys: Vec<String>,
+        /// Clone strategies (`shallow`, `full`) tried in order when
+        /// resolving `ref`; overrides `shallow`
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        strategies: Vec<CloneStrategy>,
+        /// Resolve `ref: auto` to the branch the remote's `HEAD` advertises,
+        /// and re-resolve when it no longer matches the locked branch
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        verify_default_branch: bool,
+        /// Branches `ref: auto` tries, in order, instead of the global list
+        /// (`APS_DEFAULT_BRANCHES`, else `main` then `master`)
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        default_branches: Vec<String>,
+        /// User-Agent sent to HTTP(S) git servers
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        user_agent: Option<String>,
+        /// Extra HTTP headers sent with every request; values may reference
+        /// environment variables (`${GATEWAY_KEY}`)
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        http_headers: BTreeMap<String, String>,
+        /// Seconds the remote may take to answer before cloning starts;
+        /// unreachable hosts fail fast instead of waiting on the OS timeout
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        connect_timeout: Option<u64>,
+        /// Seconds an HTTP(S) transfer may go without receiving any data
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        transfer_timeout: Option<u64>,
+        /// Extra git configuration for the clone (e.g. `http.postBuffer`,
+        /// `core.longpaths`), without touching the user's global config
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        git_config: BTreeMap<String, String>,
+        /// Clone only the history newer than this timestamp (RFC 3339 or a
+        /// date) instead of a fixed depth; overrides `shallow`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        shallow_since: Option<String>,
+        /// Full SHA the resolved commit must be; sync fails if the ref has
+        /// moved away from it
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        expected_commit: Option<String>,
+        /// File on the default branch that `indexfile:<channel>` refs look
+        /// channels up in (default `channels.json`)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        index_file: Option<String>,
+        /// Initialize the repository's submodules in the clone
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        submodules: bool,
+        /// Only initialize the submodules at these paths (implies
+        /// `submodules`); paths that are not submodules warn
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        submodule_paths: Vec<String>,
+        /// Fetch `refs/notes/*` and record the note on the resolved commit
+        /// in the lockfile
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        fetch_notes: bool,
+        /// Record the resolved commit's nearest tag description
+        /// (`git describe --tags`) in the lockfile
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        describe: bool,
+        /// Only describe by tags matching this glob (implies `describe`)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        describe_match: Option<String>,
     },
     /// Local filesystem source
     Filesystem {
@@ -181,17 +537,10 @@ impl Source {
     /// Convert this Source to a SourceAdapter implementation
     pub fn to_adapter(&self) -> Box<dyn SourceAdapter> {
         match self {
-            Source::Git {
-                repo,
-                r#ref,
-                shallow,
-                path,
-            } => Box::new(GitSource::new(
-                repo.clone(),
-                r#ref.clone(),
-                *shallow,
-                path.clone(),
-            )),
+            Source::Git { .. } => Box::new(
+                self.git_source()
+                    .expect("git source always builds a git adapter"),
+            ),
             Source::Filesystem {
                 root,
                 symlink,
@@ -200,18 +549,118 @@ impl Source {
         }
     }
 
-    /// Get git source info (repo URL and ref) if this is a git source
-    pub fn git_info(&self) -> Option<(&str, &str)> {
+    /// Build the git adapter if this is a git source
+    pub fn git_source(&self) -> Option<GitSource> {
         match self {
-            Source::Git { repo, r#ref, .. } => Some((repo.as_str(), r#ref.as_str())),
+            Source::Git {
+                repo,
+                r#ref,
+                shallow,
+                path,
+                as_of,
+                message_match,
+                follow_redirects,
+                fallback_to_default_branch,
+                follow_moved,
+                client_cert,
+                client_key,
+                channels,
+                require_signed_tag,
+                signing_keys,
+                strategies,
+                verify_default_branch,
+                default_branches,
+                user_agent,
+                http_headers,
+                connect_timeout,
+                transfer_timeout,
+                git_config,
+                shallow_since,
+                expected_commit,
+                index_file,
+                submodules,
+                submodule_paths,
+                fetch_notes,
+                describe,
+                describe_match,
+            } => Some(
+                GitSource::new(
+                    repo.clone(),
+                    resolve_channel_ref(r#ref, channels).into_owned(),
+                    *shallow,
+                    path.clone(),
+                )
+                .with_as_of(as_of.clone())
+                .with_message_match(message_match.clone())
+                .with_follow_redirects(*follow_redirects)
+                .with_default_branch_fallback(*fallback_to_default_branch)
+                .with_follow_moved(*follow_moved)
+                .with_client_cert(client_cert.clone(), client_key.clone())
+                .with_signed_tag_policy(*require_signed_tag, signing_keys.clone())
+                .with_strategies(strategies.clone())
+                .with_verify_default_branch(*verify_default_branch)
+                .with_default_branches(default_branches.clone())
+                .with_http_identity(user_agent.clone(), http_headers.clone())
+                .with_timeouts(*connect_timeout, *transfer_timeout)
+                .with_git_config(git_config.clone())
+                .with_shallow_since(shallow_since.clone())
+                .with_expected_commit(expected_commit.clone())
+                .with_index_file(index_file.clone())
+                .with_submodules(*submodules, submodule_paths.clone())
+                .with_fetch_notes(*fetch_notes)
+                .with_describe(*describe, describe_match.clone()),
+            ),
             Source::Filesystem { .. } => None,
         }
     }
 
-    /// Get the path within a git source (for cloning at specific commits)
-    pub fn git_path(&self) -> Option<&str> {
+    /// Get git source info (repo URL and ref) if this is a git source.
+    ///
+    /// A `channel:<name>` ref is returned as the branch or tag it maps to.
+    pub fn git_info(&self) -> Option<(&str, Cow<'_, str>)> {
         match self {
-            Source::Git { path, .. } => path.as_deref(),
+            Source::Git {
+                repo,
+                r#ref,
+                channels,
+                ..
+            } => Some((repo.as_str(), resolve_channel_ref(r#ref, channels))),
+            Source::Filesystem { .. } => None,
+        }
+    }
+
+    /// Transport settings (client certificates, headers, extra git config)
+    /// for git commands run against this source; defaults for filesystem
+    /// sources
+    pub fn clone_options(&self) -> CloneOptions {
+        self.git_source()
+            .map(|git| git.clone_options())
+            .unwrap_or_default()
+    }
+
+    /// Get the `as_of` timestamp if this is a git source pinned to a date
+    pub fn as_of(&self) -> Option<&str> {
+        match self {
+            Source::Git { as_of, .. } => as_of.as_deref(),
+            Source::Filesystem { .. } => None,
+        }
+    }
+
+    /// Whether `ref: auto` follows the remote's advertised `HEAD` branch
+    pub fn verify_default_branch(&self) -> bool {
+        match self {
+            Source::Git {
+                verify_default_branch,
+                ..
+            } => *verify_default_branch,
+            Source::Filesystem { .. } => false,
+        }
+    }
+
+    /// Get the commit message pattern if this git source selects commits by message
+    pub fn message_match(&self) -> Option<&str> {
+        match self {
+            Source::Git { message_match, .. } => message_match.as_deref(),
             Source::Filesystem { .. } => None,
         }
     }
@@ -238,6 +687,22 @@ impl Source {
     }
 }
 
+/// Map a `channel:<name>` ref to its branch or tag: the entry for `name` in
+/// `channels`, or `release/<name>` by convention. Other refs are returned
+/// unchanged.
+pub fn resolve_channel_ref<'a>(
+    git_ref: &'a str,
+    channels: &'a BTreeMap<String, String>,
+) -> Cow<'a, str> {
+    match git_ref.strip_prefix(CHANNEL_REF_PREFIX) {
+        Some(channel) => match channels.get(channel) {
+            Some(mapped) => Cow::Borrowed(mapped.as_str()),
+            None => Cow::Owned(format!("release/{}", channel)),
+        },
+        None => Cow::Borrowed(git_ref),
+    }
+}
+
 /// Discover and load a manifest
 pub fn discover_manifest(override_path: Option<&Path>) -> Result<(Manifest, PathBuf)> {
     let manifest_path = if let Some(path) = override_path {
@@ -310,6 +775,30 @@ pub fn validate_manifest(manifest: &Manifest) -> Result<()> {
             });
         }
 
+        // Reject malformed as_of timestamps before any cloning happens
+        for source in entry.source.iter().chain(&entry.sources) {
+            if let Some(as_of) = source.as_of() {
+                parse_as_of(as_of)?;
+            }
+            if let Source::Git {
+                git_config,
+                shallow_since,
+                expected_commit,
+                ..
+            } = source
+            {
+                check_git_config(git_config)?;
+                if let Some(since) = shallow_since {
+                    parse_shallow_since(since)?;
+                }
+                if let Some(sha) = expected_commit {
+                    if !is_full_commit_sha(sha) {
+                        return Err(ApsError::InvalidExpectedCommit { value: sha.clone() });
+                    }
+                }
+            }
+        }
+
         // Validate source configuration based on kind
         if entry.kind == AssetKind::CompositeAgentsMd {
             // Composite entries require sources array
@@ -318,6 +807,11 @@ pub fn validate_manifest(manifest: &Manifest) -> Result<()> {
                     id: entry.id.clone(),
                 });
             }
+            if !entry.extra_dests.is_empty() {
+                return Err(ApsError::CompositeExtraDests {
+                    id: entry.id.clone(),
+                });
+            }
         } else {
             // Non-composite entries require single source
             if entry.source.is_none() {
@@ -344,6 +838,25 @@ pub fn manifest_dir(manifest_path: &Path) -> PathBuf {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_channel_refs_map_to_branches() {
+        let channels = BTreeMap::from([("beta".to_string(), "next".to_string())]);
+        assert_eq!(resolve_channel_ref("channel:beta", &channels), "next");
+        assert_eq!(
+            resolve_channel_ref("channel:stable", &channels),
+            "release/stable"
+        );
+        assert_eq!(resolve_channel_ref("main", &channels), "main");
+
+        let source: Source = serde_yaml::from_str(
+            "type: git\nrepo: https://example.com/r.git\nref: channel:beta\nchannels:\n  beta: next\n",
+        )
+        .unwrap();
+        let (_, git_ref) = source.git_info().unwrap();
+        assert_eq!(git_ref, "next");
+        assert_eq!(source.git_source().unwrap().git_ref, "next");
+    }
+
     #[test]
     fn test_entry_destination_default() {
         let entry = Entry {
@@ -356,7 +869,26 @@ mod tests {
             }),
             sources: Vec::new(),
             dest: None,
+            extra_dests: Vec::new(),
             include: Vec::new(),
+            required_files: Vec::new(),
+            placeholders: false,
+            source_symlinks: SymlinkMode::default(),
+            source_kind: None,
+            codeowners: false,
+            licenses: false,
+            last_changed: false,
+            encoding: None,
+            ensure_final_newline: false,
+            trim_trailing_whitespace: false,
+            exclude_vcs: false,
+            max_files: None,
+            metrics: false,
+            validate_notebooks: false,
+            strip_notebook_outputs: false,
+            preserve_mtime: None,
+            include_export_ignored: false,
+            submodule_pins: false,
         };
 
         assert_eq!(entry.destination(), PathBuf::from("AGENTS.md"));
@@ -375,6 +907,25 @@ mod tests {
             sources: Vec::new(),
             dest: Some("custom/path/AGENTS.md".to_string()),
             include: Vec::new(),
+            required_files: Vec::new(),
+            extra_dests: Vec::new(),
+            placeholders: false,
+            source_symlinks: SymlinkMode::default(),
+            source_kind: None,
+            codeowners: false,
+            licenses: false,
+            last_changed: false,
+            encoding: None,
+            ensure_final_newline: false,
+            trim_trailing_whitespace: false,
+            exclude_vcs: false,
+            max_files: None,
+            metrics: false,
+            validate_notebooks: false,
+            strip_notebook_outputs: false,
+            preserve_mtime: None,
+            include_export_ignored: false,
+            submodule_pins: false,
         };
 
         assert_eq!(entry.destination(), PathBuf::from("custom/path/AGENTS.md"));
@@ -395,6 +946,25 @@ mod tests {
             sources: Vec::new(),
             dest: Some("$TEST_DEST_VAR/AGENTS.md".to_string()),
             include: Vec::new(),
+            required_files: Vec::new(),
+            extra_dests: Vec::new(),
+            placeholders: false,
+            source_symlinks: SymlinkMode::default(),
+            source_kind: None,
+            codeowners: false,
+            licenses: false,
+            last_changed: false,
+            encoding: None,
+            ensure_final_newline: false,
+            trim_trailing_whitespace: false,
+            exclude_vcs: false,
+            max_files: None,
+            metrics: false,
+            validate_notebooks: false,
+            strip_notebook_outputs: false,
+            preserve_mtime: None,
+            include_export_ignored: false,
+            submodule_pins: false,
         };
 
         assert_eq!(entry.destination(), PathBuf::from("/custom/dest/AGENTS.md"));
@@ -415,6 +985,25 @@ mod tests {
             sources: Vec::new(),
             dest: Some("~/agents/AGENTS.md".to_string()),
             include: Vec::new(),
+            required_files: Vec::new(),
+            extra_dests: Vec::new(),
+            placeholders: false,
+            source_symlinks: SymlinkMode::default(),
+            source_kind: None,
+            codeowners: false,
+            licenses: false,
+            last_changed: false,
+            encoding: None,
+            ensure_final_newline: false,
+            trim_trailing_whitespace: false,
+            exclude_vcs: false,
+            max_files: None,
+            metrics: false,
+            validate_notebooks: false,
+            strip_notebook_outputs: false,
+            preserve_mtime: None,
+            include_export_ignored: false,
+            submodule_pins: false,
         };
 
         let result = entry.destination();
@@ -442,7 +1031,26 @@ mod tests {
                 },
             ],
             dest: None,
+            extra_dests: Vec::new(),
             include: Vec::new(),
+            required_files: Vec::new(),
+            placeholders: false,
+            source_symlinks: SymlinkMode::default(),
+            source_kind: None,
+            codeowners: false,
+            licenses: false,
+            last_changed: false,
+            encoding: None,
+            ensure_final_newline: false,
+            trim_trailing_whitespace: false,
+            exclude_vcs: false,
+            max_files: None,
+            metrics: false,
+            validate_notebooks: false,
+            strip_notebook_outputs: false,
+            preserve_mtime: None,
+            include_export_ignored: false,
+            submodule_pins: false,
         };
 
         assert!(entry.is_composite());
@@ -469,6 +1077,32 @@ mod tests {
                     r#ref: "main".to_string(),
                     shallow: true,
                     path: Some("AGENTS.md".to_string()),
+                    as_of: None,
+                    message_match: None,
+                    follow_redirects: None,
+                    fallback_to_default_branch: false,
+                    follow_moved: false,
+                    client_cert: None,
+                    client_key: None,
+                    channels: BTreeMap::new(),
+                    require_signed_tag: false,
+                    signing_keys: Vec::new(),
+                    strategies: Vec::new(),
+                    verify_default_branch: false,
+                    default_branches: Vec::new(),
+                    user_agent: None,
+                    http_headers: BTreeMap::new(),
+                    connect_timeout: None,
+                    transfer_timeout: None,
+                    git_config: BTreeMap::new(),
+                    shallow_since: None,
+                    expected_commit: None,
+                    index_file: None,
+                    submodules: false,
+                    submodule_paths: Vec::new(),
+                    fetch_notes: false,
+                    describe: false,
+                    describe_match: None,
                 },
                 // Another filesystem source
                 Source::Filesystem {
@@ -479,6 +1113,25 @@ mod tests {
             ],
             dest: Some("./AGENTS.md".to_string()),
             include: Vec::new(),
+            required_files: Vec::new(),
+            extra_dests: Vec::new(),
+            placeholders: false,
+            source_symlinks: SymlinkMode::default(),
+            source_kind: None,
+            codeowners: false,
+            licenses: false,
+            last_changed: false,
+            encoding: None,
+            ensure_final_newline: false,
+            trim_trailing_whitespace: false,
+            exclude_vcs: false,
+            max_files: None,
+            metrics: false,
+            validate_notebooks: false,
+            strip_notebook_outputs: false,
+            preserve_mtime: None,
+            include_export_ignored: false,
+            submodule_pins: false,
         };
 
         assert!(entry.is_composite());
@@ -0,0 +1,295 @@
+//! Jupyter notebook (`.ipynb`) validation and output stripping.
+//!
+//! Both are opt-in per entry:
+//! - `validate_notebooks` parses every synced notebook and checks the
+//!   structure Jupyter needs: a JSON object with `cells`, `metadata`,
+//!   `nbformat` and `nbformat_minor`, and cells with a `cell_type` and
+//!   `source`. The first problem is reported with the field it concerns.
+//! - `strip_notebook_outputs` empties the `outputs` and resets the
+//!   `execution_count` of code cells while copying, so synced notebooks
+//!   diff cleanly. Notebooks that have nothing to strip are copied
+//!   byte-for-byte; stripped ones are written the way Jupyter writes them
+//!   (one-space indent, keys in their original order, trailing newline).
+
+use serde_yaml::{Mapping, Value};
+use std::fmt::Write;
+use std::path::Path;
+
+/// File extension of Jupyter notebooks
+const NOTEBOOK_EXTENSION: &str = "ipynb";
+
+/// Whether `path` is a Jupyter notebook
+pub fn is_notebook(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(NOTEBOOK_EXTENSION))
+}
+
+/// Parse notebook JSON (JSON is read as the YAML subset it is)
+fn parse(bytes: &[u8]) -> Result<Mapping, String> {
+    let text = std::str::from_utf8(bytes).map_err(|e| format!("not UTF-8: {}", e))?;
+    if !text.trim_start().starts_with('{') {
+        return Err("not a JSON object".to_string());
+    }
+    match serde_yaml::from_str(text) {
+        Ok(Value::Mapping(map)) => Ok(map),
+        Ok(_) => Err("not a JSON object".to_string()),
+        Err(e) => Err(format!("invalid JSON: {}", e)),
+    }
+}
+
+/// Check a notebook's structure, describing the first missing or invalid
+/// field
+pub fn validate_notebook(bytes: &[u8]) -> Result<(), String> {
+    let notebook = parse(bytes)?;
+    let field = |name: &str| notebook.get(name);
+
+    match field("nbformat") {
+        Some(Value::Number(n)) if n.is_u64() => {}
+        Some(_) => return Err("`nbformat` must be an integer".to_string()),
+        None => return Err("missing `nbformat`".to_string()),
+    }
+    match field("nbformat_minor") {
+        Some(Value::Number(n)) if n.is_u64() => {}
+        Some(_) => return Err("`nbformat_minor` must be an integer".to_string()),
+        None => return Err("missing `nbformat_minor`".to_string()),
+    }
+    match field("metadata") {
+        Some(Value::Mapping(_)) => {}
+        Some(_) => return Err("`metadata` must be an object".to_string()),
+        None => return Err("missing `metadata`".to_string()),
+    }
+    let cells = match field("cells") {
+        Some(Value::Sequence(cells)) => cells,
+        Some(_) => return Err("`cells` must be an array".to_string()),
+        None => return Err("missing `cells`".to_string()),
+    };
+
+    for (i, cell) in cells.iter().enumerate() {
+        let Value::Mapping(cell) = cell else {
+            return Err(format!("`cells[{}]` must be an object", i));
+        };
+        let cell_type = match cell.get("cell_type") {
+            Some(Value::String(t)) => t.as_str(),
+            Some(_) => return Err(format!("`cells[{}].cell_type` must be a string", i)),
+            None => return Err(format!("missing `cells[{}].cell_type`", i)),
+        };
+        match cell.get("source") {
+            Some(Value::String(_)) => {}
+            Some(Value::Sequence(lines)) if lines.iter().all(Value::is_string) => {}
+            Some(_) => {
+                return Err(format!(
+                    "`cells[{}].source` must be a string or an array of strings",
+                    i
+                ))
+            }
+            None => return Err(format!("missing `cells[{}].source`", i)),
+        }
+        if cell_type == "code" && !matches!(cell.get("outputs"), Some(Value::Sequence(_))) {
+            return Err(format!("`cells[{}].outputs` must be an array", i));
+        }
+    }
+    Ok(())
+}
+
+/// Clear code cell outputs and execution counts.
+///
+/// Returns `Ok(None)` when there was nothing to strip.
+pub fn strip_outputs(bytes: &[u8]) -> Result<Option<Vec<u8>>, String> {
+    let mut notebook = parse(bytes)?;
+    let Some(Value::Sequence(cells)) = notebook.get_mut("cells") else {
+        return Ok(None);
+    };
+
+    let mut changed = false;
+    for cell in cells.iter_mut() {
+        let Value::Mapping(cell) = cell else {
+            continue;
+        };
+        if cell.get("cell_type").and_then(Value::as_str) != Some("code") {
+            continue;
+        }
+        if let Some(outputs) = cell.get_mut("outputs") {
+            if !matches!(outputs, Value::Sequence(s) if s.is_empty()) {
+                *outputs = Value::Sequence(Vec::new());
+                changed = true;
+            }
+        }
+        if let Some(count) = cell.get_mut("execution_count") {
+            if !count.is_null() {
+                *count = Value::Null;
+                changed = true;
+            }
+        }
+    }
+    if !changed {
+        return Ok(None);
+    }
+
+    let mut out = String::with_capacity(bytes.len());
+    write_json(&mut out, &Value::Mapping(notebook), 0)?;
+    out.push('\n');
+    Ok(Some(out.into_bytes()))
+}
+
+/// Write `value` as JSON indented by one space per level, as Jupyter does
+fn write_json(out: &mut String, value: &Value, depth: usize) -> Result<(), String> {
+    let indent = |out: &mut String, depth: usize| {
+        out.push('\n');
+        out.extend(std::iter::repeat_n(' ', depth));
+    };
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => {
+            if n.as_f64().is_some_and(|f| !f.is_finite()) {
+                return Err(format!("number {} cannot be written as JSON", n));
+            }
+            let _ = write!(out, "{}", n);
+        }
+        Value::String(s) => write_string(out, s),
+        Value::Sequence(items) if items.is_empty() => out.push_str("[]"),
+        Value::Sequence(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                indent(out, depth + 1);
+                write_json(out, item, depth + 1)?;
+            }
+            indent(out, depth);
+            out.push(']');
+        }
+        Value::Mapping(map) if map.is_empty() => out.push_str("{}"),
+        Value::Mapping(map) => {
+            out.push('{');
+            for (i, (key, item)) in map.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                indent(out, depth + 1);
+                let Value::String(key) = key else {
+                    return Err("object keys must be strings".to_string());
+                };
+                write_string(out, key);
+                out.push_str(": ");
+                write_json(out, item, depth + 1)?;
+            }
+            indent(out, depth);
+            out.push('}');
+        }
+        Value::Tagged(_) => return Err("unexpected YAML tag".to_string()),
+    }
+    Ok(())
+}
+
+/// Write a JSON string literal, escaping only what JSON requires
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTEBOOK: &str = r##"{
+ "cells": [
+  {
+   "cell_type": "markdown",
+   "metadata": {},
+   "source": [
+    "# Prompt \"review\"\n",
+    "Résumé"
+   ]
+  },
+  {
+   "cell_type": "code",
+   "execution_count": 3,
+   "metadata": {},
+   "outputs": [
+    {
+     "name": "stdout",
+     "output_type": "stream",
+     "text": [
+      "ok\n"
+     ]
+    }
+   ],
+   "source": "print('ok')"
+  }
+ ],
+ "metadata": {
+  "kernelspec": {
+   "name": "python3"
+  }
+ },
+ "nbformat": 4,
+ "nbformat_minor": 5
+}
+"##;
+
+    #[test]
+    fn test_validate_reports_the_offending_field() {
+        validate_notebook(NOTEBOOK.as_bytes()).unwrap();
+
+        let check = |json: &str| validate_notebook(json.as_bytes()).unwrap_err();
+        assert_eq!(
+            check(r#"{"metadata": {}, "nbformat": 4, "nbformat_minor": 5}"#),
+            "missing `cells`"
+        );
+        assert_eq!(
+            check(
+                r#"{"cells": [{"source": ""}], "metadata": {}, "nbformat": 4, "nbformat_minor": 5}"#
+            ),
+            "missing `cells[0].cell_type`"
+        );
+        assert_eq!(
+            check(r#"{"cells": [], "metadata": {}, "nbformat": "4", "nbformat_minor": 5}"#),
+            "`nbformat` must be an integer"
+        );
+        assert!(check("{\"cells\": [").starts_with("invalid JSON"));
+        assert_eq!(check("cells: []"), "not a JSON object");
+    }
+
+    #[test]
+    fn test_strip_outputs_rewrites_only_when_needed() {
+        let stripped = strip_outputs(NOTEBOOK.as_bytes()).unwrap().unwrap();
+        let expected = NOTEBOOK
+            .replace("\"execution_count\": 3", "\"execution_count\": null")
+            .replace(
+                r#""outputs": [
+    {
+     "name": "stdout",
+     "output_type": "stream",
+     "text": [
+      "ok\n"
+     ]
+    }
+   ],"#,
+                r#""outputs": [],"#,
+            );
+        assert_eq!(String::from_utf8(stripped.clone()).unwrap(), expected);
+        validate_notebook(&stripped).unwrap();
+
+        // Already clean: copied unchanged
+        assert_eq!(strip_outputs(&stripped).unwrap(), None);
+        assert!(is_notebook(Path::new("prompts/review.IPYNB")));
+        assert!(!is_notebook(Path::new("prompts/review.json")));
+    }
+}
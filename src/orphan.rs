@@ -271,8 +271,8 @@ fn delete_orphan(orphan: &OrphanedPath, manifest_dir: &Path) -> Result<()> {
         debug!("Removed symlink at {:?}", path);
     } else if path.is_file() {
         // Regular file - backup first
-        let backup_path = create_backup(manifest_dir, path)?;
-        println!("  Backed up to: {:?}", backup_path);
+        let backup = create_backup(manifest_dir, path)?;
+        println!("  Backed up to: {:?}", backup.path());
 
         std::fs::remove_file(path)
             .map_err(|e| ApsError::io(e, format!("Failed to remove file {:?}", path)))?;
@@ -286,8 +286,8 @@ fn delete_orphan(orphan: &OrphanedPath, manifest_dir: &Path) -> Result<()> {
             debug!("Removed aps-managed directory at {:?}", path);
         } else {
             // Directory with non-symlink content - backup first
-            let backup_path = create_backup(manifest_dir, path)?;
-            println!("  Backed up to: {:?}", backup_path);
+            let backup = create_backup(manifest_dir, path)?;
+            println!("  Backed up to: {:?}", backup.path());
 
             std::fs::remove_dir_all(path)
                 .map_err(|e| ApsError::io(e, format!("Failed to remove directory {:?}", path)))?;
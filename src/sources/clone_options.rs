@@ -0,0 +1,725 @@
+//! Builder for configuring how git sources are cloned.
+
+use crate::error::{ApsError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::time::Duration;
+
+/// Environment variable listing the branches `ref: auto` tries, in order and
+/// comma-separated (e.g. `trunk,main,master`), for sources that do not set
+/// `default_branches`
+pub const DEFAULT_BRANCHES_ENV: &str = "APS_DEFAULT_BRANCHES";
+
+/// Number of `GIT_CONFIG_KEY_<n>` / `GIT_CONFIG_VALUE_<n>` pairs git reads
+/// from its environment
+const GIT_CONFIG_COUNT_ENV: &str = "GIT_CONFIG_COUNT";
+
+/// Branches `ref: auto` tries when nothing else is configured
+const BUILTIN_DEFAULT_BRANCHES: &[&str] = &["main", "master"];
+
+/// Branches `ref: auto` tries, in order: `configured` when non-empty, else
+/// the `APS_DEFAULT_BRANCHES` list, else `main` then `master`
+pub fn default_branches(configured: &[String]) -> Vec<String> {
+    if !configured.is_empty() {
+        return configured.to_vec();
+    }
+    let from_env: Vec<String> = std::env::var(DEFAULT_BRANCHES_ENV)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect();
+    if !from_env.is_empty() {
+        return from_env;
+    }
+    BUILTIN_DEFAULT_BRANCHES
+        .iter()
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// How git follows HTTP redirects (`http.followRedirects`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FollowRedirects {
+    /// Follow redirects only for the initial request (git's default); later
+    /// requests go to the redirected base URL
+    #[default]
+    Initial,
+    /// Follow every redirect
+    All,
+    /// Never follow redirects
+    #[serde(rename = "none")]
+    Never,
+}
+
+impl FollowRedirects {
+    /// Value for git's `http.followRedirects` setting
+    fn config_value(self) -> &'static str {
+        match self {
+            FollowRedirects::Initial => "initial",
+            FollowRedirects::All => "true",
+            FollowRedirects::Never => "false",
+        }
+    }
+}
+
+/// A way of cloning a repository, tried in order by
+/// [`CloneOptions::strategies`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CloneStrategy {
+    /// Only the tip commit (`--depth 1`)
+    Shallow,
+    /// Full history
+    Full,
+}
+
+impl std::fmt::Display for CloneStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CloneStrategy::Shallow => "shallow",
+            CloneStrategy::Full => "full",
+        })
+    }
+}
+
+/// What a clone is for, set with [`CloneOptions::intent`] to pick a history
+/// depth that suits the operation instead of a fixed one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CloneIntent {
+    /// Only the files at the ref are read: the tip commit (`--depth 1`)
+    CheckoutOnly,
+    /// The ref is compared with a locked commit: start from the tip and
+    /// deepen until `locked` is in the clone
+    NeedDiffAgainstLock { locked: String },
+    /// History is walked (logs, blame, last-changed commits): full history
+    NeedFullHistory,
+}
+
+/// Extra HTTP headers sent with every request; values are kept out of
+/// `Debug` output since they often carry gateway keys
+#[derive(Clone, Default)]
+struct HttpHeaders(Vec<(String, String)>);
+
+impl std::fmt::Debug for HttpHeaders {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.0.iter().map(|(name, _)| (name, "***")))
+            .finish()
+    }
+}
+
+/// Options controlling a `git clone`.
+///
+/// The defaults match a plain `git clone` of a single branch: full history,
+/// tags as git fetches them by default, and no submodules.
+///
+/// ```ignore
+/// let options = CloneOptions::new().depth(1).submodules(true);
+/// clone_and_resolve(url, "main", &options)?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CloneOptions {
+    depth: Option<u32>,
+    submodules: bool,
+    submodule_paths: Vec<String>,
+    follow_redirects: Option<FollowRedirects>,
+    describe: bool,
+    describe_match: Option<String>,
+    default_branch_fallback: bool,
+    default_branches: Vec<String>,
+    client_cert: Option<String>,
+    client_key: Option<String>,
+    follow_moved: bool,
+    fetch_notes: bool,
+    strategies: Vec<CloneStrategy>,
+    user_agent: Option<String>,
+    http_headers: HttpHeaders,
+    connect_timeout: Option<Duration>,
+    transfer_timeout: Option<Duration>,
+    git_config: BTreeMap<String, String>,
+    shallow_since: Option<String>,
+    expected_commit: Option<String>,
+    intent: Option<CloneIntent>,
+}
+
+impl CloneOptions {
+    /// Create options with default (full clone) behavior
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit history to the given number of commits (`--depth`); replaces
+    /// any `shallow_since`
+    pub fn depth(mut self, depth: u32) -> Self {
+        self.depth = Some(depth);
+        self.shallow_since = None;
+        self
+    }
+
+    /// Shorthand for `depth(1)` when `shallow` is true, full history otherwise
+    pub fn shallow(mut self, shallow: bool) -> Self {
+        self.depth = shallow.then_some(1);
+        self.shallow_since = None;
+        self
+    }
+
+    /// Limit history to commits newer than `date` (`--shallow-since`), an
+    /// ISO 8601 timestamp; replaces any depth, as git cannot combine them
+    pub fn shallow_since(mut self, date: impl Into<String>) -> Self {
+        self.depth = None;
+        self.shallow_since = Some(date.into());
+        self
+    }
+
+    /// Initialize and clone submodules (`--recurse-submodules`)
+    pub fn submodules(mut self, submodules: bool) -> Self {
+        self.submodules = submodules;
+        self
+    }
+
+    /// Initialize only the submodules at these paths, leaving the others
+    /// uninitialized (implies `submodules(true)`)
+    pub fn submodule_paths<I, S>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.submodules = true;
+        self.submodule_paths = paths.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Control how HTTP redirects are followed (`http.followRedirects`)
+    pub fn follow_redirects(mut self, follow: FollowRedirects) -> Self {
+        self.follow_redirects = Some(follow);
+        self
+    }
+
+    /// Describe the resolved commit by its nearest tag (`git describe --tags`)
+    pub fn describe(mut self, describe: bool) -> Self {
+        self.describe = describe;
+        self
+    }
+
+    /// Only consider tags matching this glob when describing the commit
+    /// (implies `describe(true)`)
+    pub fn describe_match(mut self, pattern: impl Into<String>) -> Self {
+        self.describe = true;
+        self.describe_match = Some(pattern.into());
+        self
+    }
+
+    /// Clone the remote's default branch (with a warning) when the requested
+    /// branch does not exist, instead of failing
+    pub fn default_branch_fallback(mut self, fallback: bool) -> Self {
+        self.default_branch_fallback = fallback;
+        self
+    }
+
+    /// Branches `ref: auto` tries, in order, instead of the global list
+    pub fn default_branches(
+        mut self,
+        branches: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.default_branches = branches.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Present a client certificate for mutual TLS (`http.sslCert`)
+    pub fn client_cert(mut self, path: impl Into<String>) -> Self {
+        self.client_cert = Some(path.into());
+        self
+    }
+
+    /// Private key for the client certificate (`http.sslKey`); may be
+    /// omitted when the certificate file also holds the key
+    pub fn client_key(mut self, path: impl Into<String>) -> Self {
+        self.client_key = Some(path.into());
+        self
+    }
+
+    /// Accept a redirect to a different repository (a renamed or transferred
+    /// repo) with a warning, instead of failing
+    pub fn follow_moved(mut self, follow: bool) -> Self {
+        self.follow_moved = follow;
+        self
+    }
+
+    /// Fetch `refs/notes/*` and read the note attached to the resolved commit
+    pub fn fetch_notes(mut self, fetch: bool) -> Self {
+        self.fetch_notes = fetch;
+        self
+    }
+
+    /// Try these strategies in order when cloning a ref, falling back to the
+    /// next one when a clone fails; each overrides the configured depth.
+    /// Empty (the default) makes a single clone with the configured depth.
+    pub fn strategies(mut self, strategies: impl IntoIterator<Item = CloneStrategy>) -> Self {
+        self.strategies = strategies.into_iter().collect();
+        self
+    }
+
+    /// Identify as this User-Agent to HTTP(S) servers (`http.userAgent`)
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Send an extra header with every HTTP(S) request (`http.extraHeader`);
+    /// may be called repeatedly
+    pub fn http_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.http_headers.0.push((name.into(), value.into()));
+        self
+    }
+
+    /// Fail when the remote has not answered a first `ls-remote` within
+    /// this long, before any clone starts
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Abort an HTTP(S) transfer that has received no data for this long
+    /// (`http.lowSpeedLimit=1` with `http.lowSpeedTime`); slow but steady
+    /// transfers are not affected
+    pub fn transfer_timeout(mut self, timeout: Duration) -> Self {
+        self.transfer_timeout = Some(timeout);
+        self
+    }
+
+    /// Configured clone depth, if history is limited
+    pub fn get_depth(&self) -> Option<u32> {
+        self.depth
+    }
+
+    /// Whether submodules are initialized
+    pub fn get_submodules(&self) -> bool {
+        self.submodules
+    }
+
+    /// Submodule paths to initialize; empty means all of them
+    pub fn get_submodule_paths(&self) -> &[String] {
+        &self.submodule_paths
+    }
+
+    /// Whether the resolved commit is described by its nearest tag
+    pub fn get_describe(&self) -> bool {
+        self.describe
+    }
+
+    /// Configured tag pattern for describing commits, if any
+    pub fn get_describe_match(&self) -> Option<&str> {
+        self.describe_match.as_deref()
+    }
+
+    /// Whether a missing branch falls back to the remote's default branch
+    pub fn get_default_branch_fallback(&self) -> bool {
+        self.default_branch_fallback
+    }
+
+    /// Branches `ref: auto` tries, in order (see [`default_branches`](fn@default_branches))
+    pub fn get_default_branches(&self) -> Vec<String> {
+        default_branches(&self.default_branches)
+    }
+
+    /// Whether a redirect to a moved repository is followed
+    pub fn get_follow_moved(&self) -> bool {
+        self.follow_moved
+    }
+
+    /// Whether commit notes are fetched
+    pub fn get_fetch_notes(&self) -> bool {
+        self.fetch_notes
+    }
+
+    /// Clone strategies in preference order; empty when not configured
+    pub fn get_strategies(&self) -> &[CloneStrategy] {
+        &self.strategies
+    }
+
+    /// Extra git configuration for the clone (`-c key=value` on every
+    /// command, and saved to the clone's config for later ones); may be
+    /// called repeatedly. Check keys with [`check_git_config`] first.
+    pub fn git_config(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.git_config.insert(key.into(), value.into());
+        self
+    }
+
+    /// Validate the extra git configuration
+    pub fn check_git_config(&self) -> Result<()> {
+        check_git_config(&self.git_config)
+    }
+
+    /// Configured history cut-off date, if history is limited by date
+    pub fn get_shallow_since(&self) -> Option<&str> {
+        self.shallow_since.as_deref()
+    }
+
+    /// Require the ref to resolve to exactly this commit (full SHA); a
+    /// mismatch fails with [`ApsError::UnexpectedCommit`](crate::error::ApsError::UnexpectedCommit)
+    pub fn expect_commit(mut self, sha: impl Into<String>) -> Self {
+        self.expected_commit = Some(sha.into());
+        self
+    }
+
+    /// Commit the ref must resolve to, if pinned
+    pub fn get_expected_commit(&self) -> Option<&str> {
+        self.expected_commit.as_deref()
+    }
+
+    /// Choose the history depth for what the clone is for (see
+    /// [`CloneIntent`]); replaces any depth or `shallow_since`, so set
+    /// it last. A diff intent deepens the clone after cloning until the
+    /// locked commit is present.
+    pub fn intent(self, intent: CloneIntent) -> Self {
+        let mut options = self.shallow(intent != CloneIntent::NeedFullHistory);
+        options.intent = Some(intent);
+        options
+    }
+
+    /// Declared intent, if any
+    pub fn get_intent(&self) -> Option<&CloneIntent> {
+        self.intent.as_ref()
+    }
+
+    /// Whether history is limited by depth or by date
+    pub fn is_shallow(&self) -> bool {
+        self.depth.is_some() || self.shallow_since.is_some()
+    }
+
+    /// Configured bound on the connect phase, if any
+    pub fn get_connect_timeout(&self) -> Option<Duration> {
+        self.connect_timeout
+    }
+
+    /// `-c key=value` arguments placed before the git subcommand
+    pub(crate) fn config_args(&self) -> Vec<OsString> {
+        let mut args = Vec::new();
+        if let Some(follow) = self.follow_redirects {
+            args.push("-c".into());
+            args.push(format!("http.followRedirects={}", follow.config_value()).into());
+        }
+        if let Some(ref cert) = self.client_cert {
+            args.push("-c".into());
+            args.push(format!("http.sslCert={}", cert).into());
+        }
+        if let Some(ref key) = self.client_key {
+            args.push("-c".into());
+            args.push(format!("http.sslKey={}", key).into());
+        }
+        if let Some(ref user_agent) = self.user_agent {
+            args.push("-c".into());
+            args.push(format!("http.userAgent={}", user_agent).into());
+        }
+        if let Some(timeout) = self.transfer_timeout {
+            // git only accepts whole seconds; round up so a short timeout
+            // never becomes "no limit"
+            let seconds = timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0);
+            args.push("-c".into());
+            args.push("http.lowSpeedLimit=1".into());
+            args.push("-c".into());
+            args.push(format!("http.lowSpeedTime={}", seconds.max(1)).into());
+        }
+        for (key, value) in &self.git_config {
+            args.push("-c".into());
+            args.push(format!("{}={}", key, value).into());
+        }
+        args
+    }
+
+    /// Environment carrying the HTTP headers as `GIT_CONFIG_KEY_<n>` /
+    /// `GIT_CONFIG_VALUE_<n>` pairs, so their values stay off git's command
+    /// line (visible to other local users in `ps`). Numbering continues
+    /// after any config the environment already passes this way.
+    pub(crate) fn config_env(&self) -> Vec<(String, String)> {
+        let inherited = std::env::var(GIT_CONFIG_COUNT_ENV)
+            .ok()
+            .and_then(|count| count.parse::<usize>().ok())
+            .unwrap_or(0);
+        self.config_env_after(inherited)
+    }
+
+    fn config_env_after(&self, inherited: usize) -> Vec<(String, String)> {
+        let headers = &self.http_headers.0;
+        if headers.is_empty() {
+            return Vec::new();
+        }
+        let mut env = Vec::new();
+        for (n, (name, value)) in (inherited..).zip(headers) {
+            env.push((
+                format!("GIT_CONFIG_KEY_{}", n),
+                "http.extraHeader".to_string(),
+            ));
+            env.push((
+                format!("GIT_CONFIG_VALUE_{}", n),
+                format!("{}: {}", name, value),
+            ));
+        }
+        env.push((
+            GIT_CONFIG_COUNT_ENV.to_string(),
+            (inherited + headers.len()).to_string(),
+        ));
+        env
+    }
+
+    /// Pathspec limiting `git submodule update` to the allowlisted paths
+    pub(crate) fn submodule_pathspec(&self) -> Vec<OsString> {
+        if self.submodule_paths.is_empty() {
+            return Vec::new();
+        }
+        std::iter::once("--".into())
+            .chain(self.submodule_paths.iter().map(OsString::from))
+            .collect()
+    }
+
+    /// Flags appended to `git clone`
+    pub(crate) fn clone_args(&self) -> Vec<OsString> {
+        let mut args: Vec<OsString> = Vec::new();
+        if let Some(depth) = self.depth {
+            args.push("--depth".into());
+            args.push(depth.to_string().into());
+        }
+        if let Some(ref since) = self.shallow_since {
+            args.push(format!("--shallow-since={}", since).into());
+        }
+        if self.submodules {
+            if self.submodule_paths.is_empty() {
+                args.push("--recurse-submodules".into());
+            }
+            for path in &self.submodule_paths {
+                args.push(format!("--recurse-submodules={}", path).into());
+            }
+            if self.is_shallow() {
+                args.push("--shallow-submodules".into());
+            }
+        }
+        // Persist the extra config so commands run in the clone later
+        // (checkouts, fetches) see it too
+        for (key, value) in &self.git_config {
+            args.push("--config".into());
+            args.push(format!("{}={}", key, value).into());
+        }
+        args
+    }
+}
+
+/// Check git config keys (`section[.subsection].name`) and values
+pub fn check_git_config(config: &BTreeMap<String, String>) -> Result<()> {
+    for (key, value) in config {
+        let invalid = |reason: &str| ApsError::InvalidGitConfig {
+            key: key.clone(),
+            reason: reason.to_string(),
+        };
+        let (Some((section, _)), Some((head, name))) = (key.split_once('.'), key.rsplit_once('.'))
+        else {
+            return Err(invalid("expected `section.name`"));
+        };
+        if section.is_empty()
+            || !section
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        {
+            return Err(invalid(
+                "the section may only contain letters, digits and `-`",
+            ));
+        }
+        if !name.starts_with(|c: char| c.is_ascii_alphabetic())
+            || !name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        {
+            return Err(invalid(
+                "the variable name must start with a letter and contain only letters, digits and `-`",
+            ));
+        }
+        if head.contains(['\n', '\0']) {
+            return Err(invalid("the subsection may not contain newlines"));
+        }
+        if value.contains(['\n', '\0']) {
+            return Err(invalid("the value may not contain newlines"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(args: Vec<OsString>) -> Vec<String> {
+        args.into_iter()
+            .map(|a| a.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_default_is_plain_clone() {
+        let options = CloneOptions::new();
+        assert!(options.clone_args().is_empty());
+        assert!(options.config_args().is_empty());
+    }
+
+    #[test]
+    fn test_builder_flags() {
+        let options = CloneOptions::new()
+            .shallow(true)
+            .submodules(true)
+            .follow_redirects(FollowRedirects::Never)
+            .client_cert("/etc/aps/client.pem")
+            .client_key("/etc/aps/client.key");
+
+        assert_eq!(
+            strings(options.clone_args()),
+            [
+                "--depth",
+                "1",
+                "--recurse-submodules",
+                "--shallow-submodules"
+            ]
+        );
+        assert_eq!(
+            strings(
+                CloneOptions::new()
+                    .submodule_paths(["vendor/prompts", "shared"])
+                    .clone_args()
+            ),
+            [
+                "--recurse-submodules=vendor/prompts",
+                "--recurse-submodules=shared"
+            ]
+        );
+        assert_eq!(
+            strings(options.config_args()),
+            [
+                "-c",
+                "http.followRedirects=false",
+                "-c",
+                "http.sslCert=/etc/aps/client.pem",
+                "-c",
+                "http.sslKey=/etc/aps/client.key"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_http_headers_are_passed_but_not_printed() {
+        let options = CloneOptions::new()
+            .user_agent("aps-ci/1.0")
+            .http_header("X-Gateway-Key", "s3cret");
+
+        // The header value must not show up on git's command line
+        assert_eq!(
+            strings(options.config_args()),
+            ["-c", "http.userAgent=aps-ci/1.0"]
+        );
+        let env = |pairs: &[(&str, &str)]| -> Vec<(String, String)> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        assert_eq!(
+            options.config_env_after(0),
+            env(&[
+                ("GIT_CONFIG_KEY_0", "http.extraHeader"),
+                ("GIT_CONFIG_VALUE_0", "X-Gateway-Key: s3cret"),
+                ("GIT_CONFIG_COUNT", "1"),
+            ])
+        );
+        assert_eq!(
+            options.config_env_after(2),
+            env(&[
+                ("GIT_CONFIG_KEY_2", "http.extraHeader"),
+                ("GIT_CONFIG_VALUE_2", "X-Gateway-Key: s3cret"),
+                ("GIT_CONFIG_COUNT", "3"),
+            ])
+        );
+        assert!(CloneOptions::new().config_env().is_empty());
+        let debug = format!("{:?}", options);
+        assert!(debug.contains("X-Gateway-Key"));
+        assert!(!debug.contains("s3cret"));
+    }
+
+    #[test]
+    fn test_git_config_is_passed_and_persisted() {
+        let options = CloneOptions::new()
+            .git_config("http.postBuffer", "524288000")
+            .git_config("core.longpaths", "true");
+
+        assert_eq!(
+            strings(options.config_args()),
+            [
+                "-c",
+                "core.longpaths=true",
+                "-c",
+                "http.postBuffer=524288000"
+            ]
+        );
+        assert_eq!(
+            strings(options.clone_args()),
+            [
+                "--config",
+                "core.longpaths=true",
+                "--config",
+                "http.postBuffer=524288000"
+            ]
+        );
+        options.check_git_config().unwrap();
+
+        let config = |key: &str, value: &str| {
+            check_git_config(&BTreeMap::from([(key.to_string(), value.to_string())]))
+        };
+        config(
+            "url.https://mirror.example/.insteadOf",
+            "https://github.com/",
+        )
+        .unwrap();
+        config("safe.directory", "*").unwrap();
+        for key in [
+            "postBuffer",
+            "http.",
+            ".postBuffer",
+            "http.1buffer",
+            "ht tp.postBuffer",
+        ] {
+            assert!(
+                matches!(config(key, "1"), Err(ApsError::InvalidGitConfig { .. })),
+                "{}",
+                key
+            );
+        }
+        assert!(config("core.longpaths", "true\n[core]").is_err());
+    }
+
+    #[test]
+    fn test_shallow_since_replaces_depth() {
+        let options = CloneOptions::new()
+            .depth(5)
+            .shallow_since("2024-06-01T00:00:00+00:00");
+        assert_eq!(
+            strings(options.clone_args()),
+            ["--shallow-since=2024-06-01T00:00:00+00:00"]
+        );
+        assert!(options.is_shallow());
+        assert_eq!(options.get_depth(), None);
+
+        let options = options.shallow(true);
+        assert_eq!(strings(options.clone_args()), ["--depth", "1"]);
+        assert_eq!(options.get_shallow_since(), None);
+    }
+
+    #[test]
+    fn test_transfer_timeout_rounds_up_to_whole_seconds() {
+        let options = CloneOptions::new()
+            .connect_timeout(Duration::from_secs(5))
+            .transfer_timeout(Duration::from_millis(1500));
+
+        assert_eq!(
+            strings(options.config_args()),
+            ["-c", "http.lowSpeedLimit=1", "-c", "http.lowSpeedTime=2"]
+        );
+        assert_eq!(options.get_connect_timeout(), Some(Duration::from_secs(5)));
+    }
+}
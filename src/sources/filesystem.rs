@@ -1,6 +1,6 @@
 //! Filesystem source adapter for local file/directory sources.
 
-use super::{expand_path, ResolvedSource, SourceAdapter};
+use super::{expand_path, is_root_path, ResolvedSource, SourceAdapter};
 use crate::error::Result;
 use std::path::{Path, PathBuf};
 
@@ -53,8 +53,9 @@ impl SourceAdapter for FilesystemSource {
             manifest_dir.join(&expanded_root)
         };
 
-        // If path is ".", use root directly; otherwise join
-        let source_path = if path == "." {
+        // If path names the root, use it directly; otherwise join
+        let is_root = is_root_path(&path);
+        let source_path = if is_root {
             root_path.clone()
         } else {
             root_path.join(&path)
@@ -62,13 +63,13 @@ impl SourceAdapter for FilesystemSource {
 
         // Preserve original root (with shell variables) and expanded root
         // for lockfile path transformation
-        let original_root = if path == "." {
+        let original_root = if is_root {
             self.root.clone()
         } else {
             format!("{}/{}", self.root, path)
         };
 
-        let expanded_root_with_path = if path == "." {
+        let expanded_root_with_path = if is_root {
             root_path.to_string_lossy().to_string()
         } else {
             source_path.to_string_lossy().to_string()
@@ -80,6 +81,7 @@ impl SourceAdapter for FilesystemSource {
             self.symlink,
             original_root,
             expanded_root_with_path,
-        ))
+        )
+        .with_root(root_path))
     }
 }
@@ -0,0 +1,504 @@
+//! Record and replay git traffic for offline, deterministic test runs.
+//!
+//! [`RecordingTransport`] wraps a real transport and captures every git
+//! command that reaches a remote (`clone`, `fetch`, `ls-remote`, `pull`,
+//! `submodule`) into a fixture directory: the command line, its output and,
+//! for commands that change a clone, a snapshot of the repository directory
+//! afterwards. [`ReplayTransport`] answers those commands from the fixture
+//! without touching the network, restoring snapshots in place, and runs
+//! local commands (`rev-parse`, `checkout`, ...) against the restored clone
+//! with the git CLI.
+//!
+//! Replay is sequential: the pipeline must issue the same remote commands in
+//! the same order as when it was recorded. Absolute paths (temporary clone
+//! directories differ between runs) are replaced by numbered placeholders.
+//! Credentials in URLs and `http.extraHeader` values are redacted in the
+//! transcript and in snapshotted git config, so fixtures can be committed.
+//!
+//! Layout of a fixture directory:
+//!
+//! ```text
+//! fixture.yaml      # transcript of remote commands
+//! snapshots/<n>/    # repository after command <n>
+//! ```
+
+use super::transport::{GitCli, GitOutput, GitTransport};
+use crate::error::{ApsError, Result};
+use crate::redact::redact_secrets;
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use walkdir::WalkDir;
+
+/// Transcript file inside a fixture directory
+const TRANSCRIPT_FILE: &str = "fixture.yaml";
+
+/// Directory of repository snapshots inside a fixture directory
+const SNAPSHOT_DIR: &str = "snapshots";
+
+/// git subcommands that talk to a remote
+const REMOTE_COMMANDS: &[&str] = &["clone", "fetch", "ls-remote", "pull", "submodule"];
+
+/// One recorded remote command
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Exchange {
+    /// Arguments after path placeholders and redaction
+    args: Vec<String>,
+    success: bool,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    stdout: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    stderr: String,
+    /// Snapshot of the repository the command changed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    snapshot: Option<usize>,
+}
+
+/// Contents of `fixture.yaml`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Transcript {
+    exchanges: Vec<Exchange>,
+}
+
+/// Numbered stand-ins for absolute paths, in order of first appearance
+#[derive(Debug, Default)]
+struct PathPlaceholders {
+    paths: Vec<String>,
+}
+
+impl PathPlaceholders {
+    fn replace(&mut self, arg: &str) -> String {
+        if !Path::new(arg).is_absolute() {
+            return arg.to_string();
+        }
+        for (i, path) in self.paths.iter().enumerate() {
+            if arg == path {
+                return format!("<path{}>", i);
+            }
+            if let Some(rest) = arg.strip_prefix(path.as_str()) {
+                if rest.starts_with(std::path::is_separator) {
+                    return format!("<path{}>{}", i, rest.replace('\\', "/"));
+                }
+            }
+        }
+        self.paths.push(arg.to_string());
+        format!("<path{}>", self.paths.len() - 1)
+    }
+}
+
+/// A git command line as far as fixtures are concerned
+struct Invocation {
+    /// Arguments after path placeholders and redaction
+    args: Vec<String>,
+    /// Subcommand after the global `-c` / `-C` options
+    subcommand: Option<String>,
+    /// Repository the command works on: the `-C` directory, or the
+    /// destination of a clone
+    repo: Option<PathBuf>,
+}
+
+impl Invocation {
+    fn parse(args: &[OsString], paths: &mut PathPlaceholders) -> Self {
+        let raw: Vec<String> = args
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+
+        let mut repo = None;
+        let mut i = 0;
+        while i + 1 < raw.len() && (raw[i] == "-c" || raw[i] == "-C") {
+            if raw[i] == "-C" {
+                repo = Some(PathBuf::from(&raw[i + 1]));
+            }
+            i += 2;
+        }
+        let subcommand = raw.get(i).cloned();
+        if subcommand.as_deref() == Some("clone") {
+            // `clone [options] <url> <dest>`
+            repo = raw
+                .last()
+                .filter(|dest| raw.len() > i + 2 && !dest.starts_with('-'))
+                .map(PathBuf::from);
+        }
+
+        Self {
+            args: raw
+                .iter()
+                .map(|arg| paths.replace(&redact_arg(arg)))
+                .collect(),
+            subcommand,
+            repo,
+        }
+    }
+
+    fn is_remote(&self) -> bool {
+        self.subcommand
+            .as_deref()
+            .is_some_and(|cmd| REMOTE_COMMANDS.contains(&cmd))
+    }
+
+    fn changes_repo(&self) -> bool {
+        self.is_remote() && self.subcommand.as_deref() != Some("ls-remote")
+    }
+}
+
+/// Mask credentials in a single argument
+fn redact_arg(arg: &str) -> String {
+    if let Some((key, value)) = arg.split_once('=') {
+        if key.to_ascii_lowercase().ends_with(".extraheader") {
+            let name = value.split_once(':').map_or(value, |(name, _)| name);
+            return format!("{}={}: ***", key, name.trim());
+        }
+    }
+    redact_secrets(arg)
+}
+
+fn fixture_error(dir: &Path, message: impl Into<String>) -> ApsError {
+    ApsError::GitFixture {
+        path: dir.display().to_string(),
+        message: message.into(),
+    }
+}
+
+/// Copy `src` to `dest`, keeping symlinks as links. With `redact`,
+/// credentials are masked in git config files.
+fn copy_tree(src: &Path, dest: &Path, redact: bool) -> Result<()> {
+    // A bare repository (mirror clone) keeps its config at the top level
+    let bare = src.join("HEAD").is_file() && !src.join(".git").exists();
+
+    for entry in WalkDir::new(src).follow_links(false) {
+        let entry =
+            entry.map_err(|e| ApsError::io(e.into(), format!("Failed to read {:?}", src)))?;
+        let relative = entry.path().strip_prefix(src).unwrap_or(entry.path());
+        let target = dest.join(relative);
+        let file_type = entry.file_type();
+
+        if file_type.is_dir() {
+            std::fs::create_dir_all(&target)
+                .map_err(|e| ApsError::io(e, format!("Failed to create {:?}", target)))?;
+        } else if file_type.is_symlink() {
+            let link = std::fs::read_link(entry.path())
+                .map_err(|e| ApsError::io(e, format!("Failed to read link {:?}", entry.path())))?;
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(&link, &target)
+                .map_err(|e| ApsError::io(e, format!("Failed to create link {:?}", target)))?;
+            #[cfg(not(unix))]
+            std::fs::copy(entry.path(), &target)
+                .map(|_| drop(link))
+                .map_err(|e| ApsError::io(e, format!("Failed to copy {:?}", entry.path())))?;
+        } else if redact && is_git_config(relative, bare) {
+            let config = std::fs::read_to_string(entry.path())
+                .map_err(|e| ApsError::io(e, format!("Failed to read {:?}", entry.path())))?;
+            std::fs::write(&target, redact_config(&config))
+                .map_err(|e| ApsError::io(e, format!("Failed to write {:?}", target)))?;
+        } else {
+            std::fs::copy(entry.path(), &target)
+                .map_err(|e| ApsError::io(e, format!("Failed to copy {:?}", entry.path())))?;
+        }
+    }
+    Ok(())
+}
+
+/// Mask credentials in git config text: URLs and `extraHeader` values,
+/// which `clone --config` persists
+fn redact_config(config: &str) -> String {
+    let mut out = String::with_capacity(config.len());
+    for line in config.split_inclusive('\n') {
+        let header = line
+            .split_once('=')
+            .filter(|(key, _)| key.trim().eq_ignore_ascii_case("extraheader"));
+        match header {
+            Some((key, value)) => {
+                let name = value.split_once(':').map_or(value, |(name, _)| name);
+                out.push_str(&format!("{}= {}: ***\n", key, name.trim()));
+            }
+            None => out.push_str(&redact_secrets(line)),
+        }
+    }
+    out
+}
+
+/// Whether `relative` is a repository's (or submodule's) git config file
+fn is_git_config(relative: &Path, bare: bool) -> bool {
+    relative.file_name().is_some_and(|name| name == "config")
+        && (relative
+            .components()
+            .any(|c| c == Component::Normal(".git".as_ref()))
+            || (bare && relative.parent() == Some(Path::new(""))))
+}
+
+/// Transport that records remote git traffic into a fixture directory.
+///
+/// Commands run through the wrapped transport as usual; call
+/// [`save`](Self::save) when done to write the transcript.
+pub struct RecordingTransport<T: GitTransport = GitCli> {
+    inner: T,
+    dir: PathBuf,
+    state: Mutex<RecordState>,
+}
+
+#[derive(Default)]
+struct RecordState {
+    paths: PathPlaceholders,
+    transcript: Transcript,
+}
+
+impl<T: GitTransport> RecordingTransport<T> {
+    /// Record into `dir`, replacing any fixture already there
+    pub fn new(inner: T, dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        let snapshots = dir.join(SNAPSHOT_DIR);
+        if snapshots.exists() {
+            std::fs::remove_dir_all(&snapshots)
+                .map_err(|e| ApsError::io(e, format!("Failed to clear {:?}", snapshots)))?;
+        }
+        std::fs::create_dir_all(&snapshots)
+            .map_err(|e| ApsError::io(e, format!("Failed to create {:?}", snapshots)))?;
+        Ok(Self {
+            inner,
+            dir,
+            state: Mutex::new(RecordState::default()),
+        })
+    }
+
+    /// Write the transcript of everything recorded so far
+    pub fn save(&self) -> Result<()> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let yaml = serde_yaml::to_string(&state.transcript)
+            .map_err(|e| fixture_error(&self.dir, e.to_string()))?;
+        let path = self.dir.join(TRANSCRIPT_FILE);
+        std::fs::write(&path, yaml)
+            .map_err(|e| ApsError::io(e, format!("Failed to write {:?}", path)))
+    }
+
+    fn record(&self, args: &[OsString], output: GitOutput) -> Result<GitOutput> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let invocation = Invocation::parse(args, &mut state.paths);
+        if !invocation.is_remote() {
+            return Ok(output);
+        }
+
+        let n = state.transcript.exchanges.len();
+        let snapshot = match invocation.repo {
+            Some(ref repo) if output.success && invocation.changes_repo() && repo.is_dir() => {
+                copy_tree(repo, &self.dir.join(SNAPSHOT_DIR).join(n.to_string()), true)?;
+                Some(n)
+            }
+            _ => None,
+        };
+        state.transcript.exchanges.push(Exchange {
+            args: invocation.args,
+            success: output.success,
+            stdout: redact_secrets(&output.stdout),
+            stderr: redact_secrets(&output.stderr),
+            snapshot,
+        });
+        Ok(output)
+    }
+}
+
+impl<T: GitTransport> GitTransport for RecordingTransport<T> {
+    fn run(&self, args: &[OsString]) -> Result<GitOutput> {
+        let output = self.inner.run(args)?;
+        self.record(args, output)
+    }
+
+    fn run_env(&self, args: &[OsString], env: &[(String, String)]) -> Result<GitOutput> {
+        let output = self.inner.run_env(args, env)?;
+        self.record(args, output)
+    }
+
+    fn run_with_deadline(
+        &self,
+        args: &[OsString],
+        env: &[(String, String)],
+        timeout: Duration,
+    ) -> Result<Option<GitOutput>> {
+        match self.inner.run_with_deadline(args, env, timeout)? {
+            Some(output) => self.record(args, output).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Transport that replays a fixture recorded by [`RecordingTransport`].
+///
+/// Remote commands must match the transcript in order; anything else fails
+/// with [`ApsError::GitFixture`]. Call [`finish`](Self::finish) to check that
+/// the whole transcript was used.
+pub struct ReplayTransport {
+    dir: PathBuf,
+    exchanges: Vec<Exchange>,
+    state: Mutex<ReplayState>,
+}
+
+#[derive(Default)]
+struct ReplayState {
+    paths: PathPlaceholders,
+    next: usize,
+}
+
+impl ReplayTransport {
+    /// Load the fixture in `dir`
+    pub fn load(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        let path = dir.join(TRANSCRIPT_FILE);
+        let yaml = std::fs::read_to_string(&path)
+            .map_err(|e| ApsError::io(e, format!("Failed to read {:?}", path)))?;
+        let transcript: Transcript =
+            serde_yaml::from_str(&yaml).map_err(|e| fixture_error(&dir, e.to_string()))?;
+        Ok(Self {
+            dir,
+            exchanges: transcript.exchanges,
+            state: Mutex::new(ReplayState::default()),
+        })
+    }
+
+    /// Fail if recorded commands were never replayed
+    pub fn finish(&self) -> Result<()> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let left = self.exchanges.len() - state.next;
+        if left > 0 {
+            return Err(fixture_error(
+                &self.dir,
+                format!(
+                    "{} recorded command(s) were not replayed, next: `git {}`",
+                    left,
+                    self.exchanges[state.next].args.join(" ")
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl GitTransport for ReplayTransport {
+    fn run(&self, args: &[OsString]) -> Result<GitOutput> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let invocation = Invocation::parse(args, &mut state.paths);
+        if !invocation.is_remote() {
+            drop(state);
+            return GitCli.run(args);
+        }
+
+        let command = invocation.args.join(" ");
+        let Some(exchange) = self.exchanges.get(state.next) else {
+            return Err(fixture_error(
+                &self.dir,
+                format!("no recorded command left for `git {}`", command),
+            ));
+        };
+        if exchange.args != invocation.args {
+            return Err(fixture_error(
+                &self.dir,
+                format!(
+                    "command {} was `git {}` when recorded, now `git {}`",
+                    state.next,
+                    exchange.args.join(" "),
+                    command
+                ),
+            ));
+        }
+        state.next += 1;
+
+        if let (Some(n), Some(repo)) = (exchange.snapshot, &invocation.repo) {
+            if repo.exists() {
+                std::fs::remove_dir_all(repo)
+                    .map_err(|e| ApsError::io(e, format!("Failed to clear {:?}", repo)))?;
+            }
+            copy_tree(
+                &self.dir.join(SNAPSHOT_DIR).join(n.to_string()),
+                repo,
+                false,
+            )?;
+        }
+        Ok(GitOutput {
+            success: exchange.success,
+            stdout: exchange.stdout.clone(),
+            stderr: exchange.stderr.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::clone_options::CloneOptions;
+    use crate::sources::git::clone_and_resolve_with;
+    use std::process::Command;
+    use tempfile::TempDir;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {:?}", args);
+    }
+
+    #[test]
+    fn test_replay_resolves_offline() {
+        let temp = TempDir::new().unwrap();
+        let origin = temp.path().join("origin");
+        std::fs::create_dir_all(&origin).unwrap();
+        git(&origin, &["init", "-q", "-b", "main"]);
+        std::fs::write(origin.join("AGENTS.md"), "# Agents\n").unwrap();
+        git(&origin, &["add", "."]);
+        git(&origin, &["commit", "-qm", "init"]);
+        let url = format!("file://{}", origin.display());
+        let options = CloneOptions::new()
+            .http_header("X-Gateway-Key", "s3cret")
+            .git_config("http.extraHeader", "Authorization: Bearer s3cret");
+
+        let fixture = temp.path().join("fixture");
+        let recorder = RecordingTransport::new(GitCli, &fixture).unwrap();
+        let recorded = clone_and_resolve_with(&recorder, &url, "main", &options).unwrap();
+        recorder.save().unwrap();
+        let transcript = std::fs::read_to_string(fixture.join(TRANSCRIPT_FILE)).unwrap();
+        // Headers travel in git's environment, never in its arguments
+        assert!(!transcript.contains("X-Gateway-Key"));
+        assert!(!transcript.contains("s3cret"));
+        let config = std::fs::read_to_string(fixture.join("snapshots/0/.git/config")).unwrap();
+        assert!(config.contains("Authorization: ***"));
+        assert!(!config.contains("s3cret"));
+
+        // The origin is gone: everything comes from the fixture
+        std::fs::remove_dir_all(&origin).unwrap();
+        let replay = ReplayTransport::load(&fixture).unwrap();
+        let replayed = clone_and_resolve_with(&replay, &url, "main", &options).unwrap();
+        replay.finish().unwrap();
+        assert_eq!(replayed.commit_sha, recorded.commit_sha);
+        assert_eq!(replayed.resolved_ref, "main");
+        assert_eq!(
+            std::fs::read_to_string(replayed.repo_path.join("AGENTS.md")).unwrap(),
+            "# Agents\n"
+        );
+
+        // A different command sequence is reported, not silently served
+        let replay = ReplayTransport::load(&fixture).unwrap();
+        let result = clone_and_resolve_with(&replay, &url, "release", &options);
+        assert!(matches!(result, Err(ApsError::GitFixture { .. })));
+    }
+
+    #[test]
+    fn test_paths_become_placeholders() {
+        let mut paths = PathPlaceholders::default();
+        assert_eq!(paths.replace("/tmp/.tmpA1"), "<path0>");
+        assert_eq!(paths.replace("/tmp/.tmpB2"), "<path1>");
+        assert_eq!(paths.replace("/tmp/.tmpA1/sub/dir"), "<path0>/sub/dir");
+        assert_eq!(paths.replace("origin"), "origin");
+        assert_eq!(
+            redact_arg("http.extraHeader=X-Gateway-Key: s3cret"),
+            "http.extraHeader=X-Gateway-Key: ***"
+        );
+        assert_eq!(
+            redact_arg("https://bob:pw@git.example.com/r.git"),
+            "https://***@git.example.com/r.git"
+        );
+    }
+}
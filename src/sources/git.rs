@@ -1,23 +1,90 @@
 //! Git source adapter for cloning repositories.
 
-use super::{expand_path, GitInfo, ResolvedSource, SourceAdapter};
+use super::clone_options::{self, CloneIntent, CloneOptions, CloneStrategy, FollowRedirects};
+use super::transport::{GitCli, GitOutput, GitTransport};
+use super::{
+    expand_path, is_root_path, GitInfo, ResolvedSource, SourceAdapter, TreeEntry, TreeEntryKind,
+};
 use crate::error::{ApsError, Result};
+use crate::host_policy::HostPolicy;
+use crate::lockfile::{LastChange, SubmodulePin};
+use crate::redact::{redact_secrets, redact_url};
+use crate::repo_id::{canonicalize_repo_url, CanonicalRepoId};
+use crate::url_rewrite::{UrlRewrites, URL_REWRITES_ENV};
+use crate::version::{Version, VersionReq};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ffi::OsString;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tempfile::TempDir;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 /// Git source adapter for cloning repositories
 #[derive(Debug, Clone)]
 pub struct GitSource {
     /// Repository URL (SSH or HTTPS)
     pub repo: String,
-    /// Git ref (branch, tag, commit) - "auto" tries main then master
+    /// Git ref (branch, tag, commit) - "auto" tries the default branches
+    /// (main then master unless configured)
     pub git_ref: String,
     /// Whether to use shallow clone
     pub shallow: bool,
     /// Optional path within the repository
     pub path: Option<String>,
+    /// Pin to the newest commit on the ref at or before this timestamp
+    pub as_of: Option<String>,
+    /// Select the newest commit whose message matches this extended regex
+    pub message_match: Option<String>,
+    /// How HTTP redirects are followed (git's default when unset)
+    pub follow_redirects: Option<FollowRedirects>,
+    /// Clone the remote's default branch when `git_ref` does not exist
+    pub default_branch_fallback: bool,
+    /// Follow a redirect to a moved repository instead of failing
+    pub follow_moved: bool,
+    /// Client certificate presented for mutual TLS
+    pub client_cert: Option<String>,
+    /// Private key for `client_cert`
+    pub client_key: Option<String>,
+    /// Only accept a ref that is a GPG-signed annotated tag
+    pub require_signed_tag: bool,
+    /// Fingerprints (or long key IDs) allowed to sign the tag; any key in the
+    /// keyring when empty
+    pub signing_keys: Vec<String>,
+    /// Clone strategies tried in order when resolving the ref
+    pub strategies: Vec<CloneStrategy>,
+    /// Resolve `auto` to the branch the remote's `HEAD` advertises
+    pub verify_default_branch: bool,
+    /// Branches `auto` tries, in order; the global list when empty
+    pub default_branches: Vec<String>,
+    /// User-Agent sent to HTTP(S) servers
+    pub user_agent: Option<String>,
+    /// Extra HTTP headers; values are expanded from the environment
+    pub http_headers: BTreeMap<String, String>,
+    /// Seconds the remote may take to answer before cloning starts
+    pub connect_timeout: Option<u64>,
+    /// Seconds an HTTP(S) transfer may go without receiving data
+    pub transfer_timeout: Option<u64>,
+    /// Extra git configuration applied to the clone
+    pub git_config: BTreeMap<String, String>,
+    /// Only fetch history newer than this timestamp (replaces `shallow`)
+    pub shallow_since: Option<String>,
+    /// Full SHA the selected commit must be; guards against moved refs
+    pub expected_commit: Option<String>,
+    /// Index file read by `indexfile:<channel>` refs
+    pub index_file: Option<String>,
+    /// What the clone is for; picks the depth of a shallow clone
+    pub intent: Option<CloneIntent>,
+    /// Initialize submodules in the clone
+    pub submodules: bool,
+    /// Submodules to initialize; all of them when empty
+    pub submodule_paths: Vec<String>,
+    /// Read the git note on the resolved commit
+    pub fetch_notes: bool,
+    /// Describe the resolved commit by its nearest tag
+    pub describe: bool,
+    /// Tags considered when describing; all of them when unset
+    pub describe_match: Option<String>,
 }
 
 impl GitSource {
@@ -28,8 +95,456 @@ impl GitSource {
             git_ref,
             shallow,
             path,
+            as_of: None,
+            message_match: None,
+            follow_redirects: None,
+            default_branch_fallback: false,
+            follow_moved: false,
+            client_cert: None,
+            client_key: None,
+            require_signed_tag: false,
+            signing_keys: Vec::new(),
+            strategies: Vec::new(),
+            verify_default_branch: false,
+            default_branches: Vec::new(),
+            user_agent: None,
+            http_headers: BTreeMap::new(),
+            connect_timeout: None,
+            transfer_timeout: None,
+            git_config: BTreeMap::new(),
+            shallow_since: None,
+            expected_commit: None,
+            index_file: None,
+            intent: None,
+            submodules: false,
+            submodule_paths: Vec::new(),
+            fetch_notes: false,
+            describe: false,
+            describe_match: None,
         }
     }
+
+    /// Resolve to the newest commit at or before the given timestamp
+    pub fn with_as_of(mut self, as_of: Option<String>) -> Self {
+        self.as_of = as_of;
+        self
+    }
+
+    /// Select the newest commit whose message matches `pattern`
+    pub fn with_message_match(mut self, pattern: Option<String>) -> Self {
+        self.message_match = pattern;
+        self
+    }
+
+    /// Control how HTTP redirects are followed when talking to the remote
+    pub fn with_follow_redirects(mut self, follow: Option<FollowRedirects>) -> Self {
+        self.follow_redirects = follow;
+        self
+    }
+
+    /// Fall back to the remote's default branch when the ref is missing
+    pub fn with_default_branch_fallback(mut self, fallback: bool) -> Self {
+        self.default_branch_fallback = fallback;
+        self
+    }
+
+    /// Follow (with a warning) a redirect to a renamed or transferred repository
+    pub fn with_follow_moved(mut self, follow: bool) -> Self {
+        self.follow_moved = follow;
+        self
+    }
+
+    /// Authenticate to HTTPS remotes with a client certificate and key
+    pub fn with_client_cert(mut self, cert: Option<String>, key: Option<String>) -> Self {
+        self.client_cert = cert;
+        self.client_key = key;
+        self
+    }
+
+    /// Require `git_ref` to be a signed annotated tag, signed by one of
+    /// `keys` when any are given
+    pub fn with_signed_tag_policy(mut self, require: bool, keys: Vec<String>) -> Self {
+        self.require_signed_tag = require;
+        self.signing_keys = keys;
+        self
+    }
+
+    /// Try these clone strategies in order, instead of one clone whose depth
+    /// follows `shallow`
+    pub fn with_strategies(mut self, strategies: Vec<CloneStrategy>) -> Self {
+        self.strategies = strategies;
+        self
+    }
+
+    /// Resolve `auto` to the remote's advertised default branch instead of
+    /// trying `main`, then `master`
+    pub fn with_verify_default_branch(mut self, verify: bool) -> Self {
+        self.verify_default_branch = verify;
+        self
+    }
+
+    /// Branches `auto` tries, in order (e.g. `trunk`, `main`, `master`)
+    pub fn with_default_branches(mut self, branches: Vec<String>) -> Self {
+        self.default_branches = branches;
+        self
+    }
+
+    /// Identify to HTTP(S) servers with a User-Agent and extra headers
+    pub fn with_http_identity(
+        mut self,
+        user_agent: Option<String>,
+        http_headers: BTreeMap<String, String>,
+    ) -> Self {
+        self.user_agent = user_agent;
+        self.http_headers = http_headers;
+        self
+    }
+
+    /// Bound the connect phase and transfer stalls, in seconds
+    pub fn with_timeouts(mut self, connect: Option<u64>, transfer: Option<u64>) -> Self {
+        self.connect_timeout = connect;
+        self.transfer_timeout = transfer;
+        self
+    }
+
+    /// Apply extra git configuration (`http.postBuffer`, `core.longpaths`,
+    /// ...) to every git command run for this source
+    pub fn with_git_config(mut self, config: BTreeMap<String, String>) -> Self {
+        self.git_config = config;
+        self
+    }
+
+    /// Clone only the history newer than `since` instead of a fixed depth
+    pub fn with_shallow_since(mut self, since: Option<String>) -> Self {
+        self.shallow_since = since;
+        self
+    }
+
+    /// Require the resolved commit to be `sha`
+    pub fn with_expected_commit(mut self, sha: Option<String>) -> Self {
+        self.expected_commit = sha;
+        self
+    }
+
+    /// Look up `indexfile:<channel>` refs in this file instead of
+    /// [`DEFAULT_INDEX_FILE`]
+    pub fn with_index_file(mut self, path: Option<String>) -> Self {
+        self.index_file = path;
+        self
+    }
+
+    /// Initialize submodules, only those at `paths` when it is not empty
+    pub fn with_submodules(mut self, submodules: bool, paths: Vec<String>) -> Self {
+        self.submodules = submodules || !paths.is_empty();
+        self.submodule_paths = paths;
+        self
+    }
+
+    /// Fetch commit notes and read the one on the resolved commit
+    pub fn with_fetch_notes(mut self, fetch: bool) -> Self {
+        self.fetch_notes = fetch;
+        self
+    }
+
+    /// Describe the resolved commit by its nearest tag, only considering
+    /// tags that match `pattern` when given
+    pub fn with_describe(mut self, describe: bool, pattern: Option<String>) -> Self {
+        self.describe = describe || pattern.is_some();
+        self.describe_match = pattern;
+        self
+    }
+
+    /// Size a shallow clone for what it is used for (see [`CloneIntent`]).
+    ///
+    /// Ignored when the source asks for full history, a strategy list or
+    /// `shallow_since`.
+    pub fn with_intent(mut self, intent: Option<CloneIntent>) -> Self {
+        self.intent = intent;
+        self
+    }
+
+    /// The ref to clone: `git_ref`; for `auto` with `verify_default_branch`
+    /// the branch the remote's `HEAD` points at (when the remote advertises
+    /// one); for `indexfile:<channel>` the ref the index file maps the
+    /// channel to; for a version requirement such as `^1.2` the highest
+    /// release tag it admits
+    fn effective_ref(&self) -> Result<String> {
+        if let Some(channel) = self.git_ref.strip_prefix(INDEX_FILE_REF_PREFIX) {
+            return self.ref_from_index_file(channel);
+        }
+        if let Some(req) = VersionReq::parse(&self.git_ref) {
+            return list_semver_tags(&self.repo, &self.clone_options())?
+                .latest_matching(&req)
+                .map(|tag| tag.name.clone())
+                .ok_or_else(|| ApsError::NoMatchingVersion {
+                    requirement: self.git_ref.clone(),
+                    repo: redact_url(&self.repo),
+                });
+        }
+        if self.git_ref == "auto" && self.verify_default_branch {
+            if let Some(branch) = remote_default_branch(&GitCli, &self.repo, &self.clone_options())?
+            {
+                return Ok(branch);
+            }
+        }
+        Ok(self.git_ref.clone())
+    }
+
+    /// Read the index file from a shallow clone of the default branch and
+    /// return the ref it maps `channel` to
+    fn ref_from_index_file(&self, channel: &str) -> Result<String> {
+        let path = self.index_file.as_deref().unwrap_or(DEFAULT_INDEX_FILE);
+        let invalid = |reason: &str| ApsError::InvalidChannelIndex {
+            path: path.to_string(),
+            reason: reason.to_string(),
+        };
+        if !Path::new(path)
+            .components()
+            .all(|c| matches!(c, std::path::Component::Normal(_)))
+        {
+            return Err(invalid("must be a relative path inside the repository"));
+        }
+
+        let default_ref = GitSource {
+            git_ref: "auto".to_string(),
+            ..self.clone()
+        }
+        .effective_ref()?;
+        let options = self
+            .clone_options()
+            .shallow(true)
+            .default_branches(self.default_branches.iter().cloned());
+        let index = clone_and_resolve(&self.repo, &default_ref, &options)?;
+        let content = std::fs::read_to_string(index.repo_path.join(path)).map_err(|_| {
+            invalid(&format!(
+                "not found on the default branch '{}'",
+                index.resolved_ref
+            ))
+        })?;
+        let channels = parse_channel_index(path, &content)?;
+        let Some(target) = channels.get(channel) else {
+            return Err(ApsError::ChannelNotInIndex {
+                channel: channel.to_string(),
+                path: path.to_string(),
+                available: channels.keys().cloned().collect::<Vec<_>>().join(", "),
+            });
+        };
+        info!(
+            "Channel {} in {} of {} points at {}",
+            channel,
+            path,
+            redact_url(&self.repo),
+            target
+        );
+        Ok(target.clone())
+    }
+
+    /// Clone options shared by every git operation on this source.
+    ///
+    /// History depth is decided per operation; this covers transport settings.
+    pub fn clone_options(&self) -> CloneOptions {
+        let mut options = CloneOptions::new();
+        if let Some(follow) = self.follow_redirects {
+            options = options.follow_redirects(follow);
+        }
+        if let Some(ref cert) = self.client_cert {
+            options = options.client_cert(expand_path(cert));
+        }
+        if let Some(ref key) = self.client_key {
+            options = options.client_key(expand_path(key));
+        }
+        if let Some(ref user_agent) = self.user_agent {
+            options = options.user_agent(user_agent);
+        }
+        for (name, value) in &self.http_headers {
+            options = options.http_header(name, expand_path(value));
+        }
+        if let Some(seconds) = self.connect_timeout {
+            options = options.connect_timeout(Duration::from_secs(seconds));
+        }
+        if let Some(seconds) = self.transfer_timeout {
+            options = options.transfer_timeout(Duration::from_secs(seconds));
+        }
+        for (key, value) in &self.git_config {
+            options = options.git_config(key, value);
+        }
+        if let Some(ref pattern) = self.describe_match {
+            options = options.describe_match(pattern);
+        }
+        if !self.submodule_paths.is_empty() {
+            options = options.submodule_paths(self.submodule_paths.iter().cloned());
+        } else if self.submodules {
+            options = options.submodules(true);
+        }
+        options
+            .describe(self.describe)
+            .fetch_notes(self.fetch_notes)
+            .default_branch_fallback(self.default_branch_fallback)
+            .default_branches(self.default_branches.iter().cloned())
+            .follow_moved(self.follow_moved)
+    }
+
+    /// Like [`SourceAdapter::resolve`], reusing a clone from `cache` made
+    /// for another entry with the same repository, ref and clone settings
+    pub fn resolve_cached(&self, cache: &CloneCache) -> Result<ResolvedSource> {
+        let resolved_git =
+            cache.get_or_clone(self.cache_key(None), &self.repo, || self.checkout())?;
+        Ok(self.to_resolved(resolved_git, self.path()))
+    }
+
+    /// Clone the repository at a specific (locked) commit, or reuse a cached
+    /// clone of it, and resolve the source path within it
+    pub fn resolve_at_commit_cached(
+        &self,
+        cache: &CloneCache,
+        commit_sha: &str,
+        resolved_ref: &str,
+    ) -> Result<ResolvedSource> {
+        let resolved_git =
+            cache.get_or_clone(self.cache_key(Some(commit_sha)), &self.repo, || {
+                self.checkout_at_commit(commit_sha, resolved_ref)
+            })?;
+        Ok(self.to_resolved(resolved_git, self.path()))
+    }
+
+    /// Identity of the checkout this source needs; `path` is not part of it
+    fn cache_key(&self, commit_sha: Option<&str>) -> String {
+        format!(
+            "{:?}",
+            (
+                &self.repo,
+                (
+                    &self.git_ref,
+                    self.verify_default_branch,
+                    &self.default_branches,
+                    &self.expected_commit,
+                    &self.index_file,
+                ),
+                (
+                    self.shallow,
+                    &self.strategies,
+                    &self.shallow_since,
+                    &self.intent,
+                ),
+                &self.as_of,
+                &self.message_match,
+                self.follow_redirects,
+                self.default_branch_fallback,
+                self.follow_moved,
+                (&self.client_cert, &self.client_key),
+                (&self.user_agent, &self.http_headers, &self.git_config),
+                (self.require_signed_tag, &self.signing_keys),
+                (
+                    self.submodules,
+                    &self.submodule_paths,
+                    self.fetch_notes,
+                    (self.describe, &self.describe_match),
+                    commit_sha,
+                ),
+            )
+        )
+    }
+
+    /// Clone the ref (applying `as_of` / `message_match`) without resolving a path
+    fn checkout(&self) -> Result<ResolvedGitSource> {
+        info!("Cloning git repository: {}", redact_url(&self.repo));
+        let git_ref = self.effective_ref()?;
+
+        // Clone the repository; an "as of" lookup needs the branch history
+        let (mut resolved_git, options) = if let Some(ref as_of) = self.as_of {
+            let before = parse_as_of(as_of)?;
+            let options = self.clone_options().shallow(false);
+            let mut resolved = clone_and_resolve(&self.repo, &git_ref, &options)?;
+            resolved.commit_sha = checkout_as_of(&resolved.repo_path, &before)?;
+            (resolved, options)
+        } else {
+            let mut options = self
+                .clone_options()
+                .shallow(self.shallow)
+                .strategies(self.strategies.iter().copied());
+            if let Some(ref since) = self.shallow_since {
+                options = options.shallow_since(parse_shallow_since(since)?);
+            } else if let Some(intent) = self
+                .intent
+                .clone()
+                .filter(|_| self.shallow && self.strategies.is_empty())
+            {
+                options = options.intent(intent);
+            }
+            if let (Some(ref sha), None) = (&self.expected_commit, &self.message_match) {
+                options = options.expect_commit(sha);
+            }
+            (clone_and_resolve(&self.repo, &git_ref, &options)?, options)
+        };
+
+        // Search back from the checked-out commit, deepening a shallow clone
+        if let Some(ref pattern) = self.message_match {
+            resolved_git.commit_sha = checkout_by_message_with(
+                &GitCli,
+                &resolved_git.repo_path,
+                pattern,
+                &resolved_git.resolved_ref,
+                &options,
+            )?;
+        }
+
+        // `as_of` and `message_match` select an older commit than the ref's
+        // tip; the pin applies to that one
+        if self.as_of.is_some() || self.message_match.is_some() {
+            if let Some(ref expected) = self.expected_commit {
+                verify_expected_commit(&git_ref, expected, &resolved_git.commit_sha)?;
+            }
+        }
+
+        if self.require_signed_tag {
+            verify_signed_tag_with(
+                &GitCli,
+                &resolved_git.repo_path,
+                &self.git_ref,
+                &resolved_git.commit_sha,
+                &self.signing_keys,
+            )?;
+        }
+
+        Ok(resolved_git)
+    }
+
+    /// Clone the repository at a locked commit without resolving a path
+    fn checkout_at_commit(
+        &self,
+        commit_sha: &str,
+        resolved_ref: &str,
+    ) -> Result<ResolvedGitSource> {
+        let mut options = self.clone_options();
+        if let Some(ref since) = self.shallow_since {
+            options = options.shallow_since(parse_shallow_since(since)?);
+        }
+        clone_at_commit_with(&GitCli, &self.repo, commit_sha, resolved_ref, &options)
+    }
+
+    /// Wrap a finished clone into a `ResolvedSource` pointing at `path`
+    fn to_resolved(&self, resolved_git: Arc<ResolvedGitSource>, path: &str) -> ResolvedSource {
+        let path = expand_path(path);
+        let source_path = if is_root_path(&path) {
+            resolved_git.repo_path.clone()
+        } else {
+            resolved_git.repo_path.join(&path)
+        };
+
+        let git_info = GitInfo {
+            resolved_ref: resolved_git.resolved_ref.clone(),
+            commit_sha: resolved_git.commit_sha.clone(),
+            describe: resolved_git.describe.clone(),
+            notes: resolved_git.notes.clone(),
+            final_url: resolved_git.final_url.clone(),
+            strategy: resolved_git.strategy,
+            shallow_refused: resolved_git.shallow_refused,
+        };
+
+        let repo_root = resolved_git.repo_path.clone();
+        ResolvedSource::git(source_path, self.display_name(), git_info, resolved_git)
+            .with_root(repo_root)
+    }
 }
 
 impl SourceAdapter for GitSource {
@@ -50,30 +565,55 @@ impl SourceAdapter for GitSource {
     }
 
     fn resolve(&self, _manifest_dir: &Path) -> Result<ResolvedSource> {
-        info!("Cloning git repository: {}", self.repo);
+        let resolved_git = self.checkout()?;
+        Ok(self.to_resolved(Arc::new(resolved_git), self.path()))
+    }
+}
 
-        // Clone the repository
-        let resolved_git = clone_and_resolve(&self.repo, &self.git_ref, self.shallow)?;
+/// Git checkouts shared by the entries of one run.
+///
+/// Entries that point at the same repository and ref with the same clone
+/// settings reuse one clone, whatever `path` each of them installs. Clones
+/// stay on disk until the cache is dropped.
+#[derive(Default)]
+pub struct CloneCache {
+    clones: Mutex<HashMap<String, Arc<ResolvedGitSource>>>,
+    /// Some clone of each repository, for history queries across commits
+    by_repo: Mutex<HashMap<CanonicalRepoId, PathBuf>>,
+}
 
-        // Build the path within the cloned repo
-        let path = expand_path(self.path());
-        let source_path = if path == "." {
-            resolved_git.repo_path.clone()
-        } else {
-            resolved_git.repo_path.join(&path)
-        };
+impl CloneCache {
+    /// Create an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-        let git_info = GitInfo {
-            resolved_ref: resolved_git.resolved_ref.clone(),
-            commit_sha: resolved_git.commit_sha.clone(),
-        };
+    /// Path of a clone of `repo` made during this run, if any (any ref or
+    /// depth; the most complete history is not guaranteed)
+    pub fn repo_path(&self, repo: &str) -> Option<PathBuf> {
+        let by_repo = self.by_repo.lock().unwrap_or_else(|e| e.into_inner());
+        by_repo.get(&canonicalize_repo_url(repo)).cloned()
+    }
 
-        Ok(ResolvedSource::git(
-            source_path,
-            self.display_name(),
-            git_info,
-            resolved_git,
-        ))
+    /// Return the clone of `repo` stored under `key`, running `clone` on a
+    /// miss
+    fn get_or_clone<F>(&self, key: String, repo: &str, clone: F) -> Result<Arc<ResolvedGitSource>>
+    where
+        F: FnOnce() -> Result<ResolvedGitSource>,
+    {
+        let lock = || self.clones.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(existing) = lock().get(&key) {
+            debug!("Reusing existing clone at {:?}", existing.repo_path);
+            return Ok(Arc::clone(existing));
+        }
+        let resolved = Arc::new(clone()?);
+        lock().insert(key, Arc::clone(&resolved));
+        self.by_repo
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(canonicalize_repo_url(repo))
+            .or_insert_with(|| resolved.repo_path.clone());
+        Ok(resolved)
     }
 }
 
@@ -87,135 +627,429 @@ pub struct ResolvedGitSource {
     pub resolved_ref: String,
     /// Commit SHA at the resolved ref
     pub commit_sha: String,
+    /// Nearest tag description of the commit (`git describe --tags`), e.g.
+    /// `v1.2.0-3-gabc123d`; `None` when not requested or no reachable tag
+    /// exists
+    pub describe: Option<String>,
+    /// Where the repository was actually cloned from, when the server
+    /// redirected to a moved repository (see `follow_moved`)
+    pub final_url: Option<String>,
+    /// Git note attached to the commit (`refs/notes/commits`), fetched when
+    /// `fetch_notes` is set
+    pub notes: Option<String>,
+    /// Strategy that produced the clone, when a strategy list was configured
+    pub strategy: Option<CloneStrategy>,
+    /// Whether the full history was cloned because the server refused the
+    /// configured shallow clone
+    pub shallow_refused: bool,
 }
 
 /// Clone a git repository and resolve the ref using the git CLI.
 /// This inherits the user's existing git configuration (SSH, credentials, etc.)
-pub fn clone_and_resolve(url: &str, git_ref: &str, shallow: bool) -> Result<ResolvedGitSource> {
-    info!("Cloning git repository: {}", url);
-
-    // Create temp directory for the clone
-    let temp_dir = TempDir::new()
-        .map_err(|e| ApsError::io(e, "Failed to create temp directory for git clone"))?;
-
-    let repo_path = temp_dir.path().to_path_buf();
+pub fn clone_and_resolve(
+    url: &str,
+    git_ref: &str,
+    options: &CloneOptions,
+) -> Result<ResolvedGitSource> {
+    clone_and_resolve_with(&GitCli, url, git_ref, options)
+}
 
-    // For auto ref, we need to try different branches
-    let refs_to_try = if git_ref == "auto" {
-        vec!["main", "master"]
+/// Clone a git repository and resolve the ref through the given transport
+pub fn clone_and_resolve_with(
+    transport: &dyn GitTransport,
+    url: &str,
+    git_ref: &str,
+    options: &CloneOptions,
+) -> Result<ResolvedGitSource> {
+    let rewritten = rewrite_url(url);
+    let url = rewritten.as_deref().unwrap_or(url);
+    let resolved = if options.get_strategies().is_empty() {
+        clone_full_when_shallow_refused(transport, url, git_ref, options)
     } else {
-        vec![git_ref]
-    };
-
-    let resolved_ref = clone_with_ref_fallback(url, &repo_path, &refs_to_try, shallow)?;
+        clone_with_strategies(transport, url, git_ref, options)
+    }?;
+    if let Some(expected) = options.get_expected_commit() {
+        verify_expected_commit(git_ref, expected, &resolved.commit_sha)?;
+    }
+    Ok(resolved)
+}
 
-    // Get the commit SHA
-    let commit_sha = get_head_commit(&repo_path)?;
+/// Fail unless `actual` is the pinned `expected` commit (full SHAs,
+/// compared case-insensitively)
+fn verify_expected_commit(git_ref: &str, expected: &str, actual: &str) -> Result<()> {
+    if expected.eq_ignore_ascii_case(actual) {
+        return Ok(());
+    }
+    Err(ApsError::UnexpectedCommit {
+        git_ref: git_ref.to_string(),
+        expected: expected.to_ascii_lowercase(),
+        actual: actual.to_string(),
+    })
+}
 
+/// Apply the global URL rewrites (`APS_URL_REWRITES`) to a source URL,
+/// returning the new URL when a rule matched
+fn rewrite_url(url: &str) -> Option<String> {
+    let rewritten = UrlRewrites::from_env().rewrite(url)?;
     info!(
-        "Cloned {} at ref '{}' (commit {})",
-        url,
-        resolved_ref,
-        &commit_sha[..8.min(commit_sha.len())]
+        "Rewriting {} to {} ({})",
+        redact_url(url),
+        redact_url(&rewritten),
+        URL_REWRITES_ENV
     );
+    Some(rewritten)
+}
 
-    Ok(ResolvedGitSource {
-        _temp_dir: temp_dir,
-        repo_path,
-        resolved_ref,
-        commit_sha,
-    })
+/// Clone `url` at `git_ref` with the clone options as given
+fn clone_once(
+    transport: &dyn GitTransport,
+    url: &str,
+    git_ref: &str,
+    options: &CloneOptions,
+) -> Result<ResolvedGitSource> {
+    let mut resolved = handle_moved_repository(url, options, |url| {
+        clone_ref_with(transport, url, git_ref, options)
+    });
+    // A date-bounded clone selects nothing when the ref's tip is older than
+    // the cut-off; the tip alone is then the recent history
+    if let (Err(ApsError::GitError { message }), Some(since)) =
+        (&resolved, options.get_shallow_since())
+    {
+        if message.contains("no commits selected for shallow requests") {
+            info!(
+                "No commits after {} on {}; cloning the tip only",
+                since, git_ref
+            );
+            let tip_only = options.clone().depth(1);
+            resolved = handle_moved_repository(url, &tip_only, |url| {
+                clone_ref_with(transport, url, git_ref, &tip_only)
+            });
+        }
+    }
+    let mut resolved = resolved?;
+    if let Some(CloneIntent::NeedDiffAgainstLock { locked }) = options.get_intent() {
+        // Without the locked commit the clone still serves the ref; only the
+        // diff against the lock is unavailable
+        if let Err(e) = ensure_commit_present_with(transport, &resolved.repo_path, locked, options)
+        {
+            warn!(
+                "Locked commit {} of {} is not reachable from {}: {}",
+                &locked[..8.min(locked.len())],
+                redact_url(url),
+                git_ref,
+                e
+            );
+        }
+    }
+    warn_unknown_submodules(&resolved.repo_path, options);
+    if options.get_fetch_notes() {
+        resolved.notes = fetch_commit_notes(transport, &resolved, options)?;
+    }
+    Ok(resolved)
 }
 
-/// Try to clone with fallback refs using git CLI
-fn clone_with_ref_fallback(url: &str, path: &Path, refs: &[&str], shallow: bool) -> Result<String> {
-    let mut last_error = None;
+/// Clone with the options as given; when the server refuses the shallow
+/// clone they ask for, warn and clone the full history instead.
+///
+/// A strategy list is left alone: it already says what to try after a
+/// shallow clone fails.
+fn clone_full_when_shallow_refused(
+    transport: &dyn GitTransport,
+    url: &str,
+    git_ref: &str,
+    options: &CloneOptions,
+) -> Result<ResolvedGitSource> {
+    match clone_once(transport, url, git_ref, options) {
+        Err(ApsError::GitShallowRefused { message }) if options.is_shallow() => {
+            warn!(
+                "{} refused a shallow clone; cloning the full history instead ({})",
+                redact_url(url),
+                message
+            );
+            let full = options.clone().shallow(false);
+            let mut resolved = clone_once(transport, url, git_ref, &full)?;
+            resolved.shallow_refused = true;
+            Ok(resolved)
+        }
+        resolved => resolved,
+    }
+}
 
-    for ref_name in refs {
-        debug!("Trying to clone with ref '{}'", ref_name);
+/// Fetch `refs/notes/*` from origin and read the note on the resolved
+/// commit; `None` when the remote has no notes or none for this commit
+fn fetch_commit_notes(
+    transport: &dyn GitTransport,
+    resolved: &ResolvedGitSource,
+    options: &CloneOptions,
+) -> Result<Option<String>> {
+    let repo_args = |rest: &[&str]| {
+        let mut args = options.config_args();
+        args.push("-C".into());
+        args.push(resolved.repo_path.as_os_str().to_owned());
+        args.extend(git_args(rest.iter().copied()));
+        args
+    };
 
-        // Clean up any previous failed attempt
-        if path.exists() {
-            let _ = std::fs::remove_dir_all(path);
-        }
+    let fetch = transport.run_env(
+        &repo_args(&["fetch", "--quiet", "origin", "+refs/notes/*:refs/notes/*"]),
+        &options.config_env(),
+    )?;
+    if !fetch.success {
+        // A remote without notes has no refs matching the refspec
+        debug!("No notes fetched: {}", redact_secrets(fetch.stderr.trim()));
+        return Ok(None);
+    }
 
-        // Build git clone command
-        let mut cmd = Command::new("git");
-        cmd.arg("clone");
+    let show = transport.run(&repo_args(&["notes", "show", &resolved.commit_sha]))?;
+    if !show.success {
+        debug!(
+            "No note for commit {}",
+            &resolved.commit_sha[..8.min(resolved.commit_sha.len())]
+        );
+        return Ok(None);
+    }
+    let note = show.stdout.trim_end();
+    Ok((!note.is_empty()).then(|| note.to_string()))
+}
 
-        if shallow {
-            cmd.arg("--depth").arg("1");
+/// Try each configured strategy in order, returning the first clone that
+/// succeeds and recording its strategy. Failures (other than policy errors,
+/// which every strategy would hit) fall through to the next strategy.
+fn clone_with_strategies(
+    transport: &dyn GitTransport,
+    url: &str,
+    git_ref: &str,
+    options: &CloneOptions,
+) -> Result<ResolvedGitSource> {
+    let strategies = options.get_strategies().to_vec();
+    let mut last_error = None;
+    for (i, strategy) in strategies.iter().enumerate() {
+        let attempt = options
+            .clone()
+            .strategies([])
+            .shallow(*strategy == CloneStrategy::Shallow);
+        match clone_once(transport, url, git_ref, &attempt) {
+            Ok(mut resolved) => {
+                debug!("Cloned {} with the {} strategy", redact_url(url), strategy);
+                resolved.strategy = Some(*strategy);
+                return Ok(resolved);
+            }
+            Err(e @ (ApsError::HostNotAllowed { .. } | ApsError::RepositoryMoved { .. })) => {
+                return Err(e)
+            }
+            Err(e) => {
+                if let Some(next) = strategies.get(i + 1) {
+                    warn!(
+                        "{} clone of {} failed, trying {}: {}",
+                        strategy,
+                        redact_url(url),
+                        next,
+                        e
+                    );
+                }
+                last_error = Some(e);
+            }
         }
+    }
+    Err(last_error.expect("strategy list is not empty"))
+}
 
-        cmd.arg("--branch").arg(ref_name);
-        cmd.arg("--single-branch");
-        cmd.arg(url);
-        cmd.arg(path);
+/// Warn about allowlisted submodule paths that the checkout's `.gitmodules`
+/// does not declare; git silently skips them
+fn warn_unknown_submodules(repo_path: &Path, options: &CloneOptions) {
+    let allowlist = options.get_submodule_paths();
+    if allowlist.is_empty() {
+        return;
+    }
+    let gitmodules = std::fs::read_to_string(repo_path.join(".gitmodules")).unwrap_or_default();
+    for path in unknown_submodule_paths(&gitmodules, allowlist) {
+        warn!(
+            "Submodule path '{}' is not declared in .gitmodules; nothing was initialized for it",
+            path
+        );
+    }
+}
 
-        debug!("Running: git clone --branch {} {}", ref_name, url);
+/// Submodules of `commit` in the clone at `repo_path` and the commits they
+/// are pinned to, by repository-relative path.
+///
+/// Pins come from the gitlink entries of the commit's tree and URLs from its
+/// `.gitmodules`, so nothing is fetched and uninitialized submodules are
+/// listed too. Nested submodules are not, as their pins live in the
+/// submodules' own repositories.
+pub fn list_submodule_pins(
+    repo_path: &Path,
+    commit: &str,
+) -> Result<BTreeMap<String, SubmodulePin>> {
+    list_submodule_pins_with(&GitCli, repo_path, commit)
+}
 
-        let output = cmd.output().map_err(|e| ApsError::GitError {
-            message: format!("Failed to execute git command: {}", e),
-        })?;
+/// [`list_submodule_pins`] through the given transport
+pub fn list_submodule_pins_with(
+    transport: &dyn GitTransport,
+    repo_path: &Path,
+    commit: &str,
+) -> Result<BTreeMap<String, SubmodulePin>> {
+    let tree = run_in(transport, repo_path, ["ls-tree", "-r", "-z", commit])?;
+    if !tree.success {
+        return Err(ApsError::GitError {
+            message: format!(
+                "Failed to list tree of {}: {}",
+                &commit[..8.min(commit.len())],
+                redact_secrets(tree.stderr.trim())
+            ),
+        });
+    }
+    let gitlinks: Vec<(&str, &str)> = tree
+        .stdout
+        .split('\0')
+        .filter_map(|record| {
+            let (meta, path) = record.split_once('\t')?;
+            let mut fields = meta.split_whitespace();
+            (fields.next()? == "160000").then_some(())?;
+            Some((path, fields.nth(1)?))
+        })
+        .collect();
+    if gitlinks.is_empty() {
+        return Ok(BTreeMap::new());
+    }
 
-        if output.status.success() {
-            return Ok(ref_name.to_string());
-        }
+    // A commit without .gitmodules still has its gitlinks
+    let spec = format!("{}:.gitmodules", commit);
+    let gitmodules = run_in(transport, repo_path, ["show", spec.as_str()])?;
+    let urls = if gitmodules.success {
+        submodule_urls(&gitmodules.stdout)
+    } else {
+        HashMap::new()
+    };
+    Ok(gitlinks
+        .into_iter()
+        .map(|(path, sha)| {
+            let pin = SubmodulePin {
+                url: urls.get(path).cloned(),
+                commit: sha.to_string(),
+            };
+            (path.to_string(), pin)
+        })
+        .collect())
+}
 
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        debug!("Failed to clone with ref '{}': {}", ref_name, stderr);
-        last_error = Some(stderr.to_string());
+/// URL of each submodule declared in `.gitmodules`, by its `path`
+fn submodule_urls(gitmodules: &str) -> HashMap<String, String> {
+    let mut sections: Vec<(Option<String>, Option<String>)> = Vec::new();
+    for line in gitmodules.lines().map(str::trim) {
+        if line.starts_with('[') {
+            sections.push((None, None));
+            continue;
+        }
+        let (Some(section), Some((key, value))) = (sections.last_mut(), line.split_once('='))
+        else {
+            continue;
+        };
+        let value = value.trim().to_string();
+        match key.trim() {
+            "path" => section.0 = Some(value.trim_end_matches('/').to_string()),
+            "url" => section.1 = Some(value),
+            _ => {}
+        }
     }
+    sections
+        .into_iter()
+        .filter_map(|(path, url)| Some((path?, url?)))
+        .collect()
+}
 
-    // All refs failed
-    let error_detail = last_error
-        .map(|e| format!(": {}", e.trim()))
-        .unwrap_or_default();
-
-    Err(ApsError::GitError {
-        message: format!(
-            "Failed to clone with refs {:?}{}",
-            refs.iter().map(|s| s.to_string()).collect::<Vec<_>>(),
-            error_detail
-        ),
-    })
+/// Entries of `allowlist` that match no `path = ...` line of `.gitmodules`
+fn unknown_submodule_paths<'a>(gitmodules: &str, allowlist: &'a [String]) -> Vec<&'a str> {
+    let declared: Vec<&str> = gitmodules
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            (key.trim() == "path").then(|| value.trim().trim_end_matches('/'))
+        })
+        .collect();
+    allowlist
+        .iter()
+        .map(String::as_str)
+        .filter(|path| !declared.contains(&path.trim_end_matches('/')))
+        .collect()
 }
 
-/// Get the HEAD commit SHA using git CLI
-fn get_head_commit(repo_path: &Path) -> Result<String> {
-    let output = Command::new("git")
-        .arg("-C")
-        .arg(repo_path)
-        .arg("rev-parse")
-        .arg("HEAD")
-        .output()
-        .map_err(|e| ApsError::GitError {
-            message: format!("Failed to execute git rev-parse: {}", e),
-        })?;
+/// Run `clone` against `url`, handling a repository that has moved.
+///
+/// Git reports a redirect it followed as `warning: redirecting to <url>`, and
+/// one it refused with a `redirect: <url>` line. When the new location is a
+/// different repository, the clone fails with [`ApsError::RepositoryMoved`]
+/// unless `follow_moved` is set. With it, aps warns, keeps the clone (or
+/// retries a refused one against the new URL) and records the new location
+/// in `final_url`.
+fn handle_moved_repository<F>(
+    url: &str,
+    options: &CloneOptions,
+    clone: F,
+) -> Result<ResolvedGitSource>
+where
+    F: Fn(&str) -> Result<ResolvedGitSource>,
+{
+    let accept_move = |new_url: &str| {
+        if !options.get_follow_moved() {
+            return Err(ApsError::RepositoryMoved {
+                url: redact_url(url),
+                new_url: redact_url(new_url),
+            });
+        }
+        warn!(
+            "Repository {} has moved to {}; update `repo` in the manifest",
+            redact_url(url),
+            redact_url(new_url)
+        );
+        Ok(())
+    };
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(ApsError::GitError {
-            message: format!("Failed to get HEAD commit: {}", stderr.trim()),
-        });
+    match clone(url) {
+        Ok(resolved) => {
+            if let Some(ref new_url) = resolved.final_url {
+                accept_move(new_url)?;
+            }
+            Ok(resolved)
+        }
+        Err(ApsError::GitRedirect { message }) => {
+            let Some(new_url) = moved_location(url, &message) else {
+                return Err(ApsError::GitRedirect { message });
+            };
+            accept_move(&new_url)?;
+            let mut resolved = clone(&new_url)?;
+            resolved.final_url.get_or_insert(new_url);
+            Ok(resolved)
+        }
+        Err(e) => Err(e),
     }
+}
 
-    let sha = String::from_utf8_lossy(&output.stdout).trim().to_string();
-    Ok(sha)
+/// The repository git was redirected to, if it differs from `url`
+fn moved_location(url: &str, stderr: &str) -> Option<String> {
+    let target = stderr.lines().find_map(|line| {
+        let line = line.trim();
+        line.strip_prefix("warning: redirecting to ")
+            .or_else(|| line.strip_prefix("redirect: "))
+    })?;
+    let target = target.split("/info/refs").next().unwrap_or(target);
+    let target = target.trim_end_matches('/');
+    (canonicalize_repo_url(target) != canonicalize_repo_url(url)).then(|| target.to_string())
 }
 
-/// Clone a git repository at a specific commit SHA.
-/// This is used when respecting locked versions from the lockfile.
-pub fn clone_at_commit(
+/// Clone `url` at `git_ref`, without following repository moves
+fn clone_ref_with(
+    transport: &dyn GitTransport,
     url: &str,
-    commit_sha: &str,
-    resolved_ref: &str,
+    git_ref: &str,
+    options: &CloneOptions,
 ) -> Result<ResolvedGitSource> {
-    info!(
-        "Cloning git repository at locked commit: {} @ {}",
-        url,
-        &commit_sha[..8.min(commit_sha.len())]
-    );
+    HostPolicy::from_env().check(url)?;
+    options.check_git_config()?;
+    check_connect(transport, url, options)?;
+    let log_url = redact_url(url);
+    info!("Cloning git repository: {}", log_url);
 
     // Create temp directory for the clone
     let temp_dir = TempDir::new()
@@ -223,105 +1057,3296 @@ pub fn clone_at_commit(
 
     let repo_path = temp_dir.path().to_path_buf();
 
-    // Clone with no checkout first, then fetch the specific commit
-    // This approach works even if the commit is not at a branch head
-    let mut cmd = Command::new("git");
-    cmd.arg("clone")
-        .arg("--no-checkout")
-        .arg(url)
-        .arg(&repo_path);
+    // A branch or tag may be named like a SHA (`cafe1234`, `20240101`);
+    // refs win, so only a name the remote does not have is a commit
+    if is_commit_prefix(git_ref) && !remote_has_ref(transport, url, git_ref, options)? {
+        return clone_at_commit_prefix(transport, url, temp_dir, git_ref, options);
+    }
+    if is_reflog_ref(git_ref) {
+        return clone_at_reflog_entry(transport, url, temp_dir, git_ref, options);
+    }
 
-    debug!("Running: git clone --no-checkout {}", url);
+    // For auto ref, we need to try different branches
+    let default_branches = options.get_default_branches();
+    let refs_to_try: Vec<&str> = if git_ref == "auto" {
+        default_branches.iter().map(String::as_str).collect()
+    } else {
+        vec![git_ref]
+    };
 
-    let output = cmd.output().map_err(|e| ApsError::GitError {
-        message: format!("Failed to execute git command: {}", e),
-    })?;
+    let (resolved_ref, final_url) =
+        match clone_with_ref_fallback(transport, url, &repo_path, &refs_to_try, options) {
+            // `auto` accepts whatever the remote checks out, even a bare commit
+            Err(ApsError::NoBranches { .. }) if git_ref == "auto" => {
+                warn!(
+                    "{} has no branches; using the commit its detached HEAD points at",
+                    log_url
+                );
+                clone_detached_head(transport, url, &repo_path, options)?
+            }
+            result => result?,
+        };
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(ApsError::GitError {
-            message: format!("Failed to clone repository: {}", stderr.trim()),
-        });
-    }
+    // Get the commit SHA
+    let commit_sha = get_head_commit(transport, &repo_path)?;
+    let describe = describe_with(transport, &repo_path, "HEAD", options)?;
 
-    // Checkout the specific commit
-    let checkout_output = Command::new("git")
-        .arg("-C")
-        .arg(&repo_path)
-        .arg("checkout")
-        .arg(commit_sha)
-        .output()
-        .map_err(|e| ApsError::GitError {
-            message: format!("Failed to execute git checkout: {}", e),
-        })?;
+    info!(
+        "Cloned {} at ref '{}' (commit {}{})",
+        log_url,
+        resolved_ref,
+        &commit_sha[..8.min(commit_sha.len())],
+        describe
+            .as_deref()
+            .map(|d| format!(", {}", d))
+            .unwrap_or_default()
+    );
 
-    if !checkout_output.status.success() {
-        let stderr = String::from_utf8_lossy(&checkout_output.stderr);
-        return Err(ApsError::GitError {
-            message: format!(
-                "Failed to checkout commit {}: {}",
-                &commit_sha[..8.min(commit_sha.len())],
-                stderr.trim()
-            ),
+    Ok(ResolvedGitSource {
+        _temp_dir: temp_dir,
+        repo_path,
+        resolved_ref,
+        commit_sha,
+        describe,
+        final_url,
+        notes: None,
+        strategy: None,
+        shallow_refused: false,
+    })
+}
+
+/// Check that `tag` is a GPG-signed annotated tag pointing at `commit_sha`,
+/// signed by one of `keys` when any are given.
+///
+/// Signatures are verified by `git verify-tag` against the user's keyring.
+fn verify_signed_tag_with(
+    transport: &dyn GitTransport,
+    repo_path: &Path,
+    tag: &str,
+    commit_sha: &str,
+    keys: &[String],
+) -> Result<()> {
+    let reject = |reason: String| ApsError::UnsignedReleaseTag {
+        tag: tag.to_string(),
+        reason,
+    };
+    let run = |rest: &[&str]| {
+        let mut args = git_args(["-C"]);
+        args.push(repo_path.as_os_str().to_owned());
+        args.extend(git_args(rest.iter().copied()));
+        transport.run(&args)
+    };
+    let tag_ref = format!(
+        "refs/tags/{}",
+        tag.strip_prefix("refs/tags/").unwrap_or(tag)
+    );
+
+    let kind = run(&["cat-file", "-t", &tag_ref])?;
+    if !kind.success {
+        return Err(reject("it is not a tag".to_string()));
+    }
+    if kind.stdout.trim() != "tag" {
+        return Err(reject(
+            "it is a lightweight tag, not an annotated one".to_string(),
+        ));
+    }
+
+    let target = run(&["rev-parse", &format!("{}^{{commit}}", tag_ref)])?;
+    if !target.success || target.stdout.trim() != commit_sha {
+        return Err(reject(
+            "the tag does not point at the resolved commit".to_string(),
+        ));
+    }
+
+    let verify = run(&["verify-tag", "--raw", &tag_ref])?;
+    if !verify.success {
+        return Err(reject(if verify.stderr.contains("no signature found") {
+            "the tag is not signed".to_string()
+        } else {
+            format!(
+                "the signature did not verify: {}",
+                redact_secrets(verify.stderr.trim())
+            )
+        }));
+    }
+
+    if !keys.is_empty() {
+        let signers = signing_fingerprints(&verify.stderr);
+        if !signers.iter().any(|fpr| key_matches(fpr, keys)) {
+            return Err(reject(format!(
+                "it is signed by {}, which is not in signing_keys",
+                signers
+                    .first()
+                    .map(String::as_str)
+                    .unwrap_or("an unknown key")
+            )));
+        }
+    }
+    debug!("Verified signed tag {}", tag);
+    Ok(())
+}
+
+/// Signing key and primary key fingerprints from a `VALIDSIG` status line of
+/// `git verify-tag --raw`
+fn signing_fingerprints(status: &str) -> Vec<String> {
+    status
+        .lines()
+        .filter_map(|line| line.strip_prefix("[GNUPG:] VALIDSIG "))
+        .flat_map(|rest| {
+            let fields: Vec<&str> = rest.split_whitespace().collect();
+            // Field 0 is the signing (sub)key, field 9 its primary key
+            [fields.first(), fields.get(9)]
+                .into_iter()
+                .flatten()
+                .map(|f| f.to_ascii_uppercase())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Whether a fingerprint matches an allowed fingerprint or long key ID
+fn key_matches(fingerprint: &str, keys: &[String]) -> bool {
+    keys.iter().any(|key| {
+        let key: String = key
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .trim_start_matches("0x")
+            .to_ascii_uppercase();
+        key.len() >= 16 && fingerprint.ends_with(&key)
+    })
+}
+
+/// Whether a ref looks like a (possibly abbreviated) commit SHA: 7 to 40 hex digits
+pub fn is_commit_prefix(git_ref: &str) -> bool {
+    (7..=40).contains(&git_ref.len()) && git_ref.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Whether the remote has a branch or tag called `name`
+fn remote_has_ref(
+    transport: &dyn GitTransport,
+    url: &str,
+    name: &str,
+    options: &CloneOptions,
+) -> Result<bool> {
+    let mut args = options.config_args();
+    args.extend(git_args([
+        "ls-remote".to_string(),
+        "--refs".to_string(),
+        url.to_string(),
+        format!("refs/heads/{}", name),
+        format!("refs/tags/{}", name),
+    ]));
+    let output = transport.run_env(&args, &options.config_env())?;
+    if !output.success {
+        return Err(git_failure(format!(
+            "Failed to list refs of {}: {}",
+            redact_url(url),
+            redact_secrets(output.stderr.trim())
+        )));
+    }
+    Ok(!output.stdout.trim().is_empty())
+}
+
+/// Whether `sha` is a full commit hash (SHA-1 or SHA-256)
+pub fn is_full_commit_sha(sha: &str) -> bool {
+    matches!(sha.len(), 40 | 64) && sha.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Clone the repository and check out the single commit matching `prefix`.
+///
+/// Abbreviated SHAs cannot be fetched directly, so this clones every branch
+/// and tag and disambiguates locally. The resolved ref is the full SHA.
+fn clone_at_commit_prefix(
+    transport: &dyn GitTransport,
+    url: &str,
+    temp_dir: TempDir,
+    prefix: &str,
+    options: &CloneOptions,
+) -> Result<ResolvedGitSource> {
+    let repo_path = temp_dir.path().to_path_buf();
+    debug!("Resolving commit prefix {} in {}", prefix, redact_url(url));
+
+    let mut args = options.config_args();
+    args.extend(git_args(["clone", "--no-checkout", url]));
+    args.push(repo_path.as_os_str().to_owned());
+    let output = transport.run_env(&args, &options.config_env())?;
+    if !output.success {
+        return Err(git_failure(format!(
+            "Failed to clone repository: {}",
+            redact_secrets(output.stderr.trim())
+        )));
+    }
+    let final_url = moved_location(url, &output.stderr);
+
+    let repo_args = |rest: &[&str]| {
+        let mut args = git_args(["-C"]);
+        args.push(repo_path.as_os_str().to_owned());
+        args.extend(git_args(rest.iter().copied()));
+        args
+    };
+
+    let peel = format!("{}^{{commit}}", prefix.to_ascii_lowercase());
+    let output = transport.run(&repo_args(&["rev-parse", "--verify", "--quiet", &peel]))?;
+    if !output.success {
+        return Err(if output.stderr.contains("is ambiguous") {
+            ApsError::AmbiguousCommit {
+                prefix: prefix.to_string(),
+            }
+        } else {
+            ApsError::CommitNotFound {
+                prefix: prefix.to_string(),
+            }
         });
     }
+    let commit_sha = output.stdout.trim().to_string();
+    checkout_commit(transport, &repo_path, &commit_sha, options)?;
 
+    let describe = describe_with(transport, &repo_path, &commit_sha, options)?;
     info!(
-        "Cloned {} at locked commit {} (ref was '{}')",
-        url,
+        "Cloned {} at commit {} (from prefix {})",
+        redact_url(url),
         &commit_sha[..8.min(commit_sha.len())],
-        resolved_ref
+        prefix
     );
 
     Ok(ResolvedGitSource {
         _temp_dir: temp_dir,
         repo_path,
-        resolved_ref: resolved_ref.to_string(),
-        commit_sha: commit_sha.to_string(),
+        resolved_ref: commit_sha.clone(),
+        commit_sha,
+        describe,
+        final_url,
+        notes: None,
+        strategy: None,
+        shallow_refused: false,
     })
 }
 
-/// Get the commit SHA for a ref from a remote repository without cloning.
-/// Uses `git ls-remote` which is much faster than a full clone.
-pub fn get_remote_commit_sha(url: &str, git_ref: &str) -> Result<Option<String>> {
-    // For "auto" ref, try main then master
-    let refs_to_try = if git_ref == "auto" {
-        vec!["main", "master"]
-    } else {
-        vec![git_ref]
+/// Check out `commit_sha` as a detached HEAD in a `--no-checkout` clone,
+/// applying the submodule settings
+fn checkout_commit(
+    transport: &dyn GitTransport,
+    repo_path: &Path,
+    commit_sha: &str,
+    options: &CloneOptions,
+) -> Result<()> {
+    let repo_args = |rest: &[&str]| {
+        let mut args = git_args(["-C"]);
+        args.push(repo_path.as_os_str().to_owned());
+        args.extend(git_args(rest.iter().copied()));
+        args
     };
 
-    for ref_name in refs_to_try {
-        debug!("Checking remote ref '{}' for {}", ref_name, url);
-
-        let output = Command::new("git")
-            .arg("ls-remote")
-            .arg("--refs")
-            .arg(url)
-            .arg(format!("refs/heads/{}", ref_name))
-            .output()
-            .map_err(|e| ApsError::GitError {
-                message: format!("Failed to execute git ls-remote: {}", e),
-            })?;
+    let run = |args: Vec<OsString>| -> Result<()> {
+        // Submodule updates fetch, so they need the HTTP headers too
+        let output = transport.run_env(&args, &options.config_env())?;
+        if !output.success {
+            return Err(ApsError::GitError {
+                message: format!(
+                    "Failed to checkout commit {}: {}",
+                    &commit_sha[..8.min(commit_sha.len())],
+                    redact_secrets(output.stderr.trim())
+                ),
+            });
+        }
+        Ok(())
+    };
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            debug!("git ls-remote failed for ref '{}': {}", ref_name, stderr);
-            continue;
+    run(repo_args(&["checkout", "--quiet", "--detach", commit_sha]))?;
+    if options.get_submodules() {
+        // `git submodule update` fails outright on a path that is not a
+        // submodule, so only the declared ones are passed
+        let allowlist = options.get_submodule_paths();
+        let gitmodules = std::fs::read_to_string(repo_path.join(".gitmodules")).unwrap_or_default();
+        let unknown = unknown_submodule_paths(&gitmodules, allowlist);
+        let paths: Vec<&str> = allowlist
+            .iter()
+            .map(String::as_str)
+            .filter(|path| !unknown.contains(path))
+            .collect();
+        if allowlist.is_empty() || !paths.is_empty() {
+            let mut update = options.config_args();
+            update.extend(repo_args(&["submodule", "update", "--init", "--recursive"]));
+            if !paths.is_empty() {
+                update.push("--".into());
+                update.extend(paths.into_iter().map(OsString::from));
+            }
+            run(update)?;
         }
+    }
+    Ok(())
+}
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        // Output format: "<sha>\trefs/heads/<branch>"
-        if let Some(line) = stdout.lines().next() {
-            if let Some(sha) = line.split_whitespace().next() {
-                if !sha.is_empty() {
-                    debug!("Found remote commit {} for ref '{}'", sha, ref_name);
-                    return Ok(Some(sha.to_string()));
+/// Clone a local repository at a reflog or stash entry such as `stash@{0}`
+/// or `HEAD@{2}`.
+///
+/// A clone does not carry the source's reflogs, so the selector is resolved
+/// in the source repository itself, and the commit is then fetched from it by
+/// SHA: stash and older reflog commits are usually not reachable from any
+/// ref. The resolved ref echoes the selector.
+fn clone_at_reflog_entry(
+    transport: &dyn GitTransport,
+    url: &str,
+    temp_dir: TempDir,
+    selector: &str,
+    options: &CloneOptions,
+) -> Result<ResolvedGitSource> {
+    let repo_path = temp_dir.path().to_path_buf();
+    let commit_sha = resolve_reflog_ref(transport, url, selector)?;
+    debug!(
+        "Reflog entry {} of {} is commit {}",
+        selector,
+        redact_url(url),
+        commit_sha
+    );
+
+    let mut args = options.config_args();
+    args.extend(git_args(["clone", "--no-checkout", url]));
+    args.push(repo_path.as_os_str().to_owned());
+    let output = transport.run_env(&args, &options.config_env())?;
+    if !output.success {
+        return Err(git_failure(format!(
+            "Failed to clone repository: {}",
+            redact_secrets(output.stderr.trim())
+        )));
+    }
+    fetch_local_commit(transport, &repo_path, url, &commit_sha)?;
+    checkout_commit(transport, &repo_path, &commit_sha, options)?;
+
+    let describe = describe_with(transport, &repo_path, &commit_sha, options)?;
+    info!(
+        "Cloned {} at commit {} (from {})",
+        redact_url(url),
+        &commit_sha[..8.min(commit_sha.len())],
+        selector
+    );
+
+    Ok(ResolvedGitSource {
+        _temp_dir: temp_dir,
+        repo_path,
+        resolved_ref: selector.to_string(),
+        commit_sha,
+        describe,
+        final_url: None,
+        notes: None,
+        strategy: None,
+        shallow_refused: false,
+    })
+}
+
+/// Whether a ref selects a reflog entry (`stash@{0}`, `HEAD@{2}`,
+/// `main@{yesterday}`) rather than naming a ref or commit
+pub fn is_reflog_ref(git_ref: &str) -> bool {
+    git_ref.ends_with('}')
+        && git_ref
+            .split_once("@{")
+            .is_some_and(|(_, selector)| selector.len() > 1)
+}
+
+/// Resolve a reflog selector to a commit in the local repository at `url`
+fn resolve_reflog_ref(transport: &dyn GitTransport, url: &str, selector: &str) -> Result<String> {
+    let id = canonicalize_repo_url(url);
+    if id.host.is_some() {
+        return Err(ApsError::ReflogRefNotLocal {
+            git_ref: selector.to_string(),
+            url: redact_url(url),
+        });
+    }
+    let mut args = git_args(["-C"]);
+    args.push(id.path.into());
+    let peel = format!("{}^{{commit}}", selector);
+    args.extend(git_args(["rev-parse", "--verify", "--quiet", &peel]));
+    let output = transport.run(&args)?;
+    if !output.success {
+        return Err(ApsError::GitRefNotFound {
+            refs: vec![selector.to_string()],
+        });
+    }
+    Ok(output.stdout.trim().to_string())
+}
+
+/// Fetch a commit that the local repository at `url` does not advertise
+fn fetch_local_commit(
+    transport: &dyn GitTransport,
+    repo_path: &Path,
+    url: &str,
+    commit_sha: &str,
+) -> Result<()> {
+    let mut args = git_args(["-C"]);
+    args.push(repo_path.as_os_str().to_owned());
+    args.extend(git_args([
+        "-c",
+        "uploadpack.allowAnySHA1InWant=true",
+        "fetch",
+        "--quiet",
+        "--no-tags",
+        url,
+        commit_sha,
+    ]));
+    let output = transport.run(&args)?;
+    if !output.success {
+        return Err(ApsError::GitError {
+            message: format!(
+                "Failed to fetch commit {}: {}",
+                &commit_sha[..8.min(commit_sha.len())],
+                redact_secrets(output.stderr.trim())
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Normalize an `as_of` timestamp into an ISO 8601 date git understands.
+///
+/// Accepts RFC 3339 (`2024-06-01T12:00:00Z`) or a plain date (`2024-06-01`),
+/// which means the end of that day in UTC.
+pub fn parse_as_of(value: &str) -> Result<String> {
+    if let Ok(ts) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(ts.to_rfc3339());
+    }
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        if let Some(end_of_day) = date.and_hms_opt(23, 59, 59) {
+            return Ok(end_of_day.and_utc().to_rfc3339());
+        }
+    }
+    Err(ApsError::InvalidAsOf {
+        value: value.to_string(),
+    })
+}
+
+/// Normalize a `shallow_since` timestamp into an ISO 8601 date git
+/// understands.
+///
+/// Accepts RFC 3339 or a plain date, which means the start of that day in
+/// UTC (so the day's own commits are included).
+pub fn parse_shallow_since(value: &str) -> Result<String> {
+    if let Ok(ts) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(ts.to_rfc3339());
+    }
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        if let Some(start_of_day) = date.and_hms_opt(0, 0, 0) {
+            return Ok(start_of_day.and_utc().to_rfc3339());
+        }
+    }
+    Err(ApsError::InvalidShallowSince {
+        value: value.to_string(),
+    })
+}
+
+/// Check out the newest commit at or before `before` in a full clone,
+/// returning its SHA
+fn checkout_as_of(repo_path: &Path, before: &str) -> Result<String> {
+    checkout_as_of_with(&GitCli, repo_path, before)
+}
+
+/// Check out the newest commit at or before `before` through the given transport
+pub fn checkout_as_of_with(
+    transport: &dyn GitTransport,
+    repo_path: &Path,
+    before: &str,
+) -> Result<String> {
+    let mut args = git_args(["-C"]);
+    args.push(repo_path.as_os_str().to_owned());
+    args.extend(git_args([
+        "rev-list".to_string(),
+        "-1".to_string(),
+        format!("--before={}", before),
+        "HEAD".to_string(),
+    ]));
+
+    let output = transport.run(&args)?;
+    let sha = output.stdout.trim().to_string();
+    if !output.success || sha.is_empty() {
+        return Err(ApsError::GitError {
+            message: format!(
+                "No commit found at or before {}{}",
+                before,
+                if output.stderr.trim().is_empty() {
+                    String::new()
+                } else {
+                    format!(": {}", redact_secrets(output.stderr.trim()))
                 }
+            ),
+        });
+    }
+
+    checkout_detached(transport, repo_path, &sha)?;
+
+    info!(
+        "Pinned to commit {} (as of {})",
+        &sha[..8.min(sha.len())],
+        before
+    );
+    Ok(sha)
+}
+
+/// Ref prefix selecting a channel from an index file committed to the
+/// repository, e.g. `indexfile:stable`
+pub const INDEX_FILE_REF_PREFIX: &str = "indexfile:";
+
+/// Index file read by `indexfile:` refs unless the source names another
+pub const DEFAULT_INDEX_FILE: &str = "channels.json";
+
+/// Parse a channel index: a JSON (or YAML) object mapping channel names to
+/// a tag, branch or commit SHA
+fn parse_channel_index(path: &str, content: &str) -> Result<BTreeMap<String, String>> {
+    let invalid = |reason: String| ApsError::InvalidChannelIndex {
+        path: path.to_string(),
+        reason,
+    };
+    let channels: BTreeMap<String, String> =
+        serde_yaml::from_str(content).map_err(|e| invalid(e.to_string()))?;
+    if let Some((channel, _)) = channels.iter().find(|(_, target)| target.trim().is_empty()) {
+        return Err(invalid(format!("channel '{}' has an empty ref", channel)));
+    }
+    Ok(channels)
+}
+
+/// History depths tried, in order, when deepening a shallow clone to search
+/// it by message or to find a commit. The last value bounds a message search.
+const MESSAGE_SEARCH_DEPTHS: &[u32] = &[50, 100, 200, 400, 800, 1000];
+
+/// Check out the newest commit reachable from `HEAD` whose message matches
+/// `pattern` (a POSIX extended regex), returning its SHA.
+///
+/// A shallow clone is deepened step by step (up to the last of
+/// [`MESSAGE_SEARCH_DEPTHS`]) until a match is found.
+pub fn checkout_by_message_with(
+    transport: &dyn GitTransport,
+    repo_path: &Path,
+    pattern: &str,
+    git_ref: &str,
+    options: &CloneOptions,
+) -> Result<String> {
+    let mut depths = MESSAGE_SEARCH_DEPTHS.iter();
+    loop {
+        if let Some(sha) = find_commit_by_message(transport, repo_path, pattern)? {
+            checkout_detached(transport, repo_path, &sha)?;
+            info!(
+                "Selected commit {} matching message /{}/",
+                &sha[..8.min(sha.len())],
+                pattern
+            );
+            return Ok(sha);
+        }
+
+        let shallow = run_in(
+            transport,
+            repo_path,
+            ["rev-parse", "--is-shallow-repository"],
+        )?;
+        let next_depth = depths.next();
+        let searched = match (shallow.stdout.trim() == "true", next_depth) {
+            (false, _) => Some("the full history".to_string()),
+            (true, None) => {
+                let count = run_in(transport, repo_path, ["rev-list", "--count", "HEAD"])?;
+                Some(format!("the latest {} commits", count.stdout.trim()))
             }
+            (true, Some(_)) => None,
+        };
+        if let Some(searched) = searched {
+            return Err(ApsError::NoCommitMatchesMessage {
+                pattern: pattern.to_string(),
+                git_ref: git_ref.to_string(),
+                searched,
+            });
+        }
+
+        let depth = next_depth.expect("a depth remains while searching");
+        debug!(
+            "No commit matches /{}/ yet; deepening to {}",
+            pattern, depth
+        );
+        let mut args = options.config_args();
+        args.extend(git_args(["-C".into(), repo_path.as_os_str().to_owned()]));
+        args.extend(git_args([
+            "fetch".to_string(),
+            "--quiet".to_string(),
+            format!("--depth={}", depth),
+            "origin".to_string(),
+        ]));
+        let output = transport.run_env(&args, &options.config_env())?;
+        if !output.success {
+            return Err(git_failure(format!(
+                "Failed to deepen history: {}",
+                redact_secrets(output.stderr.trim())
+            )));
         }
     }
+}
+
+/// Make sure `sha` is in the clone at `repo_path`, deepening a shallow
+/// (depth- or date-bounded) clone step by step until it is, and fetching the
+/// full history as a last resort
+pub fn ensure_commit_present_with(
+    transport: &dyn GitTransport,
+    repo_path: &Path,
+    sha: &str,
+    options: &CloneOptions,
+) -> Result<()> {
+    let object = format!("{}^{{commit}}", sha);
+    let present = || -> Result<bool> {
+        Ok(run_in(transport, repo_path, ["cat-file", "-e", object.as_str()])?.success)
+    };
+    if present()? {
+        return Ok(());
+    }
 
-    // No matching ref found
-    Ok(None)
+    let steps = MESSAGE_SEARCH_DEPTHS
+        .iter()
+        .map(|depth| format!("--depth={}", depth))
+        .chain(std::iter::once("--unshallow".to_string()));
+    for step in steps {
+        let shallow = run_in(
+            transport,
+            repo_path,
+            ["rev-parse", "--is-shallow-repository"],
+        )?;
+        if shallow.stdout.trim() != "true" {
+            break;
+        }
+        debug!(
+            "Commit {} not in clone yet; fetching with {}",
+            &sha[..8.min(sha.len())],
+            step
+        );
+        let mut args = options.config_args();
+        args.extend(git_args(["-C".into(), repo_path.as_os_str().to_owned()]));
+        args.extend(git_args(["fetch".to_string(), "--quiet".to_string(), step]));
+        args.push("origin".into());
+        let output = transport.run_env(&args, &options.config_env())?;
+        if !output.success {
+            return Err(git_failure(format!(
+                "Failed to deepen history: {}",
+                redact_secrets(output.stderr.trim())
+            )));
+        }
+        if present()? {
+            return Ok(());
+        }
+    }
+    Err(ApsError::GitError {
+        message: format!(
+            "Commit {} is not in the history of the clone",
+            &sha[..8.min(sha.len())]
+        ),
+    })
+}
+
+/// Newest commit reachable from `HEAD` with a message line matching `pattern`
+fn find_commit_by_message(
+    transport: &dyn GitTransport,
+    repo_path: &Path,
+    pattern: &str,
+) -> Result<Option<String>> {
+    let grep = format!("--grep={}", pattern);
+    let output = run_in(
+        transport,
+        repo_path,
+        ["rev-list", "-1", "-E", grep.as_str(), "HEAD"],
+    )?;
+    if !output.success {
+        return Err(ApsError::GitError {
+            message: format!(
+                "Failed to search commit messages for /{}/: {}",
+                pattern,
+                output.stderr.trim()
+            ),
+        });
+    }
+    let sha = output.stdout.trim();
+    Ok((!sha.is_empty()).then(|| sha.to_string()))
+}
+
+/// Run `git -C <repo_path> <args>`
+fn run_in<I, S>(transport: &dyn GitTransport, repo_path: &Path, args: I) -> Result<GitOutput>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<OsString>,
+{
+    let mut full = git_args(["-C"]);
+    full.push(repo_path.as_os_str().to_owned());
+    full.extend(git_args(args));
+    transport.run(&full)
+}
+
+/// Detach `HEAD` at `sha`
+fn checkout_detached(transport: &dyn GitTransport, repo_path: &Path, sha: &str) -> Result<()> {
+    let output = run_in(
+        transport,
+        repo_path,
+        ["checkout", "--quiet", "--detach", sha],
+    )?;
+    if !output.success {
+        return Err(ApsError::GitError {
+            message: format!(
+                "Failed to checkout commit {}: {}",
+                &sha[..8.min(sha.len())],
+                redact_secrets(output.stderr.trim())
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Build an argument list for a git invocation
+fn git_args<I, S>(args: I) -> Vec<OsString>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<OsString>,
+{
+    args.into_iter().map(Into::into).collect()
+}
+
+/// Try to clone with fallback refs
+fn clone_with_ref_fallback(
+    transport: &dyn GitTransport,
+    url: &str,
+    path: &Path,
+    refs: &[&str],
+    options: &CloneOptions,
+) -> Result<(String, Option<String>)> {
+    let mut last_error = None;
+    let log_url = redact_url(url);
+
+    for ref_name in refs {
+        debug!("Trying to clone with ref '{}'", ref_name);
+
+        // Clean up any previous failed attempt
+        if path.exists() {
+            let _ = std::fs::remove_dir_all(path);
+        }
+
+        let output = if is_full_ref(ref_name) {
+            fetch_exact_ref(transport, url, path, ref_name, options)?
+        } else {
+            // Build git clone command
+            let mut args = options.config_args();
+            args.push("clone".into());
+            args.extend(options.clone_args());
+            args.extend(git_args(["--branch", ref_name, "--single-branch", url]));
+            args.push(path.as_os_str().to_owned());
+
+            debug!("Running: git clone --branch {} {}", ref_name, log_url);
+            transport.run_env(&args, &options.config_env())?
+        };
+
+        if output.success {
+            return Ok((ref_name.to_string(), moved_location(url, &output.stderr)));
+        }
+
+        let stderr = redact_secrets(&output.stderr);
+        debug!("Failed to clone with ref '{}': {}", ref_name, stderr);
+        last_error = Some(stderr);
+    }
+
+    // A missing branch is expected when the remote has nothing to clone
+    let branch_missing = last_error
+        .as_deref()
+        .is_some_and(|e| e.contains("not found in upstream"));
+    if branch_missing {
+        match remote_branches(transport, url, options)? {
+            RemoteBranches::Empty => return Err(ApsError::EmptyRepository { url: log_url }),
+            RemoteBranches::DetachedHead => return Err(ApsError::NoBranches { url: log_url }),
+            RemoteBranches::Present | RemoteBranches::Unknown => {}
+        }
+    }
+
+    // Opt-in: substitute the branch the remote's HEAD points at
+    if branch_missing && options.get_default_branch_fallback() {
+        if let Some(branch) = remote_default_branch(transport, url, options)? {
+            if !refs.contains(&branch.as_str()) {
+                warn!(
+                    "Ref {} not found in {}; falling back to its default branch '{}'",
+                    refs.iter()
+                        .map(|r| format!("'{}'", r))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    log_url,
+                    branch
+                );
+                return clone_with_ref_fallback(transport, url, path, &[&branch], options);
+            }
+        }
+    }
+
+    // All refs failed
+    let error_detail = last_error
+        .map(|e| format!(": {}", e.trim()))
+        .unwrap_or_default();
+
+    Err(git_failure(format!(
+        "Failed to clone with refs {:?}{}",
+        refs.iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+        error_detail
+    )))
+}
+
+/// What a remote advertises, used to explain why a branch clone failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RemoteBranches {
+    /// No refs at all: the repository has no commits
+    Empty,
+    /// A `HEAD` but no `refs/heads/*`, as left by mirrors that publish a
+    /// detached commit
+    DetachedHead,
+    /// At least one branch exists
+    Present,
+    /// The remote could not be listed
+    Unknown,
+}
+
+/// Classify the remote's refs (`git ls-remote <url>`)
+fn remote_branches(
+    transport: &dyn GitTransport,
+    url: &str,
+    options: &CloneOptions,
+) -> Result<RemoteBranches> {
+    let mut args = options.config_args();
+    args.extend(git_args(["ls-remote", url]));
+    let output = transport.run_env(&args, &options.config_env())?;
+    if !output.success {
+        return Ok(RemoteBranches::Unknown);
+    }
+    let names: Vec<&str> = output
+        .stdout
+        .lines()
+        .filter_map(|line| line.split('\t').nth(1))
+        .collect();
+    Ok(if names.is_empty() {
+        RemoteBranches::Empty
+    } else if names.iter().any(|name| name.starts_with("refs/heads/")) {
+        RemoteBranches::Present
+    } else if names.contains(&"HEAD") {
+        RemoteBranches::DetachedHead
+    } else {
+        RemoteBranches::Unknown
+    })
+}
+
+/// Clone the remote's `HEAD` without naming a branch, for repositories whose
+/// `HEAD` is a detached commit; returns the commit as the resolved ref
+fn clone_detached_head(
+    transport: &dyn GitTransport,
+    url: &str,
+    path: &Path,
+    options: &CloneOptions,
+) -> Result<(String, Option<String>)> {
+    if path.exists() {
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    let mut args = options.config_args();
+    args.push("clone".into());
+    args.extend(options.clone_args());
+    args.push(url.into());
+    args.push(path.as_os_str().to_owned());
+
+    debug!("Running: git clone {}", redact_url(url));
+    let output = transport.run_env(&args, &options.config_env())?;
+    if !output.success {
+        return Err(git_failure(format!(
+            "Failed to clone the detached HEAD of {}: {}",
+            redact_url(url),
+            redact_secrets(output.stderr.trim())
+        )));
+    }
+    let sha = get_head_commit(transport, path)?;
+    Ok((sha, moved_location(url, &output.stderr)))
+}
+
+/// Branch the remote's `HEAD` points at (`git ls-remote --symref <url> HEAD`)
+fn remote_default_branch(
+    transport: &dyn GitTransport,
+    url: &str,
+    options: &CloneOptions,
+) -> Result<Option<String>> {
+    let mut args = options.config_args();
+    args.extend(git_args(["ls-remote", "--symref", url, "HEAD"]));
+    let output = transport.run_env(&args, &options.config_env())?;
+    if !output.success {
+        return Ok(None);
+    }
+    Ok(parse_symref_head(&output.stdout))
+}
+
+/// Extract the branch from a `ref: refs/heads/<branch>\tHEAD` line
+fn parse_symref_head(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (target, name) = line.strip_prefix("ref: ")?.split_once('\t')?;
+        (name.trim() == "HEAD")
+            .then(|| target.strip_prefix("refs/heads/"))
+            .flatten()
+            .map(str::to_string)
+    })
+}
+
+/// Build the error for a failed git command, recognizing redirect problems
+fn git_failure(message: String) -> ApsError {
+    if is_redirect_error(&message) {
+        ApsError::GitRedirect { message }
+    } else if is_sso_error(&message) {
+        let authorization_url = sso_authorization_url(&message).unwrap_or_else(|| {
+            "enable SSO for the organization in the token's settings on the git host".to_string()
+        });
+        ApsError::SsoAuthorizationRequired {
+            authorization_url,
+            message,
+        }
+    } else if is_shallow_refused(&message) {
+        ApsError::GitShallowRefused { message }
+    } else if is_tls_error(&message) {
+        ApsError::GitTls { message }
+    } else if is_daemon_unreachable(&message) {
+        ApsError::GitDaemonUnreachable { message }
+    } else if message.contains("repository not exported") {
+        ApsError::GitRepoNotExported { message }
+    } else if let Some(phase) = timeout_phase(&message) {
+        ApsError::GitTimeout {
+            phase: phase.to_string(),
+            message,
+        }
+    } else {
+        ApsError::GitError { message }
+    }
+}
+
+/// Whether git (curl) stderr indicates a blocked, disallowed or looping redirect
+fn is_redirect_error(stderr: &str) -> bool {
+    let lower = stderr.to_ascii_lowercase();
+    lower.contains("unable to update url base from redirection")
+        || lower.contains("redirects followed")
+        || lower.contains("maximum amount of redirects")
+        || ["301", "302", "303", "307", "308"]
+            .iter()
+            .any(|code| lower.contains(&format!("returned error: {}", code)))
+}
+
+/// Whether the server refused a token (or SSH key) that is not authorized for
+/// an organization enforcing SAML single sign-on, as GitHub does with
+/// `remote: ... enabled or enforced SAML SSO` or `Resource protected by
+/// organization SAML enforcement`
+fn is_sso_error(stderr: &str) -> bool {
+    let lower = stderr.to_ascii_lowercase();
+    lower.contains("/sso?authorization_request=")
+        || (lower.contains("saml") && (lower.contains("sso") || lower.contains("enforcement")))
+}
+
+/// The SSO page an SSO refusal points at, e.g. `https://github.com/orgs/acme/sso`.
+///
+/// The `authorization_request` query is dropped: stderr has been redacted by
+/// now, and the page without it still starts the authorization.
+fn sso_authorization_url(stderr: &str) -> Option<String> {
+    stderr
+        .split_whitespace()
+        .map(|word| {
+            word.trim_matches(|c: char| matches!(c, '\'' | '"' | '`' | '<' | '>' | '(' | ')'))
+        })
+        .map(|word| word.trim_end_matches(['.', ',', ';']))
+        .find(|word| word.starts_with("https://") && word.contains("/sso"))
+        .map(|url| url.split('?').next().unwrap_or(url).to_string())
+}
+
+/// Whether the server refused to serve limited history: a dumb HTTP
+/// server or a server without shallow support (`does not support shallow
+/// capabilities`, `Server does not support shallow clients`,
+/// `Server does not support --shallow-since`)
+fn is_shallow_refused(stderr: &str) -> bool {
+    let lower = stderr.to_ascii_lowercase();
+    [
+        "does not support shallow",
+        "does not support --shallow",
+        "does not support --deepen",
+    ]
+    .iter()
+    .any(|needle| lower.contains(needle))
+}
+
+/// Whether git (curl) stderr indicates a failed TLS handshake or an unusable
+/// client certificate/key
+fn is_tls_error(stderr: &str) -> bool {
+    let lower = stderr.to_ascii_lowercase();
+    [
+        "client certificate",
+        "unable to set private key",
+        "ssl certificate problem",
+        "ssl certificate verify",
+        "ssl_connect",
+        "ssl routines",
+        "tlsv1 alert",
+        "sslv3 alert",
+        "gnutls_handshake",
+        "schannel",
+    ]
+    .iter()
+    .any(|needle| lower.contains(needle))
+}
+
+/// Whether git stderr shows a refused or failed TCP connection to a git
+/// daemon (`git://`); git reports these as `unable to connect to <host>`
+/// followed by one `errno=` line per address tried
+fn is_daemon_unreachable(stderr: &str) -> bool {
+    stderr.contains("unable to connect to") && stderr.contains("errno=")
+}
+
+/// Which phase a timed-out git command was in, from its (curl or ssh)
+/// stderr: `transfer` when the low-speed limit tripped, `connect` when the
+/// connection was never established
+fn timeout_phase(stderr: &str) -> Option<&'static str> {
+    let lower = stderr.to_ascii_lowercase();
+    if lower.contains("operation too slow") {
+        Some("transfer")
+    } else if lower.contains("connection timed out") || lower.contains("connect timeout") {
+        Some("connect")
+    } else {
+        None
+    }
+}
+
+/// Bound the connect phase: when a connect timeout is configured, require
+/// the remote to answer `git ls-remote <url> HEAD` in time before cloning
+fn check_connect(transport: &dyn GitTransport, url: &str, options: &CloneOptions) -> Result<()> {
+    let Some(timeout) = options.get_connect_timeout() else {
+        return Ok(());
+    };
+    let mut args = options.config_args();
+    args.extend(git_args(["ls-remote", url, "HEAD"]));
+    match transport.run_with_deadline(&args, &options.config_env(), timeout)? {
+        None => Err(ApsError::GitTimeout {
+            phase: "connect".to_string(),
+            message: format!(
+                "{} did not respond within {:.1}s",
+                redact_url(url),
+                timeout.as_secs_f64()
+            ),
+        }),
+        Some(output) if !output.success => Err(git_failure(format!(
+            "Failed to reach {}: {}",
+            redact_url(url),
+            redact_secrets(output.stderr.trim())
+        ))),
+        Some(_) => Ok(()),
+    }
+}
+
+/// Whether a ref is a full refname such as `refs/pull/123/head`.
+///
+/// Full refnames (pull/merge request refs in particular) are not branches,
+/// so `git clone --branch` cannot check them out; they are fetched explicitly.
+fn is_full_ref(git_ref: &str) -> bool {
+    git_ref.starts_with("refs/")
+}
+
+/// Check out a full refname by initializing an empty repository and fetching
+/// exactly that ref. Returns the output of the first failing step, or a
+/// successful output.
+fn fetch_exact_ref(
+    transport: &dyn GitTransport,
+    url: &str,
+    path: &Path,
+    git_ref: &str,
+    options: &CloneOptions,
+) -> Result<GitOutput> {
+    debug!("Fetching exact ref {} from {}", git_ref, redact_url(url));
+
+    let mut init = git_args(["init", "--quiet"]);
+    init.push(path.as_os_str().to_owned());
+
+    let repo_args = |rest: Vec<OsString>| {
+        let mut args = options.config_args();
+        args.push("-C".into());
+        args.push(path.as_os_str().to_owned());
+        args.extend(rest);
+        args
+    };
+
+    let mut fetch = git_args(["fetch", "--no-tags"]);
+    if let Some(depth) = options.get_depth() {
+        fetch.extend(git_args(["--depth".to_string(), depth.to_string()]));
+    }
+    if let Some(since) = options.get_shallow_since() {
+        fetch.push(format!("--shallow-since={}", since).into());
+    }
+    fetch.extend(git_args(["origin", git_ref]));
+
+    let mut steps = vec![
+        init,
+        repo_args(git_args(["remote", "add", "origin", url])),
+        repo_args(fetch),
+        repo_args(git_args(["checkout", "--quiet", "--detach", "FETCH_HEAD"])),
+    ];
+    if options.get_submodules() {
+        let mut update = git_args(["submodule", "update", "--init", "--recursive"]);
+        if options.is_shallow() {
+            update.extend(git_args(["--depth", "1"]));
+        }
+        update.extend(options.submodule_pathspec());
+        steps.push(repo_args(update));
+    }
+
+    for args in steps {
+        let output = transport.run_env(&args, &options.config_env())?;
+        if !output.success {
+            return Ok(output);
+        }
+    }
+    Ok(GitOutput {
+        success: true,
+        ..Default::default()
+    })
+}
+
+/// Get the HEAD commit SHA of a local clone
+fn get_head_commit(transport: &dyn GitTransport, repo_path: &Path) -> Result<String> {
+    let mut args = git_args(["-C"]);
+    args.push(repo_path.as_os_str().to_owned());
+    args.extend(git_args(["rev-parse", "HEAD"]));
+
+    let output = transport.run(&args)?;
+
+    if !output.success {
+        return Err(ApsError::GitError {
+            message: format!(
+                "Failed to get HEAD commit: {}",
+                redact_secrets(output.stderr.trim())
+            ),
+        });
+    }
+
+    Ok(output.stdout.trim().to_string())
+}
+
+/// Committer date (RFC 3339) of `commit` in the clone at `repo_path`
+pub fn commit_date(repo_path: &Path, commit: &str) -> Result<String> {
+    commit_date_with(&GitCli, repo_path, commit)
+}
+
+/// [`commit_date`] through the given transport
+pub fn commit_date_with(
+    transport: &dyn GitTransport,
+    repo_path: &Path,
+    commit: &str,
+) -> Result<String> {
+    let output = run_in(transport, repo_path, ["show", "-s", "--format=%cI", commit])?;
+    if !output.success {
+        return Err(ApsError::GitError {
+            message: format!(
+                "Failed to read the date of commit {}: {}",
+                commit,
+                redact_secrets(output.stderr.trim())
+            ),
+        });
+    }
+    Ok(output.stdout.trim().to_string())
+}
+
+/// List the checked-out commit's tree at `path` (a directory or file in a clone)
+pub(crate) fn list_tree(path: &Path) -> Result<Vec<TreeEntry>> {
+    list_tree_with(&GitCli, path)
+}
+
+/// List the checked-out commit's tree at `path` through the given transport.
+///
+/// Uses `ls-tree -r -l` so sizes come from the object database without
+/// reading blobs. Paths are relative to `path` (or its file name for files).
+pub(crate) fn list_tree_with(transport: &dyn GitTransport, path: &Path) -> Result<Vec<TreeEntry>> {
+    let (dir, file) = if path.is_file() {
+        (path.parent().unwrap_or(path), path.file_name())
+    } else {
+        (path, None)
+    };
+    let mut args = git_args(["-C"]);
+    args.push(dir.as_os_str().to_owned());
+    args.extend(git_args(["ls-tree", "-r", "-l", "-z", "HEAD"]));
+    if let Some(file) = file {
+        args.push("--".into());
+        args.push(file.to_owned());
+    }
+
+    let output = transport.run(&args)?;
+    if !output.success {
+        return Err(ApsError::GitError {
+            message: format!(
+                "Failed to list tree: {}",
+                redact_secrets(output.stderr.trim())
+            ),
+        });
+    }
+
+    output
+        .stdout
+        .split('\0')
+        .filter(|record| !record.is_empty())
+        .map(parse_tree_record)
+        .collect()
+}
+
+/// Paths passed to one `git check-attr`, keeping the command line short
+const EXPORT_IGNORE_BATCH: usize = 500;
+
+/// Paths of `paths` (relative to the clone at `repo_root`) that
+/// `.gitattributes` marks `export-ignore`, which `git archive` leaves out.
+///
+/// Directories only match patterns naming the directory itself, as in git,
+/// so callers should pass directories as well as files.
+pub fn export_ignored_paths(repo_root: &Path, paths: &[String]) -> Result<BTreeSet<String>> {
+    export_ignored_paths_with(&GitCli, repo_root, paths)
+}
+
+/// [`export_ignored_paths`] through the given transport
+pub fn export_ignored_paths_with(
+    transport: &dyn GitTransport,
+    repo_root: &Path,
+    paths: &[String],
+) -> Result<BTreeSet<String>> {
+    let mut ignored = BTreeSet::new();
+    for chunk in paths.chunks(EXPORT_IGNORE_BATCH) {
+        let mut args = git_args(["-C"]);
+        args.push(repo_root.as_os_str().to_owned());
+        args.extend(git_args(["check-attr", "-z", "export-ignore", "--"]));
+        args.extend(git_args(chunk));
+        let output = transport.run(&args)?;
+        if !output.success {
+            return Err(ApsError::GitError {
+                message: format!(
+                    "Failed to read export-ignore attributes: {}",
+                    redact_secrets(output.stderr.trim())
+                ),
+            });
+        }
+        // Records are `<path> NUL export-ignore NUL <value> NUL`
+        let fields: Vec<&str> = output.stdout.split('\0').collect();
+        for record in fields.chunks_exact(3) {
+            if record[2] == "set" {
+                ignored.insert(record[0].to_string());
+            }
+        }
+    }
+    Ok(ignored)
+}
+
+/// Parse one `ls-tree -l -z` record: `<mode> <type> <object> <size>\t<path>`
+fn parse_tree_record(record: &str) -> Result<TreeEntry> {
+    let malformed = || ApsError::GitError {
+        message: format!("Unexpected ls-tree output: {:?}", record),
+    };
+    let (meta, path) = record.split_once('\t').ok_or_else(malformed)?;
+    let mut fields = meta.split_whitespace();
+    let mode = fields
+        .next()
+        .and_then(|m| u32::from_str_radix(m, 8).ok())
+        .ok_or_else(malformed)?;
+    let size = fields.nth(2).ok_or_else(malformed)?;
+    let kind = TreeEntryKind::from_mode(mode).ok_or_else(malformed)?;
+
+    Ok(TreeEntry {
+        path: path.to_string(),
+        size: size.parse().ok(),
+        mode,
+        kind,
+    })
+}
+
+/// Describe `commit` by its nearest tag (`git describe --tags`) when the
+/// options ask for it.
+///
+/// The options' `describe_match` restricts the tags considered (`--match`).
+/// Returns `None` when describing is off or no tag is reachable, which is
+/// common for shallow clones.
+pub fn describe_with(
+    transport: &dyn GitTransport,
+    repo_path: &Path,
+    commit: &str,
+    options: &CloneOptions,
+) -> Result<Option<String>> {
+    if !options.get_describe() {
+        return Ok(None);
+    }
+    let pattern = options.get_describe_match();
+    let mut args = git_args(["-C"]);
+    args.push(repo_path.as_os_str().to_owned());
+    args.extend(git_args(["describe", "--tags"]));
+    if let Some(pattern) = pattern {
+        args.extend(git_args(["--match", pattern]));
+    }
+    args.push(commit.into());
+
+    let output = transport.run(&args)?;
+    if !output.success {
+        debug!(
+            "No tag describes {}: {}",
+            commit,
+            redact_secrets(output.stderr.trim())
+        );
+        return Ok(None);
+    }
+    let description = output.stdout.trim();
+    Ok((!description.is_empty()).then(|| description.to_string()))
+}
+
+/// Clone a git repository at a specific commit SHA through the given transport.
+/// This is used when respecting locked versions from the lockfile.
+pub fn clone_at_commit_with(
+    transport: &dyn GitTransport,
+    url: &str,
+    commit_sha: &str,
+    resolved_ref: &str,
+    options: &CloneOptions,
+) -> Result<ResolvedGitSource> {
+    let rewritten = rewrite_url(url);
+    let url = rewritten.as_deref().unwrap_or(url);
+    let mut resolved = handle_moved_repository(url, options, |url| {
+        clone_commit_with(transport, url, commit_sha, resolved_ref, options)
+    })?;
+    warn_unknown_submodules(&resolved.repo_path, options);
+    if options.get_fetch_notes() {
+        resolved.notes = fetch_commit_notes(transport, &resolved, options)?;
+    }
+    Ok(resolved)
+}
+
+/// Clone `url` at a locked commit, without following repository moves
+fn clone_commit_with(
+    transport: &dyn GitTransport,
+    url: &str,
+    commit_sha: &str,
+    resolved_ref: &str,
+    options: &CloneOptions,
+) -> Result<ResolvedGitSource> {
+    HostPolicy::from_env().check(url)?;
+    options.check_git_config()?;
+    check_connect(transport, url, options)?;
+    let log_url = redact_url(url);
+    info!(
+        "Cloning git repository at locked commit: {} @ {}",
+        log_url,
+        &commit_sha[..8.min(commit_sha.len())]
+    );
+
+    // Create temp directory for the clone
+    let temp_dir = TempDir::new()
+        .map_err(|e| ApsError::io(e, "Failed to create temp directory for git clone"))?;
+
+    let repo_path = temp_dir.path().to_path_buf();
+
+    // Clone with no checkout first, then fetch the specific commit
+    // This approach works even if the commit is not at a branch head
+    let clone = |bound: Option<String>| {
+        let mut args = options.config_args();
+        args.extend(git_args(["clone", "--no-checkout"]));
+        args.extend(bound.map(OsString::from));
+        args.push(url.into());
+        args.push(repo_path.as_os_str().to_owned());
+        transport.run_env(&args, &options.config_env())
+    };
+
+    debug!("Running: git clone --no-checkout {}", log_url);
+
+    let since = options.get_shallow_since();
+    let mut output = clone(since.map(|since| format!("--shallow-since={}", since)))?;
+    // Nothing is newer than the cut-off: start from the tip and deepen below
+    if !output.success && since.is_some() && output.stderr.contains("no commits selected") {
+        output = clone(Some("--depth=1".to_string()))?;
+    }
+
+    if !output.success {
+        return Err(git_failure(format!(
+            "Failed to clone repository: {}",
+            redact_secrets(output.stderr.trim())
+        )));
+    }
+    let final_url = moved_location(url, &output.stderr);
+
+    // Full refnames such as pull request heads are not fetched by a clone
+    if is_full_ref(resolved_ref) {
+        let mut args = options.config_args();
+        args.push("-C".into());
+        args.push(repo_path.as_os_str().to_owned());
+        args.extend(git_args(["fetch", "--no-tags", "origin", resolved_ref]));
+
+        let output = transport.run_env(&args, &options.config_env())?;
+        if !output.success {
+            return Err(ApsError::GitError {
+                message: format!(
+                    "Failed to fetch ref {}: {}",
+                    resolved_ref,
+                    redact_secrets(output.stderr.trim())
+                ),
+            });
+        }
+    }
+
+    // Stash and reflog commits are not advertised; fetch them by SHA
+    if is_reflog_ref(resolved_ref) {
+        fetch_local_commit(transport, &repo_path, url, commit_sha)?;
+    }
+
+    // A date-bounded clone may start after the locked commit
+    if options.is_shallow() {
+        ensure_commit_present_with(transport, &repo_path, commit_sha, options)?;
+    }
+    checkout_commit(transport, &repo_path, commit_sha, options)?;
+
+    info!(
+        "Cloned {} at locked commit {} (ref was '{}')",
+        log_url,
+        &commit_sha[..8.min(commit_sha.len())],
+        resolved_ref
+    );
+    let describe = describe_with(transport, &repo_path, commit_sha, options)?;
+
+    Ok(ResolvedGitSource {
+        _temp_dir: temp_dir,
+        repo_path,
+        resolved_ref: resolved_ref.to_string(),
+        commit_sha: commit_sha.to_string(),
+        describe,
+        final_url,
+        notes: None,
+        strategy: None,
+        shallow_refused: false,
+    })
+}
+
+/// Get the commit SHA for a ref from a remote repository without cloning.
+/// Uses `git ls-remote` which is much faster than a full clone.
+///
+/// `auto` tries the source's `default_branches` (see [`default_branches`](clone_options::default_branches)).
+/// `options` supplies the transport settings (client certificates, headers,
+/// extra git config) the clone would use.
+pub fn get_remote_commit_sha(
+    url: &str,
+    git_ref: &str,
+    options: &CloneOptions,
+) -> Result<Option<String>> {
+    get_remote_commit_sha_with(&GitCli, url, git_ref, options)
+}
+
+/// Get the commit SHA for a remote ref through the given transport
+pub fn get_remote_commit_sha_with(
+    transport: &dyn GitTransport,
+    url: &str,
+    git_ref: &str,
+    options: &CloneOptions,
+) -> Result<Option<String>> {
+    let rewritten = rewrite_url(url);
+    let url = rewritten.as_deref().unwrap_or(url);
+    HostPolicy::from_env().check(url)?;
+    options.check_git_config()?;
+    if is_reflog_ref(git_ref) {
+        return Ok(resolve_reflog_ref(transport, url, git_ref).ok());
+    }
+    if VersionReq::parse(git_ref).is_some() {
+        let refs = list_remote_refs_with(transport, url, options)?;
+        return Ok(refs
+            .resolve(git_ref, &options.get_default_branches())
+            .map(|(_, sha)| sha));
+    }
+
+    // For "auto" ref, try the default branches in order
+    let auto_branches = options.get_default_branches();
+    let refs_to_try: Vec<&str> = if git_ref == "auto" {
+        auto_branches.iter().map(String::as_str).collect()
+    } else {
+        vec![git_ref]
+    };
+
+    let log_url = redact_url(url);
+    for ref_name in refs_to_try {
+        debug!("Checking remote ref '{}' for {}", ref_name, log_url);
+
+        let mut args = options.config_args();
+        args.extend(git_args([
+            "ls-remote".to_string(),
+            "--refs".to_string(),
+            url.to_string(),
+            if is_full_ref(ref_name) {
+                ref_name.to_string()
+            } else {
+                format!("refs/heads/{}", ref_name)
+            },
+        ]));
+        let output = transport.run_env(&args, &options.config_env())?;
+
+        if !output.success {
+            debug!(
+                "git ls-remote failed for ref '{}': {}",
+                ref_name,
+                redact_secrets(&output.stderr)
+            );
+            continue;
+        }
+
+        // Output format: "<sha>\trefs/heads/<branch>"
+        if let Some(line) = output.stdout.lines().next() {
+            if let Some(sha) = line.split_whitespace().next() {
+                if !sha.is_empty() {
+                    debug!("Found remote commit {} for ref '{}'", sha, ref_name);
+                    return Ok(Some(sha.to_string()));
+                }
+            }
+        }
+    }
+
+    // No matching ref found
+    Ok(None)
+}
+
+/// Branch the remote's `HEAD` points at, without cloning
+pub fn get_remote_default_branch(url: &str, options: &CloneOptions) -> Result<Option<String>> {
+    let rewritten = rewrite_url(url);
+    let url = rewritten.as_deref().unwrap_or(url);
+    HostPolicy::from_env().check(url)?;
+    options.check_git_config()?;
+    remote_default_branch(&GitCli, url, options)
+}
+
+/// Refs advertised by a remote, as listed by a single `git ls-remote`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RemoteRefs {
+    refs: BTreeMap<String, String>,
+}
+
+impl RemoteRefs {
+    /// Parse `git ls-remote` output, preferring peeled (`^{}`) tag commits
+    pub fn parse(output: &str) -> Self {
+        let mut refs = BTreeMap::new();
+        let mut peeled = Vec::new();
+        for line in output.lines() {
+            let Some((sha, name)) = line.split_once('\t') else {
+                continue;
+            };
+            match name.strip_suffix("^{}") {
+                Some(tag) => peeled.push((tag.to_string(), sha.to_string())),
+                None => {
+                    refs.insert(name.to_string(), sha.to_string());
+                }
+            }
+        }
+        refs.extend(peeled);
+        Self { refs }
+    }
+
+    /// Resolve a manifest ref to `(resolved ref, commit SHA)`.
+    ///
+    /// `auto` tries the source's `default_branches` (see
+    /// [`default_branches`](clone_options::default_branches)); short names match branches before tags; full
+    /// refnames match exactly; a version requirement matches the highest
+    /// release tag it admits.
+    pub fn resolve(&self, git_ref: &str, default_branches: &[String]) -> Option<(String, String)> {
+        if let Some(req) = VersionReq::parse(git_ref) {
+            return self
+                .semver_tags()
+                .latest_matching(&req)
+                .map(|tag| (tag.name.clone(), tag.sha.clone()));
+        }
+        let names = if git_ref == "auto" {
+            clone_options::default_branches(default_branches)
+        } else {
+            vec![git_ref.to_string()]
+        };
+        for name in &names {
+            let candidates = if is_full_ref(name) {
+                vec![name.to_string()]
+            } else {
+                vec![
+                    format!("refs/heads/{}", name),
+                    format!("refs/tags/{}", name),
+                ]
+            };
+            if let Some(sha) = candidates.iter().find_map(|c| self.refs.get(c)) {
+                return Some((name.to_string(), sha.clone()));
+            }
+        }
+        None
+    }
+
+    /// Tags whose names parse as semantic versions, sorted from lowest to
+    /// highest precedence, plus the tag names that did not parse
+    pub fn semver_tags(&self) -> SemverTags {
+        let mut tags = Vec::new();
+        let mut skipped = Vec::new();
+        let tag_refs = self
+            .refs
+            .iter()
+            .filter_map(|(name, sha)| Some((name.strip_prefix("refs/tags/")?, sha)));
+        for (tag, sha) in tag_refs {
+            match Version::parse(tag) {
+                Some(version) => tags.push(TagInfo {
+                    name: tag.to_string(),
+                    is_prerelease: version.is_prerelease(),
+                    version,
+                    sha: sha.clone(),
+                }),
+                None => skipped.push(tag.to_string()),
+            }
+        }
+        // Tie-break on the name so `v1.0.0` and `1.0.0` sort deterministically
+        tags.sort_by(|a, b| a.version.cmp(&b.version).then_with(|| a.name.cmp(&b.name)));
+        SemverTags { tags, skipped }
+    }
+}
+
+/// A remote tag named after a semantic version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagInfo {
+    /// Tag name without `refs/tags/`, e.g. `v1.2.0`
+    pub name: String,
+    pub version: Version,
+    /// Commit the tag points at (peeled for annotated tags)
+    pub sha: String,
+    pub is_prerelease: bool,
+}
+
+/// Semver-named tags of a remote, lowest precedence first
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SemverTags {
+    pub tags: Vec<TagInfo>,
+    /// Tag names that are not semantic versions, e.g. `latest`
+    pub skipped: Vec<String>,
+}
+
+impl SemverTags {
+    /// Highest-precedence release tag that `req` admits
+    pub fn latest_matching(&self, req: &VersionReq) -> Option<&TagInfo> {
+        self.tags
+            .iter()
+            .rev()
+            .find(|tag| !tag.is_prerelease && req.matches(&tag.version))
+    }
+}
+
+/// List a remote's semver-named tags with one `git ls-remote`, using the
+/// transport settings in `options`
+pub fn list_semver_tags(url: &str, options: &CloneOptions) -> Result<SemverTags> {
+    list_semver_tags_with(&GitCli, url, options)
+}
+
+/// List a remote's semver-named tags through the given transport
+pub fn list_semver_tags_with(
+    transport: &dyn GitTransport,
+    url: &str,
+    options: &CloneOptions,
+) -> Result<SemverTags> {
+    Ok(list_remote_refs_with(transport, url, options)?.semver_tags())
+}
+
+/// List every ref a remote advertises with one `git ls-remote`, using the
+/// transport settings in `options`
+pub fn list_remote_refs(url: &str, options: &CloneOptions) -> Result<RemoteRefs> {
+    list_remote_refs_with(&GitCli, url, options)
+}
+
+/// List every ref a remote advertises through the given transport
+pub fn list_remote_refs_with(
+    transport: &dyn GitTransport,
+    url: &str,
+    options: &CloneOptions,
+) -> Result<RemoteRefs> {
+    let rewritten = rewrite_url(url);
+    let url = rewritten.as_deref().unwrap_or(url);
+    HostPolicy::from_env().check(url)?;
+    options.check_git_config()?;
+    debug!("Listing remote refs for {}", redact_url(url));
+
+    let mut args = options.config_args();
+    args.extend(git_args(["ls-remote", url]));
+    let output = transport.run_env(&args, &options.config_env())?;
+    if !output.success {
+        return Err(git_failure(format!(
+            "Failed to list refs of {}: {}",
+            redact_url(url),
+            redact_secrets(output.stderr.trim())
+        )));
+    }
+    Ok(RemoteRefs::parse(&output.stdout))
+}
+
+/// Refs that changed while updating a mirror
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MirrorReport {
+    /// Refs that did not exist in the mirror before
+    pub added: Vec<String>,
+    /// Refs that now point at a different object
+    pub updated: Vec<String>,
+    /// Refs pruned because the remote deleted them
+    pub removed: Vec<String>,
+}
+
+impl MirrorReport {
+    /// Whether the mirror was already up to date
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+
+    fn between(before: &RemoteRefs, after: &RemoteRefs) -> Self {
+        let mut report = Self::default();
+        for (name, sha) in &after.refs {
+            match before.refs.get(name) {
+                None => report.added.push(name.clone()),
+                Some(old) if old != sha => report.updated.push(name.clone()),
+                Some(_) => {}
+            }
+        }
+        report.removed = before
+            .refs
+            .keys()
+            .filter(|name| !after.refs.contains_key(*name))
+            .cloned()
+            .collect();
+        report
+    }
+}
+
+/// Create or update a bare mirror of `url` at `mirror_path`.
+///
+/// The first run clones with `--mirror`; later runs fetch every ref with the
+/// mirror refspec and prune refs the remote deleted.
+pub fn mirror_sync(url: &str, mirror_path: &Path) -> Result<MirrorReport> {
+    mirror_sync_with(&GitCli, url, mirror_path)
+}
+
+/// Create or update a bare mirror through the given transport
+pub fn mirror_sync_with(
+    transport: &dyn GitTransport,
+    url: &str,
+    mirror_path: &Path,
+) -> Result<MirrorReport> {
+    let rewritten = rewrite_url(url);
+    let url = rewritten.as_deref().unwrap_or(url);
+    HostPolicy::from_env().check(url)?;
+    let log_url = redact_url(url);
+
+    let exists = mirror_path.exists();
+    let before = if exists {
+        mirror_refs(transport, mirror_path)?
+    } else {
+        RemoteRefs::default()
+    };
+
+    let args = if exists {
+        info!("Updating mirror of {} at {:?}", log_url, mirror_path);
+        let mut args = git_args(["-C".into(), mirror_path.as_os_str().to_owned()]);
+        args.extend(git_args(["fetch", "--prune", url, "+refs/*:refs/*"]));
+        args
+    } else {
+        info!("Creating mirror of {} at {:?}", log_url, mirror_path);
+        let mut args = git_args(["clone", "--mirror", url]);
+        args.push(mirror_path.as_os_str().to_owned());
+        args
+    };
+    let output = transport.run(&args)?;
+    if !output.success {
+        return Err(git_failure(format!(
+            "Failed to mirror {}: {}",
+            log_url,
+            redact_secrets(output.stderr.trim())
+        )));
+    }
+
+    let after = mirror_refs(transport, mirror_path)?;
+    let report = MirrorReport::between(&before, &after);
+    if report.is_empty() {
+        debug!("Mirror of {} is up to date", log_url);
+    } else {
+        debug!(
+            "Mirror of {}: {} added, {} updated, {} removed",
+            log_url,
+            report.added.len(),
+            report.updated.len(),
+            report.removed.len()
+        );
+    }
+    Ok(report)
+}
+
+/// Every ref stored in a (bare) repository
+fn mirror_refs(transport: &dyn GitTransport, repo_path: &Path) -> Result<RemoteRefs> {
+    let mut args = git_args(["-C".into(), repo_path.as_os_str().to_owned()]);
+    args.extend(git_args([
+        "for-each-ref",
+        "--format=%(objectname)%09%(refname)",
+    ]));
+    let output = transport.run(&args)?;
+    if !output.success {
+        return Err(git_failure(format!(
+            "Failed to list refs in {:?}: {}",
+            repo_path,
+            output.stderr.trim()
+        )));
+    }
+    Ok(RemoteRefs::parse(&output.stdout))
+}
+
+/// Most commits searched for the last change to each file
+pub const LAST_CHANGED_MAX_COMMITS: u32 = 1000;
+
+/// Find the most recent commit that modified each of `files` (paths relative
+/// to `repo_root`), walking back at most [`LAST_CHANGED_MAX_COMMITS`] commits
+/// from `HEAD` that touch `pathspec`.
+///
+/// Files unchanged within that window are left out. A shallow clone is first
+/// deepened to the window; files that only appear in a shallow boundary
+/// commit are left out as well, since the boundary commit records every file
+/// as added.
+pub fn last_changed_commits(
+    repo_root: &Path,
+    pathspec: &str,
+    files: &[String],
+) -> Result<BTreeMap<String, LastChange>> {
+    last_changed_commits_with(&GitCli, repo_root, pathspec, files)
+}
+
+/// [`last_changed_commits`] through the given transport
+pub fn last_changed_commits_with(
+    transport: &dyn GitTransport,
+    repo_root: &Path,
+    pathspec: &str,
+    files: &[String],
+) -> Result<BTreeMap<String, LastChange>> {
+    let run = |rest: Vec<OsString>| {
+        let mut args = git_args(["-C"]);
+        args.push(repo_root.as_os_str().to_owned());
+        args.extend(rest);
+        transport.run(&args)
+    };
+    let max_commits = LAST_CHANGED_MAX_COMMITS.to_string();
+
+    let shallow_file = repo_root.join(".git").join("shallow");
+    if shallow_file.exists() {
+        let deepen = run(git_args([
+            "fetch".to_string(),
+            "--quiet".to_string(),
+            format!("--deepen={}", LAST_CHANGED_MAX_COMMITS),
+            "origin".to_string(),
+        ]))?;
+        if !deepen.success {
+            warn!(
+                "Could not deepen shallow clone for last-changed commits: {}",
+                redact_secrets(deepen.stderr.trim())
+            );
+        }
+    }
+    let boundary: Vec<String> = std::fs::read_to_string(&shallow_file)
+        .map(|content| content.lines().map(str::to_string).collect())
+        .unwrap_or_default();
+
+    let log = run(git_args([
+        "-c",
+        "core.quotePath=false",
+        "log",
+        "-n",
+        &max_commits,
+        "--no-renames",
+        "--format=%x00%H %cI",
+        "--name-only",
+        "HEAD",
+        "--",
+        pathspec,
+    ]))?;
+    if !log.success {
+        return Err(git_failure(format!(
+            "Failed to read history of {:?}: {}",
+            pathspec,
+            log.stderr.trim()
+        )));
+    }
+
+    let wanted: std::collections::HashSet<&str> = files.iter().map(String::as_str).collect();
+    let mut seen = std::collections::HashSet::new();
+    let mut changes = BTreeMap::new();
+    let mut current: Option<(&str, &str)> = None;
+    for line in log.stdout.lines() {
+        if let Some(header) = line.strip_prefix('\0') {
+            current = header.split_once(' ');
+            continue;
+        }
+        let Some((commit, date)) = current else {
+            continue;
+        };
+        if line.is_empty() || !wanted.contains(line) || !seen.insert(line) {
+            continue;
+        }
+        if boundary.iter().any(|b| b == commit) {
+            continue;
+        }
+        changes.insert(
+            line.to_string(),
+            LastChange {
+                commit: commit.to_string(),
+                date: date.to_string(),
+            },
+        );
+    }
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::transport::GitOutput;
+    use std::sync::Mutex;
+
+    /// Scripted transport: answers each call with the next queued output and
+    /// records the arguments (and extra environment) it was called with
+    struct MockTransport {
+        responses: Mutex<Vec<GitOutput>>,
+        calls: Mutex<Vec<Vec<String>>>,
+        envs: Mutex<Vec<Vec<(String, String)>>>,
+    }
+
+    impl MockTransport {
+        fn new(responses: Vec<GitOutput>) -> Self {
+            Self {
+                responses: Mutex::new(responses.into_iter().rev().collect()),
+                calls: Mutex::new(Vec::new()),
+                envs: Mutex::new(Vec::new()),
+            }
+        }
+
+        fn calls(&self) -> Vec<Vec<String>> {
+            self.calls.lock().unwrap().clone()
+        }
+
+        fn envs(&self) -> Vec<Vec<(String, String)>> {
+            self.envs.lock().unwrap().clone()
+        }
+    }
+
+    impl GitTransport for MockTransport {
+        fn run(&self, args: &[OsString]) -> Result<GitOutput> {
+            self.run_env(args, &[])
+        }
+
+        fn run_env(&self, args: &[OsString], env: &[(String, String)]) -> Result<GitOutput> {
+            self.envs.lock().unwrap().push(env.to_vec());
+            self.calls.lock().unwrap().push(
+                args.iter()
+                    .map(|a| a.to_string_lossy().to_string())
+                    .collect(),
+            );
+            Ok(self
+                .responses
+                .lock()
+                .unwrap()
+                .pop()
+                .expect("unexpected git call"))
+        }
+    }
+
+    #[test]
+    fn test_parse_as_of_accepts_dates_and_timestamps() {
+        assert_eq!(
+            parse_as_of("2024-06-01").unwrap(),
+            "2024-06-01T23:59:59+00:00"
+        );
+        assert_eq!(
+            parse_as_of("2024-06-01T08:30:00+02:00").unwrap(),
+            "2024-06-01T08:30:00+02:00"
+        );
+        assert!(matches!(
+            parse_as_of("last tuesday"),
+            Err(ApsError::InvalidAsOf { .. })
+        ));
+    }
+
+    #[test]
+    fn test_checkout_as_of_without_matching_commit() {
+        let transport = MockTransport::new(vec![GitOutput::ok("")]);
+
+        let err = checkout_as_of_with(&transport, Path::new("/repo"), "2001-01-01T00:00:00+00:00")
+            .err()
+            .unwrap();
+
+        assert!(err
+            .to_string()
+            .contains("No commit found at or before 2001-01-01"));
+        assert_eq!(transport.calls().len(), 1);
+    }
+
+    #[test]
+    fn test_message_search_deepens_shallow_clone_until_match() {
+        let transport = MockTransport::new(vec![
+            GitOutput::ok(""),
+            GitOutput::ok("true\n"),
+            GitOutput::ok(""),
+            GitOutput::ok("abc123def456\n"),
+            GitOutput::ok(""),
+        ]);
+
+        let sha = checkout_by_message_with(
+            &transport,
+            Path::new("/repo"),
+            "^release:",
+            "main",
+            &CloneOptions::new(),
+        )
+        .unwrap();
+
+        assert_eq!(sha, "abc123def456");
+        let calls = transport.calls();
+        assert_eq!(
+            calls[0][2..],
+            ["rev-list", "-1", "-E", "--grep=^release:", "HEAD"]
+        );
+        assert_eq!(calls[2][2..], ["fetch", "--quiet", "--depth=50", "origin"]);
+        assert_eq!(
+            calls[4][2..],
+            ["checkout", "--quiet", "--detach", "abc123def456"]
+        );
+    }
+
+    #[test]
+    fn test_message_search_reports_pattern_when_history_is_exhausted() {
+        let transport = MockTransport::new(vec![GitOutput::ok(""), GitOutput::ok("false\n")]);
+
+        let err = checkout_by_message_with(
+            &transport,
+            Path::new("/repo"),
+            "^release:",
+            "main",
+            &CloneOptions::new(),
+        )
+        .err()
+        .unwrap();
+
+        assert!(matches!(err, ApsError::NoCommitMatchesMessage { .. }));
+        assert_eq!(
+            err.to_string(),
+            "No commit on 'main' has a message matching '^release:' (searched the full history)"
+        );
+    }
+
+    #[test]
+    fn test_auto_ref_falls_back_to_master() {
+        let transport = MockTransport::new(vec![
+            GitOutput::failed("fatal: Remote branch main not found"),
+            GitOutput::ok(""),
+            GitOutput::ok("abc123def456\n"),
+            GitOutput::ok("v1.2.0-3-gabc123d\n"),
+        ]);
+
+        let resolved = clone_and_resolve_with(
+            &transport,
+            "https://example.com/r.git",
+            "auto",
+            &CloneOptions::new().shallow(true).describe(true),
+        )
+        .unwrap();
+
+        assert_eq!(resolved.resolved_ref, "master");
+        assert_eq!(resolved.commit_sha, "abc123def456");
+        assert_eq!(resolved.describe.as_deref(), Some("v1.2.0-3-gabc123d"));
+        let calls = transport.calls();
+        assert_eq!(calls.len(), 4);
+        assert!(calls[0].contains(&"main".to_string()));
+        assert!(calls[1].contains(&"master".to_string()));
+        assert!(calls[1].contains(&"--depth".to_string()));
+        assert_eq!(calls[2][2..], ["rev-parse", "HEAD"]);
+    }
+
+    #[test]
+    fn test_empty_repository_is_reported() {
+        let transport = MockTransport::new(vec![
+            GitOutput::failed("fatal: Remote branch main not found in upstream origin"),
+            GitOutput::failed("fatal: Remote branch master not found in upstream origin"),
+            GitOutput::ok(""),
+        ]);
+
+        let err = clone_and_resolve_with(
+            &transport,
+            "https://example.com/empty.git",
+            "auto",
+            &CloneOptions::new(),
+        )
+        .err()
+        .unwrap();
+
+        assert!(matches!(err, ApsError::EmptyRepository { .. }));
+        assert_eq!(
+            transport.calls()[2],
+            ["ls-remote", "https://example.com/empty.git"]
+        );
+    }
+
+    #[test]
+    fn test_detached_head_without_branches() {
+        let head = GitOutput::ok("abc123\tHEAD\n");
+        let transport = MockTransport::new(vec![
+            GitOutput::failed("fatal: Remote branch main not found in upstream origin"),
+            GitOutput::failed("fatal: Remote branch master not found in upstream origin"),
+            head.clone(),
+            GitOutput::ok(""),
+            GitOutput::ok("abc123\n"),
+            GitOutput::ok("abc123\n"),
+        ]);
+
+        let resolved = clone_and_resolve_with(
+            &transport,
+            "https://example.com/mirror.git",
+            "auto",
+            &CloneOptions::new(),
+        )
+        .unwrap();
+
+        assert_eq!(resolved.resolved_ref, "abc123");
+        assert_eq!(resolved.commit_sha, "abc123");
+        let clone = &transport.calls()[3];
+        assert_eq!(clone[0], "clone");
+        assert!(!clone.contains(&"--branch".to_string()));
+
+        // A named branch cannot be satisfied, and says why
+        let transport = MockTransport::new(vec![
+            GitOutput::failed("fatal: Remote branch main not found in upstream origin"),
+            head,
+        ]);
+        let err = clone_and_resolve_with(
+            &transport,
+            "https://example.com/mirror.git",
+            "main",
+            &CloneOptions::new(),
+        )
+        .err()
+        .unwrap();
+        assert!(matches!(err, ApsError::NoBranches { .. }));
+    }
+
+    #[test]
+    fn test_missing_branch_falls_back_to_default_branch_when_enabled() {
+        let missing = "fatal: Remote branch main not found in upstream origin";
+        let transport = MockTransport::new(vec![
+            GitOutput::failed(missing),
+            GitOutput::ok("abc123\trefs/heads/develop\n"),
+            GitOutput::ok("ref: refs/heads/develop\tHEAD\nabc123\tHEAD\n"),
+            GitOutput::ok(""),
+            GitOutput::ok("abc123\n"),
+        ]);
+        let options = CloneOptions::new().default_branch_fallback(true);
+
+        let resolved =
+            clone_and_resolve_with(&transport, "https://example.com/r.git", "main", &options)
+                .unwrap();
+
+        assert_eq!(resolved.resolved_ref, "develop");
+        let calls = transport.calls();
+        assert_eq!(
+            calls[2],
+            ["ls-remote", "--symref", "https://example.com/r.git", "HEAD"]
+        );
+        assert!(calls[3].contains(&"develop".to_string()));
+
+        // Without the opt-in the missing branch is an error
+        let transport = MockTransport::new(vec![
+            GitOutput::failed(missing),
+            GitOutput::ok("abc123\trefs/heads/develop\n"),
+        ]);
+        let err = clone_and_resolve_with(
+            &transport,
+            "https://example.com/r.git",
+            "main",
+            &CloneOptions::new(),
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("not found in upstream"));
+    }
+
+    #[test]
+    fn test_all_refs_failing_reports_last_error() {
+        let transport = MockTransport::new(vec![
+            GitOutput::failed("fatal: not found main"),
+            GitOutput::failed("fatal: not found master"),
+        ]);
+
+        let err = clone_and_resolve_with(
+            &transport,
+            "https://example.com/r.git",
+            "auto",
+            &CloneOptions::new(),
+        )
+        .err()
+        .unwrap();
+
+        match err {
+            ApsError::GitError { message } => {
+                assert!(message.contains("[\"main\", \"master\"]"));
+                assert!(message.contains("not found master"));
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_list_tree_reads_sizes_and_modes_from_ls_tree() {
+        let transport = MockTransport::new(vec![GitOutput::ok(
+            "100644 blob aaaa      12\tprompts/a.md\0\
+             100755 blob bbbb     340\tbin/run.sh\0\
+             120000 blob cccc       4\tlatest.md\0\
+             160000 commit dddd       -\tvendor/shared\0",
+        )]);
+
+        let entries = list_tree_with(&transport, Path::new("/repo/does-not-exist")).unwrap();
+
+        assert_eq!(
+            transport.calls()[0],
+            [
+                "-C",
+                "/repo/does-not-exist",
+                "ls-tree",
+                "-r",
+                "-l",
+                "-z",
+                "HEAD"
+            ]
+        );
+        let kinds: Vec<_> = entries
+            .iter()
+            .map(|e| (e.path.as_str(), e.size, e.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("prompts/a.md", Some(12), TreeEntryKind::File),
+                ("bin/run.sh", Some(340), TreeEntryKind::Executable),
+                ("latest.md", Some(4), TreeEntryKind::Symlink),
+                ("vendor/shared", None, TreeEntryKind::Submodule),
+            ]
+        );
+        assert_eq!(entries[2].mode, 0o120000);
+    }
+
+    #[test]
+    fn test_last_changed_commits_takes_newest_change() {
+        let temp = TempDir::new().unwrap();
+        let transport = MockTransport::new(vec![GitOutput::ok(
+            "\0ccc 2024-03-03T10:00:00+00:00\n\nprompts/a.md\n\
+             \0bbb 2024-02-02T10:00:00+00:00\n\nprompts/a.md\nprompts/b.md\nprompts/other.md\n",
+        )]);
+
+        let changes = last_changed_commits_with(
+            &transport,
+            temp.path(),
+            "prompts",
+            &[
+                "prompts/a.md".to_string(),
+                "prompts/b.md".to_string(),
+                "prompts/c.md".to_string(),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes["prompts/a.md"].commit, "ccc");
+        assert_eq!(changes["prompts/b.md"].commit, "bbb");
+        assert_eq!(changes["prompts/b.md"].date, "2024-02-02T10:00:00+00:00");
+        assert_eq!(transport.calls()[0][4..7], ["log", "-n", "1000"]);
+    }
+
+    #[test]
+    fn test_mirror_sync_reports_ref_changes() {
+        let temp = TempDir::new().unwrap();
+        let mirror = temp.path().join("mirror.git");
+
+        // First run clones with --mirror; every ref is new
+        let transport = MockTransport::new(vec![
+            GitOutput::ok(""),
+            GitOutput::ok("aaaa\trefs/heads/main\nbbbb\trefs/tags/v1\n"),
+        ]);
+        let report = mirror_sync_with(&transport, "https://example.com/r.git", &mirror).unwrap();
+        assert_eq!(report.added, ["refs/heads/main", "refs/tags/v1"]);
+        assert_eq!(
+            transport.calls()[0],
+            [
+                "clone",
+                "--mirror",
+                "https://example.com/r.git",
+                &mirror.to_string_lossy()
+            ]
+        );
+
+        // Later runs fetch with pruning and diff the refs
+        std::fs::create_dir_all(&mirror).unwrap();
+        let transport = MockTransport::new(vec![
+            GitOutput::ok("aaaa\trefs/heads/main\nbbbb\trefs/tags/v1\n"),
+            GitOutput::ok(""),
+            GitOutput::ok("cccc\trefs/heads/main\ndddd\trefs/heads/next\n"),
+        ]);
+        let report = mirror_sync_with(&transport, "https://example.com/r.git", &mirror).unwrap();
+        assert_eq!(
+            report,
+            MirrorReport {
+                added: vec!["refs/heads/next".to_string()],
+                updated: vec!["refs/heads/main".to_string()],
+                removed: vec!["refs/tags/v1".to_string()],
+            }
+        );
+        assert_eq!(
+            transport.calls()[1][2..],
+            [
+                "fetch",
+                "--prune",
+                "https://example.com/r.git",
+                "+refs/*:refs/*"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_remote_refs_resolve_branches_tags_and_full_refs() {
+        let transport = MockTransport::new(vec![GitOutput::ok(
+            "1111\tHEAD\n\
+             1111\trefs/heads/main\n\
+             2222\trefs/heads/release\n\
+             3333\trefs/tags/v1.0\n\
+             4444\trefs/tags/v1.0^{}\n\
+             5555\trefs/pull/7/head\n",
+        )]);
+
+        let refs = list_remote_refs_with(
+            &transport,
+            "https://example.com/r.git",
+            &CloneOptions::new(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            transport.calls()[0],
+            ["ls-remote", "https://example.com/r.git"]
+        );
+        let resolve = |r: &str| refs.resolve(r, &[]).map(|(_, sha)| sha);
+        assert_eq!(
+            refs.resolve("auto", &[]),
+            Some(("main".into(), "1111".into()))
+        );
+        let org_branches = ["trunk".to_string(), "release".to_string()];
+        assert_eq!(
+            refs.resolve("auto", &org_branches),
+            Some(("release".into(), "2222".into()))
+        );
+        assert_eq!(resolve("release").as_deref(), Some("2222"));
+        assert_eq!(resolve("v1.0").as_deref(), Some("4444"));
+        assert_eq!(resolve("refs/pull/7/head").as_deref(), Some("5555"));
+        assert_eq!(resolve("missing"), None);
+    }
+
+    #[test]
+    fn test_semver_tags_sorted_with_prereleases_flagged() {
+        let transport = MockTransport::new(vec![GitOutput::ok(
+            "1111\trefs/heads/main\n\
+             2222\trefs/tags/v1.10.0\n\
+             3333\trefs/tags/v1.2.0\n\
+             4444\trefs/tags/v2.0.0-rc.1\n\
+             5555\trefs/tags/v2.0.0-rc.1^{}\n\
+             6666\trefs/tags/latest\n\
+             7777\trefs/tags/1.9.0-beta\n",
+        )]);
+
+        let options = CloneOptions::new().client_cert("/certs/client.pem");
+        let semver =
+            list_semver_tags_with(&transport, "https://example.com/r.git", &options).unwrap();
+
+        assert_eq!(
+            transport.calls()[0][..3],
+            ["-c", "http.sslCert=/certs/client.pem", "ls-remote"]
+        );
+
+        let names: Vec<&str> = semver.tags.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["v1.2.0", "1.9.0-beta", "v1.10.0", "v2.0.0-rc.1"]);
+        assert_eq!(semver.skipped, ["latest"]);
+        assert_eq!(semver.tags[3].sha, "5555");
+        assert!(semver.tags[1].is_prerelease);
+        let latest = |req: &str| {
+            let req = VersionReq::parse(req).unwrap();
+            semver.latest_matching(&req).map(|t| t.name.clone())
+        };
+        assert_eq!(latest("^1.2").as_deref(), Some("v1.10.0"));
+        assert_eq!(latest("~1.2").as_deref(), Some("v1.2.0"));
+        assert_eq!(latest("^2"), None);
+    }
+
+    #[test]
+    fn test_remote_commit_for_version_requirement_uses_tags() {
+        let transport = MockTransport::new(vec![GitOutput::ok(
+            "1111\trefs/heads/main\n\
+             2222\trefs/tags/v1.4.0\n\
+             3333\trefs/tags/v1.5.0\n\
+             4444\trefs/tags/v1.5.0^{}\n\
+             5555\trefs/tags/v2.0.0\n",
+        )]);
+
+        let sha = get_remote_commit_sha_with(
+            &transport,
+            "https://example.com/r.git",
+            "^1.4",
+            &CloneOptions::new(),
+        )
+        .unwrap();
+
+        assert_eq!(sha.as_deref(), Some("4444"));
+        assert_eq!(
+            transport.calls()[0][..2],
+            ["ls-remote", "https://example.com/r.git"]
+        );
+    }
+
+    #[test]
+    fn test_redirect_failures_are_classified() {
+        let transport = MockTransport::new(vec![GitOutput::failed(
+            "fatal: unable to update url base from redirection:\n  asked for: https://old.example.com/r.git/info/refs",
+        )]);
+        let options = CloneOptions::new().follow_redirects(FollowRedirects::Never);
+
+        let err = clone_and_resolve_with(
+            &transport,
+            "https://old.example.com/r.git",
+            "main",
+            &options,
+        )
+        .err()
+        .unwrap();
+
+        assert!(matches!(err, ApsError::GitRedirect { .. }));
+        assert_eq!(
+            transport.calls()[0][..2],
+            ["-c", "http.followRedirects=false"]
+        );
+        assert!(is_redirect_error(
+            "fatal: unable to access 'https://h/r.git/': Maximum (20) redirects followed"
+        ));
+        assert!(!is_redirect_error("fatal: repository not found"));
+    }
+
+    #[test]
+    fn test_moved_repository_requires_opt_in() {
+        let redirected = || GitOutput {
+            success: true,
+            stdout: String::new(),
+            stderr: "warning: redirecting to https://github.com/new-org/prompts.git/\n".to_string(),
+        };
+        let transport = MockTransport::new(vec![redirected(), GitOutput::ok("abc123\n")]);
+        let err = clone_and_resolve_with(
+            &transport,
+            "https://github.com/old-org/prompts.git",
+            "main",
+            &CloneOptions::new(),
+        )
+        .err()
+        .unwrap();
+        assert!(
+            matches!(err, ApsError::RepositoryMoved { ref new_url, .. } if new_url == "https://github.com/new-org/prompts.git")
+        );
+
+        let transport = MockTransport::new(vec![redirected(), GitOutput::ok("abc123\n")]);
+        let resolved = clone_and_resolve_with(
+            &transport,
+            "https://github.com/old-org/prompts.git",
+            "main",
+            &CloneOptions::new().follow_moved(true),
+        )
+        .unwrap();
+        assert_eq!(
+            resolved.final_url.as_deref(),
+            Some("https://github.com/new-org/prompts.git")
+        );
+
+        // Redirects within the same repository are not moves
+        assert_eq!(
+            moved_location(
+                "https://github.com/org/prompts",
+                "warning: redirecting to https://github.com/org/prompts.git/"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_refused_move_is_retried_at_new_url() {
+        let transport = MockTransport::new(vec![
+            GitOutput::failed(
+                "fatal: unable to update url base from redirection:\n  asked for: https://github.com/old/prompts.git/info/refs?service=git-upload-pack\n   redirect: https://github.com/new/prompts.git/info/refs?service=git-upload-pack",
+            ),
+            GitOutput::ok(""),
+            GitOutput::ok("abc123\n"),
+        ]);
+        let options = CloneOptions::new()
+            .follow_redirects(FollowRedirects::Never)
+            .follow_moved(true);
+
+        let resolved = clone_and_resolve_with(
+            &transport,
+            "https://github.com/old/prompts.git",
+            "main",
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(
+            resolved.final_url.as_deref(),
+            Some("https://github.com/new/prompts.git")
+        );
+        assert!(transport.calls()[1].contains(&"https://github.com/new/prompts.git".to_string()));
+    }
+
+    #[test]
+    fn test_list_submodule_pins_reads_gitlinks_and_gitmodules() {
+        let transport = MockTransport::new(vec![
+            GitOutput::ok(
+                "100644 blob 1111111111111111111111111111111111111111\t.gitmodules\x00160000 commit 2222222222222222222222222222222222222222\tvendor/prompts\x00160000 commit 3333333333333333333333333333333333333333\tundeclared\0",
+            ),
+            GitOutput::ok(
+                "[submodule \"prompts\"]\n\tpath = vendor/prompts\n\turl = https://github.com/org/prompts.git\n",
+            ),
+        ]);
+        let pins = list_submodule_pins_with(&transport, Path::new("/repo"), "abc123").unwrap();
+
+        assert_eq!(pins.len(), 2);
+        assert_eq!(
+            pins["vendor/prompts"],
+            SubmodulePin {
+                url: Some("https://github.com/org/prompts.git".to_string()),
+                commit: "2222222222222222222222222222222222222222".to_string(),
+            }
+        );
+        assert_eq!(pins["undeclared"].url, None);
+        assert_eq!(transport.calls()[1][2..], ["show", "abc123:.gitmodules"]);
+    }
+
+    #[test]
+    fn test_parse_channel_index() {
+        let channels =
+            parse_channel_index("channels.json", r#"{"stable": "v1.2.0", "beta": "main"}"#)
+                .unwrap();
+        assert_eq!(channels["stable"], "v1.2.0");
+        assert_eq!(channels["beta"], "main");
+
+        assert!(matches!(
+            parse_channel_index("channels.json", r#"["v1.2.0"]"#),
+            Err(ApsError::InvalidChannelIndex { .. })
+        ));
+        assert!(matches!(
+            parse_channel_index("channels.json", r#"{"stable": ""}"#),
+            Err(ApsError::InvalidChannelIndex { ref reason, .. }) if reason.contains("stable")
+        ));
+    }
+
+    #[test]
+    fn test_unknown_submodule_paths() {
+        let gitmodules = "\
+[submodule \"prompts\"]
+\tpath = vendor/prompts
+\turl = https://github.com/org/prompts.git
+[submodule \"big\"]
+\tpath = assets/big
+\turl = https://github.com/org/big.git
+";
+        let allowlist = ["vendor/prompts/".to_string(), "missing".to_string()];
+        assert_eq!(unknown_submodule_paths(gitmodules, &allowlist), ["missing"]);
+        assert_eq!(
+            unknown_submodule_paths("", &allowlist[..1]),
+            ["vendor/prompts/"]
+        );
+    }
+
+    #[test]
+    fn test_signed_tag_policy() {
+        let path = Path::new("/tmp/repo");
+        let validsig = "[GNUPG:] NEWSIG\n[GNUPG:] VALIDSIG 1111222233334444555566667777888899990000 2024-01-01 1704067200 0 4 0 1 10 00 AAAABBBBCCCCDDDDEEEEFFFF0000111122223333\n[GNUPG:] GOODSIG\n";
+        let signed = || GitOutput {
+            success: true,
+            stdout: String::new(),
+            stderr: validsig.to_string(),
+        };
+        let script = |verify: GitOutput| {
+            MockTransport::new(vec![
+                GitOutput::ok("tag\n"),
+                GitOutput::ok("abc123\n"),
+                verify,
+            ])
+        };
+
+        let transport = script(signed());
+        verify_signed_tag_with(&transport, path, "v1.0", "abc123", &[]).unwrap();
+        assert!(transport.calls()[0].contains(&"refs/tags/v1.0".to_string()));
+
+        // The allowlist may name the primary key by its long key ID
+        let keys = ["0x0000 1111 2222 3333".to_string()];
+        verify_signed_tag_with(&script(signed()), path, "v1.0", "abc123", &keys).unwrap();
+
+        let reason = |transport: MockTransport, keys: &[String]| match verify_signed_tag_with(
+            &transport, path, "v1.0", "abc123", keys,
+        ) {
+            Err(ApsError::UnsignedReleaseTag { reason, .. }) => reason,
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        };
+        assert_eq!(
+            reason(script(GitOutput::failed("error: no signature found")), &[]),
+            "the tag is not signed"
+        );
+        assert!(reason(script(signed()), &["DEADBEEFDEADBEEF".to_string()])
+            .starts_with("it is signed by 1111222233334444555566667777888899990000"));
+        assert_eq!(
+            reason(MockTransport::new(vec![GitOutput::ok("commit\n")]), &[]),
+            "it is a lightweight tag, not an annotated one"
+        );
+        assert_eq!(
+            reason(
+                MockTransport::new(vec![GitOutput::failed("fatal: Not a valid object name")]),
+                &[]
+            ),
+            "it is not a tag"
+        );
+    }
+
+    #[test]
+    fn test_ensure_commit_present_deepens_until_found() {
+        let repo = Path::new("/tmp/clone");
+        let sha = "abc12345def67890";
+        let transport = MockTransport::new(vec![
+            GitOutput::failed(""),
+            GitOutput::ok("true\n"),
+            GitOutput::ok(""),
+            GitOutput::failed(""),
+            GitOutput::ok("true\n"),
+            GitOutput::ok(""),
+            GitOutput::ok(""),
+        ]);
+        ensure_commit_present_with(&transport, repo, sha, &CloneOptions::new()).unwrap();
+
+        let calls = transport.calls();
+        assert_eq!(
+            calls[0][2..],
+            ["cat-file", "-e", "abc12345def67890^{commit}"]
+        );
+        assert_eq!(calls[2][2..], ["fetch", "--quiet", "--depth=50", "origin"]);
+        assert_eq!(calls[5][4], "--depth=100");
+        assert_eq!(calls.len(), 7);
+
+        // A complete clone without the commit is not deepened
+        let transport = MockTransport::new(vec![GitOutput::failed(""), GitOutput::ok("false\n")]);
+        let err = ensure_commit_present_with(&transport, repo, sha, &CloneOptions::new());
+        assert!(matches!(err, Err(ApsError::GitError { message }) if message.contains("abc12345")));
+    }
+
+    #[test]
+    fn test_clone_intent_picks_depth_and_deepens_to_locked_commit() {
+        let full = CloneOptions::new()
+            .depth(5)
+            .intent(CloneIntent::NeedFullHistory);
+        assert!(!full.is_shallow());
+        let checkout = CloneOptions::new().intent(CloneIntent::CheckoutOnly);
+        assert_eq!(checkout.get_depth(), Some(1));
+
+        let transport = MockTransport::new(vec![
+            GitOutput::ok(""),
+            GitOutput::ok("abc123\n"),
+            GitOutput::failed(""),
+            GitOutput::ok("true\n"),
+            GitOutput::ok(""),
+            GitOutput::ok(""),
+        ]);
+        let options = CloneOptions::new().intent(CloneIntent::NeedDiffAgainstLock {
+            locked: "def456".to_string(),
+        });
+        clone_and_resolve_with(&transport, "https://example.com/r.git", "main", &options).unwrap();
+
+        let calls = transport.calls();
+        assert!(calls[0].contains(&"--depth".to_string()));
+        assert_eq!(calls[2][calls[2].len() - 1], "def456^{commit}");
+        assert_eq!(calls[4][calls[4].len() - 2], "--depth=50");
+        assert_eq!(calls.len(), 6);
+    }
+
+    #[test]
+    fn test_parse_shallow_since() {
+        assert_eq!(
+            parse_shallow_since("2024-03-01").unwrap(),
+            "2024-03-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            parse_shallow_since("2024-03-01T12:30:00+02:00").unwrap(),
+            "2024-03-01T12:30:00+02:00"
+        );
+        assert!(matches!(
+            parse_shallow_since("last week"),
+            Err(ApsError::InvalidShallowSince { .. })
+        ));
+    }
+
+    #[test]
+    fn test_timeouts_name_the_phase() {
+        let url = "https://git.example.com/r.git";
+        let options = CloneOptions::new()
+            .connect_timeout(Duration::from_secs(5))
+            .transfer_timeout(Duration::from_secs(30));
+        let transport = MockTransport::new(vec![GitOutput::failed(
+            "fatal: unable to access 'https://git.example.com/r.git/': Failed to connect to git.example.com port 443 after 5001 ms: Connection timed out",
+        )]);
+        let err = clone_and_resolve_with(&transport, url, "main", &options)
+            .err()
+            .unwrap();
+        assert!(matches!(err, ApsError::GitTimeout { ref phase, .. } if phase == "connect"));
+        // The probe runs before any clone, with the transfer limits applied
+        let calls = transport.calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0][4..], ["ls-remote", url, "HEAD"]);
+        assert_eq!(calls[0][3], "http.lowSpeedTime=30");
+
+        let transport = MockTransport::new(vec![
+            GitOutput::ok("1111\tHEAD\n"),
+            GitOutput::failed(
+                "error: RPC failed; curl 28 Operation too slow. Less than 1 bytes/sec transferred the last 30 seconds\nfatal: expected flush after ref listing",
+            ),
+        ]);
+        let err = clone_and_resolve_with(&transport, url, "main", &options)
+            .err()
+            .unwrap();
+        assert!(matches!(err, ApsError::GitTimeout { ref phase, .. } if phase == "transfer"));
+        assert!(err.to_string().starts_with("Git transfer timed out: "));
+    }
+
+    #[test]
+    fn test_fetch_notes_reads_note_on_resolved_commit() {
+        let clone = || vec![GitOutput::ok(""), GitOutput::ok("abc123\n")];
+        let options = CloneOptions::new().fetch_notes(true);
+
+        let mut script = clone();
+        script.extend([GitOutput::ok(""), GitOutput::ok("Reviewed-by: Jane\n")]);
+        let transport = MockTransport::new(script);
+        let resolved =
+            clone_and_resolve_with(&transport, "https://example.com/r.git", "main", &options)
+                .unwrap();
+        assert_eq!(resolved.notes.as_deref(), Some("Reviewed-by: Jane"));
+        let calls = transport.calls();
+        assert!(calls[2].contains(&"+refs/notes/*:refs/notes/*".to_string()));
+        assert_eq!(calls[3][calls[3].len() - 3..], ["notes", "show", "abc123"]);
+
+        // A remote without notes is not an error
+        let mut script = clone();
+        script.push(GitOutput::failed(
+            "fatal: couldn't find remote ref refs/notes/*",
+        ));
+        let transport = MockTransport::new(script);
+        let resolved =
+            clone_and_resolve_with(&transport, "https://example.com/r.git", "main", &options)
+                .unwrap();
+        assert_eq!(resolved.notes, None);
+    }
+
+    #[test]
+    fn test_shallow_refusal_falls_back_to_full_clone() {
+        let transport = MockTransport::new(vec![
+            GitOutput::failed("fatal: Server does not support shallow clients"),
+            GitOutput::ok(""),
+            GitOutput::ok("abc123\n"),
+        ]);
+        let options = CloneOptions::new().shallow(true);
+
+        let resolved =
+            clone_and_resolve_with(&transport, "https://example.com/r.git", "main", &options)
+                .unwrap();
+
+        assert!(resolved.shallow_refused);
+        let calls = transport.calls();
+        assert!(calls[0].contains(&"--depth".to_string()));
+        assert!(!calls[1].contains(&"--depth".to_string()));
+
+        // Other failures are not retried
+        let transport = MockTransport::new(vec![GitOutput::failed("fatal: unreachable")]);
+        assert!(matches!(
+            clone_and_resolve_with(&transport, "https://example.com/r.git", "main", &options),
+            Err(ApsError::GitError { .. })
+        ));
+        assert_eq!(transport.calls().len(), 1);
+        assert!(matches!(
+            git_failure("fatal: dumb http transport does not support shallow capabilities".into()),
+            ApsError::GitShallowRefused { .. }
+        ));
+    }
+
+    #[test]
+    fn test_clone_strategies_fall_back_in_order() {
+        let transport = MockTransport::new(vec![
+            GitOutput::failed("fatal: dumb http transport does not support shallow capabilities"),
+            GitOutput::ok(""),
+            GitOutput::ok("abc123\n"),
+        ]);
+        let options = CloneOptions::new().strategies([CloneStrategy::Shallow, CloneStrategy::Full]);
+
+        let resolved =
+            clone_and_resolve_with(&transport, "https://example.com/r.git", "main", &options)
+                .unwrap();
+
+        assert_eq!(resolved.strategy, Some(CloneStrategy::Full));
+        let calls = transport.calls();
+        assert!(calls[0].contains(&"--depth".to_string()));
+        assert!(!calls[1].contains(&"--depth".to_string()));
+
+        // Every strategy failing reports the last error
+        let transport = MockTransport::new(vec![GitOutput::failed("fatal: unreachable")]);
+        let options = CloneOptions::new().strategies([CloneStrategy::Full]);
+        assert!(
+            clone_and_resolve_with(&transport, "https://example.com/r.git", "main", &options)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_tls_failures_are_classified() {
+        let transport = MockTransport::new(vec![GitOutput::failed(
+            "fatal: unable to access 'https://git.corp/r.git/': could not load PEM client certificate from /etc/aps/client.pem, OpenSSL error error:80000002:system library::No such file or directory",
+        )]);
+        let options = CloneOptions::new()
+            .client_cert("/etc/aps/client.pem")
+            .client_key("/etc/aps/client.key");
+
+        let err = clone_and_resolve_with(&transport, "https://git.corp/r.git", "main", &options)
+            .err()
+            .unwrap();
+
+        assert!(matches!(err, ApsError::GitTls { .. }));
+        assert_eq!(
+            transport.calls()[0][..4],
+            [
+                "-c",
+                "http.sslCert=/etc/aps/client.pem",
+                "-c",
+                "http.sslKey=/etc/aps/client.key"
+            ]
+        );
+        assert!(is_tls_error(
+            "fatal: unable to access 'https://h/r.git/': OpenSSL/3.0.2: error:0A00045C:SSL routines::tlsv13 alert certificate required"
+        ));
+        assert!(!is_tls_error("fatal: repository not found"));
+    }
+
+    #[test]
+    fn test_export_ignored_paths_reads_check_attr_records() {
+        let transport = MockTransport::new(vec![GitOutput::ok(
+            "docs\0export-ignore\0set\0README.md\0export-ignore\0unspecified\0tests/a.md\0export-ignore\0set\0",
+        )]);
+        let paths = ["docs", "README.md", "tests/a.md"].map(String::from);
+        let ignored = export_ignored_paths_with(&transport, Path::new("/repo"), &paths).unwrap();
+        assert_eq!(
+            ignored.into_iter().collect::<Vec<_>>(),
+            ["docs", "tests/a.md"]
+        );
+        assert_eq!(
+            transport.calls()[0][2..7],
+            ["check-attr", "-z", "export-ignore", "--", "docs"]
+        );
+    }
+
+    #[test]
+    fn test_sso_refusals_carry_the_authorization_url() {
+        let transport = MockTransport::new(vec![GitOutput::failed(
+            "remote: The 'acme' organization has enabled or enforced SAML SSO.\nremote: To access this repository, visit https://github.com/enterprises/acme/sso?authorization_request=AB12 and try your request again.\nfatal: unable to access 'https://github.com/acme/prompts.git/': The requested URL returned error: 403\n",
+        )]);
+        let err = clone_and_resolve_with(
+            &transport,
+            "https://github.com/acme/prompts.git",
+            "main",
+            &CloneOptions::new(),
+        )
+        .err()
+        .unwrap();
+        assert!(matches!(
+            err,
+            ApsError::SsoAuthorizationRequired { ref authorization_url, .. }
+                if authorization_url == "https://github.com/enterprises/acme/sso"
+        ));
+
+        let pat = "remote: Resource protected by organization SAML enforcement. You must grant your Personal Access token access to this organization.";
+        assert!(is_sso_error(pat));
+        assert_eq!(sso_authorization_url(pat), None);
+        assert!(!is_sso_error("remote: Invalid username or password."));
+    }
+
+    #[test]
+    fn test_git_daemon_failures_are_classified() {
+        let transport = MockTransport::new(vec![GitOutput::failed(
+            "fatal: unable to connect to 127.0.0.1:\n127.0.0.1[0: 127.0.0.1]: errno=Connection refused\n",
+        )]);
+        let err = clone_and_resolve_with(
+            &transport,
+            "git://127.0.0.1/prompts.git",
+            "main",
+            &CloneOptions::new(),
+        )
+        .err()
+        .unwrap();
+        assert!(matches!(err, ApsError::GitDaemonUnreachable { .. }));
+
+        let transport = MockTransport::new(vec![GitOutput::failed(
+            "fatal: remote error: access denied or repository not exported: /prompts.git\n",
+        )]);
+        let err = clone_and_resolve_with(
+            &transport,
+            "git://git.lan/prompts.git",
+            "main",
+            &CloneOptions::new(),
+        )
+        .err()
+        .unwrap();
+        assert!(matches!(err, ApsError::GitRepoNotExported { .. }));
+        assert!(!is_daemon_unreachable(
+            "ssh: connect to host git.lan port 22: Connection refused"
+        ));
+    }
+
+    #[test]
+    fn test_describe_match_limits_tags() {
+        let transport = MockTransport::new(vec![
+            GitOutput::ok(""),
+            GitOutput::ok("abc123\n"),
+            GitOutput::failed("fatal: No names found, cannot describe anything."),
+        ]);
+        let options = CloneOptions::new().describe_match("v*");
+
+        let resolved =
+            clone_and_resolve_with(&transport, "https://example.com/r.git", "main", &options)
+                .unwrap();
+
+        assert_eq!(resolved.describe, None);
+        assert_eq!(
+            transport.calls()[2][2..],
+            ["describe", "--tags", "--match", "v*", "HEAD"]
+        );
+    }
+
+    #[test]
+    fn test_short_sha_resolves_to_full_commit() {
+        let full = "abc1234def5678abc1234def5678abc1234def56";
+        let transport = MockTransport::new(vec![
+            GitOutput::ok(""),
+            GitOutput::ok(""),
+            GitOutput::ok(format!("{}\n", full)),
+            GitOutput::ok(""),
+        ]);
+
+        let resolved = clone_and_resolve_with(
+            &transport,
+            "https://example.com/r.git",
+            "ABC1234",
+            &CloneOptions::new().shallow(true),
+        )
+        .unwrap();
+
+        assert_eq!(resolved.commit_sha, full);
+        assert_eq!(resolved.resolved_ref, full);
+        let calls = transport.calls();
+        assert_eq!(calls[0][3..], ["refs/heads/ABC1234", "refs/tags/ABC1234"]);
+        assert_eq!(calls[1][..2], ["clone", "--no-checkout"]);
+        assert_eq!(
+            calls[2][2..],
+            ["rev-parse", "--verify", "--quiet", "abc1234^{commit}"]
+        );
+        assert_eq!(calls[3][2..], ["checkout", "--quiet", "--detach", full]);
+    }
+
+    #[test]
+    fn test_short_sha_ambiguity_and_absence() {
+        for (stderr, ambiguous) in [
+            ("error: short object ID abc1234 is ambiguous", true),
+            ("", false),
+        ] {
+            let transport = MockTransport::new(vec![
+                GitOutput::ok(""),
+                GitOutput::ok(""),
+                GitOutput::failed(stderr),
+            ]);
+            let err = clone_and_resolve_with(
+                &transport,
+                "https://example.com/r.git",
+                "abc1234",
+                &CloneOptions::new(),
+            )
+            .err()
+            .unwrap();
+            if ambiguous {
+                assert!(matches!(err, ApsError::AmbiguousCommit { .. }));
+            } else {
+                assert!(matches!(err, ApsError::CommitNotFound { .. }));
+            }
+        }
+        assert!(!is_commit_prefix("main"));
+        assert!(!is_commit_prefix("abc123"));
+        assert!(is_commit_prefix("deadbeef"));
+    }
+
+    #[test]
+    fn test_reflog_ref_resolves_in_local_source() {
+        let stash = "1111111111111111111111111111111111111111";
+        let transport = MockTransport::new(vec![
+            GitOutput::ok(format!("{}\n", stash)),
+            GitOutput::ok(""),
+            GitOutput::ok(""),
+            GitOutput::ok(""),
+        ]);
+
+        let resolved = clone_and_resolve_with(
+            &transport,
+            "file:///work/prompts",
+            "stash@{0}",
+            &CloneOptions::new(),
+        )
+        .unwrap();
+
+        assert_eq!(resolved.commit_sha, stash);
+        assert_eq!(resolved.resolved_ref, "stash@{0}");
+        let calls = transport.calls();
+        assert_eq!(calls[0][..2], ["-C", "/work/prompts"]);
+        assert_eq!(
+            calls[0][2..],
+            ["rev-parse", "--verify", "--quiet", "stash@{0}^{commit}"]
+        );
+        assert_eq!(
+            calls[2][4..],
+            [
+                "fetch",
+                "--quiet",
+                "--no-tags",
+                "file:///work/prompts",
+                stash
+            ]
+        );
+        assert_eq!(calls[3][2..], ["checkout", "--quiet", "--detach", stash]);
+
+        let err = clone_and_resolve_with(
+            &MockTransport::new(vec![]),
+            "https://example.com/r.git",
+            "HEAD@{2}",
+            &CloneOptions::new(),
+        )
+        .err()
+        .unwrap();
+        assert!(matches!(err, ApsError::ReflogRefNotLocal { .. }));
+        assert!(!is_reflog_ref("main"));
+        assert!(!is_reflog_ref("v1@{"));
+        assert!(!is_reflog_ref("HEAD@{}"));
+    }
+
+    #[test]
+    fn test_pull_request_ref_is_fetched_exactly() {
+        let transport = MockTransport::new(vec![
+            GitOutput::ok(""),
+            GitOutput::ok(""),
+            GitOutput::ok(""),
+            GitOutput::ok(""),
+            GitOutput::ok("0badc0de\n"),
+        ]);
+
+        let resolved = clone_and_resolve_with(
+            &transport,
+            "https://example.com/r.git",
+            "refs/pull/123/head",
+            &CloneOptions::new().shallow(true),
+        )
+        .unwrap();
+
+        assert_eq!(resolved.resolved_ref, "refs/pull/123/head");
+        assert_eq!(resolved.commit_sha, "0badc0de");
+        let calls = transport.calls();
+        assert_eq!(calls[0][..2], ["init", "--quiet"]);
+        assert_eq!(
+            calls[1][2..],
+            ["remote", "add", "origin", "https://example.com/r.git"]
+        );
+        assert_eq!(
+            calls[2][2..],
+            [
+                "fetch",
+                "--no-tags",
+                "--depth",
+                "1",
+                "origin",
+                "refs/pull/123/head"
+            ]
+        );
+        assert_eq!(
+            calls[3][2..],
+            ["checkout", "--quiet", "--detach", "FETCH_HEAD"]
+        );
+    }
+
+    #[test]
+    fn test_ls_remote_uses_full_refnames_as_is() {
+        let transport = MockTransport::new(vec![GitOutput::ok(
+            "feedbeef\trefs/merge-requests/45/head\n",
+        )]);
+
+        let sha = get_remote_commit_sha_with(
+            &transport,
+            "https://example.com/r.git",
+            "refs/merge-requests/45/head",
+            &CloneOptions::new(),
+        )
+        .unwrap();
+
+        assert_eq!(sha.as_deref(), Some("feedbeef"));
+        assert_eq!(
+            transport.calls()[0].last().unwrap(),
+            "refs/merge-requests/45/head"
+        );
+    }
+
+    #[test]
+    fn test_ls_remote_presents_client_certificate() {
+        let transport = MockTransport::new(vec![
+            GitOutput::ok("feedbeef\trefs/heads/main\n"),
+            GitOutput::ok("feedbeef\trefs/heads/main\n"),
+        ]);
+        let options = CloneOptions::new()
+            .client_cert("/certs/client.pem")
+            .client_key("/certs/client.key");
+
+        get_remote_commit_sha_with(&transport, "https://example.com/r.git", "main", &options)
+            .unwrap();
+        list_remote_refs_with(&transport, "https://example.com/r.git", &options).unwrap();
+
+        for call in transport.calls() {
+            assert_eq!(
+                call[..4],
+                [
+                    "-c",
+                    "http.sslCert=/certs/client.pem",
+                    "-c",
+                    "http.sslKey=/certs/client.key"
+                ]
+            );
+            assert_eq!(call[4], "ls-remote");
+        }
+    }
+
+    #[test]
+    fn test_ls_remote_applies_source_git_config() {
+        let transport = MockTransport::new(vec![
+            GitOutput::ok("feedbeef\trefs/heads/main\n"),
+            GitOutput::ok("feedbeef\trefs/heads/main\n"),
+        ]);
+        let options = CloneOptions::new()
+            .git_config("http.sslCAInfo", "/certs/ca.pem")
+            .git_config("http.proxy", "http://proxy:3128");
+
+        get_remote_commit_sha_with(&transport, "https://example.com/r.git", "main", &options)
+            .unwrap();
+        list_remote_refs_with(&transport, "https://example.com/r.git", &options).unwrap();
+
+        for call in transport.calls() {
+            assert_eq!(
+                call[..4],
+                [
+                    "-c",
+                    "http.proxy=http://proxy:3128",
+                    "-c",
+                    "http.sslCAInfo=/certs/ca.pem"
+                ]
+            );
+        }
+
+        let invalid = CloneOptions::new().git_config("http", "x");
+        assert!(matches!(
+            list_remote_refs_with(&transport, "https://example.com/r.git", &invalid),
+            Err(ApsError::InvalidGitConfig { .. })
+        ));
+    }
+
+    #[test]
+    fn test_ls_remote_sends_http_identity() {
+        let transport = MockTransport::new(vec![
+            GitOutput::ok("feedbeef\trefs/heads/main\n"),
+            GitOutput::ok("ref: refs/heads/main\tHEAD\n"),
+        ]);
+        let options = CloneOptions::new()
+            .user_agent("aps-ci/1.0")
+            .http_header("X-Gateway-Key", "s3cret");
+
+        get_remote_commit_sha_with(&transport, "https://example.com/r.git", "main", &options)
+            .unwrap();
+        remote_default_branch(&transport, "https://example.com/r.git", &options).unwrap();
+
+        for (call, env) in transport.calls().iter().zip(transport.envs()) {
+            assert_eq!(call[..3], ["-c", "http.userAgent=aps-ci/1.0", "ls-remote"]);
+            assert!(!call.iter().any(|arg| arg.contains("s3cret")));
+            assert!(env.contains(&(
+                "GIT_CONFIG_VALUE_0".to_string(),
+                "X-Gateway-Key: s3cret".to_string()
+            )));
+        }
+    }
+
+    #[test]
+    fn test_ls_remote_parses_first_sha() {
+        let transport = MockTransport::new(vec![
+            GitOutput::ok(""),
+            GitOutput::ok("feedbeef\trefs/heads/master\n"),
+        ]);
+
+        let sha = get_remote_commit_sha_with(
+            &transport,
+            "https://example.com/r.git",
+            "auto",
+            &CloneOptions::new(),
+        )
+        .unwrap();
+
+        assert_eq!(sha.as_deref(), Some("feedbeef"));
+        assert_eq!(transport.calls()[1].last().unwrap(), "refs/heads/master");
+    }
+
+    #[test]
+    fn test_clone_at_commit_reports_checkout_failure() {
+        let transport = MockTransport::new(vec![
+            GitOutput::ok(""),
+            GitOutput::failed("fatal: reference is not a tree"),
+        ]);
+
+        let err = clone_at_commit_with(
+            &transport,
+            "https://example.com/r.git",
+            "0123456789",
+            "main",
+            &CloneOptions::new(),
+        )
+        .err()
+        .unwrap();
+
+        assert!(err
+            .to_string()
+            .contains("Failed to checkout commit 01234567"));
+    }
+
+    #[test]
+    fn test_clone_at_commit_deepens_date_bounded_clone_before_checkout() {
+        let transport = MockTransport::new(vec![
+            GitOutput::failed("fatal: error processing shallow info: no commits selected"),
+            GitOutput::ok(""),
+            GitOutput::failed(""),
+            GitOutput::ok("true\n"),
+            GitOutput::ok(""),
+            GitOutput::ok(""),
+            GitOutput::ok(""),
+        ]);
+        let options = CloneOptions::new().shallow_since("2024-06-01T00:00:00+00:00");
+
+        let resolved = clone_at_commit_with(
+            &transport,
+            "https://example.com/r.git",
+            "0123456789",
+            "main",
+            &options,
+        )
+        .unwrap();
+        assert_eq!(resolved.commit_sha, "0123456789");
+
+        let calls = transport.calls();
+        assert!(calls[0].contains(&"--shallow-since=2024-06-01T00:00:00+00:00".to_string()));
+        assert!(calls[1].contains(&"--depth=1".to_string()));
+        assert_eq!(calls[2][2..], ["cat-file", "-e", "0123456789^{commit}"]);
+        assert_eq!(calls[4][4], "--depth=50");
+        assert_eq!(
+            calls[6][2..],
+            ["checkout", "--quiet", "--detach", "0123456789"]
+        );
+    }
 }
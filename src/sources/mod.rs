@@ -3,14 +3,29 @@
 //! This module defines the `SourceAdapter` trait and provides implementations
 //! for different source types (filesystem, git, etc.).
 
+mod clone_options;
 mod filesystem;
+#[cfg(test)]
+mod fixture;
 mod git;
+mod transport;
 
+pub use clone_options::{
+    check_git_config, CloneIntent, CloneOptions, CloneStrategy, FollowRedirects,
+};
 pub use filesystem::FilesystemSource;
-pub use git::{clone_at_commit, get_remote_commit_sha, GitSource};
-
-use crate::error::Result;
+pub use git::{
+    commit_date, export_ignored_paths, get_remote_commit_sha, get_remote_default_branch,
+    is_commit_prefix, is_full_commit_sha, last_changed_commits, list_remote_refs,
+    list_submodule_pins, mirror_sync, parse_as_of, parse_shallow_since, CloneCache, GitSource,
+    RemoteRefs, INDEX_FILE_REF_PREFIX,
+};
+pub use transport::{GitCli, GitOutput, GitTransport};
+
+use crate::apsignore::ApsIgnore;
+use crate::error::{ApsError, Result};
 use crate::lockfile::LockedEntry;
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 /// Result of resolving a source - contains the path to content and metadata
@@ -28,6 +43,10 @@ pub struct ResolvedSource {
     pub original_root: Option<String>,
     /// Expanded root path (for filesystem sources, used for path substitution)
     pub expanded_root: Option<String>,
+    /// Repository checkout or filesystem root that contains `source_path`.
+    ///
+    /// Symlinks in copied content must resolve inside this directory.
+    pub root: Option<PathBuf>,
     /// Holder to keep temp directories alive (for git sources)
     _temp_holder: Option<Box<dyn std::any::Any + Send + Sync>>,
 }
@@ -48,6 +67,7 @@ impl ResolvedSource {
             git_info: None,
             original_root: Some(original_root),
             expanded_root: Some(expanded_root),
+            root: None,
             _temp_holder: None,
         }
     }
@@ -66,10 +86,17 @@ impl ResolvedSource {
             git_info: Some(git_info),
             original_root: None,
             expanded_root: None,
+            root: None,
             _temp_holder: Some(Box::new(temp_holder)),
         }
     }
 
+    /// Set the repository or filesystem root containing the source path
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+
     /// Create a LockedEntry from this resolved source
     pub fn to_locked_entry(
         &self,
@@ -78,13 +105,16 @@ impl ResolvedSource {
         symlinked_items: Vec<String>,
     ) -> LockedEntry {
         if let Some(ref git_info) = self.git_info {
-            LockedEntry::new_git(
+            let mut entry = LockedEntry::new_git(
                 &self.source_display,
                 &dest_path.to_string_lossy(),
                 git_info.resolved_ref.clone(),
                 git_info.commit_sha.clone(),
                 checksum,
-            )
+            );
+            entry.describe = git_info.describe.clone();
+            entry.notes = git_info.notes.clone();
+            entry
         } else {
             // For filesystem sources, preserve shell variables in paths
             let (target_path, transformed_items) = if self.use_symlink {
@@ -109,6 +139,151 @@ impl ResolvedSource {
         }
     }
 
+    /// List files in the resolved source, relative to `source_path`.
+    ///
+    /// Paths use `/` separators and are sorted; `.git` is skipped. Together
+    /// with [`ResolvedSource::read_file`] this lets callers serve content
+    /// straight from the resolved tree without copying it to a destination.
+    pub fn list_files(&self) -> Result<Vec<String>> {
+        if self.source_path.is_file() {
+            let name = self
+                .source_path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            return Ok(vec![name]);
+        }
+
+        let mut files = Vec::new();
+        for entry in walkdir::WalkDir::new(&self.source_path)
+            .follow_links(true)
+            .into_iter()
+            .filter_entry(|e| e.file_name() != ".git")
+        {
+            let entry = entry.map_err(|e| {
+                ApsError::io(
+                    std::io::Error::other(e),
+                    "Failed to traverse source directory",
+                )
+            })?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            if let Ok(rel) = entry.path().strip_prefix(&self.source_path) {
+                files.push(rel.to_string_lossy().replace('\\', "/"));
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    /// Read a file from the resolved source by its path relative to `source_path`.
+    ///
+    /// Absolute paths, `..` segments, and paths that resolve (through
+    /// symlinks) outside the source root are rejected.
+    pub fn read_file(&self, rel_path: &str) -> Result<Vec<u8>> {
+        let invalid = || ApsError::InvalidSourceFilePath {
+            path: rel_path.to_string(),
+        };
+        let rel = Path::new(rel_path);
+        if rel_path.is_empty()
+            || !rel
+                .components()
+                .all(|c| matches!(c, std::path::Component::Normal(_)))
+        {
+            return Err(invalid());
+        }
+
+        // A single-file source is addressed by its own file name
+        let path = if self.source_path.is_file() {
+            if self.source_path.file_name() != Some(rel.as_os_str()) {
+                return Err(invalid());
+            }
+            self.source_path.clone()
+        } else {
+            self.source_path.join(rel)
+        };
+
+        let canonical = path
+            .canonicalize()
+            .map_err(|e| ApsError::io(e, format!("Failed to read source file {:?}", path)))?;
+        let root = self.root.as_deref().unwrap_or(&self.source_path);
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        if !canonical.starts_with(&root) {
+            return Err(invalid());
+        }
+
+        std::fs::read(&canonical)
+            .map_err(|e| ApsError::io(e, format!("Failed to read source file {:?}", path)))
+    }
+
+    /// List the resolved tree without copying or reading file contents.
+    ///
+    /// Git sources are listed from the commit's tree objects (`git ls-tree`),
+    /// so sizes come from object metadata and paths outside a sparse checkout
+    /// are still included. Filesystem sources are walked without following
+    /// symlinks. Paths are relative to `source_path` and sorted.
+    pub fn list_tree(&self) -> Result<Vec<TreeEntry>> {
+        let mut entries = if self.git_info.is_some() {
+            git::list_tree(&self.source_path)?
+        } else {
+            list_filesystem_tree(&self.source_path)?
+        };
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(entries)
+    }
+
+    /// Read the resolved tree into memory, keyed by path relative to
+    /// `source_path`, without writing anything to a destination.
+    ///
+    /// Entries come from [`ResolvedSource::list_tree`], so git sources keep
+    /// the modes recorded in the commit. Directory sources honor `include`
+    /// (prefixes of top-level names, as in the manifest) and the source's
+    /// `.apsignore`. Symlinks are kept as links: their content is the link
+    /// target. Submodules are skipped.
+    pub fn to_memory(&self, include: &[String]) -> Result<BTreeMap<PathBuf, MemoryFile>> {
+        let (base, is_dir) = match self.source_path.parent() {
+            Some(parent) if self.source_path.is_file() => (parent, false),
+            _ => (self.source_path.as_path(), true),
+        };
+        let ignore = is_dir.then(|| ApsIgnore::load(&self.source_path)).flatten();
+
+        let mut files = BTreeMap::new();
+        for entry in self.list_tree()? {
+            if entry.kind == TreeEntryKind::Submodule {
+                continue;
+            }
+            if is_dir {
+                let top = entry.path.split('/').next().unwrap_or_default();
+                let included =
+                    include.is_empty() || include.iter().any(|prefix| top.starts_with(prefix));
+                let ignored = ignore
+                    .as_ref()
+                    .is_some_and(|ignore| ignore.is_ignored(&entry.path, false));
+                if !included || ignored {
+                    continue;
+                }
+            }
+
+            let path = base.join(&entry.path);
+            let read_error = |e| ApsError::io(e, format!("Failed to read source file {:?}", path));
+            let contents = if entry.kind == TreeEntryKind::Symlink {
+                let target = std::fs::read_link(&path).map_err(read_error)?;
+                target.to_string_lossy().replace('\\', "/").into_bytes()
+            } else {
+                std::fs::read(&path).map_err(read_error)?
+            };
+            files.insert(
+                PathBuf::from(&entry.path),
+                MemoryFile {
+                    contents,
+                    mode: entry.mode,
+                },
+            );
+        }
+        Ok(files)
+    }
+
     /// Replace expanded root path with original root path to preserve shell variables
     fn preserve_shell_vars_in_path(&self, expanded_path: &str) -> String {
         if let (Some(ref original), Some(ref expanded)) = (&self.original_root, &self.expanded_root)
@@ -121,13 +296,135 @@ impl ResolvedSource {
     }
 }
 
+/// One file of [`ResolvedSource::to_memory`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryFile {
+    /// File content; for a symlink, its target
+    pub contents: Vec<u8>,
+    /// Git-style file mode (`0o100644`, `0o100755` or `0o120000`)
+    pub mode: u32,
+}
+
 /// Git-specific resolution metadata
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct GitInfo {
     /// Resolved ref name (e.g., "main", "master", or the original ref)
     pub resolved_ref: String,
     /// Commit SHA at the resolved ref
     pub commit_sha: String,
+    /// Nearest tag description of the commit, when requested
+    pub describe: Option<String>,
+    /// Git note on the commit, when notes were fetched
+    pub notes: Option<String>,
+    /// Where the repository has moved to, when a redirect was followed
+    pub final_url: Option<String>,
+    /// Strategy that produced the clone, when the source lists strategies
+    pub strategy: Option<CloneStrategy>,
+    /// Whether the full history was cloned because the server refused a
+    /// shallow clone
+    pub shallow_refused: bool,
+}
+
+/// Kind of an entry in a source tree listing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeEntryKind {
+    /// Regular file
+    File,
+    /// Executable file
+    Executable,
+    /// Symbolic link (not followed)
+    Symlink,
+    /// Git submodule (a commit, not checked out content)
+    Submodule,
+}
+
+/// One entry of [`ResolvedSource::list_tree`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeEntry {
+    /// Path relative to the resolved source, with `/` separators
+    pub path: String,
+    /// Size in bytes (`None` for submodules)
+    pub size: Option<u64>,
+    /// Git-style file mode (e.g. `0o100644`, `0o120000`)
+    pub mode: u32,
+    /// What the entry is
+    pub kind: TreeEntryKind,
+}
+
+impl TreeEntryKind {
+    /// Map a git tree mode to an entry kind
+    fn from_mode(mode: u32) -> Option<Self> {
+        match mode {
+            0o100644 | 0o100664 => Some(TreeEntryKind::File),
+            0o100755 => Some(TreeEntryKind::Executable),
+            0o120000 => Some(TreeEntryKind::Symlink),
+            0o160000 => Some(TreeEntryKind::Submodule),
+            _ => None,
+        }
+    }
+}
+
+/// Walk a filesystem source, reporting git-style modes
+fn list_filesystem_tree(source_path: &Path) -> Result<Vec<TreeEntry>> {
+    let base = if source_path.is_file() {
+        source_path.parent().unwrap_or(source_path)
+    } else {
+        source_path
+    };
+    let mut entries = Vec::new();
+    for entry in walkdir::WalkDir::new(source_path)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git")
+    {
+        let entry = entry.map_err(|e| {
+            ApsError::io(
+                std::io::Error::other(e),
+                "Failed to traverse source directory",
+            )
+        })?;
+        let file_type = entry.file_type();
+        if file_type.is_dir() {
+            continue;
+        }
+        let metadata = entry.metadata().map_err(|e| {
+            ApsError::io(
+                std::io::Error::other(e),
+                format!("Failed to read metadata for {:?}", entry.path()),
+            )
+        })?;
+        let kind = if file_type.is_symlink() {
+            TreeEntryKind::Symlink
+        } else if is_executable(&metadata) {
+            TreeEntryKind::Executable
+        } else {
+            TreeEntryKind::File
+        };
+        let mode = match kind {
+            TreeEntryKind::File => 0o100644,
+            TreeEntryKind::Executable => 0o100755,
+            TreeEntryKind::Symlink => 0o120000,
+            TreeEntryKind::Submodule => 0o160000,
+        };
+        let rel = entry.path().strip_prefix(base).unwrap_or(entry.path());
+        entries.push(TreeEntry {
+            path: rel.to_string_lossy().replace('\\', "/"),
+            size: Some(metadata.len()),
+            mode,
+            kind,
+        });
+    }
+    Ok(entries)
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &std::fs::Metadata) -> bool {
+    false
 }
 
 /// Trait for source adapters that can resolve and provide content
@@ -159,6 +456,11 @@ pub fn expand_path(path: &str) -> String {
         .unwrap_or_else(|_| path.to_string())
 }
 
+/// Whether a source path names the whole source root (`.`, `./` or empty)
+pub fn is_root_path(path: &str) -> bool {
+    matches!(path.trim_end_matches('/'), "" | ".")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -374,6 +676,128 @@ mod tests {
 
     // ==================== ResolvedSource tests ====================
 
+    fn resolved_dir(dir: &Path) -> ResolvedSource {
+        ResolvedSource::filesystem(
+            dir.to_path_buf(),
+            "filesystem:.".to_string(),
+            false,
+            ".".to_string(),
+            dir.to_string_lossy().to_string(),
+        )
+        .with_root(dir)
+    }
+
+    #[test]
+    fn test_resolved_source_list_and_read_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        std::fs::create_dir_all(dir.join("rules/nested")).unwrap();
+        std::fs::create_dir_all(dir.join(".git")).unwrap();
+        std::fs::write(dir.join("rules/b.md"), "b").unwrap();
+        std::fs::write(dir.join("rules/nested/a.md"), "a").unwrap();
+        std::fs::write(dir.join(".git/HEAD"), "ref").unwrap();
+
+        let resolved = resolved_dir(dir);
+        assert_eq!(
+            resolved.list_files().unwrap(),
+            vec!["rules/b.md".to_string(), "rules/nested/a.md".to_string()]
+        );
+        assert_eq!(resolved.read_file("rules/nested/a.md").unwrap(), b"a");
+    }
+
+    #[test]
+    fn test_resolved_source_read_file_rejects_traversal() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("source");
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(temp_dir.path().join("secret.txt"), "secret").unwrap();
+
+        let resolved = resolved_dir(&source);
+        for path in ["../secret.txt", "/etc/passwd", "", "./a/../../secret.txt"] {
+            assert!(
+                matches!(
+                    resolved.read_file(path),
+                    Err(ApsError::InvalidSourceFilePath { .. })
+                ),
+                "{}",
+                path
+            );
+        }
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(temp_dir.path().join("secret.txt"), source.join("link.txt"))
+                .unwrap();
+            assert!(matches!(
+                resolved.read_file("link.txt"),
+                Err(ApsError::InvalidSourceFilePath { .. })
+            ));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolved_source_list_tree_reports_kinds() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        std::fs::write(dir.join("a.md"), "hello").unwrap();
+        std::fs::write(dir.join("run.sh"), "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(dir.join("run.sh"), std::fs::Permissions::from_mode(0o755))
+            .unwrap();
+        std::os::unix::fs::symlink("a.md", dir.join("link.md")).unwrap();
+
+        let entries = resolved_dir(dir).list_tree().unwrap();
+        let summary: Vec<_> = entries
+            .iter()
+            .map(|e| (e.path.as_str(), e.mode, e.kind))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("a.md", 0o100644, TreeEntryKind::File),
+                ("link.md", 0o120000, TreeEntryKind::Symlink),
+                ("run.sh", 0o100755, TreeEntryKind::Executable),
+            ]
+        );
+        assert_eq!(entries[0].size, Some(5));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_to_memory_applies_include_and_apsignore() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().join("assets");
+        std::fs::create_dir_all(dir.join("rules")).unwrap();
+        std::fs::create_dir_all(dir.join("notes")).unwrap();
+        std::fs::write(dir.join("rules/style.md"), "style").unwrap();
+        std::fs::write(dir.join("rules/wip.draft.md"), "wip").unwrap();
+        std::fs::write(dir.join("notes/todo.md"), "todo").unwrap();
+        std::fs::write(dir.join("run.sh"), "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(dir.join("run.sh"), std::fs::Permissions::from_mode(0o755))
+            .unwrap();
+        std::os::unix::fs::symlink("rules/style.md", dir.join("rules-link.md")).unwrap();
+        std::fs::write(dir.join(".apsignore"), "*.draft.md\n").unwrap();
+
+        let source = FilesystemSource::new("assets".to_string(), false, None);
+        let include = ["r".to_string()];
+        let resolved = source.resolve(temp_dir.path()).unwrap();
+        let files = resolved.to_memory(&include).unwrap();
+
+        let paths: Vec<_> = files.keys().map(|p| p.to_string_lossy()).collect();
+        assert_eq!(paths, ["rules/style.md", "rules-link.md", "run.sh"]);
+        assert_eq!(files[Path::new("rules/style.md")].contents, b"style");
+        assert_eq!(files[Path::new("run.sh")].mode, 0o100755);
+        let link = &files[Path::new("rules-link.md")];
+        assert_eq!(
+            (link.contents.as_slice(), link.mode),
+            (&b"rules/style.md"[..], 0o120000)
+        );
+    }
+
     #[test]
     fn test_resolved_source_filesystem_to_locked_entry() {
         let resolved = ResolvedSource::filesystem(
@@ -451,6 +875,7 @@ mod tests {
         let git_info = GitInfo {
             resolved_ref: "main".to_string(),
             commit_sha: "abc123def456".to_string(),
+            ..Default::default()
         };
 
         let resolved = ResolvedSource::git(
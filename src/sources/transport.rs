@@ -0,0 +1,208 @@
+//! Transport abstraction over the git CLI.
+//!
+//! Every git operation aps performs (clone, checkout, rev-parse, ls-remote)
+//! goes through a `GitTransport`. The default implementation shells out to
+//! the user's `git` binary; tests inject a scripted transport instead of
+//! touching the network or the filesystem.
+
+use crate::error::{ApsError, Result};
+use std::ffi::OsString;
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Captured result of a git invocation
+#[derive(Debug, Clone, Default)]
+pub struct GitOutput {
+    /// Whether git exited successfully
+    pub success: bool,
+    /// Captured standard output
+    pub stdout: String,
+    /// Captured standard error
+    pub stderr: String,
+}
+
+impl GitOutput {
+    /// Successful output with the given stdout
+    #[allow(dead_code)] // Used by scripted transports in tests
+    pub fn ok(stdout: impl Into<String>) -> Self {
+        Self {
+            success: true,
+            stdout: stdout.into(),
+            stderr: String::new(),
+        }
+    }
+
+    /// Failed output with the given stderr
+    #[allow(dead_code)] // Used by scripted transports in tests
+    pub fn failed(stderr: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            stdout: String::new(),
+            stderr: stderr.into(),
+        }
+    }
+}
+
+/// Something that can run git commands
+pub trait GitTransport: Send + Sync {
+    /// Run `git <args>` and capture its output.
+    ///
+    /// Returns an error only if git could not be executed at all; a non-zero
+    /// exit status is reported through [`GitOutput::success`].
+    fn run(&self, args: &[OsString]) -> Result<GitOutput>;
+
+    /// Like [`run`](Self::run), with `env` added to git's environment.
+    ///
+    /// Carries configuration that must stay off the command line, where
+    /// other local users could read it. Scripted transports may ignore it.
+    fn run_env(&self, args: &[OsString], env: &[(String, String)]) -> Result<GitOutput> {
+        let _ = env;
+        self.run(args)
+    }
+
+    /// Like [`run_env`](Self::run_env), but give up once `timeout` has
+    /// elapsed, returning `None`.
+    ///
+    /// Transports that cannot enforce a deadline run to completion.
+    fn run_with_deadline(
+        &self,
+        args: &[OsString],
+        env: &[(String, String)],
+        timeout: Duration,
+    ) -> Result<Option<GitOutput>> {
+        let _ = timeout;
+        self.run_env(args, env).map(Some)
+    }
+}
+
+/// Default transport: the `git` CLI on `PATH`.
+///
+/// This inherits the user's existing git configuration (SSH, credentials, etc.)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GitCli;
+
+impl GitTransport for GitCli {
+    fn run(&self, args: &[OsString]) -> Result<GitOutput> {
+        self.run_env(args, &[])
+    }
+
+    fn run_env(&self, args: &[OsString], env: &[(String, String)]) -> Result<GitOutput> {
+        let output = Command::new("git")
+            .args(args)
+            .envs(env.iter().map(|(key, value)| (key, value)))
+            .output()
+            .map_err(|e| ApsError::GitError {
+                message: format!("Failed to execute git command: {}", e),
+            })?;
+
+        Ok(GitOutput {
+            success: output.status.success(),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        })
+    }
+
+    fn run_with_deadline(
+        &self,
+        args: &[OsString],
+        env: &[(String, String)],
+        timeout: Duration,
+    ) -> Result<Option<GitOutput>> {
+        let mut child = Command::new("git")
+            .args(args)
+            .envs(env.iter().map(|(key, value)| (key, value)))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| ApsError::GitError {
+                message: format!("Failed to execute git command: {}", e),
+            })?;
+
+        // Drain both pipes so a chatty git cannot block on a full pipe
+        let drain = |pipe: Option<Box<dyn Read + Send>>| {
+            thread::spawn(move || {
+                let mut buf = Vec::new();
+                if let Some(mut pipe) = pipe {
+                    let _ = pipe.read_to_end(&mut buf);
+                }
+                String::from_utf8_lossy(&buf).to_string()
+            })
+        };
+        let stdout = drain(child.stdout.take().map(|p| Box::new(p) as _));
+        let stderr = drain(child.stderr.take().map(|p| Box::new(p) as _));
+
+        let deadline = Instant::now() + timeout;
+        let status = loop {
+            let waited = child.try_wait().map_err(|e| ApsError::GitError {
+                message: format!("Failed to wait for git command: {}", e),
+            })?;
+            if let Some(status) = waited {
+                break status;
+            }
+            if Instant::now() >= deadline {
+                // Helpers git spawned may keep the pipes open, so the
+                // readers are left to finish on their own
+                let _ = child.kill();
+                let _ = child.wait();
+                return Ok(None);
+            }
+            thread::sleep(Duration::from_millis(20));
+        };
+
+        Ok(Some(GitOutput {
+            success: status.success(),
+            stdout: stdout.join().unwrap_or_default(),
+            stderr: stderr.join().unwrap_or_default(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_with_deadline_gives_up_on_hung_git() {
+        let args: Vec<OsString> = ["-c", "alias.nap=!sleep 5", "nap"]
+            .iter()
+            .map(OsString::from)
+            .collect();
+
+        let started = Instant::now();
+        let output = GitCli
+            .run_with_deadline(&args, &[], Duration::from_millis(200))
+            .unwrap();
+
+        assert!(output.is_none());
+        assert!(started.elapsed() < Duration::from_secs(3));
+
+        let version = GitCli
+            .run_with_deadline(&[OsString::from("--version")], &[], Duration::from_secs(30))
+            .unwrap()
+            .unwrap();
+        assert!(version.success);
+        assert!(version.stdout.starts_with("git version"));
+    }
+
+    #[test]
+    fn test_run_env_passes_config_through_the_environment() {
+        let args: Vec<OsString> = ["config", "--get-all", "http.extraHeader"]
+            .iter()
+            .map(OsString::from)
+            .collect();
+        let env = [
+            ("GIT_CONFIG_COUNT", "1"),
+            ("GIT_CONFIG_KEY_0", "http.extraHeader"),
+            ("GIT_CONFIG_VALUE_0", "X-Gateway-Key: s3cret"),
+        ]
+        .map(|(key, value)| (key.to_string(), value.to_string()));
+
+        let output = GitCli.run_env(&args, &env).unwrap();
+
+        assert!(output.success, "{}", output.stderr);
+        assert_eq!(output.stdout.trim(), "X-Gateway-Key: s3cret");
+    }
+}
@@ -1,5 +1,8 @@
+use crate::install::{EntryDrift, FileChangeKind, PhaseTimings, SyncPlan};
+use crate::updates::{UpdateState, UpdateStatus};
 use console::{style, Style};
 use std::path::Path;
+use std::time::Duration;
 
 /// Status of a sync operation for display purposes
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -251,6 +254,218 @@ pub fn print_sync_summary(
     }
 }
 
+/// Print the result of `aps sync --check`
+pub fn print_check_results(drifts: &[EntryDrift], manifest_path: &Path) {
+    let manifest_display = manifest_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| manifest_path.to_string_lossy().to_string());
+    println!(
+        "{} {}",
+        style("Checking").dim(),
+        style(&manifest_display).cyan()
+    );
+    println!();
+
+    let green = Style::new().green();
+    let dim = Style::new().dim();
+    let red = Style::new().red();
+
+    for drift in drifts {
+        if drift.is_clean() {
+            println!(
+                "  {} {} {}",
+                dim.apply_to("·"),
+                dim.apply_to(&drift.id),
+                dim.apply_to("[current]")
+            );
+            continue;
+        }
+        println!(
+            "  {} {} {}",
+            red.apply_to("✗"),
+            red.apply_to(&drift.id),
+            red.apply_to("[out of date]")
+        );
+        for (marker, paths) in [
+            ("+", &drift.added),
+            ("~", &drift.changed),
+            ("-", &drift.removed),
+        ] {
+            for path in paths {
+                println!("      {} {}", marker, path.display());
+            }
+        }
+    }
+    println!();
+
+    let stale = drifts.iter().filter(|d| !d.is_clean()).count();
+    if stale == 0 {
+        println!("{}", green.apply_to("All destinations are up to date"));
+    }
+}
+
+/// Print the unified diff of every changed file in the plans; binary files
+/// are only named
+pub fn print_plan_diffs(plans: &[SyncPlan]) {
+    let dim = Style::new().dim();
+    let green = Style::new().green();
+    let red = Style::new().red();
+    let cyan = Style::new().cyan();
+
+    for change in plans.iter().flat_map(|plan| &plan.changes) {
+        if change.kind != FileChangeKind::Changed {
+            continue;
+        }
+        let Some(ref diff) = change.diff else {
+            println!(
+                "{}",
+                dim.apply_to(format!("Binary file {} differs", change.path.display()))
+            );
+            continue;
+        };
+        for line in diff.lines() {
+            let styled = if line.starts_with("+++") || line.starts_with("---") {
+                dim.apply_to(line)
+            } else if line.starts_with("@@") {
+                cyan.apply_to(line)
+            } else if line.starts_with('+') {
+                green.apply_to(line)
+            } else if line.starts_with('-') {
+                red.apply_to(line)
+            } else {
+                Style::new().apply_to(line)
+            };
+            println!("{}", styled);
+        }
+    }
+}
+
+/// Print locked vs remote commits for each git entry
+pub fn print_update_report(statuses: &[UpdateStatus], manifest_path: &Path) {
+    let manifest_display = manifest_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| manifest_path.to_string_lossy().to_string());
+    println!(
+        "{} {}",
+        style("Checking upstream for").dim(),
+        style(&manifest_display).cyan()
+    );
+    println!();
+
+    let short = |sha: &Option<String>| {
+        sha.as_deref()
+            .map(|s| s[..8.min(s.len())].to_string())
+            .unwrap_or_else(|| "-".to_string())
+    };
+    let dim = Style::new().dim();
+    let yellow = Style::new().yellow();
+    let red = Style::new().red();
+
+    for status in statuses {
+        let label = format!("{} ({})", status.id, status.git_ref);
+        match status.state {
+            UpdateState::Unchanged => println!(
+                "  {} {} {}",
+                dim.apply_to("·"),
+                dim.apply_to(&label),
+                dim.apply_to(format!("[current {}]", short(&status.locked)))
+            ),
+            UpdateState::Moved => println!(
+                "  {} {} {} → {}",
+                yellow.apply_to("↑"),
+                yellow.apply_to(&label),
+                short(&status.locked),
+                yellow.apply_to(short(&status.remote))
+            ),
+            UpdateState::NotLocked => println!(
+                "  {} {} {}",
+                yellow.apply_to("+"),
+                yellow.apply_to(&label),
+                dim.apply_to(format!("[not locked, remote {}]", short(&status.remote)))
+            ),
+            UpdateState::Pinned => println!(
+                "  {} {} {}",
+                dim.apply_to("·"),
+                dim.apply_to(&label),
+                dim.apply_to("[pinned]")
+            ),
+            UpdateState::RefNotFound => println!(
+                "  {} {} {}",
+                red.apply_to("✗"),
+                red.apply_to(&label),
+                red.apply_to("[ref not found on remote]")
+            ),
+            UpdateState::Failed(ref message) => println!(
+                "  {} {} {}",
+                red.apply_to("✗"),
+                red.apply_to(&label),
+                red.apply_to(format!("[{}]", message))
+            ),
+        }
+    }
+    println!();
+
+    let moved = statuses
+        .iter()
+        .filter(|s| s.state == UpdateState::Moved)
+        .count();
+    if statuses.is_empty() {
+        println!("{}", dim.apply_to("No git entries to check"));
+    } else if moved == 0 {
+        println!(
+            "{}",
+            Style::new().green().apply_to("All git entries are current")
+        );
+    } else {
+        println!(
+            "{}",
+            yellow.apply_to(format!(
+                "{} entr{} can be upgraded with `aps sync --upgrade`",
+                moved,
+                if moved == 1 { "y" } else { "ies" }
+            ))
+        );
+    }
+}
+
+/// Print per-entry phase timings and their totals
+pub fn print_sync_timings(timings: &[(String, PhaseTimings)]) {
+    if timings.is_empty() {
+        return;
+    }
+
+    let dim = Style::new().dim();
+    let width = timings.iter().map(|(id, _)| id.len()).max().unwrap_or(0);
+    let format_row = |label: &str, t: &PhaseTimings| {
+        format!(
+            "  {:<width$}  resolve {:>9}  checksum {:>9}  install {:>9}  total {:>9}",
+            label,
+            format_duration(t.resolve),
+            format_duration(t.checksum),
+            format_duration(t.install),
+            format_duration(t.total()),
+            width = width
+        )
+    };
+
+    println!("{}", dim.apply_to("Timings:"));
+    let mut total = PhaseTimings::default();
+    for (id, t) in timings {
+        println!("{}", format_row(id, t));
+        total += *t;
+    }
+    if timings.len() > 1 {
+        println!("{}", dim.apply_to(format_row("total", &total)));
+    }
+    println!();
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.1}ms", duration.as_secs_f64() * 1000.0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
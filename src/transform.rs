@@ -0,0 +1,502 @@
+//! Content transforms applied while copying source files.
+//!
+//! Four transforms exist, all opt-in per entry:
+//! - Placeholder substitution: provenance placeholders such as
+//!   `{{APS_COMMIT_SHA}}` are replaced with the values of the resolved source
+//!   in every copied text file.
+//! - Encoding conversion: text files in a legacy encoding (Latin-1, UTF-16,
+//!   ...) are transcoded to UTF-8. Files that cannot be decoded are copied
+//!   verbatim with a warning rather than corrupted.
+//! - Whitespace normalization: trailing spaces and tabs are trimmed from
+//!   every line, and a missing final newline is added, so synced files pass
+//!   the same linters as hand-written ones.
+//! - Notebook output stripping: code cell outputs are cleared from Jupyter
+//!   notebooks (see [`crate::notebook`]). A notebook that cannot be parsed
+//!   is copied verbatim with a warning.
+//!
+//! Binary files are never modified.
+
+use crate::manifest::SourceEncoding;
+use crate::notebook::{is_notebook, strip_outputs};
+use crate::sources::ResolvedSource;
+use std::collections::BTreeMap;
+use std::path::Path;
+use tracing::warn;
+
+/// Placeholder replaced with the full resolved commit SHA
+pub const COMMIT_SHA_PLACEHOLDER: &str = "{{APS_COMMIT_SHA}}";
+/// Placeholder replaced with the abbreviated commit SHA
+pub const SHORT_SHA_PLACEHOLDER: &str = "{{APS_SHORT_SHA}}";
+/// Placeholder replaced with the resolved ref name
+pub const RESOLVED_REF_PLACEHOLDER: &str = "{{APS_RESOLVED_REF}}";
+/// Placeholder replaced with the source name (repository URL or root path)
+pub const SOURCE_PLACEHOLDER: &str = "{{APS_SOURCE}}";
+
+/// Length of the abbreviated SHA used for `{{APS_SHORT_SHA}}`
+const SHORT_SHA_LEN: usize = 8;
+
+/// Placeholder -> value map applied to copied text files
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Substitutions {
+    values: BTreeMap<String, String>,
+}
+
+impl Substitutions {
+    /// Build the provenance substitution map for a resolved source.
+    ///
+    /// Git placeholders are only populated for git sources; for filesystem
+    /// sources they are left untouched in the copied content.
+    pub fn from_resolved(resolved: &ResolvedSource) -> Self {
+        let mut values = BTreeMap::new();
+        values.insert(
+            SOURCE_PLACEHOLDER.to_string(),
+            resolved.source_display.clone(),
+        );
+        if let Some(ref git_info) = resolved.git_info {
+            let sha = &git_info.commit_sha;
+            values.insert(COMMIT_SHA_PLACEHOLDER.to_string(), sha.clone());
+            values.insert(
+                SHORT_SHA_PLACEHOLDER.to_string(),
+                sha[..SHORT_SHA_LEN.min(sha.len())].to_string(),
+            );
+            values.insert(
+                RESOLVED_REF_PLACEHOLDER.to_string(),
+                git_info.resolved_ref.clone(),
+            );
+        }
+        Self { values }
+    }
+
+    /// Stable string form of the map, used to fold the values into checksums
+    pub fn fingerprint(&self) -> String {
+        self.values
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Replace every known placeholder in `content`
+    pub fn apply(&self, content: &str) -> String {
+        let mut result = content.to_string();
+        for (placeholder, value) in &self.values {
+            if result.contains(placeholder.as_str()) {
+                result = result.replace(placeholder.as_str(), value);
+            }
+        }
+        result
+    }
+}
+
+/// Heuristic binary check: NUL bytes or invalid UTF-8 mean "not text"
+fn as_text(bytes: &[u8]) -> Option<&str> {
+    if bytes.contains(&0) {
+        return None;
+    }
+    std::str::from_utf8(bytes).ok()
+}
+
+/// Transforms applied to every copied file of an entry
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ContentTransform<'a> {
+    /// Placeholder substitutions for text files
+    pub substitutions: Option<&'a Substitutions>,
+    /// Encoding to convert text files from
+    pub encoding: Option<SourceEncoding>,
+    /// End non-empty text files with a newline
+    pub ensure_final_newline: bool,
+    /// Strip spaces and tabs at the end of every line of text files
+    pub trim_trailing_whitespace: bool,
+    /// Clear code cell outputs in Jupyter notebooks
+    pub strip_notebook_outputs: bool,
+}
+
+impl<'a> ContentTransform<'a> {
+    /// Create a transform from an entry's placeholder and encoding settings
+    pub fn new(substitutions: Option<&'a Substitutions>, encoding: Option<SourceEncoding>) -> Self {
+        Self {
+            substitutions,
+            encoding,
+            ..Default::default()
+        }
+    }
+
+    /// Add a final newline to text files that lack one
+    pub fn ensure_final_newline(mut self, enabled: bool) -> Self {
+        self.ensure_final_newline = enabled;
+        self
+    }
+
+    /// Trim trailing whitespace from every line of text files
+    pub fn trim_trailing_whitespace(mut self, enabled: bool) -> Self {
+        self.trim_trailing_whitespace = enabled;
+        self
+    }
+
+    /// Clear code cell outputs and execution counts in `.ipynb` files
+    pub fn strip_notebook_outputs(mut self, enabled: bool) -> Self {
+        self.strip_notebook_outputs = enabled;
+        self
+    }
+
+    /// Whether files are copied byte-for-byte
+    pub fn is_identity(&self) -> bool {
+        self.substitutions.is_none()
+            && self.encoding.is_none()
+            && !self.ensure_final_newline
+            && !self.trim_trailing_whitespace
+            && !self.strip_notebook_outputs
+    }
+
+    /// Transform file content; `path` selects notebook stripping and is
+    /// used in warnings.
+    ///
+    /// Returns `None` when the content is unchanged.
+    pub fn apply(&self, path: &Path, bytes: &[u8]) -> Option<Vec<u8>> {
+        let stripped = if self.strip_notebook_outputs && is_notebook(path) {
+            strip_outputs(bytes).unwrap_or_else(|reason| {
+                warn!(
+                    "Could not strip outputs from {:?} ({}); copying it unchanged",
+                    path, reason
+                );
+                None
+            })
+        } else {
+            None
+        };
+        match stripped {
+            Some(stripped) => Some(self.apply_text(path, &stripped).unwrap_or(stripped)),
+            None => self.apply_text(path, bytes),
+        }
+    }
+
+    /// Apply the text transforms (encoding, placeholders, whitespace)
+    fn apply_text(&self, path: &Path, bytes: &[u8]) -> Option<Vec<u8>> {
+        let decoded = match self.encoding {
+            Some(encoding) => match decode_to_utf8(bytes, encoding) {
+                Ok(decoded) => decoded,
+                Err(reason) => {
+                    warn!(
+                        "Could not convert {:?} from {} to UTF-8 ({}); copying it unchanged",
+                        path, encoding, reason
+                    );
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let text = match decoded {
+            Some(ref text) => text.as_str(),
+            None => as_text(bytes)?,
+        };
+        let mut out = match self.substitutions {
+            Some(subs) => subs.apply(text),
+            None => text.to_string(),
+        };
+        if self.trim_trailing_whitespace {
+            out = trim_line_ends(&out);
+        }
+        if self.ensure_final_newline && !out.is_empty() && !out.ends_with('\n') {
+            out.push('\n');
+        }
+        (out != text || decoded.is_some()).then(|| out.into_bytes())
+    }
+}
+
+/// Strip spaces and tabs before each line ending (`\n` or `\r\n`) and at
+/// the end of the text
+fn trim_line_ends(text: &str) -> String {
+    text.split_inclusive('\n')
+        .map(|line| {
+            let (body, ending) = match line.strip_suffix("\r\n") {
+                Some(body) => (body, "\r\n"),
+                None => match line.strip_suffix('\n') {
+                    Some(body) => (body, "\n"),
+                    None => (line, ""),
+                },
+            };
+            format!("{}{}", body.trim_end_matches([' ', '\t']), ending)
+        })
+        .collect()
+}
+
+/// Decode `bytes` to UTF-8 text.
+///
+/// `Ok(None)` means the content should be copied as is: it is binary, or it
+/// is already UTF-8 and needs no conversion. Content declared as an 8-bit
+/// encoding that is nonetheless valid UTF-8 is also left alone, so a mixed
+/// corpus is not double-encoded.
+fn decode_to_utf8(
+    bytes: &[u8],
+    encoding: SourceEncoding,
+) -> std::result::Result<Option<String>, String> {
+    const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+    const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+    const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+    match encoding {
+        SourceEncoding::Auto => {
+            if bytes.starts_with(UTF8_BOM) {
+                decode_to_utf8(bytes, SourceEncoding::Utf8)
+            } else if bytes.starts_with(UTF16LE_BOM) {
+                decode_to_utf8(bytes, SourceEncoding::Utf16Le)
+            } else if bytes.starts_with(UTF16BE_BOM) {
+                decode_to_utf8(bytes, SourceEncoding::Utf16Be)
+            } else if let Some(encoding) = guess_utf16(bytes) {
+                decode_to_utf8(bytes, encoding)
+            } else if bytes.contains(&0) || std::str::from_utf8(bytes).is_ok() {
+                Ok(None)
+            } else {
+                Err("no byte order mark and not valid UTF-8; set `encoding` explicitly".to_string())
+            }
+        }
+        SourceEncoding::Utf8 => match bytes.strip_prefix(UTF8_BOM) {
+            Some(rest) => std::str::from_utf8(rest)
+                .map(|text| Some(text.to_string()))
+                .map_err(|e| e.to_string()),
+            None if bytes.contains(&0) => Ok(None),
+            None => std::str::from_utf8(bytes)
+                .map(|_| None)
+                .map_err(|e| e.to_string()),
+        },
+        SourceEncoding::Utf16Le | SourceEncoding::Utf16Be => {
+            let le = encoding == SourceEncoding::Utf16Le;
+            let bom = if le { UTF16LE_BOM } else { UTF16BE_BOM };
+            let body = bytes.strip_prefix(bom).unwrap_or(bytes);
+            if !body.len().is_multiple_of(2) {
+                return Err("odd number of bytes".to_string());
+            }
+            let units: Vec<u16> = body
+                .chunks_exact(2)
+                .map(|pair| {
+                    let pair = [pair[0], pair[1]];
+                    if le {
+                        u16::from_le_bytes(pair)
+                    } else {
+                        u16::from_be_bytes(pair)
+                    }
+                })
+                .collect();
+            let text = String::from_utf16(&units).map_err(|e| e.to_string())?;
+            // Decoded NULs mean the file was binary after all
+            Ok((!text.contains('\0')).then_some(text))
+        }
+        SourceEncoding::Latin1 | SourceEncoding::Windows1252 => {
+            if bytes.contains(&0) || std::str::from_utf8(bytes).is_ok() {
+                return Ok(None);
+            }
+            let cp1252 = encoding == SourceEncoding::Windows1252;
+            Ok(Some(
+                bytes
+                    .iter()
+                    .map(|&b| match b {
+                        0x80..=0x9F if cp1252 => WINDOWS_1252_HIGH[(b - 0x80) as usize],
+                        _ => char::from(b),
+                    })
+                    .collect(),
+            ))
+        }
+    }
+}
+
+/// Guess UTF-16 without a BOM: mostly-ASCII text leaves every other byte NUL
+fn guess_utf16(bytes: &[u8]) -> Option<SourceEncoding> {
+    if bytes.len() < 4 || !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let pairs = bytes.len() / 2;
+    let even_nuls = bytes.iter().step_by(2).filter(|&&b| b == 0).count();
+    let odd_nuls = bytes.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+    if odd_nuls * 10 >= pairs * 9 && even_nuls == 0 {
+        Some(SourceEncoding::Utf16Le)
+    } else if even_nuls * 10 >= pairs * 9 && odd_nuls == 0 {
+        Some(SourceEncoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+/// Windows-1252 characters for bytes 0x80-0x9F (undefined bytes map to the
+/// C1 control with the same value, as browsers do)
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}',
+    '\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+];
+
+/// Apply a transform to file content, returning the bytes to write
+pub fn transform_content(path: &Path, bytes: Vec<u8>, transform: &ContentTransform<'_>) -> Vec<u8> {
+    transform.apply(path, &bytes).unwrap_or(bytes)
+}
+
+/// Copy a single file, applying the transform to text content.
+///
+/// Binary files (and every file when the transform is the identity) are
+/// copied byte-for-byte.
+pub fn copy_file(src: &Path, dst: &Path, transform: &ContentTransform<'_>) -> std::io::Result<()> {
+    if transform.is_identity() {
+        return std::fs::copy(src, dst).map(|_| ());
+    }
+
+    let bytes = std::fs::read(src)?;
+    match transform.apply(src, &bytes) {
+        Some(transformed) => {
+            std::fs::write(dst, transformed)?;
+            // Keep the source permissions (e.g. executable scripts)
+            let perms = std::fs::metadata(src)?.permissions();
+            std::fs::set_permissions(dst, perms)
+        }
+        None => std::fs::copy(src, dst).map(|_| ()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::GitInfo;
+    use tempfile::tempdir;
+
+    fn git_resolved(dir: &Path) -> ResolvedSource {
+        ResolvedSource::git(
+            dir.to_path_buf(),
+            "https://example.com/org/repo.git".to_string(),
+            GitInfo {
+                resolved_ref: "main".to_string(),
+                commit_sha: "0123456789abcdef0123456789abcdef01234567".to_string(),
+                ..Default::default()
+            },
+            (),
+        )
+    }
+
+    #[test]
+    fn test_apply_replaces_git_placeholders() {
+        let dir = tempdir().unwrap();
+        let subs = Substitutions::from_resolved(&git_resolved(dir.path()));
+        let out = subs.apply(
+            "sha={{APS_COMMIT_SHA}} short={{APS_SHORT_SHA}} ref={{APS_RESOLVED_REF}} src={{APS_SOURCE}}",
+        );
+        assert_eq!(
+            out,
+            "sha=0123456789abcdef0123456789abcdef01234567 short=01234567 ref=main src=https://example.com/org/repo.git"
+        );
+    }
+
+    #[test]
+    fn test_filesystem_source_leaves_git_placeholders() {
+        let dir = tempdir().unwrap();
+        let resolved = ResolvedSource::filesystem(
+            dir.path().to_path_buf(),
+            "../shared".to_string(),
+            false,
+            "../shared".to_string(),
+            "../shared".to_string(),
+        );
+        let subs = Substitutions::from_resolved(&resolved);
+        assert_eq!(
+            subs.apply("{{APS_SOURCE}} {{APS_COMMIT_SHA}}"),
+            "../shared {{APS_COMMIT_SHA}}"
+        );
+    }
+
+    #[test]
+    fn test_whitespace_normalization_applies_to_text_only() {
+        let path = Path::new("rule.md");
+        let transform = ContentTransform::default()
+            .ensure_final_newline(true)
+            .trim_trailing_whitespace(true);
+
+        assert_eq!(
+            transform
+                .apply(path, b"# Title  \r\nbody\t\n\nend  ")
+                .unwrap(),
+            b"# Title\r\nbody\n\nend\n"
+        );
+        assert_eq!(transform.apply(path, b"clean\n"), None);
+        assert_eq!(transform.apply(path, b""), None);
+        assert_eq!(transform.apply(path, b"bin \0 "), None);
+
+        let newline_only = ContentTransform::default().ensure_final_newline(true);
+        assert_eq!(newline_only.apply(path, b"a  ").unwrap(), b"a  \n");
+    }
+
+    #[test]
+    fn test_copy_file_skips_binary_content() {
+        let dir = tempdir().unwrap();
+        let subs = Substitutions::from_resolved(&git_resolved(dir.path()));
+
+        let text_src = dir.path().join("a.md");
+        let text_dst = dir.path().join("a.out");
+        std::fs::write(&text_src, "rev {{APS_SHORT_SHA}}\n").unwrap();
+        let transform = ContentTransform::new(Some(&subs), None);
+        copy_file(&text_src, &text_dst, &transform).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&text_dst).unwrap(),
+            "rev 01234567\n"
+        );
+
+        let bin_src = dir.path().join("b.bin");
+        let bin_dst = dir.path().join("b.out");
+        let mut bytes = b"{{APS_SHORT_SHA}}".to_vec();
+        bytes.push(0);
+        std::fs::write(&bin_src, &bytes).unwrap();
+        copy_file(&bin_src, &bin_dst, &transform).unwrap();
+        assert_eq!(std::fs::read(&bin_dst).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_decode_legacy_encodings_to_utf8() {
+        let decode = |bytes: &[u8], encoding| decode_to_utf8(bytes, encoding).unwrap();
+
+        assert_eq!(
+            decode(b"caf\xe9", SourceEncoding::Latin1).as_deref(),
+            Some("café")
+        );
+        assert_eq!(
+            decode(b"\x93quoted\x94 \x80", SourceEncoding::Windows1252).as_deref(),
+            Some("\u{201C}quoted\u{201D} €")
+        );
+        // Already UTF-8: left alone even when declared Latin-1
+        assert_eq!(decode("café".as_bytes(), SourceEncoding::Latin1), None);
+
+        let utf16le = b"\xff\xfeh\x00i\x00";
+        assert_eq!(decode(utf16le, SourceEncoding::Auto).as_deref(), Some("hi"));
+        assert_eq!(
+            decode(b"\x00h\x00i", SourceEncoding::Auto).as_deref(),
+            Some("hi")
+        );
+        assert_eq!(
+            decode(b"\xef\xbb\xbfhi", SourceEncoding::Auto).as_deref(),
+            Some("hi")
+        );
+        assert_eq!(decode(b"plain", SourceEncoding::Auto), None);
+
+        // Binary content is never converted
+        assert_eq!(decode(b"\x89PNG\x00\x01\xff", SourceEncoding::Auto), None);
+        assert_eq!(decode(b"\x00\x01\xe9", SourceEncoding::Latin1), None);
+
+        // Undetectable legacy text is reported instead of guessed
+        assert!(decode_to_utf8(b"caf\xe9", SourceEncoding::Auto).is_err());
+        assert!(decode_to_utf8(b"odd", SourceEncoding::Utf16Le).is_err());
+    }
+
+    #[test]
+    fn test_copy_file_converts_encoding_and_keeps_undecodable_files() {
+        let dir = tempdir().unwrap();
+        let transform = ContentTransform::new(None, Some(SourceEncoding::Auto));
+
+        let src = dir.path().join("utf16.md");
+        let dst = dir.path().join("utf16.out");
+        std::fs::write(&src, b"\xff\xfe#\x00 \x00\xe9\x00").unwrap();
+        copy_file(&src, &dst, &transform).unwrap();
+        assert_eq!(std::fs::read_to_string(&dst).unwrap(), "# é");
+
+        let src = dir.path().join("latin1.md");
+        let dst = dir.path().join("latin1.out");
+        std::fs::write(&src, b"caf\xe9").unwrap();
+        copy_file(&src, &dst, &transform).unwrap();
+        assert_eq!(std::fs::read(&dst).unwrap(), b"caf\xe9");
+    }
+}
@@ -0,0 +1,262 @@
+//! Update checks: compare locked commits with the current remote tips.
+//!
+//! Each repository is queried once with `git ls-remote`, no matter how many
+//! entries (or refs) point at it, unless those entries reach it with
+//! different client certificates, headers or git config; nothing is
+//! cloned. Failures are
+//! recorded per entry so one unreachable remote does not hide the rest of
+//! the report.
+
+use crate::error::Result;
+use crate::lockfile::Lockfile;
+use crate::manifest::{resolve_channel_ref, Entry, Source};
+use crate::repo_id::{canonicalize_repo_url, CanonicalRepoId};
+use crate::sources::{is_commit_prefix, CloneOptions, RemoteRefs, INDEX_FILE_REF_PREFIX};
+use std::collections::HashMap;
+use std::ffi::OsString;
+
+/// Outcome of checking one entry against its remote
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateState {
+    /// The remote ref still points at the locked commit
+    Unchanged,
+    /// The remote ref moved past the locked commit
+    Moved,
+    /// The entry has no locked commit yet
+    NotLocked,
+    /// The source is pinned with `as_of`, `message_match`, a commit SHA or an
+    /// index file, so the ref's tip does not decide what is installed
+    Pinned,
+    /// The remote does not advertise the ref
+    RefNotFound,
+    /// The remote could not be queried
+    Failed(String),
+}
+
+/// Locked vs remote commit for one git entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpdateStatus {
+    /// Entry ID
+    pub id: String,
+    /// Repository URL as written in the manifest
+    pub repo: String,
+    /// Ref as written in the manifest (a channel ref is shown as its branch)
+    pub git_ref: String,
+    /// Commit recorded in the lockfile
+    pub locked: Option<String>,
+    /// Commit the remote ref currently points at
+    pub remote: Option<String>,
+    /// Classification of the two
+    pub state: UpdateState,
+}
+
+/// A repository as reached with one set of transport settings (`-c`
+/// arguments and environment)
+type RemoteKey = (CanonicalRepoId, Vec<OsString>, Vec<(String, String)>);
+
+/// Check every single-source git entry for upstream changes
+pub fn check_updates(entries: &[&Entry], lockfile: &Lockfile) -> Vec<UpdateStatus> {
+    check_updates_with(entries, lockfile, crate::sources::list_remote_refs)
+}
+
+/// Check entries using `list_refs` to query each repository once per set of
+/// transport settings (the entry's client certificate, headers, git config)
+pub fn check_updates_with<F>(
+    entries: &[&Entry],
+    lockfile: &Lockfile,
+    mut list_refs: F,
+) -> Vec<UpdateStatus>
+where
+    F: FnMut(&str, &CloneOptions) -> Result<RemoteRefs>,
+{
+    let mut remotes: HashMap<RemoteKey, std::result::Result<RemoteRefs, String>> = HashMap::new();
+    let mut statuses = Vec::new();
+
+    for entry in entries {
+        let Some(
+            source @ Source::Git {
+                repo,
+                r#ref,
+                as_of,
+                message_match,
+                channels,
+                default_branches,
+                ..
+            },
+        ) = entry.source.as_ref()
+        else {
+            continue;
+        };
+        let git_ref = resolve_channel_ref(r#ref, channels);
+        let locked = lockfile
+            .entries
+            .get(&entry.id)
+            .and_then(|e| e.commit.clone());
+        let mut status = UpdateStatus {
+            id: entry.id.clone(),
+            repo: repo.clone(),
+            git_ref: git_ref.to_string(),
+            locked,
+            remote: None,
+            state: UpdateState::Unchanged,
+        };
+
+        if as_of.is_some() || message_match.is_some() || git_ref.starts_with(INDEX_FILE_REF_PREFIX)
+        {
+            status.state = UpdateState::Pinned;
+            statuses.push(status);
+            continue;
+        }
+
+        let options = source.clone_options();
+        let refs = remotes
+            .entry((
+                canonicalize_repo_url(repo),
+                options.config_args(),
+                options.config_env(),
+            ))
+            .or_insert_with(|| list_refs(repo, &options).map_err(|e| e.to_string()));
+        status.state = match refs {
+            Err(message) => UpdateState::Failed(message.clone()),
+            Ok(refs) => match refs.resolve(&git_ref, default_branches) {
+                // Not a branch or tag, so a commit SHA
+                None if is_commit_prefix(&git_ref) => UpdateState::Pinned,
+                None => UpdateState::RefNotFound,
+                Some((_, sha)) => {
+                    let state = match status.locked {
+                        None => UpdateState::NotLocked,
+                        Some(ref locked) if *locked == sha => UpdateState::Unchanged,
+                        Some(_) => UpdateState::Moved,
+                    };
+                    status.remote = Some(sha);
+                    state
+                }
+            },
+        };
+        statuses.push(status);
+    }
+
+    statuses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ApsError;
+    use crate::lockfile::LockedEntry;
+
+    fn git_entry(id: &str, repo: &str, git_ref: &str) -> Entry {
+        let yaml = format!(
+            "id: {}\nkind: agents_md\nsource:\n  type: git\n  repo: {}\n  ref: {}\ndest: ./{}.md\n",
+            id, repo, git_ref, id
+        );
+        serde_yaml::from_str(&yaml).unwrap()
+    }
+
+    fn lock(lockfile: &mut Lockfile, id: &str, commit: &str) {
+        let entry = LockedEntry::new_git(
+            "repo",
+            "./x.md",
+            "main".to_string(),
+            commit.to_string(),
+            "sha256:0".to_string(),
+        );
+        lockfile.upsert(id.to_string(), entry);
+    }
+
+    #[test]
+    fn test_check_updates_queries_each_repository_once() {
+        let entries = [
+            git_entry("a", "https://github.com/org/prompts.git", "main"),
+            git_entry("b", "git@github.com:org/prompts", "v1.0"),
+            git_entry("c", "https://github.com/org/prompts", "gone"),
+            git_entry("d", "https://example.com/down.git", "main"),
+        ];
+        let entries: Vec<&Entry> = entries.iter().collect();
+        let mut lockfile = Lockfile::new();
+        lock(&mut lockfile, "a", "1111");
+        lock(&mut lockfile, "b", "2222");
+
+        let mut queried = Vec::new();
+        let statuses = check_updates_with(&entries, &lockfile, |url, _options| {
+            queried.push(url.to_string());
+            if url.contains("example.com") {
+                return Err(ApsError::GitError {
+                    message: "unreachable".to_string(),
+                });
+            }
+            Ok(RemoteRefs::parse(
+                "9999\trefs/heads/main\n2222\trefs/tags/v1.0\n",
+            ))
+        });
+
+        assert_eq!(
+            queried,
+            [
+                "https://github.com/org/prompts.git",
+                "https://example.com/down.git"
+            ]
+        );
+        let states: Vec<_> = statuses.iter().map(|s| s.state.clone()).collect();
+        assert_eq!(
+            states,
+            [
+                UpdateState::Moved,
+                UpdateState::Unchanged,
+                UpdateState::RefNotFound,
+                UpdateState::Failed("Git operation failed: unreachable".to_string()),
+            ]
+        );
+        assert_eq!(statuses[0].remote.as_deref(), Some("9999"));
+    }
+
+    #[test]
+    fn test_check_updates_prefers_refs_named_like_shas() {
+        let entries = [
+            git_entry("branch", "https://example.com/r.git", "cafe1234"),
+            git_entry("commit", "https://example.com/r.git", "deadbeef"),
+        ];
+        let entries: Vec<&Entry> = entries.iter().collect();
+        let mut lockfile = Lockfile::new();
+        lock(&mut lockfile, "branch", "1111");
+
+        let statuses = check_updates_with(&entries, &lockfile, |_, _| {
+            Ok(RemoteRefs::parse("1111\trefs/heads/cafe1234\n"))
+        });
+
+        let states: Vec<_> = statuses.iter().map(|s| s.state.clone()).collect();
+        assert_eq!(states, [UpdateState::Unchanged, UpdateState::Pinned]);
+    }
+
+    #[test]
+    fn test_check_updates_uses_each_entry_git_config() {
+        let yaml =
+            "id: b\nkind: agents_md\nsource:\n  type: git\n  repo: https://example.com/r.git\n  \
+                    git_config:\n    http.sslCAInfo: /certs/ca.pem\ndest: ./b.md\n";
+        let entries = [
+            git_entry("a", "https://example.com/r.git", "main"),
+            serde_yaml::from_str(yaml).unwrap(),
+        ];
+        let entries: Vec<&Entry> = entries.iter().collect();
+
+        let mut queried = Vec::new();
+        check_updates_with(&entries, &Lockfile::new(), |_, options| {
+            let args: Vec<String> = options
+                .config_args()
+                .iter()
+                .map(|a| a.to_string_lossy().to_string())
+                .collect();
+            queried.push(args);
+            Ok(RemoteRefs::parse("9999\trefs/heads/main\n"))
+        });
+
+        // Same repository, but the second entry needs its CA bundle
+        assert_eq!(
+            queried,
+            [
+                vec![],
+                vec!["-c".to_string(), "http.sslCAInfo=/certs/ca.pem".to_string()]
+            ]
+        );
+    }
+}
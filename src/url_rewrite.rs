@@ -0,0 +1,174 @@
+//! Global rewrites of git source URLs, in the style of git's
+//! `url.<base>.insteadOf`.
+//!
+//! Fleets that fetch through an internal mirror can set `APS_URL_REWRITES`
+//! to comma-separated `prefix=replacement` rules instead of editing every
+//! manifest, e.g. `https://github.com/=https://mirror.corp.example/github/`.
+//! Rewrites are applied before host policy checks and before git runs.
+//!
+//! Matching rules:
+//! - The longest matching prefix wins, as with git's `insteadOf`. Each URL
+//!   is rewritten at most once.
+//! - A prefix also matches the other transport's spelling of the same host
+//!   and path: `https://github.com/` matches `git@github.com:org/repo` and
+//!   `ssh://git@github.com/org/repo`, and `git@github.com:` matches
+//!   `https://github.com/org/repo`. Hosts compare case-insensitively;
+//!   credentials and ports are ignored for this comparison.
+//! - Whatever follows the prefix is appended to the replacement unchanged.
+//! - Local paths and `file://` URLs only match a literal prefix.
+
+use tracing::warn;
+
+/// Environment variable listing `prefix=replacement` rewrite rules
+pub const URL_REWRITES_ENV: &str = "APS_URL_REWRITES";
+
+/// One `prefix=replacement` rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    prefix: String,
+    replacement: String,
+}
+
+/// Ordered set of URL rewrite rules
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UrlRewrites {
+    rules: Vec<Rule>,
+}
+
+impl UrlRewrites {
+    /// Create rewrites from `(prefix, replacement)` pairs
+    pub fn new<I, P, R>(rules: I) -> Self
+    where
+        I: IntoIterator<Item = (P, R)>,
+        P: Into<String>,
+        R: Into<String>,
+    {
+        Self {
+            rules: rules
+                .into_iter()
+                .map(|(prefix, replacement)| Rule {
+                    prefix: prefix.into(),
+                    replacement: replacement.into(),
+                })
+                .filter(|rule| !rule.prefix.is_empty())
+                .collect(),
+        }
+    }
+
+    /// Parse comma-separated `prefix=replacement` rules, skipping (with a
+    /// warning) entries without a `=`
+    pub fn parse(spec: &str) -> Self {
+        Self::new(
+            spec.split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .filter_map(|rule| {
+                    let parsed = rule.split_once('=');
+                    if parsed.is_none() {
+                        warn!(
+                            "Ignoring {} entry '{}': expected prefix=replacement",
+                            URL_REWRITES_ENV, rule
+                        );
+                    }
+                    parsed.map(|(prefix, replacement)| (prefix.trim(), replacement.trim()))
+                }),
+        )
+    }
+
+    /// Read the rules from `APS_URL_REWRITES`
+    pub fn from_env() -> Self {
+        Self::parse(&std::env::var(URL_REWRITES_ENV).unwrap_or_default())
+    }
+
+    /// The rewritten URL, or `None` when no rule matches
+    pub fn rewrite(&self, url: &str) -> Option<String> {
+        let normalized = https_form(url);
+        self.rules
+            .iter()
+            .filter_map(|rule| {
+                if let Some(rest) = url.strip_prefix(&rule.prefix) {
+                    return Some((rule, rest.to_string()));
+                }
+                let prefix = https_form(&rule.prefix)?;
+                let rest = normalized.as_deref()?.strip_prefix(&prefix)?;
+                Some((rule, rest.to_string()))
+            })
+            .max_by_key(|(rule, _)| rule.prefix.len())
+            .map(|(rule, rest)| format!("{}{}", rule.replacement, rest))
+    }
+}
+
+/// A remote URL spelled as `https://host/path`, for comparing HTTP(S), SSH
+/// and scp-like spellings; `None` for local paths and other schemes
+fn https_form(url: &str) -> Option<String> {
+    let (authority, path) = match url.split_once("://") {
+        Some((scheme, rest)) => {
+            if !matches!(
+                scheme.to_ascii_lowercase().as_str(),
+                "http" | "https" | "ssh"
+            ) {
+                return None;
+            }
+            rest.split_once('/').unwrap_or((rest, ""))
+        }
+        // scp-like syntax: [user@]host:path (but not Windows drive letters)
+        None => {
+            let (authority, path) = url.split_once(':')?;
+            if authority.len() <= 1 || authority.contains('/') {
+                return None;
+            }
+            (authority, path.trim_start_matches('/'))
+        }
+    };
+    let host = authority
+        .rsplit_once('@')
+        .map(|(_, h)| h)
+        .unwrap_or(authority);
+    let host = host.split_once(':').map(|(h, _)| h).unwrap_or(host);
+    Some(format!("https://{}/{}", host.to_ascii_lowercase(), path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrites_match_every_spelling_of_a_host() {
+        let rewrites = UrlRewrites::parse(
+            "https://github.com/=https://mirror.corp.example/github/, \
+             https://github.com/acme/=https://mirror.corp.example/acme/, missing-separator",
+        );
+
+        for url in [
+            "https://github.com/org/repo.git",
+            "https://GitHub.com/org/repo.git",
+            "git@github.com:org/repo.git",
+            "ssh://git@github.com:22/org/repo.git",
+        ] {
+            assert_eq!(
+                rewrites.rewrite(url).as_deref(),
+                Some("https://mirror.corp.example/github/org/repo.git"),
+                "{}",
+                url
+            );
+        }
+        // The longest prefix wins
+        assert_eq!(
+            rewrites.rewrite("git@github.com:acme/prompts").as_deref(),
+            Some("https://mirror.corp.example/acme/prompts")
+        );
+        assert_eq!(rewrites.rewrite("https://gitlab.com/org/repo.git"), None);
+        assert_eq!(rewrites.rewrite("/srv/git/github.com/org/repo"), None);
+
+        let ssh = UrlRewrites::new([("git@github.com:", "git@mirror.corp.example:")]);
+        assert_eq!(
+            ssh.rewrite("https://github.com/org/repo").as_deref(),
+            Some("git@mirror.corp.example:org/repo")
+        );
+        let local = UrlRewrites::new([("/srv/git/", "file:///mnt/git/")]);
+        assert_eq!(
+            local.rewrite("/srv/git/prompts").as_deref(),
+            Some("file:///mnt/git/prompts")
+        );
+    }
+}
@@ -0,0 +1,295 @@
+//! Semantic version parsing and precedence, for picking release tags.
+//!
+//! Implements [SemVer 2.0.0](https://semver.org) with one leniency common in
+//! git tags: a leading `v` or `V` is accepted (`v1.2.3`).
+//!
+//! Precedence rules:
+//! - `major.minor.patch` compare numerically.
+//! - A prerelease (`1.0.0-rc.1`) is lower than the same release.
+//! - Prerelease identifiers compare left to right: numeric ones numerically,
+//!   alphanumeric ones in ASCII order, numeric lower than alphanumeric, and a
+//!   shorter list lower when it is a prefix of the other.
+//! - Build metadata (`+build.5`) does not affect precedence; it only breaks
+//!   ties so that sorting is deterministic.
+//!
+//! [`VersionReq`] is the range a manifest `ref` such as `^1.2` or `~1.2.3`
+//! selects tags from.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+/// One dot-separated prerelease identifier
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Identifier {
+    Numeric(u64),
+    Alphanumeric(String),
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Identifier::Numeric(n) => write!(f, "{}", n),
+            Identifier::Alphanumeric(s) => f.write_str(s),
+        }
+    }
+}
+
+/// A parsed semantic version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    pre: Vec<Identifier>,
+    build: Vec<String>,
+}
+
+impl Version {
+    /// Parse a version such as `1.2.3`, `v2.0.0-rc.1` or `1.0.0+build.5`;
+    /// `None` when the text is not a semantic version
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text
+            .strip_prefix('v')
+            .or_else(|| text.strip_prefix('V'))
+            .unwrap_or(text);
+        let (text, build) = match text.split_once('+') {
+            Some((rest, build)) => (rest, Some(build)),
+            None => (text, None),
+        };
+        let (core, pre) = match text.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (text, None),
+        };
+
+        let mut numbers = core.split('.').map(parse_numeric);
+        let (Some(Some(major)), Some(Some(minor)), Some(Some(patch)), None) = (
+            numbers.next(),
+            numbers.next(),
+            numbers.next(),
+            numbers.next(),
+        ) else {
+            return None;
+        };
+
+        let pre = match pre {
+            Some(pre) => pre
+                .split('.')
+                .map(|id| {
+                    if !is_identifier(id) {
+                        None
+                    } else if id.bytes().all(|b| b.is_ascii_digit()) {
+                        parse_numeric(id).map(Identifier::Numeric)
+                    } else {
+                        Some(Identifier::Alphanumeric(id.to_string()))
+                    }
+                })
+                .collect::<Option<Vec<_>>>()?,
+            None => Vec::new(),
+        };
+        let build = match build {
+            Some(build) => {
+                let ids: Vec<String> = build.split('.').map(str::to_string).collect();
+                if !ids.iter().all(|id| is_identifier(id)) {
+                    return None;
+                }
+                ids
+            }
+            None => Vec::new(),
+        };
+
+        Some(Self {
+            major,
+            minor,
+            patch,
+            pre,
+            build,
+        })
+    }
+
+    /// Whether this is a prerelease (`1.0.0-beta`)
+    pub fn is_prerelease(&self) -> bool {
+        !self.pre.is_empty()
+    }
+}
+
+/// A numeric component without leading zeros
+fn parse_numeric(part: &str) -> Option<u64> {
+    let valid = !part.is_empty()
+        && part.bytes().all(|b| b.is_ascii_digit())
+        && (part == "0" || !part.starts_with('0'));
+    valid.then(|| part.parse().ok()).flatten()
+}
+
+/// A non-empty run of ASCII alphanumerics and hyphens
+fn is_identifier(id: &str) -> bool {
+    !id.is_empty() && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => self.pre.cmp(&other.pre),
+            })
+            .then_with(|| self.build.cmp(&other.build))
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if !self.pre.is_empty() {
+            let pre: Vec<String> = self.pre.iter().map(Identifier::to_string).collect();
+            write!(f, "-{}", pre.join("."))?;
+        }
+        if !self.build.is_empty() {
+            write!(f, "+{}", self.build.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+/// Which versions a requirement admits above its lower bound
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReqOp {
+    /// `^`: no change to the leftmost non-zero component
+    Caret,
+    /// `~`: no change to major and minor (major only when no minor is given)
+    Tilde,
+}
+
+/// A version requirement: `^1.2` (at least 1.2.0, below 2.0.0) or `~1.2.3`
+/// (at least 1.2.3, below 1.3.0), with the same rules as Cargo. Missing
+/// components count as zero. Prereleases never match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionReq {
+    op: ReqOp,
+    major: u64,
+    minor: Option<u64>,
+    patch: Option<u64>,
+}
+
+impl VersionReq {
+    /// Parse `^X[.Y[.Z]]` or `~X[.Y[.Z]]` (a `v` after the operator is
+    /// accepted); `None` for anything else, such as a branch or tag name
+    pub fn parse(text: &str) -> Option<Self> {
+        let (op, rest) = if let Some(rest) = text.strip_prefix('^') {
+            (ReqOp::Caret, rest)
+        } else {
+            (ReqOp::Tilde, text.strip_prefix('~')?)
+        };
+        let rest = rest.strip_prefix('v').unwrap_or(rest);
+        let mut numbers = rest.split('.').map(parse_numeric);
+        let major = numbers.next()??;
+        let minor = numbers.next().map(|n| n.ok_or(())).transpose().ok()?;
+        let patch = numbers.next().map(|n| n.ok_or(())).transpose().ok()?;
+        if numbers.next().is_some() {
+            return None;
+        }
+        Some(Self {
+            op,
+            major,
+            minor,
+            patch,
+        })
+    }
+
+    /// Whether `version` is a release within the range
+    pub fn matches(&self, version: &Version) -> bool {
+        let lower = (self.major, self.minor.unwrap_or(0), self.patch.unwrap_or(0));
+        let upper = match (self.op, self.minor, self.patch) {
+            (ReqOp::Tilde, Some(minor), _) => (self.major, minor + 1, 0),
+            (ReqOp::Caret, Some(0), Some(patch)) if self.major == 0 => (0, 0, patch + 1),
+            (ReqOp::Caret, Some(minor), _) if self.major == 0 => (0, minor + 1, 0),
+            _ => (self.major + 1, 0, 0),
+        };
+        let core = (version.major, version.minor, version.patch);
+        !version.is_prerelease() && core >= lower && core < upper
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_precedence_follows_semver_spec() {
+        let ordered = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "v1.0.1",
+            "1.2.0",
+            "1.10.0",
+        ];
+        let versions: Vec<Version> = ordered.iter().map(|v| Version::parse(v).unwrap()).collect();
+        for pair in versions.windows(2) {
+            assert!(pair[0] < pair[1], "{} < {}", pair[0], pair[1]);
+        }
+        assert!(versions[0].is_prerelease());
+        assert!(!versions[7].is_prerelease());
+        assert_eq!(versions[8].to_string(), "1.0.1");
+        assert_eq!(
+            Version::parse("1.0.0-rc.1+build.5").unwrap().to_string(),
+            "1.0.0-rc.1+build.5"
+        );
+    }
+
+    #[test]
+    fn test_rejects_non_semver() {
+        for text in [
+            "1.2",
+            "1.2.3.4",
+            "01.2.3",
+            "1.2.3-",
+            "1.2.3-rc..1",
+            "1.2.3-01",
+            "1.2.3+",
+            "release-1.2.3",
+            "latest",
+        ] {
+            assert_eq!(Version::parse(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_version_req_ranges() {
+        let admits = |req: &str, version: &str| {
+            VersionReq::parse(req)
+                .unwrap()
+                .matches(&Version::parse(version).unwrap())
+        };
+        for (req, inside, outside) in [
+            ("^1.2", "1.9.0", "2.0.0"),
+            ("^1.2.3", "1.2.3", "1.2.2"),
+            ("^0.2.3", "0.2.9", "0.3.0"),
+            ("^0.0.3", "0.0.3", "0.0.4"),
+            ("^v1", "1.0.0", "0.9.9"),
+            ("~1.2.3", "1.2.9", "1.3.0"),
+            ("~1", "1.5.0", "2.0.0"),
+        ] {
+            assert!(admits(req, inside), "{} admits {}", req, inside);
+            assert!(!admits(req, outside), "{} rejects {}", req, outside);
+        }
+        assert!(!admits("^1.2", "1.3.0-rc.1"));
+
+        for text in ["main", "v1.2.0", "^", "^1.x", "~1.2.3.4", "^01.2", ">=1.0"] {
+            assert_eq!(VersionReq::parse(text), None, "{}", text);
+        }
+    }
+}
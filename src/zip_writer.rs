@@ -0,0 +1,371 @@
+//! Zip archive destination for synced content.
+//!
+//! [`ZipWriter`] is a [`DestinationWriter`] that streams files into a single
+//! `.zip` instead of loose files, for handing a resolved asset tree around
+//! as one download. Entries are stored uncompressed (prompts are small and
+//! the archive stays byte-for-byte reproducible), carry their Unix mode, and
+//! use a fixed timestamp.
+//!
+//! The archive is written to a temporary file next to the target and renamed
+//! into place by [`DestinationWriter::finalize`], so readers never see a
+//! half-written archive. Dropping the writer without finalizing discards it.
+//! Archives needing ZIP64 (over 4 GiB or 65535 entries) are rejected.
+
+use crate::dest_writer::DestinationWriter;
+use crate::error::{ApsError, Result};
+use crate::sources::ResolvedSource;
+use std::collections::BTreeMap;
+use std::io::{BufWriter, Seek, Write};
+use std::path::{Component, Path, PathBuf};
+use tempfile::NamedTempFile;
+
+/// Unix, zip specification 2.0
+const VERSION_MADE_BY: u16 = (3 << 8) | 20;
+/// Minimum zip version needed for stored entries
+const VERSION_NEEDED: u16 = 20;
+/// General purpose flag: file names are UTF-8
+const FLAG_UTF8: u16 = 1 << 11;
+/// DOS date for 1980-01-01, the earliest a zip can record
+const DOS_DATE: u16 = (1 << 5) | 1;
+/// Regular file type bits for the Unix mode
+const S_IFREG: u32 = 0o100000;
+
+/// Central directory record of one written entry
+#[derive(Debug, Clone, Copy)]
+struct Written {
+    offset: u32,
+    crc: u32,
+    size: u32,
+    mode: u32,
+}
+
+/// Destination that collects files into a zip archive
+#[derive(Debug)]
+pub struct ZipWriter {
+    archive: PathBuf,
+    file: Option<BufWriter<NamedTempFile>>,
+    top_level: Option<String>,
+    strip_prefix: Option<PathBuf>,
+    entries: BTreeMap<String, Written>,
+}
+
+impl ZipWriter {
+    /// Start an archive that [`finalize`](DestinationWriter::finalize) will
+    /// move to `archive`
+    pub fn create(archive: impl Into<PathBuf>) -> Result<Self> {
+        let archive = archive.into();
+        let dir = match archive.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let temp = NamedTempFile::new_in(dir).map_err(|e| {
+            ApsError::dest_io(e, dir, format!("Failed to create archive in {:?}", dir))
+        })?;
+        Ok(Self {
+            archive,
+            file: Some(BufWriter::new(temp)),
+            top_level: None,
+            strip_prefix: None,
+            entries: BTreeMap::new(),
+        })
+    }
+
+    /// Start an archive for `resolved` whose entries sit under a folder named
+    /// by the commit's short SHA (no folder for non-git sources)
+    pub fn for_source(archive: impl Into<PathBuf>, resolved: &ResolvedSource) -> Result<Self> {
+        let writer = Self::create(archive)?;
+        Ok(match resolved.git_info {
+            Some(ref info) => {
+                let sha = &info.commit_sha;
+                writer.top_level(&sha[..8.min(sha.len())])
+            }
+            None => writer,
+        })
+    }
+
+    /// Put every entry under this folder
+    pub fn top_level(mut self, folder: impl Into<String>) -> Self {
+        self.top_level = Some(folder.into().trim_matches('/').to_string());
+        self
+    }
+
+    /// Drop this leading directory from written paths; paths outside it are
+    /// kept as they are
+    pub fn strip_prefix(mut self, prefix: impl Into<PathBuf>) -> Self {
+        self.strip_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Name of `path` inside the archive
+    fn entry_name(&self, path: &Path) -> Result<String> {
+        let path = match self.strip_prefix {
+            Some(ref prefix) => path.strip_prefix(prefix).unwrap_or(path),
+            None => path,
+        };
+        let mut segments = Vec::new();
+        for component in path.components() {
+            match component {
+                Component::Normal(segment) => segments.push(segment.to_string_lossy()),
+                Component::CurDir => {}
+                _ => {
+                    return Err(ApsError::InvalidSourceFilePath {
+                        path: path.to_string_lossy().to_string(),
+                    })
+                }
+            }
+        }
+        if segments.is_empty() {
+            return Err(ApsError::InvalidSourceFilePath {
+                path: path.to_string_lossy().to_string(),
+            });
+        }
+        let name = segments.join("/");
+        Ok(match self.top_level {
+            Some(ref folder) if !folder.is_empty() => format!("{}/{}", folder, name),
+            _ => name,
+        })
+    }
+
+    fn io_error(&self, err: std::io::Error) -> ApsError {
+        archive_error(&self.archive, err)
+    }
+
+    fn too_large(&self, what: &str) -> ApsError {
+        self.io_error(std::io::Error::other(format!(
+            "{} exceeds the zip format's limits (ZIP64 is not supported)",
+            what
+        )))
+    }
+}
+
+impl DestinationWriter for ZipWriter {
+    /// Append an entry; writing the same path again replaces it in the
+    /// archive's directory
+    fn write_file(&mut self, path: &Path, bytes: &[u8], mode: u32) -> Result<()> {
+        let name = self.entry_name(path)?;
+        let size = u32::try_from(bytes.len()).map_err(|_| self.too_large(&name))?;
+        let crc = crc32(bytes);
+        let Some(file) = self.file.as_mut() else {
+            return Err(self.io_error(std::io::Error::other("archive already finalized")));
+        };
+        let offset = file
+            .stream_position()
+            .map_err(|e| archive_error(&self.archive, e))?;
+
+        let mut header = Vec::with_capacity(30 + name.len());
+        put32(&mut header, 0x0403_4b50);
+        put16(&mut header, VERSION_NEEDED);
+        put16(&mut header, FLAG_UTF8);
+        put16(&mut header, 0); // stored
+        put16(&mut header, 0); // time 00:00
+        put16(&mut header, DOS_DATE);
+        put32(&mut header, crc);
+        put32(&mut header, size);
+        put32(&mut header, size);
+        put16(&mut header, name.len() as u16);
+        put16(&mut header, 0); // no extra field
+        header.extend_from_slice(name.as_bytes());
+
+        let written = file.write_all(&header).and_then(|_| file.write_all(bytes));
+        written.map_err(|e| self.io_error(e))?;
+        let offset = u32::try_from(offset).map_err(|_| self.too_large("archive"))?;
+        self.entries.insert(
+            name,
+            Written {
+                offset,
+                crc,
+                size,
+                mode,
+            },
+        );
+        Ok(())
+    }
+
+    /// Write the central directory and move the archive into place
+    fn finalize(&mut self) -> Result<()> {
+        let Some(mut file) = self.file.take() else {
+            return Ok(());
+        };
+        if self.entries.len() > usize::from(u16::MAX) {
+            return Err(self.too_large("entry count"));
+        }
+        let start = file.stream_position().map_err(|e| self.io_error(e))?;
+
+        let mut directory = Vec::new();
+        for (name, entry) in &self.entries {
+            put32(&mut directory, 0x0201_4b50);
+            put16(&mut directory, VERSION_MADE_BY);
+            put16(&mut directory, VERSION_NEEDED);
+            put16(&mut directory, FLAG_UTF8);
+            put16(&mut directory, 0); // stored
+            put16(&mut directory, 0);
+            put16(&mut directory, DOS_DATE);
+            put32(&mut directory, entry.crc);
+            put32(&mut directory, entry.size);
+            put32(&mut directory, entry.size);
+            put16(&mut directory, name.len() as u16);
+            put16(&mut directory, 0); // extra field
+            put16(&mut directory, 0); // comment
+            put16(&mut directory, 0); // disk number
+            put16(&mut directory, 0); // internal attributes
+            put32(&mut directory, (S_IFREG | (entry.mode & 0o7777)) << 16);
+            put32(&mut directory, entry.offset);
+            directory.extend_from_slice(name.as_bytes());
+        }
+        let start = u32::try_from(start).map_err(|_| self.too_large("archive"))?;
+        let count = self.entries.len() as u16;
+        let mut end = Vec::with_capacity(22);
+        put32(&mut end, 0x0605_4b50);
+        put16(&mut end, 0); // this disk
+        put16(&mut end, 0); // disk with the directory
+        put16(&mut end, count);
+        put16(&mut end, count);
+        put32(&mut end, directory.len() as u32);
+        put32(&mut end, start);
+        put16(&mut end, 0); // comment length
+
+        let written = file
+            .write_all(&directory)
+            .and_then(|_| file.write_all(&end))
+            .and_then(|_| file.flush());
+        written.map_err(|e| self.io_error(e))?;
+        let temp = file
+            .into_inner()
+            .map_err(|e| self.io_error(e.into_error()))?;
+        temp.as_file().sync_all().map_err(|e| self.io_error(e))?;
+        temp.persist(&self.archive)
+            .map_err(|e| self.io_error(e.error))?;
+        Ok(())
+    }
+}
+
+fn archive_error(archive: &Path, err: std::io::Error) -> ApsError {
+    ApsError::dest_io(
+        err,
+        archive,
+        format!("Failed to write archive {:?}", archive),
+    )
+}
+
+fn put16(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+/// CRC-32 (IEEE 802.3) lookup table
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                0xEDB8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 checksum zip entries are verified with
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, &b| {
+        CRC_TABLE[((crc ^ u32::from(b)) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn u16_at(bytes: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes([bytes[at], bytes[at + 1]])
+    }
+
+    fn u32_at(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    /// Read `(name, mode, content)` back through the central directory
+    fn read_archive(bytes: &[u8]) -> Vec<(String, u32, Vec<u8>)> {
+        let end = bytes.len() - 22;
+        assert_eq!(u32_at(bytes, end), 0x0605_4b50);
+        let count = u16_at(bytes, end + 10);
+        let mut at = u32_at(bytes, end + 16) as usize;
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            assert_eq!(u32_at(bytes, at), 0x0201_4b50);
+            let crc = u32_at(bytes, at + 16);
+            let size = u32_at(bytes, at + 20) as usize;
+            let name_len = u16_at(bytes, at + 28) as usize;
+            let mode = u32_at(bytes, at + 38) >> 16;
+            let offset = u32_at(bytes, at + 42) as usize;
+            let name = String::from_utf8(bytes[at + 46..at + 46 + name_len].to_vec()).unwrap();
+
+            let data = offset + 30 + u16_at(bytes, offset + 26) as usize;
+            let content = bytes[data..data + size].to_vec();
+            assert_eq!(crc32(&content), crc);
+            entries.push((name, mode & 0o7777, content));
+            at += 46 + name_len;
+        }
+        entries
+    }
+
+    #[test]
+    fn test_crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn test_zip_writer_stores_files_under_top_level_folder() {
+        let temp = tempdir().unwrap();
+        let archive = temp.path().join("prompts.zip");
+        let mut writer = ZipWriter::create(&archive)
+            .unwrap()
+            .top_level("abc12345")
+            .strip_prefix("rules");
+
+        writer
+            .write_file(Path::new("rules/a.md"), b"# A\n", 0o644)
+            .unwrap();
+        writer
+            .write_file(Path::new("rules/bin/run.sh"), b"#!/bin/sh\n", 0o755)
+            .unwrap();
+        writer
+            .write_file(Path::new("rules/a.md"), b"# A v2\n", 0o644)
+            .unwrap();
+        assert!(writer
+            .write_file(Path::new("../escape.md"), b"", 0o644)
+            .is_err());
+        // Nothing is visible until the archive is finalized
+        assert!(!archive.exists());
+
+        writer.finalize().unwrap();
+
+        let entries = read_archive(&std::fs::read(&archive).unwrap());
+        assert_eq!(
+            entries,
+            [
+                ("abc12345/a.md".to_string(), 0o644, b"# A v2\n".to_vec()),
+                (
+                    "abc12345/bin/run.sh".to_string(),
+                    0o755,
+                    b"#!/bin/sh\n".to_vec()
+                ),
+            ]
+        );
+        // Only the archive is left behind, no temp files
+        assert_eq!(std::fs::read_dir(temp.path()).unwrap().count(), 1);
+    }
+}